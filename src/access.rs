@@ -0,0 +1,277 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::command::{Command, ConflictAction};
+use crate::condition::Condition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Privilege {
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl Privilege {
+    pub fn from_name(name: &str) -> Option<Privilege> {
+        match name.to_uppercase().as_str() {
+            "SELECT" => Some(Self::Select),
+            "INSERT" => Some(Self::Insert),
+            "UPDATE" => Some(Self::Update),
+            "DELETE" => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Privilege {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Select => write!(f, "SELECT"),
+            Self::Insert => write!(f, "INSERT"),
+            Self::Update => write!(f, "UPDATE"),
+            Self::Delete => write!(f, "DELETE"),
+        }
+    }
+}
+
+/// Database account. Once current user is set, only statements on tables it has privileges for can
+/// be executed. Statements which change schema, users or grants are allowed only to database owner,
+/// i.e. when no current user is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    pub name: String,
+    grants: BTreeSet<(Privilege, String)>,
+}
+
+impl User {
+    pub fn new(name: &str) -> User {
+        User { name: name.to_string(), grants: BTreeSet::new() }
+    }
+
+    pub fn grant(&mut self, privilege: Privilege, table_name: &str) {
+        self.grants.insert((privilege, table_name.to_string()));
+    }
+
+    pub fn revoke(&mut self, privilege: Privilege, table_name: &str) {
+        self.grants.remove(&(privilege, table_name.to_string()));
+    }
+
+    pub fn is_granted(&self, privilege: Privilege, table_name: &str) -> bool {
+        self.grants.contains(&(privilege, table_name.to_string()))
+    }
+
+    /// Moves grants to the renamed table, returns true if there were any
+    pub fn rename_table(&mut self, table_name: &str, new_table_name: &str) -> bool {
+        let renamed_grants: Vec<(Privilege, String)> = self.grants.iter()
+            .filter(|(_, grant_table_name)| grant_table_name == table_name)
+            .cloned()
+            .collect();
+        for (privilege, _) in &renamed_grants {
+            self.grants.remove(&(*privilege, table_name.to_string()));
+            if !new_table_name.is_empty() {
+                self.grants.insert((*privilege, new_table_name.to_string()));
+            }
+        }
+        !renamed_grants.is_empty()
+    }
+
+    /// Removes grants on dropped table, returns true if there were any
+    pub fn forget_table(&mut self, table_name: &str) -> bool {
+        self.rename_table(table_name, "")
+    }
+
+    /// User schema line is `name PRIVILEGE table, PRIVILEGE table, ...`
+    pub fn to_schema_line(&self) -> String {
+        let grants: Vec<String> = self.grants.iter()
+            .map(|(privilege, table_name)| format!("{} {}", privilege, table_name))
+            .collect();
+        format!("{} {}", self.name, grants.join(", ")).trim_end().to_string()
+    }
+
+    pub fn from_schema_line(line: &str) -> Option<User> {
+        let (name, grants) = line.split_once(' ').unwrap_or((line, ""));
+        let mut user = User::new(name);
+        for grant in grants.split(',').map(str::trim).filter(|grant| !grant.is_empty()) {
+            let (privilege_name, table_name) = grant.split_once(' ')?;
+            user.grant(Privilege::from_name(privilege_name)?, table_name.trim());
+        }
+        Some(user)
+    }
+}
+
+/// Privileges on tables which are needed to execute command, tables of EXISTS subqueries need `SELECT`.
+/// Returns `None` if command can be executed only by database owner.
+pub fn required_privileges(command: &Command) -> Option<Vec<(Privilege, String)>> {
+    let (mut privileges, where_clause) = match command {
+        Command::Select { table_name, where_clause, .. } => (vec![(Privilege::Select, table_name.to_string())], where_clause),
+        Command::SelectCrossJoin { table_names, where_clause, .. } => {
+            let privileges = table_names.iter().map(|table_name| (Privilege::Select, table_name.to_string())).collect();
+            (privileges, where_clause)
+        },
+        Command::InsertInto { table_name, .. } => (vec![(Privilege::Insert, table_name.to_string())], &None),
+        Command::Upsert { insert, action, .. } => {
+            let mut privileges = required_privileges(insert)?;
+            if let (ConflictAction::Update(_), Some((_, table_name))) = (action, privileges.first().cloned()) {
+                privileges.push((Privilege::Update, table_name));
+            }
+            return Some(privileges)
+        },
+        Command::Update { table_name, where_clause, .. } => (vec![(Privilege::Update, table_name.to_string())], where_clause),
+        Command::Delete { table_name, where_clause } | Command::Undelete { table_name, where_clause } =>
+            (vec![(Privilege::Delete, table_name.to_string())], where_clause),
+        Command::SelectChanges { table_name, .. } | Command::CheckTable { table_name } | Command::ShowStorage { table_name } =>
+            (vec![(Privilege::Select, table_name.to_string())], &None),
+        Command::SelectSeries { where_clause, .. } => (vec![], where_clause),
+        // rows of cursor are selected on declaration, so it is checked instead of fetch
+        Command::DeclareCursor { select, .. } | Command::SelectOrdered { select, .. } | Command::SelectLimited { select, .. } |
+            Command::SelectIndexHinted { select, .. } => return required_privileges(select),
+        // returned values are read from the table, as in select
+        Command::Returning { command, .. } => {
+            let mut privileges = required_privileges(command)?;
+            if let Some((_, table_name)) = privileges.first().cloned() {
+                privileges.insert(1, (Privilege::Select, table_name));
+            }
+            return Some(privileges)
+        },
+        Command::Fetch { .. } | Command::CloseCursor { .. } | Command::CreateMacro { .. } => (vec![], &None),
+        Command::SetStatementTimeout { .. } | Command::SetRandomSeed { .. } | Command::Void => (vec![], &None),
+        // files are read and written with permissions of database process, so only owner can copy
+        Command::CopyFrom { .. } | Command::CopyTo { .. } => return None,
+        _ => return None,
+    };
+
+    let subquery_table_names = where_clause.iter().flat_map(Condition::subquery_table_names);
+    privileges.extend(subquery_table_names.map(|table_name| (Privilege::Select, table_name)));
+    Some(privileges)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// `SELECT`, `CHECK TABLE`, `SHOW STORAGE`, cursor declaration and fetch, `COPY ... TO` file
+    Read,
+    /// `INSERT`, `UPDATE`, `DELETE`, `UNDELETE` and `COPY ... FROM` file
+    Write,
+    /// Tables, indexes and sequences definitions, `VACUUM`
+    Schema,
+    /// Users and grants
+    Account,
+    /// `SET`, `CLOSE` of cursor, `CREATE MACRO` and empty statement
+    Session,
+}
+
+impl StatementKind {
+    pub fn of(command: &Command) -> StatementKind {
+        match command {
+            Command::Select { .. } | Command::SelectCrossJoin { .. } | Command::SelectChanges { .. } |
+                Command::SelectSeries { .. } | Command::CheckTable { .. } | Command::ShowStorage { .. } |
+                Command::SelectOrdered { .. } | Command::SelectLimited { .. } | Command::SelectIndexHinted { .. } |
+                Command::DeclareCursor { .. } | Command::Fetch { .. } | Command::CopyTo { .. } => Self::Read,
+            Command::InsertInto { .. } | Command::Update { .. } | Command::Delete { .. } | Command::Undelete { .. } |
+                Command::CopyFrom { .. } | Command::Returning { .. } | Command::Upsert { .. } => Self::Write,
+            Command::CreateTable { .. } | Command::DropTable { .. } | Command::RenameTable { .. } |
+                Command::RenameTableColumn { .. } | Command::AddTableColumn { .. } | Command::AddColumnConstraint { .. } |
+                Command::DropColumnConstraint { .. } | Command::DropNamedConstraint { .. } | Command::DropTableColumn { .. } |
+                Command::SetTableOptions { .. } |
+                Command::CreateIndex { .. } | Command::DropIndex { .. } | Command::VacuumTable { .. } |
+                Command::CreateSequence { .. } | Command::DropSequence { .. } => Self::Schema,
+            Command::CreateUser { .. } | Command::DropUser { .. } | Command::Grant { .. } | Command::Revoke { .. } => Self::Account,
+            Command::SetStatementTimeout { .. } | Command::SetRandomSeed { .. } | Command::CloseCursor { .. } |
+                Command::CreateMacro { .. } | Command::Void => Self::Session,
+        }
+    }
+}
+
+/// Decides which statements can be executed through a database connection, it is checked before
+/// a statement is executed, see `Database::set_statement_policy`. Unlike users, policy is not stored
+/// in database file and is set by application which embeds database.
+pub struct StatementPolicy {
+    // policy is sent to other threads along with database, see `SharedDatabase`
+    allows: Box<dyn Fn(&Command) -> bool + Send>,
+}
+
+impl fmt::Debug for StatementPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StatementPolicy").finish_non_exhaustive()
+    }
+}
+
+impl StatementPolicy {
+    /// Policy which allows statements for which `allows` returns true
+    pub fn new(allows: impl Fn(&Command) -> bool + Send + 'static) -> StatementPolicy {
+        StatementPolicy { allows: Box::new(allows) }
+    }
+
+    pub fn allow_kinds(kinds: &[StatementKind]) -> StatementPolicy {
+        let kinds = kinds.to_vec();
+        Self::new(move |command| kinds.contains(&StatementKind::of(command)))
+    }
+
+    /// Allows only statements which do not change database
+    pub fn read_only() -> StatementPolicy {
+        Self::allow_kinds(&[StatementKind::Read, StatementKind::Session])
+    }
+
+    pub fn allows(&self, command: &Command) -> bool {
+        (self.allows)(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::SqlValue;
+
+    #[test]
+    fn user_schema_line() {
+        let mut user = User::new("alice");
+        assert_eq!(user.to_schema_line(), "alice");
+        user.grant(Privilege::Insert, "orders");
+        user.grant(Privilege::Select, "users");
+        user.grant(Privilege::Select, "orders");
+        assert_eq!(user.to_schema_line(), "alice SELECT orders, SELECT users, INSERT orders");
+        assert_eq!(User::from_schema_line(&user.to_schema_line()), Some(user.clone()));
+        assert_eq!(User::from_schema_line("alice"), Some(User::new("alice")));
+        assert_eq!(User::from_schema_line("alice READ users"), None);
+
+        assert!(user.rename_table("orders", "purchases"));
+        assert!(user.is_granted(Privilege::Insert, "purchases"));
+        assert!(!user.is_granted(Privilege::Select, "orders"));
+        assert!(user.forget_table("users"));
+        assert!(!user.forget_table("users"));
+        assert_eq!(user.to_schema_line(), "alice SELECT purchases, INSERT purchases");
+    }
+
+    #[test]
+    fn command_privileges() {
+        let users = SqlValue::Identificator("users".to_string());
+        let select = Command::Select {
+            table_name: users.clone(),
+            column_names: vec![],
+            where_clause: Some(Condition::exists(SqlValue::Identificator("orders".to_string()), None)),
+        };
+        assert_eq!(required_privileges(&select),
+                   Some(vec![(Privilege::Select, "users".to_string()), (Privilege::Select, "orders".to_string())]));
+        assert_eq!(required_privileges(&Command::Delete { table_name: users.clone(), where_clause: None }),
+                   Some(vec![(Privilege::Delete, "users".to_string())]));
+        assert_eq!(required_privileges(&Command::DropTable { table_name: users }), None);
+    }
+
+    #[test]
+    fn statement_policy() {
+        let users = SqlValue::Identificator("users".to_string());
+        let select = Command::Select { table_name: users.clone(), column_names: vec![], where_clause: None };
+        let delete = Command::Delete { table_name: users.clone(), where_clause: None };
+        let vacuum = Command::VacuumTable { table_name: users };
+
+        let read_only = StatementPolicy::read_only();
+        assert!(read_only.allows(&select));
+        assert!(read_only.allows(&Command::Void));
+        assert!(!read_only.allows(&delete));
+        assert!(!read_only.allows(&vacuum));
+
+        let no_vacuum = StatementPolicy::new(|command| !matches!(command, Command::VacuumTable { .. }));
+        assert!(no_vacuum.allows(&delete));
+        assert!(!no_vacuum.allows(&vacuum));
+    }
+}
@@ -24,7 +24,7 @@ impl BinaryCondition {
         let right = Self::build_row_check_value(self.right_value, table_name, column_names)?;
 
         Ok(
-            RowCheck {
+            RowCheck::Compare {
                 operator: self.operator,
                 left,
                 right,
@@ -32,6 +32,67 @@ impl BinaryCondition {
         )
     }
 
+    /// Compiles condition over rows combined from several tables, combined row holds columns
+    /// of all tables one after another in the given order. Columns can be qualified with table name,
+    /// unqualified column should be present in one of the tables only.
+    pub fn compile_for_tables(self, tables: &[(&str, &[String])]) -> Result<RowCheck, TableError> {
+        let left = Self::build_joined_row_check_value(self.left_value, tables)?;
+        let right = Self::build_joined_row_check_value(self.right_value, tables)?;
+
+        Ok(
+            RowCheck::Compare {
+                operator: self.operator,
+                left,
+                right,
+            }
+        )
+    }
+
+    fn build_joined_row_check_value(value: SqlValue, tables: &[(&str, &[String])]) -> Result<RowCheckValue, TableError> {
+        match value {
+            SqlValue::Identificator(column_string) =>
+                Ok(RowCheckValue::TableColumn(Self::joined_column_number(&column_string, tables)?)),
+            SqlValue::Function(function_call) => {
+                let arguments = function_call.arguments.into_iter()
+                    .map(|argument| Self::build_joined_row_check_value(argument, tables))
+                    .collect::<Result<Vec<RowCheckValue>, TableError>>()?;
+                Ok(RowCheckValue::Function { function: function_call.function, arguments })
+            },
+            _ => Ok(RowCheckValue::Static(value)),
+        }
+    }
+
+    /// Number of column in a row combined from given tables
+    pub fn joined_column_number(column_string: &str, tables: &[(&str, &[String])]) -> Result<usize, TableError> {
+        let mut matching_column_numbers = vec![];
+        let mut offset = 0;
+        for (table_name, column_names) in tables {
+            let unqualified_name = column_string.strip_prefix(table_name)
+                .and_then(|name| name.strip_prefix('.'));
+            for (i, column_name) in column_names.iter().enumerate() {
+                if column_name == column_string || unqualified_name == Some(column_name) {
+                    matching_column_numbers.push(offset + i);
+                }
+            }
+            offset += column_names.len();
+        }
+
+        let joined_table_name = || tables.iter()
+            .map(|(table_name, _)| *table_name)
+            .collect::<Vec<&str>>()
+            .join(", ");
+
+        match matching_column_numbers[..] {
+            [column_number] => Ok(column_number),
+            [] => match column_string.split_once('.') {
+                Some((qualifier, _)) if !tables.iter().any(|(table_name, _)| *table_name == qualifier) =>
+                    Err(TableError::UnknownTableQualifier { table_name: joined_table_name(), qualifier: qualifier.to_string() }),
+                _ => Err(TableError::ColumnNotExist { table_name: joined_table_name(), column_name: column_string.to_string() }),
+            },
+            _ => Err(TableError::AmbiguousColumnName(column_string.to_string())),
+        }
+    }
+
     pub fn build_row_check_value(value: SqlValue, table_name: &str, column_names: &[String]) -> Result<RowCheckValue, TableError> {
         match value {
             SqlValue::Identificator(column_string) => {
@@ -69,6 +130,12 @@ impl BinaryCondition {
                     })
                 }
             },
+            SqlValue::Function(function_call) => {
+                let arguments = function_call.arguments.into_iter()
+                    .map(|argument| Self::build_row_check_value(argument, table_name, column_names))
+                    .collect::<Result<Vec<RowCheckValue>, TableError>>()?;
+                Ok(RowCheckValue::Function { function: function_call.function, arguments })
+            },
             _ => Ok(RowCheckValue::Static(value)),
         }
     }
@@ -7,8 +7,7 @@ use crate::lexer::SqlValue;
 pub enum CmpError {
     CannotCompareWithInteger(SqlValue),
     CannotCompareWithFloat(SqlValue),
-    NonEqualityComparisonWithStrings { operator: CmpOperator, lvalue: String, rvalue: String },
-    OperatorNotApplicable { operator: CmpOperator, lvalue: SqlValue, rvalue: SqlValue },
+    FunctionNotEvaluated(SqlValue),
 }
 
 impl fmt::Display for CmpError {
@@ -16,12 +15,7 @@ impl fmt::Display for CmpError {
         let message = match self {
             Self::CannotCompareWithInteger(sql_value) => format!("cannot compare '{}' with integer", sql_value),
             Self::CannotCompareWithFloat(sql_value) => format!("cannot compare '{}' with float", sql_value),
-            Self::OperatorNotApplicable { operator, lvalue, rvalue } =>
-                format!("operator '{}' cannot be applied to values '{}' and {}",
-                        operator, lvalue, rvalue),
-            Self::NonEqualityComparisonWithStrings { operator, lvalue, rvalue } =>
-                format!("non-equality operator '{}' cannot be applied to strings '{}' and {}, only '=' or '<>' can be used",
-                        operator, lvalue, rvalue),
+            Self::FunctionNotEvaluated(sql_value) => format!("function call '{}' is not evaluated and cannot be compared", sql_value),
         };
 
         write!(f, "{}", message)
@@ -63,6 +57,18 @@ impl CmpOperator {
         }
     }
 
+    /// Returns operator which gives the same result when operands are swapped,
+    /// i.e. `a < b` is the same as `b > a`.
+    pub fn flip(&self) -> CmpOperator {
+        match self {
+            Self::Less => Self::Greater,
+            Self::Greater => Self::Less,
+            Self::LessEquals => Self::GreaterEquals,
+            Self::GreaterEquals => Self::LessEquals,
+            Self::Equals | Self::NotEquals | Self::IsNull => *self,
+        }
+    }
+
     pub fn apply_cmp(&self, left: &SqlValue, right: &SqlValue) -> Result<bool, CmpError> {
         match left {
             SqlValue::Integer(l_int) => self.cmp_int_to_value(*l_int, right),
@@ -70,6 +76,7 @@ impl CmpOperator {
             SqlValue::String(ref l_string) | SqlValue::Identificator(ref l_string) =>
                 self.cmp_string_to_value(l_string, right),
             SqlValue::Null => Ok(false),
+            SqlValue::Function(_) => Err(CmpError::FunctionNotEvaluated(left.clone())),
         }
     }
 
@@ -89,31 +96,17 @@ impl CmpOperator {
         }
     }
 
+    // strings are ordered lexicographically by unicode code points, which is the same as
+    // comparing their utf-8 bytes, so "B" < "a" and there is no locale-aware collation yet
     fn cmp_string_to_value(&self, l_string: &str, r_value: &SqlValue) -> Result<bool, CmpError> {
-        match self {
-            Self::Equals | Self::NotEquals => {
-                match r_value {
-                    SqlValue::Integer(_) =>
-                        Err(CmpError::CannotCompareWithInteger(SqlValue::String(l_string.to_string()))),
-                    SqlValue::Float(_) =>
-                        Err(CmpError::CannotCompareWithFloat(SqlValue::String(l_string.to_string()))),
-                    SqlValue::String(ref r_string) | SqlValue::Identificator(ref r_string) => self.cmp_strings(l_string, r_string),
-                    SqlValue::Null => Ok(false),
-                }
-            },
-            _ => Err(CmpError::OperatorNotApplicable {
-                    operator: *self,
-                    lvalue: SqlValue::String(l_string.to_string()),
-                    rvalue: r_value.clone(),
-                })
-        }
-    }
-
-    fn cmp_strings(&self, left: &str, right: &str) -> Result<bool, CmpError> {
-        match self {
-            Self::Equals => Ok(left == right),
-            Self::NotEquals => Ok(left != right),
-            _ => Err(CmpError::NonEqualityComparisonWithStrings { operator: *self, lvalue: left.to_string(), rvalue: right.to_string() })
+        match r_value {
+            SqlValue::Integer(_) =>
+                Err(CmpError::CannotCompareWithInteger(SqlValue::String(l_string.to_string()))),
+            SqlValue::Float(_) =>
+                Err(CmpError::CannotCompareWithFloat(SqlValue::String(l_string.to_string()))),
+            SqlValue::String(ref r_string) | SqlValue::Identificator(ref r_string) => Ok(self.cmp_ord(l_string, r_string.as_str())),
+            SqlValue::Null => Ok(false),
+            SqlValue::Function(_) => Err(CmpError::FunctionNotEvaluated(r_value.clone())),
         }
     }
 
@@ -206,4 +199,22 @@ mod tests {
         assert_eq!(CmpOperator::IsNull.apply(&left, &right).unwrap(), false);
         assert_eq!(CmpOperator::IsNull.apply(&right, &left).unwrap(), true);
     }
+
+    #[test]
+    fn sql_string_to_sql_string() {
+        let left = SqlValue::String("alice".to_string());
+        let right = SqlValue::String("bob".to_string());
+
+        assert_eq!(CmpOperator::Less.apply(&left, &right).unwrap(), true);
+        assert_eq!(CmpOperator::Greater.apply(&left, &right).unwrap(), false);
+        assert_eq!(CmpOperator::Equals.apply(&left, &right).unwrap(), false);
+        assert_eq!(CmpOperator::NotEquals.apply(&left, &right).unwrap(), true);
+        assert_eq!(CmpOperator::LessEquals.apply(&left, &right).unwrap(), true);
+        assert_eq!(CmpOperator::GreaterEquals.apply(&left, &right).unwrap(), false);
+
+        let left = SqlValue::String("Bob".to_string());
+        assert_eq!(CmpOperator::Less.apply(&left, &right).unwrap(), true);
+        assert_eq!(CmpOperator::Less.apply(&right, &SqlValue::String("bobby".to_string())).unwrap(), true);
+        assert_eq!(CmpOperator::Greater.apply(&right, &SqlValue::Null).unwrap(), false);
+    }
 }
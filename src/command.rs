@@ -1,50 +1,141 @@
-use crate::table::{ColumnType, Constraint};
-use crate::lexer::SqlValue;
-use crate::binary_condition::BinaryCondition;
+use crate::table::{ColumnType, Constraint, TableOptions};
+use crate::lexer::{SqlValue, Token};
+use crate::condition::Condition;
+use crate::access::Privilege;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SelectColumnName {
     Name(SqlValue),
-    AllColumns
+    AllColumns,
+    TableAllColumns(SqlValue), // table.*
+    CountAll, // COUNT(*)
+    Count(SqlValue), // COUNT(column), counts only not null values
 }
 
-#[derive(Debug)]
+impl SelectColumnName {
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, Self::CountAll | Self::Count(_))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ColumnDefinition {
     pub name: SqlValue,
     pub kind: ColumnType, // TODO: maybe use token instead, transition to sematic types should be on exec stage?
     pub column_constraints: Vec<Constraint>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub enum InsertValue {
+    Value(SqlValue),
+    NextVal(SqlValue), // holds sequence name, value is taken from sequence on execution
+    Random, // random integer generated on execution
+}
+
+/// What `INSERT ... ON CONFLICT` does when conflict column already has inserted value
+#[derive(Debug, Clone)]
+pub enum ConflictAction {
+    Nothing,
+    Update(Vec<FieldAssignment>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+/// Term of `ORDER BY`, NULLs are placed after other values in ascending order and before
+/// them in descending order, unless `NULLS FIRST` or `NULLS LAST` is given
+#[derive(Debug, Clone)]
+pub struct OrderingTerm {
+    pub key: OrderingKey,
+    pub descending: bool,
+    pub nulls: NullsOrder,
+}
+
+/// What rows are ordered by: values of selected column, or `RANDOM()` number drawn for each row
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderingKey {
+    Column(SqlValue),
+    Random,
+}
+
+/// Index names of `USE INDEX` or `IGNORE INDEX`. Planner considers only used indexes, and picks
+/// them regardless of estimated row count, while ignored ones are never scanned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexHint {
+    Use(Vec<String>),
+    Ignore(Vec<String>),
+}
+
+impl IndexHint {
+    pub fn index_names(&self) -> &[String] {
+        match self {
+            Self::Use(index_names) | Self::Ignore(index_names) => index_names,
+        }
+    }
+
+    pub fn allows(&self, index_name: &str) -> bool {
+        let is_hinted = self.index_names().iter().any(|hinted_name| hinted_name == index_name);
+        matches!(self, Self::Use(_)) == is_hinted
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FetchDirection {
+    Forward,
+    Backward,
+}
+
+impl From<SqlValue> for InsertValue {
+    fn from(value: SqlValue) -> Self {
+        Self::Value(value)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct FieldAssignment {
     pub column_name: String,
     pub value: SqlValue,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Command {
     InsertInto {
         table_name: SqlValue,
         column_names: Option<Vec<SqlValue>>,
-        values: Vec<SqlValue>,
+        values: Vec<InsertValue>,
     },
     Select {
         table_name: SqlValue,
         column_names: Vec<SelectColumnName>,
-        where_clause: Option<BinaryCondition>,
+        where_clause: Option<Condition>,
+    },
+    SelectCrossJoin {
+        table_names: Vec<SqlValue>,
+        column_names: Vec<SelectColumnName>,
+        where_clause: Option<Condition>,
     },
     Update {
         table_name: SqlValue,
         field_assignments: Vec<FieldAssignment>,
-        where_clause: Option<BinaryCondition>,
+        where_clause: Option<Condition>,
     },
     Delete {
         table_name: SqlValue,
-        where_clause: Option<BinaryCondition>,
+        where_clause: Option<Condition>,
+    },
+    Undelete {
+        table_name: SqlValue,
+        where_clause: Option<Condition>,
     },
     CreateTable {
         table_name: SqlValue,
         columns: Vec<ColumnDefinition>,
+        options: TableOptions,
+        /// Statement does nothing if table already exists, even if its columns are different
+        if_not_exists: bool,
     },
     DropTable {
         table_name: SqlValue,
@@ -72,10 +163,20 @@ pub enum Command {
         column_name: SqlValue,
         constraint: Constraint,
     },
+    /// Drops named constraint regardless of column it is kept with
+    DropNamedConstraint {
+        table_name: SqlValue,
+        constraint_name: SqlValue,
+    },
     DropTableColumn {
         table_name: SqlValue,
         column_name: SqlValue,
     },
+    /// `ALTER TABLE ... SET (...)` and `UNSET (...)`, each option name is given with its new value
+    SetTableOptions {
+        table_name: SqlValue,
+        options: Vec<(String, bool)>,
+    },
     CreateIndex {
         index_name: SqlValue,
         table_name: SqlValue,
@@ -88,19 +189,168 @@ pub enum Command {
     VacuumTable {
         table_name: SqlValue,
     },
+    CheckTable {
+        table_name: SqlValue,
+    },
+    ShowStorage {
+        table_name: SqlValue,
+    },
+    CreateSequence {
+        sequence_name: SqlValue,
+    },
+    DropSequence {
+        sequence_name: SqlValue,
+    },
+    SelectChanges {
+        table_name: SqlValue,
+        since_lsn: u64,
+    },
+    SelectSeries {
+        column_names: Vec<SelectColumnName>,
+        start: i64,
+        stop: i64,
+        step: i64,
+        where_clause: Option<Condition>,
+    },
+    SetStatementTimeout {
+        milliseconds: u64,
+    },
+    SetRandomSeed {
+        seed: u64,
+    },
+    CreateUser {
+        user_name: SqlValue,
+    },
+    DropUser {
+        user_name: SqlValue,
+    },
+    Grant {
+        privileges: Vec<Privilege>,
+        table_name: SqlValue,
+        user_name: SqlValue,
+    },
+    Revoke {
+        privileges: Vec<Privilege>,
+        table_name: SqlValue,
+        user_name: SqlValue,
+    },
+    Upsert {
+        insert: Box<Command>,
+        conflict_column_name: SqlValue,
+        action: ConflictAction,
+    },
+    /// `INSERT`, `UPDATE` or `DELETE` which returns affected rows
+    Returning {
+        command: Box<Command>,
+        column_names: Vec<SelectColumnName>,
+    },
+    SelectOrdered {
+        select: Box<Command>,
+        order_by: Vec<OrderingTerm>,
+    },
+    /// Single table select which is planned with given indexes only
+    SelectIndexHinted {
+        select: Box<Command>,
+        index_hint: IndexHint,
+    },
+    /// Select which returns at most `limit` rows after skipping `offset` ones
+    SelectLimited {
+        select: Box<Command>,
+        limit: Option<usize>,
+        offset: usize,
+    },
+    CopyFrom {
+        table_name: SqlValue,
+        file_path: String,
+    },
+    CopyTo {
+        select: Box<Command>,
+        file_path: String,
+    },
+    DeclareCursor {
+        cursor_name: SqlValue,
+        select: Box<Command>,
+    },
+    Fetch {
+        cursor_name: SqlValue,
+        direction: FetchDirection,
+        count: Option<usize>, // None fetches all remaining rows
+    },
+    CloseCursor {
+        cursor_name: SqlValue,
+    },
+    /// Body tokens replace macro calls in the following statements of the session
+    CreateMacro {
+        macro_name: SqlValue,
+        parameter_names: Vec<SqlValue>,
+        body: Vec<Token>,
+    },
     Void,
 }
 
+impl Command {
+    /// Statement keyword, used in error messages
+    pub fn statement_name(&self) -> &'static str {
+        match self {
+            Self::InsertInto { .. } => "INSERT",
+            Self::Select { .. } | Self::SelectCrossJoin { .. } | Self::SelectChanges { .. } | Self::SelectSeries { .. } |
+                Self::SelectOrdered { .. } | Self::SelectLimited { .. } | Self::SelectIndexHinted { .. } => "SELECT",
+            Self::Update { .. } => "UPDATE",
+            Self::Delete { .. } => "DELETE",
+            Self::Undelete { .. } => "UNDELETE",
+            Self::CreateTable { .. } => "CREATE TABLE",
+            Self::DropTable { .. } => "DROP TABLE",
+            Self::RenameTable { .. } | Self::RenameTableColumn { .. } | Self::AddTableColumn { .. } |
+                Self::AddColumnConstraint { .. } | Self::DropColumnConstraint { .. } | Self::DropNamedConstraint { .. } |
+                Self::DropTableColumn { .. } | Self::SetTableOptions { .. } => "ALTER TABLE",
+            Self::CreateIndex { .. } => "CREATE INDEX",
+            Self::DropIndex { .. } => "DROP INDEX",
+            Self::VacuumTable { .. } => "VACUUM",
+            Self::CheckTable { .. } => "CHECK TABLE",
+            Self::ShowStorage { .. } => "SHOW STORAGE",
+            Self::CreateSequence { .. } => "CREATE SEQUENCE",
+            Self::DropSequence { .. } => "DROP SEQUENCE",
+            Self::SetStatementTimeout { .. } | Self::SetRandomSeed { .. } => "SET",
+            Self::CreateUser { .. } => "CREATE USER",
+            Self::DropUser { .. } => "DROP USER",
+            Self::Grant { .. } => "GRANT",
+            Self::Revoke { .. } => "REVOKE",
+            Self::DeclareCursor { .. } => "DECLARE CURSOR",
+            Self::Fetch { .. } => "FETCH",
+            Self::CloseCursor { .. } => "CLOSE",
+            Self::CreateMacro { .. } => "CREATE MACRO",
+            Self::CopyFrom { .. } | Self::CopyTo { .. } => "COPY",
+            Self::Returning { command, .. } => command.statement_name(),
+            Self::Upsert { .. } => "INSERT",
+            Self::Void => "empty statement",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::fs;
+    use std::sync::{Arc, Mutex};
 
-    use crate::database::Database;
+    use crate::database::{Database, SizeQuota};
     use crate::cmp_operator::CmpOperator;
+    use crate::binary_condition::BinaryCondition;
+    use crate::condition::Condition;
     use crate::temp_file::TempFile;
     use crate::pager::page::PAGE_SIZE;
+    use crate::execution_error::ExecutionError;
+    use crate::query_result::{Pagination, QueryResult};
+    use crate::meta_command_error::MetaCommandError;
+    use crate::rename_journal::RenameJournal;
+    use crate::table::error::TableError;
+    use crate::table::ForeignKey;
+    use crate::warning::Warning;
+    use crate::access::StatementPolicy;
+    use crate::schema_hook::{SchemaChange, SchemaChangeKind};
+    use crate::function::{FunctionCall, FunctionError, ScalarFunction};
+    use crate::{lexer, parser};
 
     #[test]
     fn create_and_drop_table() {
@@ -124,6 +374,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
 
         assert!(database.execute(create_table).is_ok());
@@ -135,6 +387,33 @@ mod tests {
         assert!(database.execute(drop_table).is_ok());
     }
 
+    #[test]
+    fn create_table_if_not_exists() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let create_table = |if_not_exists| Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists,
+        };
+        let schema_changes = Arc::new(Mutex::new(0));
+        let hook_schema_changes = schema_changes.clone();
+        database.add_schema_hook(move |_| *hook_schema_changes.lock().unwrap() += 1);
+
+        assert!(matches!(database.execute(create_table(false)), Err(ExecutionError::TableAlreadyExist(_))));
+        assert!(database.execute(create_table(true)).unwrap().is_none());
+        assert_eq!(database.catalog().tables[0].columns[0].name, "id");
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        assert_eq!(*schema_changes.lock().unwrap(), 0);
+
+        database.execute(Command::DropTable { table_name: SqlValue::Identificator("users".to_string()) }).unwrap();
+        database.execute(create_table(true)).unwrap();
+        assert_eq!(database.catalog().tables[0].columns[0].name, "name");
+        assert_eq!(*schema_changes.lock().unwrap(), 2);
+    }
+
     #[test]
     fn drop_non_existing_table() {
         let (_db_file, mut database) = open_test_database();
@@ -168,13 +447,15 @@ mod tests {
                     column_constraints: vec![Constraint::Default(SqlValue::String("Doe".to_string()))],
                 }
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
         database.execute(create_table).unwrap();
 
         let insert_into_table = Command::InsertInto {
             table_name: SqlValue::Identificator("users".to_string()),
             column_names: Some(vec![SqlValue::Identificator("id".to_string()), SqlValue::String("name".to_string())]),
-            values: vec![SqlValue::Integer(1), SqlValue::Identificator("John".to_string())],
+            values: vec![SqlValue::Integer(1).into(), SqlValue::Identificator("John".to_string()).into()],
         };
         let insert_into_table_result = database.execute(insert_into_table);
         assert!(insert_into_table_result.is_ok());
@@ -182,7 +463,7 @@ mod tests {
         let insert_into_table = Command::InsertInto {
             table_name: SqlValue::Identificator("users".to_string()),
             column_names: Some(vec![SqlValue::String("name".to_string())]),
-            values: vec![SqlValue::Identificator("John".to_string())],
+            values: vec![SqlValue::Identificator("John".to_string()).into()],
         };
         let insert_into_table_result = database.execute(insert_into_table);
         assert!(insert_into_table_result.is_err());
@@ -192,7 +473,7 @@ mod tests {
         let insert_into_table = Command::InsertInto {
             table_name: SqlValue::Identificator("users".to_string()),
             column_names: Some(vec![SqlValue::Identificator("id".to_string()), SqlValue::String("name".to_string())]),
-            values: vec![SqlValue::Integer(0), SqlValue::Identificator("John".to_string())],
+            values: vec![SqlValue::Integer(0).into(), SqlValue::Identificator("John".to_string()).into()],
         };
         let insert_into_table_result = database.execute(insert_into_table);
         assert!(insert_into_table_result.is_err());
@@ -206,7 +487,7 @@ mod tests {
                 left_value: SqlValue::Integer(1),
                 right_value: SqlValue::Identificator("users.id".to_string()),
                 operator: CmpOperator::Equals,
-            }),
+            }.into()),
         };
         let select_result = database.execute(select_from_table);
         assert!(matches!(select_result, Ok(Some(_))));
@@ -242,13 +523,15 @@ mod tests {
                     column_constraints: vec![],
                 }
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
         database.execute(create_table).unwrap();
 
         let insert_into_table = Command::InsertInto {
             table_name: SqlValue::Identificator("users".to_string()),
             column_names: Some(vec![SqlValue::Identificator("id".to_string()), SqlValue::String("name".to_string())]),
-            values: vec![SqlValue::Integer(1), SqlValue::Identificator("John".to_string())],
+            values: vec![SqlValue::Integer(1).into(), SqlValue::Identificator("John".to_string()).into()],
         };
         let insert_into_table_result = database.execute(insert_into_table);
         assert!(insert_into_table_result.is_ok());
@@ -293,13 +576,15 @@ mod tests {
                     column_constraints: vec![],
                 }
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
         database.execute(create_table).unwrap();
 
         let insert_into_table = Command::InsertInto {
             table_name: SqlValue::Identificator("users".to_string()),
             column_names: Some(vec![SqlValue::Identificator("id".to_string()), SqlValue::Identificator("name".to_string())]),
-            values: vec![SqlValue::Integer(1), SqlValue::Identificator("John".to_string())],
+            values: vec![SqlValue::Integer(1).into(), SqlValue::Identificator("John".to_string()).into()],
         };
         let insert_into_table_result = database.execute(insert_into_table);
         assert!(insert_into_table_result.is_ok());
@@ -310,7 +595,7 @@ mod tests {
                 left_value: SqlValue::String("John".to_string()),
                 right_value: SqlValue::Identificator("name".to_string()),
                 operator: CmpOperator::Equals,
-            }),
+            }.into()),
         };
         let delete_from_table_result = database.execute(delete_from_table);
         assert!(delete_from_table_result.is_ok());
@@ -340,6 +625,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
 
         assert!(database.execute(create_table).is_ok());
@@ -368,6 +655,54 @@ mod tests {
         assert!(database.execute(drop_table_constraint).is_ok());
     }
 
+    #[test]
+    fn named_constraints() {
+        let (db_file, mut database) = open_test_database();
+        let positive_age = Constraint::NamedCheck("positive_age".to_string(), BinaryCondition {
+            left_value: SqlValue::Identificator("age".to_string()),
+            right_value: SqlValue::Integer(0),
+            operator: CmpOperator::Greater,
+        });
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("age".to_string()), kind: ColumnType::Integer, column_constraints: vec![positive_age.clone()] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        let insert_age = |age| Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Integer(1).into(), SqlValue::Integer(age).into()],
+        };
+        assert!(matches!(database.execute(insert_age(-1)), Err(ExecutionError::TableError(TableError::CheckViolation { .. }))));
+
+        let add_same_name = Command::AddColumnConstraint {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_name: SqlValue::Identificator("id".to_string()),
+            constraint: Constraint::NamedCheck("positive_age".to_string(), BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(0),
+                operator: CmpOperator::Greater,
+            }),
+        };
+        assert!(matches!(database.execute(add_same_name), Err(ExecutionError::TableError(TableError::ConstraintNameAlreadyExists { .. }))));
+
+        database.close().unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+        assert!(database.execute(insert_age(-1)).is_err());
+        let drop_constraint = Command::DropNamedConstraint {
+            table_name: SqlValue::Identificator("users".to_string()),
+            constraint_name: SqlValue::Identificator("positive_age".to_string()),
+        };
+        database.execute(drop_constraint.clone()).unwrap();
+        database.execute(insert_age(-1)).unwrap();
+        assert!(matches!(database.execute(drop_constraint), Err(ExecutionError::TableError(TableError::ConstraintNameNotExists { .. }))));
+    }
+
     #[test]
     fn create_table_and_rename_column() {
         let (_db_file, mut database) = open_test_database();
@@ -380,6 +715,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
 
         assert!(database.execute(create_table).is_ok());
@@ -404,7 +741,9 @@ mod tests {
                     kind: ColumnType::Integer,
                     column_constraints: vec![Constraint::NotNull],
                 },
-            ]
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
 
         assert!(database.execute(create_table).is_ok());
@@ -438,6 +777,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
 
         assert!(database.execute(create_table).is_ok());
@@ -467,6 +808,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
 
         database.execute(create_table).expect("database create table statement should be successful");
@@ -502,6 +845,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
 
         database.execute(create_table).expect("database create table statement should be successful");
@@ -541,6 +886,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
         // row size is 1 + 8 + 256 = 265 bytes, i.e. we can fit 15 rows per page
 
@@ -550,7 +897,7 @@ mod tests {
             let insert_into_table = Command::InsertInto {
                 table_name: SqlValue::Identificator("users".to_string()),
                 column_names: Some(vec![SqlValue::Identificator("id".to_string())]),
-                values: vec![SqlValue::Integer(id)],
+                values: vec![SqlValue::Integer(id).into()],
             };
             let insert_into_table_result = database.execute(insert_into_table);
             assert!(insert_into_table_result.is_ok());
@@ -564,7 +911,7 @@ mod tests {
                 left_value: SqlValue::Identificator("id".to_string()),
                 right_value: SqlValue::Integer(1),
                 operator: CmpOperator::Equals,
-            }),
+            }.into()),
         };
         let delete_from_table_result = database.execute(delete_from_table);
         assert!(delete_from_table_result.is_ok());
@@ -582,7 +929,7 @@ mod tests {
                 left_value: SqlValue::Identificator("id".to_string()),
                 right_value: SqlValue::Integer(15),
                 operator: CmpOperator::LessEquals,
-            }),
+            }.into()),
         };
         let delete_from_table_result = database.execute(delete_from_table);
         assert!(delete_from_table_result.is_ok());
@@ -591,6 +938,17 @@ mod tests {
         };
         assert!(database.execute(vacuum_table).is_ok());
         assert_eq!(fs::metadata(users_table_path.as_path()).unwrap().len(), PAGE_SIZE as u64);
+
+        let delete_from_table = Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: None,
+        };
+        assert!(database.execute(delete_from_table).is_ok());
+        let vacuum_table = Command::VacuumTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+        };
+        assert!(database.execute(vacuum_table).is_ok());
+        assert_eq!(fs::metadata(users_table_path.as_path()).unwrap().len(), 0);
     }
 
     #[test]
@@ -610,6 +968,8 @@ mod tests {
                     column_constraints: vec![],
                 },
             ],
+            options: TableOptions::default(),
+            if_not_exists: false,
         };
         // row size is 1 + 8 + 256 = 265 bytes, i.e. we can fit 15 rows per page
         database.execute(create_table).expect("database create table statement should be successful");
@@ -625,7 +985,7 @@ mod tests {
             let insert_into_table = Command::InsertInto {
                 table_name: SqlValue::Identificator("users".to_string()),
                 column_names: Some(vec![SqlValue::Identificator("id".to_string())]),
-                values: vec![SqlValue::Integer(id)],
+                values: vec![SqlValue::Integer(id).into()],
             };
             let insert_into_table_result = database.execute(insert_into_table);
             insert_into_table_result.expect("insert into table statement should be executed successfuly");
@@ -638,7 +998,7 @@ mod tests {
                 left_value: SqlValue::Integer(10),
                 right_value: SqlValue::Identificator("users.id".to_string()),
                 operator: CmpOperator::Equals,
-            }),
+            }.into()),
         };
         let select_result = database.execute(select_from_table);
         assert!(matches!(select_result, Ok(Some(_))));
@@ -650,6 +1010,2273 @@ mod tests {
         assert!(database.execute(drop_table).is_ok());
     }
 
+    #[test]
+    fn execute_batch_and_flush_schema_once() {
+        let (db_file, mut database) = open_test_database();
+
+        let commands = [
+            Command::CreateTable {
+                table_name: SqlValue::Identificator("users".to_string()),
+                columns: vec![ColumnDefinition {
+                    name: SqlValue::Identificator("id".to_string()),
+                    kind: ColumnType::Integer,
+                    column_constraints: vec![],
+                }],
+                options: TableOptions::default(),
+                if_not_exists: false,
+            },
+            Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(1).into()],
+            },
+            Command::InsertInto {
+                table_name: SqlValue::Identificator("nonexistent".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(1).into()],
+            },
+            Command::RenameTableColumn {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_name: SqlValue::Identificator("id".to_string()),
+                new_column_name: SqlValue::Identificator("user_id".to_string()),
+            },
+        ];
+
+        let results = database.execute_batch(commands);
+
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[0], Ok(None)));
+        assert!(matches!(results[1], Ok(None)));
+        assert!(matches!(results[2], Err(ExecutionError::TableNotExist(_))));
+        assert!(matches!(results[3], Ok(None)));
+
+        let schema = fs::read_to_string(db_file.path()).unwrap();
+        assert!(schema.contains("users 1 user_id INT;"));
+    }
+
+    #[test]
+    fn soft_delete_undelete_and_vacuum() {
+        let (db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition {
+                    name: SqlValue::Identificator("id".to_string()),
+                    kind: ColumnType::Integer,
+                    column_constraints: vec![],
+                },
+            ],
+            options: TableOptions { soft_delete: true, ..TableOptions::default() },
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+
+        for id in 1..=3 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let id_less_than_three = BinaryCondition {
+            left_value: SqlValue::Identificator("id".to_string()),
+            right_value: SqlValue::Integer(3),
+            operator: CmpOperator::Less,
+        };
+        let delete_from_table = Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(id_less_than_three.into()),
+        };
+        database.execute(delete_from_table).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 1);
+
+        // tombstones should survive reconnection
+        database.close().unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+
+        let undelete_from_table = Command::Undelete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(1),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        database.execute(undelete_from_table).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 2);
+
+        // table is not rebuilt while it has soft deleted row
+        let add_column = Command::AddTableColumn {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_definition: ColumnDefinition {
+                name: SqlValue::Identificator("age".to_string()),
+                kind: ColumnType::Integer,
+                column_constraints: vec![],
+            },
+        };
+        assert!(matches!(
+                database.execute(add_column),
+                Err(ExecutionError::TableError(TableError::SoftDeletedRowsNotMovable(_)))
+                ));
+
+        let vacuum_table = Command::VacuumTable { table_name: SqlValue::Identificator("users".to_string()) };
+        database.execute(vacuum_table).unwrap();
+
+        let undelete_from_table = Command::Undelete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: None,
+        };
+        database.execute(undelete_from_table).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 2);
+    }
+
+    #[test]
+    fn undelete_without_soft_delete() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition {
+                    name: SqlValue::Identificator("id".to_string()),
+                    kind: ColumnType::Integer,
+                    column_constraints: vec![],
+                },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+
+        let undelete_from_table = Command::Undelete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: None,
+        };
+        assert!(database.execute(undelete_from_table).is_err());
+    }
+
+    #[test]
+    fn create_sequence_and_insert_next_values() {
+        let (db_file, mut database) = open_test_database();
+        let create_sequence = Command::CreateSequence { sequence_name: SqlValue::Identificator("user_ids".to_string()) };
+        database.execute(create_sequence).unwrap();
+
+        let create_sequence = Command::CreateSequence { sequence_name: SqlValue::Identificator("user_ids".to_string()) };
+        assert!(matches!(database.execute(create_sequence), Err(ExecutionError::SequenceAlreadyExist(_))));
+
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition {
+                    name: SqlValue::Identificator("id".to_string()),
+                    kind: ColumnType::Integer,
+                    column_constraints: vec![],
+                },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+
+        let insert_next_id = || Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![InsertValue::NextVal(SqlValue::Identificator("user_ids".to_string()))],
+        };
+        database.execute(insert_next_id()).unwrap();
+
+        database.close().unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+        database.execute(insert_next_id()).unwrap();
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+        let select_rows = database.execute(select_from_table).unwrap().unwrap();
+        assert_eq!(
+            (0..select_rows.len()).map(|i| select_rows.row_values(i).unwrap().unwrap()).collect::<Vec<_>>(),
+            vec![vec![SqlValue::Integer(1)], vec![SqlValue::Integer(2)]]
+        );
+
+        let drop_sequence = Command::DropSequence { sequence_name: SqlValue::Identificator("user_ids".to_string()) };
+        database.execute(drop_sequence).unwrap();
+        assert!(matches!(database.execute(insert_next_id()), Err(ExecutionError::SequenceNotExist(_))));
+    }
+
+    #[test]
+    fn change_log_records_row_changes() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition {
+                    name: SqlValue::Identificator("id".to_string()),
+                    kind: ColumnType::Integer,
+                    column_constraints: vec![],
+                },
+            ],
+            options: TableOptions { change_log: true, ..TableOptions::default() },
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+
+        let insert_into_table = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Integer(1).into()],
+        };
+        database.execute(insert_into_table).unwrap();
+
+        let update_table = Command::Update {
+            table_name: SqlValue::Identificator("users".to_string()),
+            field_assignments: vec![FieldAssignment { column_name: "id".to_string(), value: SqlValue::Integer(2) }],
+            where_clause: None,
+        };
+        database.execute(update_table).unwrap();
+
+        let delete_from_table = Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: None,
+        };
+        database.execute(delete_from_table).unwrap();
+
+        let select_changes = Command::SelectChanges { table_name: SqlValue::Identificator("users".to_string()), since_lsn: 1 };
+        let changes = database.execute(select_changes).unwrap().unwrap();
+
+        assert_eq!(changes.column_names, vec!["lsn", "operation", "row_id", "old_id", "new_id"]);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes.row_values(0).unwrap().unwrap(),
+            vec![SqlValue::Integer(2), SqlValue::String("UPDATE".to_string()), SqlValue::Integer(0), SqlValue::Integer(1), SqlValue::Integer(2)]
+        );
+        assert_eq!(
+            changes.row_values(1).unwrap().unwrap(),
+            vec![SqlValue::Integer(3), SqlValue::String("DELETE".to_string()), SqlValue::Integer(0), SqlValue::Integer(2), SqlValue::Null]
+        );
+    }
+
+    #[test]
+    fn set_statement_timeout() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        let insert_into_table = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Integer(1).into()],
+        };
+        database.execute(insert_into_table).unwrap();
+
+        database.execute(Command::SetStatementTimeout { milliseconds: 60_000 }).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 1);
+
+        database.execute(Command::SetStatementTimeout { milliseconds: 0 }).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 1);
+    }
+
+    #[test]
+    fn random_values_are_reproducible_with_seed() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+
+        for _ in 0..2 {
+            database.execute(Command::SetRandomSeed { seed: 7 }).unwrap();
+            for _ in 0..2 {
+                let insert_into_table = Command::InsertInto {
+                    table_name: SqlValue::Identificator("users".to_string()),
+                    column_names: None,
+                    values: vec![InsertValue::Random],
+                };
+                database.execute(insert_into_table).unwrap();
+            }
+        }
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+        let result = database.execute(select_from_table).unwrap().unwrap();
+        let ids: Vec<SqlValue> = result.rows.iter()
+            .map(|row| row.get_cell_sql_value(&result.column_types, 0).unwrap())
+            .collect();
+
+        assert_eq!(ids.len(), 4);
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(ids[0..2], ids[2..4]);
+    }
+
+    #[test]
+    fn select_count() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+
+        for (id, name) in [(1, SqlValue::String("john".to_string())), (2, SqlValue::Null), (3, SqlValue::String("pete".to_string()))] {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![InsertValue::Value(SqlValue::Integer(id)), InsertValue::Value(name)],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::CountAll, SelectColumnName::Count(SqlValue::Identificator("users.name".to_string()))],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(1),
+                operator: CmpOperator::Greater,
+            }.into()),
+        };
+        let result = database.execute(select_from_table).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["COUNT(*)".to_string(), "COUNT(name)".to_string()]);
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].get_cell_sql_value(&result.column_types, 0).unwrap(), SqlValue::Integer(2));
+        assert_eq!(result.rows[0].get_cell_sql_value(&result.column_types, 1).unwrap(), SqlValue::Integer(1));
+        // single comparison is counted right in the scan
+        assert_eq!(database.last_statement_stats().rows_scanned, 3);
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::Count(SqlValue::Identificator("name".to_string()))],
+            where_clause: Some(Condition::and(
+                BinaryCondition {
+                    left_value: SqlValue::Identificator("id".to_string()),
+                    right_value: SqlValue::Integer(1),
+                    operator: CmpOperator::Greater,
+                }.into(),
+                BinaryCondition {
+                    left_value: SqlValue::Identificator("id".to_string()),
+                    right_value: SqlValue::Integer(3),
+                    operator: CmpOperator::Less,
+                }.into(),
+            )),
+        };
+        let result = database.execute(select_from_table).unwrap().unwrap();
+        assert_eq!(result.rows[0].get_cell_sql_value(&result.column_types, 0).unwrap(), SqlValue::Integer(0));
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::CountAll, SelectColumnName::Name(SqlValue::Identificator("id".to_string()))],
+            where_clause: None,
+        };
+        assert!(database.execute(select_from_table).is_err());
+    }
+
+    #[test]
+    fn seed_table_respects_constraints() {
+        let (_db_file, mut database) = open_test_database();
+        let check = Constraint::Check(BinaryCondition {
+            left_value: SqlValue::Identificator("id".to_string()),
+            right_value: SqlValue::Integer(500),
+            operator: CmpOperator::Greater,
+        });
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![check] },
+                ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![Constraint::NotNull] },
+                ColumnDefinition { name: SqlValue::Identificator("score".to_string()), kind: ColumnType::Float, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        database.execute(Command::SetRandomSeed { seed: 3 }).unwrap();
+
+        database.seed_table("users", 200).unwrap();
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+        let result = database.execute(select_from_table).unwrap().unwrap();
+        assert_eq!(result.rows.len(), 200);
+        for row in &result.rows {
+            assert!(matches!(row.get_cell_sql_value(&result.column_types, 0).unwrap(), SqlValue::Integer(id) if id > 500));
+            assert!(matches!(row.get_cell_sql_value(&result.column_types, 1).unwrap(), SqlValue::String(_)));
+        }
+        assert!(database.seed_table("people", 1).is_err());
+    }
+
+    #[test]
+    fn checksums_detect_corrupted_rows() {
+        let (db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+            ],
+            options: TableOptions { checksums: true, ..TableOptions::default() },
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for (id, name) in [(1, "john"), (2, "pete"), (3, "anna")] {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into(), SqlValue::String(name.to_string()).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+        let update_table = Command::Update {
+            table_name: SqlValue::Identificator("users".to_string()),
+            field_assignments: vec![FieldAssignment { column_name: "name".to_string(), value: SqlValue::String("jack".to_string()) }],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(1),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        database.execute(update_table).unwrap();
+
+        let check_table = Command::CheckTable { table_name: SqlValue::Identificator("users".to_string()) };
+        assert!(database.execute(check_table).unwrap().unwrap().is_empty());
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+
+        database.close().unwrap();
+        let table_filepath = db_file.temp_dir_path.join("users.table");
+        let table_bytes = fs::read(&table_filepath).unwrap();
+        let name_offset = table_bytes.windows(4).position(|bytes| bytes == b"pete").unwrap();
+        let mut corrupted_bytes = table_bytes.clone();
+        corrupted_bytes[name_offset + 3] = b's';
+        fs::write(&table_filepath, corrupted_bytes).unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+        assert!(matches!(
+                database.execute(select_from_table),
+                Err(ExecutionError::TableError(TableError::RowChecksumMismatch { row_id: 1, .. }))
+                ));
+
+        let check_table = Command::CheckTable { table_name: SqlValue::Identificator("users".to_string()) };
+        let result = database.execute(check_table).unwrap().unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].get_cell_sql_value(&result.column_types, 0).unwrap(), SqlValue::Integer(1));
+    }
+
+    #[test]
+    fn show_storage() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("notes".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("title".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for title in [SqlValue::String("abc".to_string()), SqlValue::Null] {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("notes".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(1).into(), title.into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let show_storage = Command::ShowStorage { table_name: SqlValue::Identificator("notes".to_string()) };
+        let result = database.execute(show_storage).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["row_size", "rows_per_page", "page_count", "row_count", "free_slots", "string_bytes_wasted", "longest_string"]);
+        let stats = result.rows[0].get_sql_values(&result.column_types).unwrap();
+        // null bitmask byte, integer and string cells
+        assert_eq!(stats, [265, 15, 1, 2, 13, 252 + 256, 3].map(SqlValue::Integer));
+    }
+
+    #[test]
+    fn compressed_strings() {
+        let (db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("status".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+            ],
+            options: TableOptions { compression: true, ..TableOptions::default() },
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for id in 0..100 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into(), SqlValue::String(["active", "blocked"][id as usize % 3 / 2].to_string()).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+        let update_table = Command::Update {
+            table_name: SqlValue::Identificator("users".to_string()),
+            field_assignments: vec![FieldAssignment { column_name: "status".to_string(), value: SqlValue::String("deleted".to_string()) }],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("status".to_string()),
+                right_value: SqlValue::String("blocked".to_string()),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        database.execute(update_table).unwrap();
+
+        // uncompressed table needs 7 pages for the same rows
+        let show_storage = Command::ShowStorage { table_name: SqlValue::Identificator("users".to_string()) };
+        let result = database.execute(show_storage).unwrap().unwrap();
+        let stats = result.rows[0].get_sql_values(&result.column_types).unwrap();
+        assert_eq!(stats[1..=3], [368, 1, 100].map(SqlValue::Integer));
+
+        database.close().unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+        let select_deleted = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::Name(SqlValue::Identificator("id".to_string()))],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("status".to_string()),
+                right_value: SqlValue::String("deleted".to_string()),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        let result = database.execute(select_deleted).unwrap().unwrap();
+        assert_eq!(result.len(), 33);
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(2)]);
+    }
+
+    #[test]
+    fn set_table_options() {
+        let (db_file, mut database) = create_users_with_id_index();
+        let users = SqlValue::Identificator("users".to_string());
+        let set_options = |options: &[(&str, bool)]| Command::SetTableOptions {
+            table_name: SqlValue::Identificator("users".to_string()),
+            options: options.iter().map(|(option_name, enabled)| (option_name.to_string(), *enabled)).collect(),
+        };
+        let delete_first_user = Command::Delete {
+            table_name: users.clone(),
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(1),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+
+        database.execute(set_options(&[("soft_delete", true), ("checksums", true)])).unwrap();
+        database.execute(delete_first_user.clone()).unwrap();
+        assert!(matches!(
+                database.execute(set_options(&[("compression", true)])),
+                Err(ExecutionError::TableError(TableError::SoftDeletedRowsNotMovable(_)))
+                ));
+        database.execute(Command::Undelete { table_name: users.clone(), where_clause: None }).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        let corrupted_rows = database.execute(Command::CheckTable { table_name: users.clone() }).unwrap().unwrap();
+        assert!(corrupted_rows.is_empty());
+
+        database.execute(set_options(&[("change_log", true)])).unwrap();
+        database.execute(delete_first_user).unwrap();
+        database.execute(Command::Undelete { table_name: users.clone(), where_clause: None }).unwrap();
+        assert!(matches!(
+                database.execute(set_options(&[("compression", true)])),
+                Err(ExecutionError::TableError(TableError::ChangeLogNotMovable(_)))
+                ));
+        database.execute(set_options(&[("change_log", false)])).unwrap();
+
+        // table is rebuilt with compressed strings, its index is rebuilt too
+        database.execute(set_options(&[("compression", true), ("checksums", false)])).unwrap();
+        assert!(matches!(database.execute(Command::CheckTable { table_name: users.clone() }), Err(ExecutionError::TableError(TableError::ChecksumsDisabled(_)))));
+        database.close().unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+        let users_schema = database.catalog().tables.remove(0);
+        assert_eq!(users_schema.options, TableOptions { soft_delete: true, compression: true, ..TableOptions::default() });
+        assert_eq!(users_schema.indexes, vec![("people_id".to_string(), "id".to_string())]);
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+
+        assert!(matches!(database.execute(set_options(&[("ttl", true)])), Err(ExecutionError::TableError(TableError::TableOptionUnknown(_)))));
+    }
+
+    #[test]
+    fn reload_schema_changed_on_disk() {
+        let (db_file, mut database) = create_users_with_id_index();
+        assert!(!database.schema_changed_on_disk());
+
+        // another connection to the same database file stands for an external tool
+        let mut other_database = Database::from(db_file.file_path.as_path()).unwrap();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("orders".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("user_id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        other_database.execute(create_table).unwrap();
+        other_database.execute(Command::CreateSequence { sequence_name: SqlValue::Identificator("order_ids".to_string()) }).unwrap();
+        other_database.close().unwrap();
+
+        assert!(database.schema_changed_on_disk());
+        assert_eq!(database.reload().unwrap(), vec!["orders".to_string()]);
+        assert!(!database.schema_changed_on_disk());
+        assert_eq!(select_all_count(&mut database, "orders"), 0);
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        assert!(database.catalog().sequences.contains(&"order_ids".to_string()));
+        assert!(database.reload().unwrap().is_empty());
+    }
+
+    #[test]
+    fn size_quota() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let insert_until_error = |database: &mut Database, table_name: &str| {
+            for id in 0.. {
+                let insert_into_table = Command::InsertInto {
+                    table_name: SqlValue::Identificator(table_name.to_string()),
+                    column_names: None,
+                    values: vec![SqlValue::Integer(id).into()],
+                };
+                if let Err(error) = database.execute(insert_into_table) {
+                    return error
+                }
+            }
+            unreachable!()
+        };
+
+        database.set_size_quota(SizeQuota { max_table_size: Some(PAGE_SIZE as u64), max_database_size: None });
+        let error = insert_until_error(&mut database, "users");
+        assert!(matches!(error, ExecutionError::TableError(TableError::SizeQuotaExceeded { max_file_size: 4096, .. })));
+        let rows_per_page = select_all_count(&mut database, "users");
+        assert!(rows_per_page > 3);
+
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("orders".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        database.set_size_quota(SizeQuota { max_table_size: None, max_database_size: Some(2 * PAGE_SIZE as u64) });
+        let error = insert_until_error(&mut database, "orders");
+        assert!(matches!(error, ExecutionError::TableError(TableError::SizeQuotaExceeded { max_file_size: 4096, .. })));
+        assert_eq!(select_all_count(&mut database, "orders"), rows_per_page);
+
+        database.set_size_quota(SizeQuota::default());
+        let insert_into_table = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Integer(100).into()],
+        };
+        database.execute(insert_into_table).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), rows_per_page + 1);
+    }
+
+    #[test]
+    fn string_functions() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for (id, name) in [(1, SqlValue::String("John".to_string())), (2, SqlValue::String("Alexandra".to_string())), (3, SqlValue::Null)] {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into(), name.into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let call = |function, arguments| SqlValue::Function(Box::new(FunctionCall { function, arguments }));
+        let name = SqlValue::Identificator("name".to_string());
+        let select = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![
+                SelectColumnName::Name(SqlValue::Identificator("id".to_string())),
+                SelectColumnName::Name(call(ScalarFunction::Upper, vec![call(ScalarFunction::Substr, vec![name.clone(), SqlValue::Integer(1), SqlValue::Integer(3)])])),
+            ],
+            where_clause: Some(BinaryCondition {
+                left_value: call(ScalarFunction::Length, vec![name.clone()]),
+                right_value: SqlValue::Integer(5),
+                operator: CmpOperator::Less,
+            }.into()),
+        };
+        let result = database.execute(select).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["id", "UPPER(SUBSTR(name, 1, 3))"]);
+        assert_eq!(result.column_types, vec![ColumnType::Integer, ColumnType::String]);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result.rows[0].get_sql_values(&result.column_types).unwrap(), vec![SqlValue::Integer(1), SqlValue::String("JOH".to_string())]);
+
+        let select_lowered = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::Name(call(ScalarFunction::Lower, vec![name.clone()]))],
+            where_clause: None,
+        };
+        let result = database.execute(select_lowered).unwrap().unwrap();
+        let names: Vec<SqlValue> = result.rows.iter().map(|row| row.get_cell_sql_value(&result.column_types, 0).unwrap()).collect();
+        assert_eq!(names, vec![SqlValue::String("john".to_string()), SqlValue::String("alexandra".to_string()), SqlValue::Null]);
+
+        let select_upper_id = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::Name(call(ScalarFunction::Upper, vec![SqlValue::Identificator("id".to_string())]))],
+            where_clause: None,
+        };
+        assert!(matches!(
+                database.execute(select_upper_id),
+                Err(ExecutionError::TableError(TableError::FunctionError(_)))
+                ));
+
+        let select_coalesced = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::Name(call(ScalarFunction::Coalesce, vec![name.clone(), SqlValue::Null, SqlValue::String("anonymous".to_string())]))],
+            where_clause: Some(BinaryCondition {
+                left_value: call(ScalarFunction::IfNull, vec![name.clone(), SqlValue::String("".to_string())]),
+                right_value: SqlValue::String("John".to_string()),
+                operator: CmpOperator::NotEquals,
+            }.into()),
+        };
+        let result = database.execute(select_coalesced).unwrap().unwrap();
+        let names: Vec<SqlValue> = result.rows.iter().map(|row| row.get_cell_sql_value(&result.column_types, 0).unwrap()).collect();
+        assert_eq!(names, vec![SqlValue::String("Alexandra".to_string()), SqlValue::String("anonymous".to_string())]);
+
+        let select_mixed_types = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::Name(call(ScalarFunction::Coalesce, vec![name.clone(), SqlValue::Integer(0)]))],
+            where_clause: None,
+        };
+        assert!(matches!(
+                database.execute(select_mixed_types),
+                Err(ExecutionError::TableError(TableError::FunctionError(FunctionError::ArgumentTypesDiffer { .. })))
+                ));
+    }
+
+    #[test]
+    fn macros() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let create_macro = || Command::CreateMacro {
+            macro_name: SqlValue::Identificator("Above".to_string()),
+            parameter_names: vec![SqlValue::Identificator("a".to_string()), SqlValue::Identificator("b".to_string())],
+            body: lexer::to_tokens("(a > b)").unwrap(),
+        };
+        database.execute(create_macro()).unwrap();
+        assert!(matches!(database.execute(create_macro()), Err(ExecutionError::MacroAlreadyExist(name)) if name == "Above"));
+
+        let tokens = database.expand_macros(lexer::to_tokens("SELECT * FROM users WHERE above(id, 1) AND NOT ABOVE(id, 2)").unwrap()).unwrap();
+        let result = database.execute(parser::parse_statement(tokens.iter()).unwrap()).unwrap().unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(2)]);
+    }
+
+    #[test]
+    fn negated_conditions_skip_null_rows() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let insert_null_id = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Null.into()],
+        };
+        database.execute(insert_null_id).unwrap();
+        let create_macro = Command::CreateMacro {
+            macro_name: SqlValue::Identificator("above_one".to_string()),
+            parameter_names: vec![SqlValue::Identificator("a".to_string())],
+            body: lexer::to_tokens("(a > 1)").unwrap(),
+        };
+        database.execute(create_macro).unwrap();
+
+        for (query, expected_count) in [
+            ("SELECT * FROM users WHERE NOT (id = 2)", 2),
+            ("SELECT * FROM users WHERE NOT id > 1", 1),
+            ("SELECT * FROM users WHERE NOT id IN (2)", 2),
+            ("SELECT * FROM users WHERE NOT above_one(id)", 1),
+            ("SELECT * FROM users WHERE NOT (id = 2 OR id IS NULL)", 2),
+            ("SELECT * FROM users WHERE NOT NOT id IS NULL", 1),
+        ] {
+            let tokens = database.expand_macros(lexer::to_tokens(query).unwrap()).unwrap();
+            let result = database.execute(parser::parse_statement(tokens.iter()).unwrap()).unwrap().unwrap();
+            assert_eq!(result.len(), expected_count, "{}", query);
+        }
+    }
+
+    #[test]
+    fn cursors() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let cursor_name = SqlValue::Identificator("all_users".to_string());
+        let declare = Command::DeclareCursor {
+            cursor_name: cursor_name.clone(),
+            select: Box::new(Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::AllColumns],
+                where_clause: None,
+            }),
+        };
+        assert!(database.execute(declare.clone()).unwrap().is_none());
+        assert!(matches!(database.execute(declare), Err(ExecutionError::CursorAlreadyExist(_))));
+
+        // rows are selected on declaration, so later inserts are not fetched
+        let insert_into_table = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Integer(4).into()],
+        };
+        database.execute(insert_into_table).unwrap();
+
+        let fetch = |direction, count| Command::Fetch { cursor_name: cursor_name.clone(), direction, count };
+        let fetched = database.execute(fetch(FetchDirection::Forward, Some(2))).unwrap().unwrap();
+        assert_eq!(fetched.column_names, vec!["id"]);
+        assert_eq!(fetched.len(), 2);
+        assert_eq!(database.execute(fetch(FetchDirection::Forward, None)).unwrap().unwrap().len(), 1);
+        assert_eq!(database.execute(fetch(FetchDirection::Backward, Some(10))).unwrap().unwrap().len(), 3);
+
+        database.execute(Command::CloseCursor { cursor_name: cursor_name.clone() }).unwrap();
+        assert!(matches!(database.execute(fetch(FetchDirection::Forward, Some(1))), Err(ExecutionError::CursorNotExist(_))));
+        assert!(matches!(database.execute(Command::CloseCursor { cursor_name: cursor_name.clone() }), Err(ExecutionError::CursorNotExist(_))));
+
+        let declare_delete = Command::DeclareCursor {
+            cursor_name,
+            select: Box::new(Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None }),
+        };
+        assert!(matches!(
+                database.execute(declare_delete),
+                Err(ExecutionError::ClauseNotSupported { clause: "DECLARE CURSOR", statement_name: "DELETE" })
+        ));
+        assert_eq!(select_all_count(&mut database, "users"), 4);
+    }
+
+    #[test]
+    fn returning_affected_rows() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let users = SqlValue::Identificator("users".to_string());
+        let id = SqlValue::Identificator("id".to_string());
+        let returning = |command| Command::Returning { command: Box::new(command), column_names: vec![SelectColumnName::Name(id.clone())] };
+        let returned_ids = |result: QueryResult| (0..result.len()).map(|i| result.row_values(i).unwrap().unwrap().remove(0)).collect::<Vec<SqlValue>>();
+
+        let insert_into_table = Command::InsertInto { table_name: users.clone(), column_names: None, values: vec![SqlValue::Integer(4).into()] };
+        let result = database.execute(returning(insert_into_table)).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["id"]);
+        assert_eq!(returned_ids(result), vec![SqlValue::Integer(4)]);
+
+        let update_table = Command::Update {
+            table_name: users.clone(),
+            field_assignments: vec![FieldAssignment { column_name: "id".to_string(), value: SqlValue::Integer(10) }],
+            where_clause: Some(BinaryCondition { left_value: id.clone(), right_value: SqlValue::Integer(3), operator: CmpOperator::Greater }.into()),
+        };
+        let result = database.execute(returning(update_table)).unwrap().unwrap();
+        assert_eq!(returned_ids(result), vec![SqlValue::Integer(10)]);
+
+        let delete_from_table = Command::Delete {
+            table_name: users.clone(),
+            where_clause: Some(BinaryCondition { left_value: id.clone(), right_value: SqlValue::Integer(2), operator: CmpOperator::Less }.into()),
+        };
+        let result = database.execute(returning(delete_from_table)).unwrap().unwrap();
+        assert_eq!(returned_ids(result), vec![SqlValue::Integer(1)]);
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+
+        // unknown returned column is found before the row is deleted
+        let delete_from_table = Command::Delete { table_name: users, where_clause: None };
+        let returning_name = Command::Returning {
+            command: Box::new(delete_from_table),
+            column_names: vec![SelectColumnName::Name(SqlValue::Identificator("name".to_string()))],
+        };
+        assert!(database.execute(returning_name).is_err());
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+
+        let vacuum = Command::VacuumTable { table_name: SqlValue::Identificator("users".to_string()) };
+        assert!(matches!(
+                database.execute(returning(vacuum)),
+                Err(ExecutionError::ClauseNotSupported { clause: "RETURNING", statement_name: "VACUUM" })
+        ));
+    }
+
+    #[test]
+    fn select_limited() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let select_page = |limit, offset| Command::SelectLimited {
+            select: Box::new(Command::SelectOrdered {
+                select: Box::new(Command::Select {
+                    table_name: SqlValue::Identificator("users".to_string()),
+                    column_names: vec![SelectColumnName::AllColumns],
+                    where_clause: None,
+                }),
+                order_by: vec![OrderingTerm { key: OrderingKey::Column(SqlValue::Identificator("id".to_string())), descending: true, nulls: NullsOrder::First }],
+            }),
+            limit,
+            offset,
+        };
+
+        let result = database.execute(select_page(Some(2), 0)).unwrap().unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(3)]);
+        assert_eq!(database.last_statement_pagination(), Some(Pagination { offset: 0, total_count: 3, has_more: true }));
+
+        let result = database.execute(select_page(Some(2), 2)).unwrap().unwrap();
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(1)]);
+        assert_eq!(database.last_statement_pagination(), Some(Pagination { offset: 2, total_count: 3, has_more: false }));
+
+        let result = database.execute(select_page(None, 5)).unwrap().unwrap();
+        assert!(result.is_empty());
+        assert_eq!(database.last_statement_pagination(), Some(Pagination { offset: 5, total_count: 3, has_more: false }));
+
+        select_all_count(&mut database, "users");
+        assert_eq!(database.last_statement_pagination(), None);
+
+        let limited_delete = Command::SelectLimited {
+            select: Box::new(Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None }),
+            limit: Some(1),
+            offset: 0,
+        };
+        assert!(matches!(
+                database.execute(limited_delete),
+                Err(ExecutionError::ClauseNotSupported { clause: "LIMIT", statement_name: "DELETE" })
+        ));
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+    }
+
+    #[test]
+    fn system_tables() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let select_columns = Command::Select {
+            table_name: SqlValue::Identificator("yarrd_columns".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("table_name".to_string()),
+                right_value: SqlValue::String("users".to_string()),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        let columns = database.execute(select_columns).unwrap().unwrap();
+        assert_eq!(columns.column_names, vec!["table_name", "column_name", "position", "column_type", "constraints"]);
+        assert_eq!(columns.row_values(0).unwrap().unwrap(), vec![
+            SqlValue::String("users".to_string()), SqlValue::String("id".to_string()), SqlValue::Integer(1),
+            SqlValue::String("INT".to_string()), SqlValue::Null,
+        ]);
+
+        let select_tables = Command::Select {
+            table_name: SqlValue::Identificator("yarrd_tables".to_string()),
+            column_names: vec![SelectColumnName::Name(SqlValue::Identificator("row_count".to_string()))],
+            where_clause: None,
+        };
+        let tables = database.execute(select_tables).unwrap().unwrap();
+        assert_eq!(tables.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(3)]);
+        assert_eq!(select_all_count(&mut database, "yarrd_indexes"), 1);
+
+        // catalog is readable without grants, but system tables cannot be changed
+        database.execute(Command::CreateUser { user_name: SqlValue::Identificator("alice".to_string()) }).unwrap();
+        database.set_current_user(Some("alice")).unwrap();
+        assert_eq!(select_all_count(&mut database, "yarrd_tables"), 1);
+        database.set_current_user(None).unwrap();
+        let delete_tables = Command::Delete { table_name: SqlValue::Identificator("yarrd_tables".to_string()), where_clause: None };
+        assert!(matches!(database.execute(delete_tables), Err(ExecutionError::TableNotExist(_))));
+        let rename_table = Command::RenameTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            new_table_name: SqlValue::Identificator("yarrd_columns".to_string()),
+        };
+        assert!(matches!(database.execute(rename_table), Err(ExecutionError::SystemTableNameReserved(_))));
+    }
+
+    #[test]
+    fn insert_on_conflict() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        database.execute(Command::AddTableColumn {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_definition: ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+        }).unwrap();
+        let upsert = |id, name: &str, action| Command::Upsert {
+            insert: Box::new(Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: Some(vec![SqlValue::Identificator("id".to_string()), SqlValue::Identificator("name".to_string())]),
+                values: vec![SqlValue::Integer(id).into(), SqlValue::String(name.to_string()).into()],
+            }),
+            conflict_column_name: SqlValue::Identificator("id".to_string()),
+            action,
+        };
+        let set_name = |name: &str| ConflictAction::Update(vec![FieldAssignment { column_name: "name".to_string(), value: SqlValue::String(name.to_string()) }]);
+        let name_of = |database: &mut Database, id| {
+            let select = Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::Name(SqlValue::Identificator("name".to_string()))],
+                where_clause: Some(BinaryCondition { left_value: SqlValue::Identificator("id".to_string()), right_value: SqlValue::Integer(id), operator: CmpOperator::Equals }.into()),
+            };
+            database.execute(select).unwrap().unwrap().row_values(0).unwrap().unwrap().remove(0)
+        };
+
+        database.execute(upsert(1, "john", ConflictAction::Nothing)).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        assert_eq!(name_of(&mut database, 1), SqlValue::Null);
+
+        database.execute(upsert(1, "john", set_name("johnny"))).unwrap();
+        database.execute(upsert(4, "anna", set_name("annie"))).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 4);
+        assert_eq!(name_of(&mut database, 1), SqlValue::String("johnny".to_string()));
+        assert_eq!(name_of(&mut database, 4), SqlValue::String("anna".to_string()));
+
+        let returning = Command::Returning { command: Box::new(upsert(4, "anna", ConflictAction::Nothing)), column_names: vec![SelectColumnName::AllColumns] };
+        let result = database.execute(returning).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["id", "name"]);
+        assert!(result.is_empty());
+
+        let by_name = Command::Upsert {
+            insert: Box::new(Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(5).into(), SqlValue::String("anna".to_string()).into()],
+            }),
+            conflict_column_name: SqlValue::Identificator("name".to_string()),
+            action: ConflictAction::Nothing,
+        };
+        assert!(matches!(database.execute(by_name), Err(ExecutionError::TableError(TableError::ColumnNotIndexed { .. }))));
+
+        let upsert_delete = Command::Upsert {
+            insert: Box::new(Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None }),
+            conflict_column_name: SqlValue::Identificator("id".to_string()),
+            action: ConflictAction::Nothing,
+        };
+        assert!(matches!(
+                database.execute(upsert_delete),
+                Err(ExecutionError::ClauseNotSupported { clause: "ON CONFLICT", statement_name: "DELETE" })
+        ));
+        assert_eq!(select_all_count(&mut database, "users"), 4);
+    }
+
+    #[test]
+    fn insert_on_conflict_with_partial_column_list() {
+        let (_db_file, mut database) = open_test_database();
+        for statement in [
+            "CREATE TABLE u (name STRING, id INT)",
+            "CREATE INDEX ui ON u id",
+            "INSERT INTO u (id) VALUES (1)",
+            "INSERT INTO u (id) VALUES (1) ON CONFLICT (id) DO NOTHING",
+        ] {
+            database.execute(parser::parse_statement(lexer::to_tokens(statement).unwrap().iter()).unwrap()).unwrap();
+        }
+
+        assert_eq!(select_all_count(&mut database, "u"), 1);
+        assert_eq!(select_by_id_count(&mut database, "u", 1), 1);
+    }
+
+    #[test]
+    fn copy_from_and_to_file() {
+        let (db_file, mut database) = create_users_with_id_index();
+        let csv_path = db_file.temp_dir_path.join("users.csv").to_str().unwrap().to_string();
+        let copy_to = Command::CopyTo {
+            select: Box::new(Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::AllColumns],
+                where_clause: None,
+            }),
+            file_path: csv_path.clone(),
+        };
+        assert!(database.execute(copy_to).unwrap().is_none());
+        assert_eq!(fs::read_to_string(&csv_path).unwrap(), "1\n2\n3\n");
+
+        let copy_from = Command::CopyFrom { table_name: SqlValue::Identificator("users".to_string()), file_path: csv_path.clone() };
+        database.execute(copy_from.clone()).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 6);
+
+        // rows before the invalid line are inserted
+        fs::write(&csv_path, "7\n\n\nseven\n8\n").unwrap();
+        assert!(matches!(
+                database.execute(copy_from),
+                Err(ExecutionError::CopyLineFailed { line_number: 4, error }) if matches!(*error, ExecutionError::CsvValueInvalid { .. })
+                ));
+        assert_eq!(select_all_count(&mut database, "users"), 7);
+
+        let copy_delete = Command::CopyTo {
+            select: Box::new(Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None }),
+            file_path: csv_path,
+        };
+        assert!(matches!(database.execute(copy_delete), Err(ExecutionError::ClauseNotSupported { clause: "COPY ... TO", .. })));
+        assert_eq!(select_all_count(&mut database, "users"), 7);
+    }
+
+    #[test]
+    fn select_ordered() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let insert_into_table = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Null.into()],
+        };
+        database.execute(insert_into_table).unwrap();
+
+        let select_ordered = |column_name: &str, nulls| Command::SelectOrdered {
+            select: Box::new(Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::AllColumns],
+                where_clause: None,
+            }),
+            order_by: vec![OrderingTerm { key: OrderingKey::Column(SqlValue::Identificator(column_name.to_string())), descending: true, nulls }],
+        };
+        let result = database.execute(select_ordered("id", NullsOrder::Last)).unwrap().unwrap();
+        let ids: Vec<SqlValue> = (0..result.len()).map(|i| result.row_values(i).unwrap().unwrap().remove(0)).collect();
+        assert_eq!(ids, vec![SqlValue::Integer(3), SqlValue::Integer(2), SqlValue::Integer(1), SqlValue::Null]);
+
+        let result = database.execute(select_ordered("users.id", NullsOrder::First)).unwrap().unwrap();
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Null]);
+        assert!(matches!(database.execute(select_ordered("name", NullsOrder::First)), Err(ExecutionError::OrderColumnNotSelected(_))));
+
+        let ordered_vacuum = Command::SelectOrdered {
+            select: Box::new(Command::VacuumTable { table_name: SqlValue::Identificator("users".to_string()) }),
+            order_by: vec![],
+        };
+        assert!(matches!(
+                database.execute(ordered_vacuum),
+                Err(ExecutionError::ClauseNotSupported { clause: "ORDER BY", statement_name: "VACUUM" })
+        ));
+        let ordered_delete = Command::SelectOrdered {
+            select: Box::new(Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None }),
+            order_by: vec![],
+        };
+        assert!(database.execute(ordered_delete).is_err());
+        assert_eq!(select_all_count(&mut database, "users"), 4);
+
+        let sample = |database: &mut Database| {
+            database.execute(Command::SetRandomSeed { seed: 2001 }).unwrap();
+            let select_sample = Command::SelectLimited {
+                select: Box::new(Command::SelectOrdered {
+                    select: Box::new(Command::Select {
+                        table_name: SqlValue::Identificator("users".to_string()),
+                        column_names: vec![SelectColumnName::AllColumns],
+                        where_clause: None,
+                    }),
+                    order_by: vec![OrderingTerm { key: OrderingKey::Random, descending: false, nulls: NullsOrder::Last }],
+                }),
+                limit: Some(2),
+                offset: 0,
+            };
+            let result = database.execute(select_sample).unwrap().unwrap();
+            (0..result.len()).map(|row_index| result.row_values(row_index).unwrap().unwrap()).collect::<Vec<_>>()
+        };
+        let rows = sample(&mut database);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(sample(&mut database), rows);
+    }
+
+    #[test]
+    fn numeric_functions() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let call = |function, arguments| SqlValue::Function(Box::new(FunctionCall { function, arguments }));
+        let id = SqlValue::Identificator("id".to_string());
+        let select = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![
+                SelectColumnName::Name(call(ScalarFunction::Mod, vec![id.clone(), SqlValue::Integer(2)])),
+                SelectColumnName::Name(call(ScalarFunction::Max, vec![id.clone(), SqlValue::Float(2.5)])),
+            ],
+            where_clause: Some(BinaryCondition {
+                left_value: call(ScalarFunction::Abs, vec![id.clone()]),
+                right_value: SqlValue::Integer(1),
+                operator: CmpOperator::Greater,
+            }.into()),
+        };
+        let result = database.execute(select).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["MOD(id, 2)", "MAX(id, 2.5e0)"]);
+        assert_eq!(result.column_types, vec![ColumnType::Integer, ColumnType::Float]);
+        let rows: Vec<Vec<SqlValue>> = result.rows.iter().map(|row| row.get_sql_values(&result.column_types).unwrap()).collect();
+        assert_eq!(rows, vec![
+            vec![SqlValue::Integer(0), SqlValue::Float(2.5)],
+            vec![SqlValue::Integer(1), SqlValue::Float(3.0)],
+        ]);
+    }
+
+    #[test]
+    fn last_statement_stats() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for id in 1..=3 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        let stats = database.last_statement_stats();
+        assert_eq!(stats.rows_scanned, 3);
+        assert_eq!(stats.index_probes, 0);
+
+        let create_index = Command::CreateIndex {
+            table_name: SqlValue::Identificator("users".to_string()),
+            index_name: SqlValue::Identificator("users_id".to_string()),
+            column_name: SqlValue::Identificator("id".to_string()),
+        };
+        database.execute(create_index).unwrap();
+
+        let select_by_id = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(2),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        assert_eq!(database.execute(select_by_id).unwrap().unwrap().len(), 1);
+        let stats = database.last_statement_stats();
+        assert_eq!(stats.rows_scanned, 1);
+        assert_eq!(stats.index_probes, 1);
+    }
+
+    #[test]
+    fn last_statement_warnings() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let null_comparison = BinaryCondition {
+            left_value: SqlValue::Identificator("id".to_string()),
+            right_value: SqlValue::Null,
+            operator: CmpOperator::Equals,
+        };
+        let select_null_id = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(Condition::or(
+                    null_comparison.clone().into(),
+                    BinaryCondition { operator: CmpOperator::IsNull, ..null_comparison }.into())),
+        };
+        assert!(database.execute(select_null_id).unwrap().unwrap().is_empty());
+        assert_eq!(database.last_statement_warnings(), &[Warning::ComparisonWithNull("id = NULL".to_string())]);
+        assert_eq!(database.last_statement_warnings()[0].to_string(),
+                   "'id = NULL' is never true, use `IS NULL` or `NOT ... IS NULL` to check for nulls");
+
+        assert_eq!(select_by_id_count(&mut database, "users", 1), 1);
+        assert!(database.last_statement_warnings().is_empty());
+    }
+
+    #[test]
+    fn sync_writes_schema_and_pages_to_disk() {
+        let (db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        let insert_into_table = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Integer(1).into()],
+        };
+        database.execute(insert_into_table).unwrap();
+
+        database.sync().unwrap();
+
+        // database is still open, so everything second one sees was synced
+        let mut synced_database = Database::from(db_file.file_path.as_path()).unwrap();
+        assert_eq!(select_all_count(&mut synced_database, "users"), 1);
+    }
+
+    #[test]
+    fn find_and_clean_up_orphaned_files() {
+        let (db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        let create_index = Command::CreateIndex {
+            table_name: SqlValue::Identificator("users".to_string()),
+            index_name: SqlValue::Identificator("users_id".to_string()),
+            column_name: SqlValue::Identificator("id".to_string()),
+        };
+        database.execute(create_index).unwrap();
+
+        let tables_dir = db_file.temp_dir_path.clone();
+        let orphaned_table = tables_dir.join("users-1234.table");
+        let orphaned_index = tables_dir.join("users-users_id-swap.hash");
+        fs::write(&orphaned_table, b"").unwrap();
+        fs::write(&orphaned_index, b"").unwrap();
+        fs::write(tables_dir.join("notes.txt"), b"").unwrap();
+
+        assert_eq!(database.orphaned_files().unwrap(), vec![orphaned_table.clone(), orphaned_index.clone()]);
+
+        let quarantined_files = database.quarantine_orphaned_files().unwrap();
+        assert_eq!(quarantined_files.len(), 2);
+        assert!(quarantined_files.iter().all(|path| path.exists()));
+        assert!(!orphaned_table.exists());
+        assert!(database.orphaned_files().unwrap().is_empty());
+
+        fs::write(&orphaned_table, b"").unwrap();
+        assert_eq!(database.delete_orphaned_files().unwrap(), vec![orphaned_table.clone()]);
+        assert!(!orphaned_table.exists());
+        assert!(tables_dir.join("users.table").exists());
+        assert!(tables_dir.join("users-users_id.hash").exists());
+    }
+
+    #[test]
+    fn temporary_table_names_are_reserved_and_not_written_to_schema() {
+        let (db_file, mut database) = open_test_database();
+        let create_temporary_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("__temp_users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        assert!(matches!(database.execute(create_temporary_table), Err(ExecutionError::TableNameReserved(_))));
+
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+
+        let rename_table = Command::RenameTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            new_table_name: SqlValue::Identificator("__temp_users".to_string()),
+        };
+        assert!(matches!(database.execute(rename_table), Err(ExecutionError::TableNameReserved(_))));
+
+        let add_table_column = Command::AddTableColumn {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_definition: ColumnDefinition {
+                name: SqlValue::Identificator("name".to_string()),
+                kind: ColumnType::String,
+                column_constraints: vec![],
+            },
+        };
+        database.execute(add_table_column).unwrap();
+        database.close().unwrap();
+
+        let schema = fs::read_to_string(db_file.file_path.as_path()).unwrap();
+        assert!(!schema.contains("__temp_"));
+        let mut database = Database::from(db_file.file_path.as_path()).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 0);
+    }
+
+    #[test]
+    fn rename_table_with_index_and_reconnect() {
+        let (db_file, mut database) = create_users_with_id_index();
+
+        let rename_table = Command::RenameTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            new_table_name: SqlValue::Identificator("people".to_string()),
+        };
+        database.execute(rename_table).unwrap();
+        drop(database);
+
+        let tables_dir = db_file.temp_dir_path.clone();
+        assert!(tables_dir.join("people.table").exists());
+        assert!(tables_dir.join("people-people_id.hash").exists());
+        assert!(!tables_dir.join("users-people_id.hash").exists());
+
+        let mut database = Database::from(db_file.file_path.as_path()).unwrap();
+        assert_eq!(select_by_id_count(&mut database, "people", 2), 1);
+        assert_eq!(database.last_statement_stats().index_probes, 1);
+        assert!(database.orphaned_files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn interrupted_rename_is_rolled_back_on_connect() {
+        let (db_file, database) = create_users_with_id_index();
+        database.close().unwrap();
+
+        let tables_dir = db_file.temp_dir_path.clone();
+        let moves = vec![
+            (tables_dir.join("users.table"), tables_dir.join("people.table")),
+            (tables_dir.join("users-people_id.hash"), tables_dir.join("people-people_id.hash")),
+        ];
+        // files are moved, but schema still has old name, same as if process crashed before commit
+        RenameJournal::new(&tables_dir, "users", "people", moves).apply().unwrap();
+
+        let mut database = Database::from(db_file.file_path.as_path()).unwrap();
+        assert_eq!(select_by_id_count(&mut database, "users", 2), 1);
+        assert_eq!(database.last_statement_stats().index_probes, 1);
+        assert!(!tables_dir.join("people.table").exists());
+    }
+
+    #[test]
+    fn select_qualified_columns() {
+        let (_db_file, mut database) = create_users_with_id_index();
+
+        let select_qualified = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![
+                SelectColumnName::Name(SqlValue::Identificator("users.id".to_string())),
+                SelectColumnName::TableAllColumns(SqlValue::Identificator("users".to_string())),
+            ],
+            where_clause: None,
+        };
+        let result = database.execute(select_qualified).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["id", "id"]);
+        assert_eq!(result.len(), 3);
+
+        let select_other_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::TableAllColumns(SqlValue::Identificator("orders".to_string()))],
+            where_clause: None,
+        };
+        assert!(matches!(
+                database.execute(select_other_table),
+                Err(ExecutionError::TableError(TableError::UnknownTableQualifier { .. }))
+            ));
+    }
+
+    #[test]
+    fn select_cross_join() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("orders".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("user_id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for (id, user_id) in [(10, 2), (11, 3), (12, 2)] {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("orders".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into(), SqlValue::Integer(user_id).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let select_cross_join = |where_clause| Command::SelectCrossJoin {
+            table_names: vec![SqlValue::Identificator("users".to_string()), SqlValue::Identificator("orders".to_string())],
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause,
+        };
+        let result = database.execute(select_cross_join(None)).unwrap().unwrap();
+        assert_eq!(result.column_names, vec!["users.id", "orders.id", "orders.user_id"]);
+        assert_eq!(result.len(), 9);
+
+        let where_clause = BinaryCondition {
+            left_value: SqlValue::Identificator("users.id".to_string()),
+            right_value: SqlValue::Identificator("orders.user_id".to_string()),
+            operator: CmpOperator::Equals,
+        };
+        let result = database.execute(select_cross_join(Some(where_clause.into()))).unwrap().unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(2), SqlValue::Integer(10), SqlValue::Integer(2)]);
+
+        let select_same_table_twice = Command::SelectCrossJoin {
+            table_names: vec![SqlValue::Identificator("users".to_string()), SqlValue::Identificator("users".to_string())],
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+        assert!(matches!(
+                database.execute(select_same_table_twice),
+                Err(ExecutionError::TableError(TableError::TableJoinedTwice(_)))
+            ));
+    }
+
+    #[test]
+    fn get_row_by_id() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions { soft_delete: true, ..TableOptions::default() },
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for id in 1..=3 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+        let delete_from_table = Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(3),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        database.execute(delete_from_table).unwrap();
+
+        assert_eq!(database.get_row("users", 1).unwrap(), Some(vec![SqlValue::Integer(2)]));
+        assert_eq!(database.last_statement_stats().rows_scanned, 1);
+        assert_eq!(database.get_row("users", 2).unwrap(), None);
+        assert_eq!(database.get_row("users", 1000000).unwrap(), None);
+        assert!(matches!(database.get_row("orders", 1), Err(ExecutionError::TableNotExist(_))));
+    }
+
+    #[test]
+    fn mass_update_and_delete_keep_index_valid() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let update_table = Command::Update {
+            table_name: SqlValue::Identificator("users".to_string()),
+            field_assignments: vec![FieldAssignment { column_name: "id".to_string(), value: SqlValue::Integer(5) }],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(1),
+                operator: CmpOperator::Greater,
+            }.into()),
+        };
+        database.execute(update_table).unwrap();
+        assert_eq!(select_by_id_count(&mut database, "users", 2), 0);
+        assert_eq!(select_by_id_count(&mut database, "users", 5), 2);
+
+        let delete_from_table = Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(5),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        database.execute(delete_from_table).unwrap();
+        assert_eq!(select_by_id_count(&mut database, "users", 5), 0);
+        assert_eq!(select_by_id_count(&mut database, "users", 1), 1);
+        assert_eq!(select_all_count(&mut database, "users"), 1);
+    }
+
+    #[test]
+    fn select_where_boolean_operators() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let id_condition = |operator, id| Condition::Binary(BinaryCondition {
+            left_value: SqlValue::Identificator("id".to_string()),
+            right_value: SqlValue::Integer(id),
+            operator,
+        });
+        let select_ids = |database: &mut Database, where_clause| {
+            let select_from_table = Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::AllColumns],
+                where_clause: Some(where_clause),
+            };
+            let result = database.execute(select_from_table).unwrap().unwrap();
+            (0..result.len())
+                .map(|row_index| result.row_values(row_index).unwrap().unwrap()[0].clone())
+                .collect::<Vec<SqlValue>>()
+        };
+
+        let where_clause = Condition::or(id_condition(CmpOperator::Equals, 1), id_condition(CmpOperator::Greater, 2));
+        assert_eq!(select_ids(&mut database, where_clause), vec![SqlValue::Integer(1), SqlValue::Integer(3)]);
+        assert_eq!(database.last_statement_stats().index_probes, 0);
+
+        let where_clause = Condition::and(id_condition(CmpOperator::Greater, 1), id_condition(CmpOperator::Equals, 3));
+        assert_eq!(select_ids(&mut database, where_clause), vec![SqlValue::Integer(3)]);
+        assert_eq!(database.last_statement_stats().index_probes, 1);
+        assert_eq!(database.last_statement_stats().rows_scanned, 1);
+
+        let where_clause = Condition::negate(id_condition(CmpOperator::Equals, 2));
+        assert_eq!(select_ids(&mut database, where_clause), vec![SqlValue::Integer(1), SqlValue::Integer(3)]);
+    }
+
+    #[test]
+    fn index_is_skipped_for_common_values() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        for _ in 0..120 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(2).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let index_stats = database.index_stats(Some("users")).unwrap();
+        assert_eq!(index_stats.len(), 1);
+        assert_eq!(index_stats[0].0, "users");
+        assert_eq!((index_stats[0].1.keys, index_stats[0].1.entries), (3, 123));
+        assert!(matches!(database.index_stats(Some("posts")), Err(ExecutionError::TableNotExist(_))));
+
+        // page holds less than 4 * 121 rows, so reading it is cheaper than fetching each row by index
+        assert_eq!(select_by_id_count(&mut database, "users", 2), 121);
+        assert_eq!(database.last_statement_stats().index_probes, 0);
+
+        assert_eq!(select_by_id_count(&mut database, "users", 1), 1);
+        assert_eq!(database.last_statement_stats().index_probes, 1);
+    }
+
+    #[test]
+    fn bulk_delete_rebuilds_index() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        for _ in 0..120 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(2).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+        let delete_by_id = |id| Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(id),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+
+        database.execute(delete_by_id(3)).unwrap();
+        assert_eq!(database.index_stats(Some("users")).unwrap()[0].1.deleted_entries, 1);
+
+        database.execute(delete_by_id(2)).unwrap();
+        let index_stats = &database.index_stats(Some("users")).unwrap()[0].1;
+        assert_eq!((index_stats.entries, index_stats.deleted_entries), (1, 0));
+        assert_eq!(select_by_id_count(&mut database, "users", 1), 1);
+    }
+
+    #[test]
+    fn page_dump() {
+        let (_db_file, mut database) = create_users_with_id_index();
+
+        let page_dump = database.page_dump("users", None, 0).unwrap();
+        assert!(page_dump.starts_with("page 0 of 1 in table 'users'"));
+        assert!(page_dump.contains("slot 0, row 0: id = 1"));
+        assert!(page_dump.contains("slot 2, row 2: id = 3"));
+        assert!(matches!(
+                database.page_dump("users", None, 1),
+                Err(ExecutionError::TableError(TableError::PageNotExist { page_id: 1, page_count: 1, .. }))
+        ));
+
+        let bucket_dump = database.page_dump("users", Some("people_id"), 0).unwrap();
+        assert!(bucket_dump.starts_with("bucket 0 of 1 in index 'people_id'"));
+        assert_eq!(bucket_dump.matches(": present").count(), 3);
+        assert!(database.page_dump("users", Some("people_id"), 1).is_err());
+        assert!(database.page_dump("users", Some("missing_index"), 0).is_err());
+    }
+
+    #[test]
+    fn salvage_damaged_database() {
+        let (db_file, mut database) = create_users_with_id_index();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("posts".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        database.close().unwrap();
+        let schema = fs::read_to_string(&db_file.file_path).unwrap();
+
+        // posts file is lost, and users file ends with a torn page
+        fs::remove_file(db_file.temp_dir_path.join("posts.table")).unwrap();
+        let users_filepath = db_file.temp_dir_path.join("users.table");
+        let mut users_bytes = fs::read(&users_filepath).unwrap();
+        users_bytes.extend_from_slice(&[0xff; 16]);
+        fs::write(&users_filepath, users_bytes).unwrap();
+        assert!(Database::from(&db_file.file_path).is_err());
+
+        let mut database = Database::salvage(&db_file.file_path).unwrap();
+        assert!(database.is_salvaged());
+        assert_eq!(database.offline_tables().keys().collect::<Vec<_>>(), ["posts"]);
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        assert!(matches!(
+                database.last_statement_warnings(),
+                [Warning::DamagedRowsSkipped { table_name, .. }] if table_name == "users"
+        ));
+        assert_eq!(select_by_id_count(&mut database, "users", 2), 1);
+
+        let select_posts = Command::Select {
+            table_name: SqlValue::Identificator("posts".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+        assert!(matches!(database.execute(select_posts), Err(ExecutionError::TableOffline { .. })));
+        let delete_users = Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None };
+        assert!(matches!(database.execute(delete_users), Err(ExecutionError::DatabaseSalvaged("DELETE"))));
+
+        database.close().unwrap();
+        assert_eq!(fs::read_to_string(&db_file.file_path).unwrap(), schema);
+    }
+
+    #[test]
+    fn select_with_index_hint() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        for _ in 0..120 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(2).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+        let select_hinted = |id, index_hint| Command::SelectIndexHinted {
+            select: Box::new(Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::AllColumns],
+                where_clause: Some(BinaryCondition {
+                    left_value: SqlValue::Identificator("id".to_string()),
+                    right_value: SqlValue::Integer(id),
+                    operator: CmpOperator::Equals,
+                }.into()),
+            }),
+            index_hint,
+        };
+
+        // common value is fetched by index once it is forced
+        let result = database.execute(select_hinted(2, IndexHint::Use(vec!["people_id".to_string()]))).unwrap().unwrap();
+        assert_eq!(result.len(), 121);
+        assert_eq!(database.last_statement_stats().index_probes, 1);
+        assert_eq!(database.last_statement_stats().rows_scanned, 121);
+
+        let result = database.execute(select_hinted(1, IndexHint::Ignore(vec!["people_id".to_string()]))).unwrap().unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(database.last_statement_stats().index_probes, 0);
+        assert_eq!(database.last_statement_stats().rows_scanned, 123);
+
+        // hint is applied only to the hinted statement
+        assert_eq!(select_by_id_count(&mut database, "users", 1), 1);
+        assert_eq!(database.last_statement_stats().index_probes, 1);
+
+        assert!(matches!(
+                database.execute(select_hinted(1, IndexHint::Use(vec!["users_id".to_string()]))),
+                Err(ExecutionError::TableError(TableError::HashIndexMissing { index_name, .. })) if index_name == "users_id"
+                ));
+        let hinted_delete = Command::SelectIndexHinted {
+            select: Box::new(Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None }),
+            index_hint: IndexHint::Ignore(vec!["people_id".to_string()]),
+        };
+        assert!(matches!(
+                database.execute(hinted_delete),
+                Err(ExecutionError::ClauseNotSupported { clause: "IGNORE INDEX", statement_name: "DELETE" })
+                ));
+        assert_eq!(select_all_count(&mut database, "users"), 123);
+    }
+
+    #[test]
+    fn select_where_intersects_indexes() {
+        let (_db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("posts".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("user_id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+                ColumnDefinition { name: SqlValue::Identificator("topic_id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for post_number in 0..12 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("posts".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(post_number % 3).into(), SqlValue::Integer(post_number % 4).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+        for column_name in ["user_id", "topic_id"] {
+            let create_index = Command::CreateIndex {
+                table_name: SqlValue::Identificator("posts".to_string()),
+                index_name: SqlValue::Identificator(format!("posts_{}", column_name)),
+                column_name: SqlValue::Identificator(column_name.to_string()),
+            };
+            database.execute(create_index).unwrap();
+        }
+
+        let column_condition = |column_name: &str, value| Condition::Binary(BinaryCondition {
+            left_value: SqlValue::Identificator(column_name.to_string()),
+            right_value: SqlValue::Integer(value),
+            operator: CmpOperator::Equals,
+        });
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("posts".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(Condition::and(
+                column_condition("user_id", 1),
+                Condition::or(column_condition("topic_id", 1), column_condition("topic_id", 3)),
+            )),
+        };
+        let result = database.execute(select_from_table).unwrap().unwrap();
+        let rows: Vec<Vec<SqlValue>> = (0..result.len()).map(|row_index| result.row_values(row_index).unwrap().unwrap()).collect();
+        assert_eq!(rows, vec![
+            vec![SqlValue::Integer(1), SqlValue::Integer(1)],
+            vec![SqlValue::Integer(1), SqlValue::Integer(3)],
+        ]);
+
+        // both indexes are probed, and only rows found by both of them are read
+        assert_eq!(database.last_statement_stats().index_probes, 3);
+        assert_eq!(database.last_statement_stats().rows_scanned, 2);
+    }
+
+    #[test]
+    fn select_where_exists() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("orders".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("user_id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for user_id in [1, 3, 1] {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("orders".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(user_id).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let user_has_orders = Condition::exists(SqlValue::Identificator("orders".to_string()), Some(BinaryCondition {
+            left_value: SqlValue::Identificator("orders.user_id".to_string()),
+            right_value: SqlValue::Identificator("users.id".to_string()),
+            operator: CmpOperator::Equals,
+        }.into()));
+        let orders_of_user = |user_id| Condition::exists(SqlValue::Identificator("orders".to_string()), Some(BinaryCondition {
+            left_value: SqlValue::Identificator("user_id".to_string()),
+            right_value: SqlValue::Integer(user_id),
+            operator: CmpOperator::Equals,
+        }.into()));
+        let mut select_user_ids = |where_clause| {
+            let select_from_table = Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::Name(SqlValue::Identificator("id".to_string()))],
+                where_clause: Some(where_clause),
+            };
+            let result = database.execute(select_from_table).unwrap().unwrap();
+            (0..result.len()).map(|row_index| result.row_values(row_index).unwrap().unwrap()[0].clone()).collect::<Vec<SqlValue>>()
+        };
+
+        assert_eq!(select_user_ids(user_has_orders.clone()), vec![SqlValue::Integer(1), SqlValue::Integer(3)]);
+        assert_eq!(select_user_ids(Condition::negate(user_has_orders.clone())), vec![SqlValue::Integer(2)]);
+        // uncorrelated subquery is the same for all rows
+        assert_eq!(select_user_ids(orders_of_user(2)), vec![]);
+        assert_eq!(select_user_ids(orders_of_user(3)).len(), 3);
+
+        let delete_from_table = Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(user_has_orders.clone()),
+        };
+        database.execute(delete_from_table).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 1);
+
+        let select_cross_join = Command::SelectCrossJoin {
+            table_names: vec![SqlValue::Identificator("users".to_string()), SqlValue::Identificator("orders".to_string())],
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(user_has_orders),
+        };
+        assert!(matches!(
+                database.execute(select_cross_join),
+                Err(ExecutionError::TableError(TableError::SubqueryNotSupported(_)))
+                ));
+    }
+
+    #[test]
+    fn medium_selectivity_lookup_reads_rows_in_table_order() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        for row_number in 0..40 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(5 + row_number % 2).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(Condition::in_list(
+                SqlValue::Identificator("id".to_string()),
+                vec![SqlValue::Integer(6), SqlValue::Integer(5)],
+            )),
+        };
+        let result = database.execute(select_from_table).unwrap().unwrap();
+        let ids: Vec<SqlValue> = (0..result.len()).map(|row_index| result.row_values(row_index).unwrap().unwrap()[0].clone()).collect();
+
+        // index scan would return all rows with id 6 before rows with id 5
+        assert_eq!(ids.len(), 40);
+        assert_eq!(ids[..4], [SqlValue::Integer(5), SqlValue::Integer(6), SqlValue::Integer(5), SqlValue::Integer(6)]);
+        assert_eq!(database.last_statement_stats().index_probes, 2);
+        assert_eq!(database.last_statement_stats().rows_scanned, 40);
+    }
+
+    #[test]
+    fn select_where_in_list() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let id = SqlValue::Identificator("id".to_string());
+        let select_ids = |database: &mut Database, where_clause| {
+            let select_from_table = Command::Select {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: vec![SelectColumnName::AllColumns],
+                where_clause: Some(where_clause),
+            };
+            let result = database.execute(select_from_table).unwrap().unwrap();
+            (0..result.len())
+                .map(|row_index| result.row_values(row_index).unwrap().unwrap()[0].clone())
+                .collect::<Vec<SqlValue>>()
+        };
+
+        let where_clause = Condition::in_list(id.clone(), vec![SqlValue::Integer(3), SqlValue::Integer(1), SqlValue::Integer(3)]);
+        assert_eq!(select_ids(&mut database, where_clause), vec![SqlValue::Integer(3), SqlValue::Integer(1)]);
+        assert_eq!(database.last_statement_stats().index_probes, 2);
+        assert_eq!(database.last_statement_stats().rows_scanned, 2);
+
+        let where_clause = Condition::negate(Condition::in_list(id, vec![SqlValue::Integer(1), SqlValue::Integer(3)]));
+        assert_eq!(select_ids(&mut database, where_clause), vec![SqlValue::Integer(2)]);
+        assert_eq!(database.last_statement_stats().index_probes, 0);
+    }
+
+    #[test]
+    fn is_null_scan_is_skipped_when_column_has_no_nulls() {
+        let (db_file, mut database) = create_users_with_id_index();
+        let id_is_null = || BinaryCondition {
+            left_value: SqlValue::Identificator("id".to_string()),
+            right_value: SqlValue::Null,
+            operator: CmpOperator::IsNull,
+        };
+        let select_nulls = || Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(id_is_null().into()),
+        };
+
+        assert_eq!(database.execute(select_nulls()).unwrap().unwrap().len(), 0);
+        assert_eq!(database.last_statement_stats().rows_scanned, 0);
+
+        let update_table = Command::Update {
+            table_name: SqlValue::Identificator("users".to_string()),
+            field_assignments: vec![FieldAssignment {
+                column_name: "id".to_string(),
+                value: SqlValue::Null,
+            }],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(1),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        database.execute(update_table).unwrap();
+        database.close().unwrap();
+
+        let schema = fs::read_to_string(db_file.path()).unwrap();
+        assert!(schema.contains("users 3 id INT; 0 people_id;; 1;"));
+
+        let mut database = Database::from(db_file.file_path.as_path()).unwrap();
+        assert_eq!(database.execute(select_nulls()).unwrap().unwrap().len(), 1);
+        assert_eq!(database.last_statement_stats().rows_scanned, 3);
+
+        let delete_nulls = Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(id_is_null().into()),
+        };
+        database.execute(delete_nulls).unwrap();
+        assert_eq!(database.execute(select_nulls()).unwrap().unwrap().len(), 0);
+        assert_eq!(database.last_statement_stats().rows_scanned, 0);
+    }
+
+    #[test]
+    fn foreign_keys() {
+        let (db_file, mut database) = create_users_with_id_index();
+        let user_foreign_key = ForeignKey { table_name: "users".to_string(), column_name: "id".to_string() };
+        let create_table = |foreign_key: ForeignKey| Command::CreateTable {
+            table_name: SqlValue::Identificator("orders".to_string()),
+            columns: vec![
+                ColumnDefinition {
+                    name: SqlValue::Identificator("user_id".to_string()),
+                    kind: ColumnType::Integer,
+                    column_constraints: vec![Constraint::References(foreign_key)],
+                },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        let missing_column = ForeignKey { table_name: "users".to_string(), column_name: "name".to_string() };
+        assert!(matches!(database.execute(create_table(missing_column)), Err(ExecutionError::ForeignKeyTargetInvalid(_))));
+        database.execute(create_table(user_foreign_key.clone())).unwrap();
+
+        let insert_order = |user_id: SqlValue| Command::InsertInto {
+            table_name: SqlValue::Identificator("orders".to_string()),
+            column_names: None,
+            values: vec![user_id.into()],
+        };
+        database.execute(insert_order(SqlValue::Integer(1))).unwrap();
+        database.execute(insert_order(SqlValue::Null)).unwrap();
+        assert!(matches!(
+                database.execute(insert_order(SqlValue::Integer(4))),
+                Err(ExecutionError::ForeignKeyViolation { value: SqlValue::Integer(4), .. })
+                ));
+
+        let update_orders = Command::Update {
+            table_name: SqlValue::Identificator("orders".to_string()),
+            field_assignments: vec![FieldAssignment { column_name: "user_id".to_string(), value: SqlValue::Integer(5) }],
+            where_clause: None,
+        };
+        assert!(matches!(database.execute(update_orders), Err(ExecutionError::ForeignKeyViolation { .. })));
+
+        let delete_user = |id| Command::Delete {
+            table_name: SqlValue::Identificator("users".to_string()),
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(id),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+        assert!(matches!(
+                database.execute(delete_user(1)),
+                Err(ExecutionError::ForeignKeyReferenced { value: SqlValue::Integer(1), .. })
+                ));
+        database.execute(delete_user(2)).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 2);
+
+        let drop_users = Command::DropTable { table_name: SqlValue::Identificator("users".to_string()) };
+        assert!(matches!(database.execute(drop_users), Err(ExecutionError::TableReferenced { .. })));
+
+        // foreign key is kept in schema
+        database.close().unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+        assert!(matches!(
+                database.execute(insert_order(SqlValue::Integer(2))),
+                Err(ExecutionError::ForeignKeyViolation { .. })
+                ));
+    }
+
+    #[test]
+    fn foreign_key_to_row_inserted_with_partial_column_list() {
+        let (_db_file, mut database) = open_test_database();
+        let mut execute = |statement: &str| database.execute(parser::parse_statement(lexer::to_tokens(statement).unwrap().iter()).unwrap());
+        for statement in [
+            "CREATE TABLE u (name STRING, id INT)",
+            "CREATE INDEX ui ON u id",
+            "CREATE INDEX un ON u name",
+            "INSERT INTO u (id) VALUES (1)",
+            "INSERT INTO u (name, id) VALUES (\"bob\", 2)",
+            "CREATE TABLE o (uid INT REFERENCES u (id))",
+            "INSERT INTO o (uid) VALUES (1)",
+            "INSERT INTO o (uid) VALUES (2)",
+        ] {
+            execute(statement).unwrap();
+        }
+
+        assert!(matches!(execute("INSERT INTO o (uid) VALUES (3)"), Err(ExecutionError::ForeignKeyViolation { .. })));
+        assert_eq!(execute("SELECT * FROM u WHERE name = \"bob\"").unwrap().unwrap().len(), 1);
+        assert_eq!(execute("SELECT * FROM u IGNORE INDEX (un) WHERE name = \"bob\"").unwrap().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn users_and_grants() {
+        let (db_file, mut database) = create_users_with_id_index();
+        let alice = SqlValue::Identificator("alice".to_string());
+        let users = SqlValue::Identificator("users".to_string());
+        assert!(matches!(database.set_current_user(Some("alice")), Err(ExecutionError::UserNotExist(_))));
+
+        database.execute(Command::CreateUser { user_name: alice.clone() }).unwrap();
+        assert!(matches!(database.execute(Command::CreateUser { user_name: alice.clone() }), Err(ExecutionError::UserAlreadyExist(_))));
+        database.execute(Command::Grant {
+            privileges: vec![Privilege::Select, Privilege::Delete],
+            table_name: users.clone(),
+            user_name: alice.clone(),
+        }).unwrap();
+        database.execute(Command::Revoke { privileges: vec![Privilege::Delete], table_name: users.clone(), user_name: alice.clone() }).unwrap();
+
+        database.set_current_user(Some("alice")).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        let insert_user = Command::InsertInto { table_name: users.clone(), column_names: None, values: vec![SqlValue::Integer(4).into()] };
+        assert!(matches!(
+                database.execute(insert_user.clone()),
+                Err(ExecutionError::PrivilegeMissing { privilege: Privilege::Insert, .. })
+                ));
+        let delete_users = Command::Delete { table_name: users.clone(), where_clause: None };
+        assert!(matches!(database.execute(delete_users), Err(ExecutionError::PrivilegeMissing { privilege: Privilege::Delete, .. })));
+        let drop_users = Command::DropTable { table_name: users.clone() };
+        assert!(matches!(database.execute(drop_users), Err(ExecutionError::OwnerOnlyStatement(_))));
+
+        database.set_current_user(None).unwrap();
+        database.execute(insert_user).unwrap();
+
+        // users and grants are kept in schema, and follow renamed table
+        database.execute(Command::RenameTable { table_name: users, new_table_name: SqlValue::Identificator("people".to_string()) }).unwrap();
+        database.close().unwrap();
+        let mut database = Database::from(db_file.path()).unwrap();
+        database.set_current_user(Some("alice")).unwrap();
+        assert_eq!(select_all_count(&mut database, "people"), 4);
+
+        database.set_current_user(None).unwrap();
+        database.execute(Command::DropUser { user_name: alice }).unwrap();
+        assert!(matches!(database.set_current_user(Some("alice")), Err(ExecutionError::UserNotExist(_))));
+    }
+
+    #[test]
+    fn statement_policy() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let delete_users = Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None };
+
+        database.set_statement_policy(Some(StatementPolicy::read_only()));
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        assert!(matches!(database.execute(delete_users.clone()), Err(ExecutionError::StatementNotAllowed("DELETE"))));
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+
+        database.set_statement_policy(None);
+        database.execute(delete_users).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 0);
+    }
+
+    #[test]
+    fn schema_hooks() {
+        let (_db_file, mut database) = create_users_with_id_index();
+        let schema_changes: Arc<Mutex<Vec<SchemaChange>>> = Default::default();
+        let hook_schema_changes = schema_changes.clone();
+        database.add_schema_hook(move |schema_change| hook_schema_changes.lock().unwrap().push(schema_change.clone()));
+
+        database.execute(Command::DropIndex {
+            table_name: SqlValue::Identificator("users".to_string()),
+            index_name: SqlValue::Identificator("people_id".to_string()),
+        }).unwrap();
+        database.execute(Command::RenameTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            new_table_name: SqlValue::Identificator("people".to_string()),
+        }).unwrap();
+        assert!(database.execute(Command::DropTable { table_name: SqlValue::Identificator("users".to_string()) }).is_err());
+        database.execute(Command::Delete { table_name: SqlValue::Identificator("people".to_string()), where_clause: None }).unwrap();
+        database.execute(Command::DropTable { table_name: SqlValue::Identificator("people".to_string()) }).unwrap();
+
+        let schema_changes = schema_changes.lock().unwrap();
+        assert_eq!(schema_changes.len(), 3);
+        let (index_dropped, renamed, dropped) = (&schema_changes[0], &schema_changes[1], &schema_changes[2]);
+        assert_eq!(index_dropped.kind, SchemaChangeKind::DropIndex);
+        assert_eq!(index_dropped.before.as_ref().unwrap().indexes, vec![("people_id".to_string(), "id".to_string())]);
+        assert!(index_dropped.after.as_ref().unwrap().indexes.is_empty());
+        assert_eq!(renamed.kind, SchemaChangeKind::AlterTable);
+        assert_eq!(renamed.before.as_ref().unwrap().name, "users");
+        assert_eq!(renamed.after.as_ref().unwrap().name, "people");
+        assert_eq!(dropped.kind, SchemaChangeKind::DropTable);
+        assert_eq!(dropped.before.as_ref().unwrap().name, "people");
+        assert_eq!(dropped.after, None);
+    }
+
+    #[test]
+    fn move_database() {
+        let (db_file, database) = create_users_with_id_index();
+        database.close().unwrap();
+        let temp_dir = db_file.temp_dir_path.clone();
+        let new_db_path = temp_dir.join("moved.db");
+        let new_tables_dir = temp_dir.join("moved_tables");
+
+        fs::write(&new_db_path, b"").unwrap();
+        assert!(matches!(
+                Database::move_to(db_file.path(), &new_db_path, Some(&new_tables_dir)),
+                Err(MetaCommandError::DatabaseFileAlreadyExist(_))
+                ));
+        fs::remove_file(&new_db_path).unwrap();
+
+        Database::move_to(db_file.path(), &new_db_path, Some(&new_tables_dir)).unwrap();
+        assert!(!db_file.path().exists());
+        assert!(!temp_dir.join("users.table").exists());
+        assert!(new_tables_dir.join("users.table").exists());
+
+        let mut database = Database::from(&new_db_path).unwrap();
+        assert_eq!(select_by_id_count(&mut database, "users", 2), 1);
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+        database.close().unwrap();
+
+        Database::move_to(&new_db_path, db_file.path(), None).unwrap();
+        assert!(!new_db_path.exists());
+        let mut database = Database::from(db_file.path()).unwrap();
+        assert_eq!(select_all_count(&mut database, "users"), 3);
+    }
+
+    fn create_users_with_id_index() -> (TempFile, Database) {
+        let (db_file, mut database) = open_test_database();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        database.execute(create_table).unwrap();
+        for id in 1..=3 {
+            let insert_into_table = Command::InsertInto {
+                table_name: SqlValue::Identificator("users".to_string()),
+                column_names: None,
+                values: vec![SqlValue::Integer(id).into()],
+            };
+            database.execute(insert_into_table).unwrap();
+        }
+        let create_index = Command::CreateIndex {
+            table_name: SqlValue::Identificator("users".to_string()),
+            index_name: SqlValue::Identificator("people_id".to_string()),
+            column_name: SqlValue::Identificator("id".to_string()),
+        };
+        database.execute(create_index).unwrap();
+
+        (db_file, database)
+    }
+
+    fn select_by_id_count(database: &mut Database, table_name: &str, id: i64) -> usize {
+        let select_by_id = Command::Select {
+            table_name: SqlValue::Identificator(table_name.to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: Some(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(id),
+                operator: CmpOperator::Equals,
+            }.into()),
+        };
+
+        database.execute(select_by_id).unwrap().unwrap().len()
+    }
+
+    fn select_all_count(database: &mut Database, table_name: &str) -> usize {
+        let select_from_table = Command::Select {
+            table_name: SqlValue::Identificator(table_name.to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+
+        database.execute(select_from_table).unwrap().unwrap().len()
+    }
+
     fn open_test_database() -> (TempFile, Database) {
         let db_file = TempFile::new("database.db").unwrap();
         let temp_dir_path = db_file.temp_dir_path.to_str().unwrap();
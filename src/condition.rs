@@ -0,0 +1,164 @@
+use std::fmt;
+
+use crate::binary_condition::BinaryCondition;
+use crate::cmp_operator::CmpOperator;
+use crate::lexer::SqlValue;
+use crate::row_check::RowCheck;
+use crate::table::error::TableError;
+
+/// Where clause of a statement, binary conditions combined with `AND`, `OR` and `NOT`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Condition {
+    Binary(BinaryCondition),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Exists(Box<Subquery>),
+}
+
+/// Subquery of `EXISTS (SELECT ... FROM table_name WHERE ...)`, selected columns do not matter
+/// for EXISTS, so they are not kept
+#[derive(Debug, PartialEq, Clone)]
+pub struct Subquery {
+    pub table_name: SqlValue,
+    pub where_clause: Option<Condition>,
+}
+
+impl From<BinaryCondition> for Condition {
+    fn from(binary_condition: BinaryCondition) -> Self {
+        Self::Binary(binary_condition)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Binary(binary_condition) => write!(f, "{}", binary_condition),
+            Self::And(left, right) => write!(f, "({}) AND ({})", left, right),
+            Self::Or(left, right) => write!(f, "({}) OR ({})", left, right),
+            Self::Not(condition) => write!(f, "NOT ({})", condition),
+            Self::Exists(subquery) => match &subquery.where_clause {
+                Some(where_clause) => write!(f, "EXISTS (SELECT * FROM {} WHERE {})", subquery.table_name, where_clause),
+                None => write!(f, "EXISTS (SELECT * FROM {})", subquery.table_name),
+            },
+        }
+    }
+}
+
+impl Condition {
+    pub fn and(left: Condition, right: Condition) -> Self {
+        Self::And(Box::new(left), Box::new(right))
+    }
+
+    pub fn or(left: Condition, right: Condition) -> Self {
+        Self::Or(Box::new(left), Box::new(right))
+    }
+
+    pub fn negate(condition: Condition) -> Self {
+        Self::Not(Box::new(condition))
+    }
+
+    pub fn exists(table_name: SqlValue, where_clause: Option<Condition>) -> Self {
+        Self::Exists(Box::new(Subquery { table_name, where_clause }))
+    }
+
+    /// `value IN (a, b, ...)` is a shortcut for `value = a OR value = b OR ...`
+    pub fn in_list(value: SqlValue, list: Vec<SqlValue>) -> Self {
+        list.into_iter()
+            .map(|list_value| Self::Binary(BinaryCondition { left_value: value.clone(), right_value: list_value, operator: CmpOperator::Equals }))
+            .reduce(Self::or)
+            .expect("IN list should not be empty")
+    }
+
+    pub fn compile(self, table_name: &str, column_names: &[String]) -> Result<RowCheck, TableError> {
+        self.compile_with(&|binary_condition| binary_condition.compile(table_name, column_names))
+    }
+
+    pub fn compile_for_tables(self, tables: &[(&str, &[String])]) -> Result<RowCheck, TableError> {
+        self.compile_with(&|binary_condition| binary_condition.compile_for_tables(tables))
+    }
+
+    /// Operand values of all binary conditions, in the same order as they appear in statement,
+    /// function calls are replaced by their arguments
+    pub fn values_mut(&mut self) -> Vec<&mut SqlValue> {
+        match self {
+            Self::Binary(binary_condition) => {
+                let mut values = binary_condition.left_value.operands_mut();
+                values.extend(binary_condition.right_value.operands_mut());
+                values
+            },
+            Self::And(left, right) | Self::Or(left, right) => {
+                let mut values = left.values_mut();
+                values.extend(right.values_mut());
+                values
+            },
+            Self::Not(condition) => condition.values_mut(),
+            Self::Exists(subquery) => {
+                let mut values = vec![&mut subquery.table_name];
+                values.extend(subquery.where_clause.iter_mut().flat_map(Condition::values_mut));
+                values
+            },
+        }
+    }
+
+    /// All binary conditions, in the same order as they appear in statement
+    pub fn binary_conditions(&self) -> Vec<&BinaryCondition> {
+        match self {
+            Self::Binary(binary_condition) => vec![binary_condition],
+            Self::And(left, right) | Self::Or(left, right) => {
+                let mut binary_conditions = left.binary_conditions();
+                binary_conditions.extend(right.binary_conditions());
+                binary_conditions
+            },
+            Self::Not(condition) => condition.binary_conditions(),
+            Self::Exists(subquery) => subquery.where_clause.iter().flat_map(Condition::binary_conditions).collect(),
+        }
+    }
+
+    pub fn has_subqueries(&self) -> bool {
+        match self {
+            Self::Binary(_) => false,
+            Self::And(left, right) | Self::Or(left, right) => left.has_subqueries() || right.has_subqueries(),
+            Self::Not(condition) => condition.has_subqueries(),
+            Self::Exists(_) => true,
+        }
+    }
+
+    /// Names of tables of EXISTS subqueries, in the order they appear in condition
+    pub fn subquery_table_names(&self) -> Vec<String> {
+        match self {
+            Self::Binary(_) => vec![],
+            Self::And(left, right) | Self::Or(left, right) => {
+                let mut table_names = left.subquery_table_names();
+                table_names.extend(right.subquery_table_names());
+                table_names
+            },
+            Self::Not(condition) => condition.subquery_table_names(),
+            Self::Exists(subquery) => vec![subquery.table_name.to_string()],
+        }
+    }
+
+    /// Replaces each EXISTS subquery with condition returned by `replace`
+    pub fn replace_subqueries<E>(self, replace: &mut dyn FnMut(Subquery) -> Result<Condition, E>) -> Result<Condition, E> {
+        match self {
+            Self::Binary(_) => Ok(self),
+            Self::And(left, right) => Ok(Self::and(left.replace_subqueries(replace)?, right.replace_subqueries(replace)?)),
+            Self::Or(left, right) => Ok(Self::or(left.replace_subqueries(replace)?, right.replace_subqueries(replace)?)),
+            Self::Not(condition) => Ok(Self::negate(condition.replace_subqueries(replace)?)),
+            Self::Exists(subquery) => replace(*subquery),
+        }
+    }
+
+    fn compile_with(self, compile_binary: &dyn Fn(BinaryCondition) -> Result<RowCheck, TableError>) -> Result<RowCheck, TableError> {
+        match self {
+            Self::Binary(binary_condition) => compile_binary(binary_condition),
+            Self::And(left, right) =>
+                Ok(RowCheck::And(Box::new(left.compile_with(compile_binary)?), Box::new(right.compile_with(compile_binary)?))),
+            Self::Or(left, right) =>
+                Ok(RowCheck::Or(Box::new(left.compile_with(compile_binary)?), Box::new(right.compile_with(compile_binary)?))),
+            Self::Not(condition) => Ok(RowCheck::Not(Box::new(condition.compile_with(compile_binary)?))),
+            // subqueries are replaced by database before where clause is compiled, when it is supported
+            Self::Exists(subquery) => Err(TableError::SubqueryNotSupported(subquery.table_name.to_string())),
+        }
+    }
+}
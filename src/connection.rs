@@ -1,42 +1,81 @@
-use crate::database::Database;
-use crate::MetaCommandError;
+use crate::shared_database::{DatabaseGuard, SharedDatabase};
+use crate::history::History;
+use crate::meta_command_error::MetaCommandError;
+use crate::output::{OutputSettings, OutputTarget};
 
 use std::path::Path;
 
+/// Session of cmd interface. Connections to the same database file share its tables, but each has
+/// its own current user, cursors and macros, see `SharedDatabase`.
 pub struct Connection {
-    database: Option<Database>
+    database: Option<SharedDatabase>,
+    pub output_settings: OutputSettings,
+    pub output_target: OutputTarget,
+    pub history: History,
 }
 
 impl Connection {
     pub fn blank() -> Self {
-        Self { database: None }
+        Self { database: None, output_settings: OutputSettings::default(), output_target: OutputTarget::default(),
+               history: History::default() }
     }
 
     pub fn from(&mut self, database_filepath: &Path) -> Result<(), MetaCommandError> {
-        let database = Database::from(database_filepath)?;
-        self.close();
+        let database = SharedDatabase::open(database_filepath)?;
+        self.close()?;
         self.database = Some(database);
         Ok(())
     }
 
-    pub fn close(&mut self) {
+    /// Connects to damaged database read-only, see `Database::salvage`
+    pub fn salvage(&mut self, database_filepath: &Path) -> Result<(), MetaCommandError> {
+        let database = SharedDatabase::salvage(database_filepath)?;
+        self.close()?;
+        self.database = Some(database);
+        Ok(())
+    }
+
+    /// Syncs and closes connected database, unless other connections still use it. Database is
+    /// disconnected even if sync fails, such error is returned to the caller.
+    pub fn close(&mut self) -> Result<(), MetaCommandError> {
         if self.database.is_some() {
             let db = std::mem::take(&mut self.database);
-            db.unwrap().close();
+            db.unwrap().close().map_err(MetaCommandError::ExecutionError)?;
         }
+        Ok(())
     }
 
     pub fn is_active(&self) -> bool {
         self.database.is_some()
     }
 
-    pub fn get_mut_database(&mut self) -> Option<&mut Database> {
-        self.database.as_mut()
+    /// Prompt of cmd interface, shows name of connected database and current user if it is set,
+    /// e.g. `yarrd(dev_app)> ` or `yarrd(alice@dev_app)> `
+    pub fn prompt(&self) -> String {
+        match self.database.as_ref().map(SharedDatabase::lock) {
+            Some(database) => match database.current_user() {
+                Some(user_name) => format!("yarrd({}@{})> ", user_name, database.name()),
+                None => format!("yarrd({})> ", database.name()),
+            },
+            None => "yarrd> ".to_string(),
+        }
+    }
+
+    /// Locks connected database, statements of other connections wait until lock is released
+    pub fn get_mut_database(&self) -> Option<DatabaseGuard<'_>> {
+        self.database.as_ref().map(SharedDatabase::lock)
+    }
+
+    /// Number of connections which use connected database, including this one
+    pub fn database_connection_count(&self) -> usize {
+        self.database.as_ref().map_or(0, SharedDatabase::handle_count)
     }
 }
 
 impl Drop for Connection {
     fn drop(&mut self) {
-        self.close();
+        if let Err(error) = self.close() {
+            eprintln!("error closing database: {}", error);
+        }
     }
 }
@@ -0,0 +1,221 @@
+use std::time::Instant;
+
+use crate::binary_condition::BinaryCondition;
+use crate::condition::Condition;
+use crate::command::SelectColumnName;
+use crate::lexer::SqlValue;
+use crate::query_rewriter;
+use crate::query_result::QueryResult;
+use crate::row::Row;
+use crate::row_check::RowCheck;
+use crate::table::ColumnType;
+use crate::table::error::TableError;
+
+/// Selects from cartesian product of given tables rows, each pair is a row combined of columns of
+/// all tables in the given order. Where clause is checked against combined rows, so it can compare
+/// columns of different tables. Columns that are present in several tables must be qualified.
+pub fn select_cross_join(select_column_names: &[SelectColumnName], tables: Vec<(String, QueryResult)>,
+                         where_clause: Option<Condition>, deadline: Option<Instant>) -> Result<QueryResult, TableError> {
+    let joined_table_name = tables.iter()
+        .map(|(table_name, _)| table_name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    let table_columns: Vec<(&str, &[String])> = tables.iter()
+        .map(|(table_name, rows)| (table_name.as_str(), rows.column_names.as_slice()))
+        .collect();
+    let column_types: Vec<ColumnType> = tables.iter()
+        .flat_map(|(_, rows)| rows.column_types.iter().copied())
+        .collect();
+    let column_nullable_flags: Vec<bool> = tables.iter()
+        .flat_map(|(_, rows)| rows.column_nullable_flags.iter().copied())
+        .collect();
+
+    let where_filter = match where_clause {
+        None => RowCheck::dummy(),
+        Some(where_clause) => query_rewriter::rewrite(where_clause.compile_for_tables(&table_columns)?),
+    };
+
+    let aggregates_count = select_column_names.iter().filter(|column_name| column_name.is_aggregate()).count();
+    if aggregates_count > 0 && aggregates_count < select_column_names.len() {
+        return Err(TableError::AggregateMixedWithColumns(joined_table_name))
+    }
+
+    let mut result_column_names = vec![];
+    let mut result_column_numbers = vec![];
+    // None stands for COUNT(*)
+    let mut counted_column_numbers = vec![];
+    for select_column_name in select_column_names {
+        match select_column_name {
+            SelectColumnName::AllColumns => {
+                for (table_name, column_names) in &table_columns {
+                    result_column_names.extend(column_names.iter().map(|column_name| format!("{}.{}", table_name, column_name)));
+                }
+                result_column_numbers.extend(0..column_types.len());
+            },
+            SelectColumnName::TableAllColumns(qualifier) => {
+                let qualifier = qualifier.to_string();
+                let table_index = table_columns.iter().position(|(table_name, _)| *table_name == qualifier)
+                    .ok_or(TableError::UnknownTableQualifier { table_name: joined_table_name.clone(), qualifier: qualifier.clone() })?;
+                let offset: usize = table_columns[..table_index].iter().map(|(_, column_names)| column_names.len()).sum();
+                let column_names = table_columns[table_index].1;
+                result_column_names.extend_from_slice(column_names);
+                result_column_numbers.extend(offset..offset + column_names.len());
+            },
+            SelectColumnName::Name(function_call @ SqlValue::Function(_)) =>
+                return Err(TableError::SelectedFunctionNotSupported(function_call.to_string())),
+            SelectColumnName::Name(column_name) => {
+                let column_name = column_name.to_string();
+                result_column_numbers.push(BinaryCondition::joined_column_number(&column_name, &table_columns)?);
+                result_column_names.push(column_name);
+            },
+            SelectColumnName::CountAll => {
+                result_column_names.push("COUNT(*)".to_string());
+                counted_column_numbers.push(None);
+            },
+            SelectColumnName::Count(column_name) => {
+                let column_name = column_name.to_string();
+                counted_column_numbers.push(Some(BinaryCondition::joined_column_number(&column_name, &table_columns)?));
+                result_column_names.push(format!("COUNT({})", column_name));
+            },
+        }
+    }
+
+    let result_column_types: Vec<ColumnType> = match aggregates_count {
+        0 => result_column_numbers.iter().map(|column_number| column_types[*column_number]).collect(),
+        _ => vec![ColumnType::Integer; result_column_names.len()],
+    };
+    let mut result = QueryResult {
+        column_nullable_flags: match aggregates_count {
+            0 => result_column_numbers.iter().map(|column_number| column_nullable_flags[*column_number]).collect(),
+            _ => vec![false; result_column_names.len()],
+        },
+        column_names: result_column_names,
+        column_types: result_column_types.clone(),
+        rows: vec![],
+    };
+    let mut counts = vec![0; counted_column_numbers.len()];
+
+    // row numbers of current combination, the last table's row number is advanced first
+    let mut row_numbers = vec![0; tables.len()];
+    let mut combinations_left = tables.iter().all(|(_, rows)| !rows.is_empty());
+    while combinations_left {
+        if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            return Err(TableError::StatementTimeout(joined_table_name))
+        }
+
+        let mut row = Row::new(&column_types);
+        let mut column_number = 0;
+        for ((_, rows), row_number) in tables.iter().zip(&row_numbers) {
+            let table_row = &rows.rows[*row_number];
+            for i in 0..rows.column_count() {
+                row.set_cell_bytes(&column_types, column_number, table_row.get_cell_bytes(&rows.column_types, i), table_row.cell_is_null(i))
+                    .map_err(TableError::CannotSetCell)?;
+                column_number += 1;
+            }
+        }
+
+        if where_filter.matches(&row, &column_types)? {
+            if aggregates_count > 0 {
+                for (count, column_number) in counts.iter_mut().zip(&counted_column_numbers) {
+                    match column_number {
+                        Some(column_number) if row.cell_is_null(*column_number) => {},
+                        _ => *count += 1,
+                    }
+                }
+            } else {
+                let result_row = result.spawn_row();
+                for (i, column_number) in result_column_numbers.iter().enumerate() {
+                    result_row.set_cell_bytes(&result_column_types, i, row.get_cell_bytes(&column_types, *column_number), row.cell_is_null(*column_number))
+                        .map_err(TableError::CannotSetCell)?;
+                }
+            }
+        }
+
+        combinations_left = false;
+        for (row_number, (_, rows)) in row_numbers.iter_mut().zip(&tables).rev() {
+            *row_number += 1;
+            if *row_number < rows.len() {
+                combinations_left = true;
+                break
+            }
+            *row_number = 0;
+        }
+    }
+
+    if aggregates_count > 0 {
+        let values: Vec<SqlValue> = counts.into_iter().map(SqlValue::Integer).collect();
+        result.rows.push(Row::from_sql_values(&values, &result.column_types).map_err(TableError::CannotSetCell)?);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmp_operator::CmpOperator;
+
+    fn table_rows(column_names: &[&str], rows: &[Vec<SqlValue>]) -> QueryResult {
+        let column_types = vec![ColumnType::Integer; column_names.len()];
+        QueryResult {
+            column_names: column_names.iter().map(|column_name| column_name.to_string()).collect(),
+            column_nullable_flags: vec![true; column_names.len()],
+            rows: rows.iter().map(|values| Row::from_sql_values(values, &column_types).unwrap()).collect(),
+            column_types,
+        }
+    }
+
+    fn tables() -> Vec<(String, QueryResult)> {
+        let users = table_rows(&["id"], &[vec![SqlValue::Integer(1)], vec![SqlValue::Integer(2)]]);
+        let posts = table_rows(&["id", "user_id"], &[
+            vec![SqlValue::Integer(10), SqlValue::Integer(2)],
+            vec![SqlValue::Integer(11), SqlValue::Integer(1)],
+            vec![SqlValue::Integer(12), SqlValue::Integer(2)],
+        ]);
+        vec![("users".to_string(), users), ("posts".to_string(), posts)]
+    }
+
+    #[test]
+    fn cross_join_produces_all_combinations() {
+        let result = select_cross_join(&[SelectColumnName::AllColumns], tables(), None, None).unwrap();
+        assert_eq!(result.column_names, vec!["users.id", "posts.id", "posts.user_id"]);
+        assert_eq!(result.len(), 6);
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(1), SqlValue::Integer(10), SqlValue::Integer(2)]);
+        assert_eq!(result.row_values(5).unwrap().unwrap(), vec![SqlValue::Integer(2), SqlValue::Integer(12), SqlValue::Integer(2)]);
+
+        let mut empty_tables = tables();
+        empty_tables[1].1.rows.clear();
+        assert!(select_cross_join(&[SelectColumnName::AllColumns], empty_tables, None, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn cross_join_where_clause_compares_tables_columns() {
+        let where_clause = BinaryCondition {
+            left_value: SqlValue::Identificator("users.id".to_string()),
+            right_value: SqlValue::Identificator("user_id".to_string()),
+            operator: CmpOperator::Equals,
+        };
+        let column_names = [
+            SelectColumnName::Name(SqlValue::Identificator("posts.id".to_string())),
+            SelectColumnName::Name(SqlValue::Identificator("users.id".to_string())),
+        ];
+        let result = select_cross_join(&column_names, tables(), Some(where_clause.clone().into()), None).unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(11), SqlValue::Integer(1)]);
+
+        let result = select_cross_join(&[SelectColumnName::CountAll], tables(), Some(where_clause.into()), None).unwrap();
+        assert_eq!(result.row_values(0).unwrap().unwrap(), vec![SqlValue::Integer(3)]);
+
+        let ambiguous_column_names = [SelectColumnName::Name(SqlValue::Identificator("id".to_string()))];
+        assert!(matches!(
+                select_cross_join(&ambiguous_column_names, tables(), None, None),
+                Err(TableError::AmbiguousColumnName(_))
+                ));
+
+        let unknown_column_names = [SelectColumnName::TableAllColumns(SqlValue::Identificator("comments".to_string()))];
+        assert!(matches!(
+                select_cross_join(&unknown_column_names, tables(), None, None),
+                Err(TableError::UnknownTableQualifier { .. })
+                ));
+    }
+}
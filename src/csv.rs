@@ -0,0 +1,167 @@
+use std::io::BufRead;
+
+use crate::command::InsertValue;
+use crate::execution_error::ExecutionError;
+use crate::lexer::SqlValue;
+use crate::table::ColumnType;
+
+const SEPARATOR: char = ',';
+const QUOTE: char = '"';
+
+/// Records of CSV file used by `COPY`, each is returned with number of its first line.
+/// Unquoted empty field is `None`, i.e. `NULL`, while `""` is an empty string. Quoted fields
+/// may contain separators, doubled quotes and line breaks. Empty lines are skipped.
+pub struct Records<R> {
+    reader: R,
+    line_number: usize,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R) -> Records<R> {
+        Records { reader, line_number: 0 }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<(usize, Vec<Option<String>>), ExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = String::new();
+        let mut first_line_number = self.line_number + 1;
+        loop {
+            match self.reader.read_line(&mut record) {
+                Ok(0) if record.is_empty() => return None,
+                Ok(0) => return Some(Err(ExecutionError::CsvQuoteUnclosed(first_line_number))),
+                Ok(_) => self.line_number += 1,
+                Err(error) => return Some(Err(error.into())),
+            }
+
+            // record continues on the next line while quote is open
+            if record.matches(QUOTE).count() % 2 == 1 { continue }
+            if record.trim_end_matches(['\r', '\n']).is_empty() {
+                record.clear();
+                first_line_number = self.line_number + 1;
+                continue
+            }
+            break
+        }
+
+        let record = record.strip_suffix('\n').unwrap_or(&record);
+        let record = record.strip_suffix('\r').unwrap_or(record);
+        Some(Ok((first_line_number, split_fields(record))))
+    }
+}
+
+fn split_fields(record: &str) -> Vec<Option<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = record.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            QUOTE if in_quotes && chars.peek() == Some(&QUOTE) => {
+                chars.next();
+                field.push(QUOTE);
+            },
+            QUOTE => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            },
+            SEPARATOR if !in_quotes => {
+                fields.push((quoted || !field.is_empty()).then(|| std::mem::take(&mut field)));
+                quoted = false;
+            },
+            char => field.push(char),
+        }
+    }
+    fields.push((quoted || !field.is_empty()).then_some(field));
+    fields
+}
+
+/// Converts record fields to values of table columns, all columns should be present in record
+pub fn insert_values(fields: Vec<Option<String>>, column_types: &[ColumnType]) -> Result<Vec<InsertValue>, ExecutionError> {
+    if fields.len() != column_types.len() {
+        return Err(ExecutionError::CsvValuesCountInvalid { expected: column_types.len(), actual: fields.len() })
+    }
+
+    fields.into_iter()
+        .zip(column_types)
+        .map(|(field, column_type)| {
+            let field = match field {
+                Some(field) => field,
+                None => return Ok(SqlValue::Null.into()),
+            };
+            let value = match column_type {
+                ColumnType::Integer => field.trim().parse().ok().map(SqlValue::Integer),
+                ColumnType::Float => field.trim().parse().ok().map(SqlValue::Float),
+                ColumnType::String => Some(SqlValue::String(field.clone())),
+            };
+            value.map(InsertValue::from).ok_or(ExecutionError::CsvValueInvalid { value: field, column_type: *column_type })
+        })
+        .collect()
+}
+
+/// CSV line of values, without line break. Strings are quoted only if needed to be read back as is.
+pub fn format_record(values: &[SqlValue]) -> String {
+    let fields: Vec<String> = values.iter()
+        .map(|value| match value {
+            SqlValue::Null => String::new(),
+            SqlValue::Integer(integer) => integer.to_string(),
+            // debug format uses exponent for large and small numbers, and is parsed back exactly
+            SqlValue::Float(float) => format!("{:?}", float),
+            SqlValue::String(string) if needs_quotes(string) => format!("{}{}{}", QUOTE, string.replace(QUOTE, "\"\""), QUOTE),
+            value => value.to_string(),
+        })
+        .collect();
+    fields.join(&SEPARATOR.to_string())
+}
+
+fn needs_quotes(string: &str) -> bool {
+    string.is_empty() || string.trim() != string || string.contains([SEPARATOR, QUOTE, '\r', '\n'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_records() {
+        let input = "1,alice,\r\n\n2,\"bob, \"\"the builder\"\"\",\"\"\n3,\"multi\nline\", 4.5\n";
+        let records: Vec<(usize, Vec<Option<String>>)> = Records::new(input.as_bytes()).collect::<Result<_, _>>().unwrap();
+        let some = |string: &str| Some(string.to_string());
+        assert_eq!(records, vec![
+            (1, vec![some("1"), some("alice"), None]),
+            (3, vec![some("2"), some("bob, \"the builder\""), some("")]),
+            (4, vec![some("3"), some("multi\nline"), some(" 4.5")]),
+        ]);
+
+        assert!(matches!(Records::new("1,\"open\n2,3".as_bytes()).next(), Some(Err(ExecutionError::CsvQuoteUnclosed(1)))));
+    }
+
+    #[test]
+    fn format_and_read_back() {
+        let values = vec![
+            SqlValue::Integer(-7), SqlValue::Float(1e-20), SqlValue::Null, SqlValue::String(String::new()),
+            SqlValue::String("say \"hi\", then\nleave ".to_string()), SqlValue::String("plain".to_string()),
+        ];
+        let line = format_record(&values);
+        assert_eq!(line, "-7,1e-20,,\"\",\"say \"\"hi\"\", then\nleave \",plain");
+
+        let column_types = [ColumnType::Integer, ColumnType::Float, ColumnType::Integer, ColumnType::String, ColumnType::String, ColumnType::String];
+        let (_, fields) = Records::new(line.as_bytes()).next().unwrap().unwrap();
+        let read_values: Vec<SqlValue> = insert_values(fields, &column_types).unwrap().into_iter()
+            .map(|value| match value {
+                InsertValue::Value(value) => value,
+                value => panic!("unexpected insert value {:?}", value),
+            })
+            .collect();
+        assert_eq!(read_values, values);
+
+        assert!(matches!(insert_values(vec![Some("x".to_string())], &[ColumnType::Integer]),
+                         Err(ExecutionError::CsvValueInvalid { .. })));
+        assert!(matches!(insert_values(vec![None], &[ColumnType::Integer, ColumnType::String]),
+                         Err(ExecutionError::CsvValuesCountInvalid { expected: 2, actual: 1 })));
+    }
+}
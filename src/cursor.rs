@@ -0,0 +1,72 @@
+use crate::command::FetchDirection;
+use crate::query_result::QueryResult;
+
+/// Result of `DECLARE ... CURSOR FOR SELECT`, which is fetched by parts. Rows are selected
+/// when cursor is declared, so later changes of the table are not seen by the cursor.
+/// Position is a number of rows before the cursor, so it is between 0 and rows count.
+#[derive(Debug)]
+pub struct Cursor {
+    result: QueryResult,
+    position: usize,
+}
+
+impl Cursor {
+    pub fn new(result: QueryResult) -> Cursor {
+        Cursor { result, position: 0 }
+    }
+
+    /// Returns up to `count` rows after the cursor, or before it in reverse order when fetched
+    /// backward, and moves cursor past them. `None` count fetches all rows in given direction.
+    pub fn fetch(&mut self, direction: FetchDirection, count: Option<usize>) -> QueryResult {
+        let rows = match direction {
+            FetchDirection::Forward => {
+                let end = count.map_or(self.result.len(), |count| self.position.saturating_add(count).min(self.result.len()));
+                let rows = self.result.rows[self.position..end].to_vec();
+                self.position = end;
+                rows
+            },
+            FetchDirection::Backward => {
+                let start = count.map_or(0, |count| self.position.saturating_sub(count));
+                let rows = self.result.rows[start..self.position].iter().rev().cloned().collect();
+                self.position = start;
+                rows
+            },
+        };
+
+        QueryResult {
+            column_types: self.result.column_types.clone(),
+            column_names: self.result.column_names.clone(),
+            column_nullable_flags: self.result.column_nullable_flags.clone(),
+            rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::SqlValue;
+    use crate::row::Row;
+    use crate::table::ColumnType;
+
+    fn fetched_values(result: QueryResult) -> Vec<SqlValue> {
+        result.rows.iter().map(|row| row.get_cell_sql_value(&result.column_types, 0).unwrap()).collect()
+    }
+
+    #[test]
+    fn fetch_forward_and_backward() {
+        let column_types = vec![ColumnType::Integer];
+        let rows = (1..=5).map(|i| Row::from_sql_values(&[SqlValue::Integer(i)], &column_types).unwrap()).collect();
+        let result = QueryResult { column_types, column_names: vec!["id".to_string()], column_nullable_flags: vec![true], rows };
+        let mut cursor = Cursor::new(result);
+        let integers = |values: &[i64]| values.iter().copied().map(SqlValue::Integer).collect::<Vec<SqlValue>>();
+
+        assert_eq!(fetched_values(cursor.fetch(FetchDirection::Forward, Some(2))), integers(&[1, 2]));
+        assert_eq!(fetched_values(cursor.fetch(FetchDirection::Forward, Some(2))), integers(&[3, 4]));
+        assert_eq!(fetched_values(cursor.fetch(FetchDirection::Backward, Some(3))), integers(&[4, 3, 2]));
+        assert_eq!(fetched_values(cursor.fetch(FetchDirection::Forward, None)), integers(&[2, 3, 4, 5]));
+        assert!(cursor.fetch(FetchDirection::Forward, Some(10)).is_empty());
+        assert_eq!(fetched_values(cursor.fetch(FetchDirection::Backward, None)), integers(&[5, 4, 3, 2, 1]));
+        assert!(cursor.fetch(FetchDirection::Backward, Some(1)).is_empty());
+    }
+}
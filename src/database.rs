@@ -1,29 +1,114 @@
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Write};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::fs::{self, File, OpenOptions};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::command::{Command, ColumnDefinition, FieldAssignment, SelectColumnName};
+use crate::command::{Command, ColumnDefinition, ConflictAction, FieldAssignment, IndexHint, InsertValue, SelectColumnName};
+use crate::condition::{Condition, Subquery};
 use crate::binary_condition::BinaryCondition;
-use crate::lexer::SqlValue;
-use crate::table::{Table, ColumnType, Constraint};
+use crate::cmp_operator::CmpOperator;
+use crate::lexer::{SqlValue, Token};
+use crate::table::error::TableError;
+use crate::table::{Table, ColumnType, Constraint, ForeignKey, TableOptions, TOMBSTONES_EXTENSION, CHANGE_LOG_EXTENSION, CHECKSUMS_EXTENSION};
 use crate::execution_error::ExecutionError;
 use crate::meta_command_error::MetaCommandError;
-use crate::query_result::QueryResult;
+use crate::query_result::{Pagination, QueryResult};
 use crate::helpers::get_timestamp;
+use crate::sequence::{Sequence, SEQUENCE_EXTENSION};
+use crate::hash_index::{HashIndexStats, HASH_INDEX_EXTENSION};
+use crate::rename_journal::RenameJournal;
+use crate::statement_stats::StatementStats;
+use crate::random::Random;
+use crate::session::Session;
+use crate::series;
+use crate::cross_join;
+use crate::system_table;
+use crate::subquery;
+use crate::cursor::Cursor;
+use crate::sql_macro::{self, Macro, MacroError};
+use crate::ordering;
+use crate::csv;
 use crate::parser;
+use crate::warning::{self, Warning};
+use crate::access::{self, Privilege, StatementKind, StatementPolicy, User};
+use crate::schema_diff::{Catalog, ColumnSchema, TableSchema};
+use crate::schema_hook::{SchemaChange, SchemaChangeKind, SchemaHook};
 
 const TABLE_EXTENSION: &str = "table";
+// rows generated by `.seed` are regenerated this many times at most if they fail CHECK constraint
+const SEED_ATTEMPTS_PER_ROW: usize = 100;
+const SEED_NULL_ONE_IN: u64 = 10;
+const SEED_NUMBER_BOUND: u64 = 1000;
+const SEED_STRING_MAX_LENGTH: u64 = 12;
+pub const SEQUENCE_SCHEMA_PREFIX: &str = "SEQUENCE ";
+pub const USER_SCHEMA_PREFIX: &str = "USER ";
+const QUARANTINE_DIR_NAME: &str = "quarantine";
+// tables with this prefix exist only during ALTER TABLE rebuild, they are never written to
+// schema, and user tables cannot be named with it
+const TEMPORARY_TABLE_PREFIX: &str = "__temp_";
+// name of rename journal of moved table files, it is never a table name, so interrupted move
+// is always rolled back when database is connected at its old path
+const MOVE_JOURNAL_NAME: &str = "__temp_movedb";
+// appended to database file path, schema is written there first
+const SCHEMA_SWAP_EXTENSION: &str = ".swap";
+// files with other extensions are never treated as orphaned, since they are not created by database
+const DATABASE_FILE_EXTENSIONS: [&str; 6] = [
+    TABLE_EXTENSION, HASH_INDEX_EXTENSION, TOMBSTONES_EXTENSION, CHANGE_LOG_EXTENSION, CHECKSUMS_EXTENSION, SEQUENCE_EXTENSION,
+];
+
+/// Limits of bytes taken by table files with rows, set by application which embeds database,
+/// see `Database::set_size_quota`. Index, change log and checksums files are not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SizeQuota {
+    pub max_table_size: Option<u64>,
+    /// Sum of sizes of all tables
+    pub max_database_size: Option<u64>,
+}
 
 pub struct Database {
     tables: HashMap<String, Table>,
+    sequences: HashMap<String, Sequence>,
+    users: BTreeMap<String, User>,
+    // current user, cursors, macros and other settings of the client which executes statements
+    session: Session,
+    schema_hooks: Vec<SchemaHook>,
+    size_quota: SizeQuota,
     database_filepath: PathBuf,
     tables_dir: PathBuf,
+    // tables dir as it is written in database file, relative paths are resolved from database file dir
+    stored_tables_dir: PathBuf,
+    batch_in_progress: bool,
+    schema_flush_pending: bool,
+    statement_deadline: Option<Instant>,
+    // renames made during batch, they are committed after deferred schema write
+    pending_rename_journals: Vec<RenameJournal>,
+    // modification time of database file when it was last read or written, other changes are made
+    // by external tools and are picked up by `reload`
+    schema_modified_at: Option<SystemTime>,
+    // opened by `salvage`: only reading statements are executed and nothing is written back to files
+    salvaged: bool,
+    // tables of salvaged database which could not be opened, with the reason
+    offline_tables: BTreeMap<String, String>,
 }
 
 impl Database {
     pub fn from(database_filepath: &Path) -> Result<Database, MetaCommandError> {
+        Self::open(database_filepath, false)
+    }
+
+    /// Opens database with damaged tables, so that the rest of data can be selected and exported.
+    /// Tables which cannot be opened are left offline instead of failing the whole database, and
+    /// scans skip rows of unreadable pages and rows which do not match their checksums, reporting
+    /// them as warnings. Database is read-only, and its files are not changed, including schema.
+    pub fn salvage(database_filepath: &Path) -> Result<Database, MetaCommandError> {
+        Self::open(database_filepath, true)
+    }
+
+    fn open(database_filepath: &Path, salvage: bool) -> Result<Database, MetaCommandError> {
         let mut tables = HashMap::new();
+        let mut sequences = HashMap::new();
         let file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -32,18 +117,108 @@ impl Database {
         let mut reader = BufReader::new(file);
         let mut tables_dir = String::new();
         reader.read_line(&mut tables_dir)?;
-        let tables_dir = PathBuf::from(tables_dir.trim());
+        let stored_tables_dir = PathBuf::from(tables_dir.trim());
+        let tables_dir = Self::resolve_tables_dir(database_filepath, &stored_tables_dir);
         if !tables_dir.is_dir() {
             return Err(MetaCommandError::DatabaseTablesDirNotExist(tables_dir));
         }
 
-        for line in reader.lines() {
-            let line = line?;
-            let table = Self::parse_schema_line(tables_dir.as_path(), line.trim())?;
-            tables.insert(table.name().to_string(), table);
+        let lines = reader.lines().collect::<io::Result<Vec<String>>>()?;
+        let schema_table_names = lines.iter()
+            .filter(|line| !line.trim().starts_with(SEQUENCE_SCHEMA_PREFIX) && !line.trim().starts_with(USER_SCHEMA_PREFIX))
+            .filter_map(|line| line.split_whitespace().next().map(str::to_string))
+            .collect();
+        // recovery renames table files, and damaged database is not changed until it is salvaged
+        if !salvage {
+            Self::recover_interrupted_renames(tables_dir.as_path(), &schema_table_names)?;
+        }
+
+        let mut users = BTreeMap::new();
+        let mut offline_tables = BTreeMap::new();
+        for line in lines {
+            if let Some(sequence_name) = line.trim().strip_prefix(SEQUENCE_SCHEMA_PREFIX) {
+                let sequence = Sequence::new(tables_dir.as_path(), sequence_name);
+                sequences.insert(sequence_name.to_string(), sequence);
+            } else if let Some(user_line) = line.trim().strip_prefix(USER_SCHEMA_PREFIX) {
+                let user = User::from_schema_line(user_line)
+                    .ok_or_else(|| MetaCommandError::ParseError(format!("invalid user definition: {}", user_line)))?;
+                users.insert(user.name.clone(), user);
+            } else {
+                match Self::parse_schema_line(tables_dir.as_path(), line.trim()) {
+                    Ok(mut table) => {
+                        table.set_salvage(salvage);
+                        tables.insert(table.name().to_string(), table);
+                    },
+                    Err(error) if salvage => {
+                        let table_name = line.split_whitespace().next().unwrap_or_default();
+                        offline_tables.insert(table_name.to_string(), error.to_string());
+                    },
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        Ok(Self {
+            tables,
+            sequences,
+            users,
+            session: Session::default(),
+            schema_hooks: vec![],
+            size_quota: SizeQuota::default(),
+            database_filepath: PathBuf::from(database_filepath),
+            tables_dir,
+            stored_tables_dir,
+            batch_in_progress: false,
+            schema_flush_pending: false,
+            statement_deadline: None,
+            pending_rename_journals: vec![],
+            schema_modified_at: Self::modified_at(database_filepath),
+            salvaged: salvage,
+            offline_tables,
+        })
+    }
+
+    /// Tables of salvaged database which could not be opened, with the reason
+    pub fn offline_tables(&self) -> &BTreeMap<String, String> {
+        &self.offline_tables
+    }
+
+    pub fn is_salvaged(&self) -> bool {
+        self.salvaged
+    }
+
+    /// Re-reads database file and opens tables and sequences which were added to it by other tools.
+    /// Tables which are already open are not re-read, since their row counts in memory are newer than
+    /// ones in file, and tables missing from file are kept, since schema is not written on each
+    /// statement. Users are not re-read as well. Returns names of added tables.
+    pub fn reload(&mut self) -> Result<Vec<String>, MetaCommandError> {
+        let reloaded = Self::open(&self.database_filepath, self.salvaged)?;
+
+        let mut added_table_names = vec![];
+        for (table_name, table) in reloaded.tables {
+            if let Entry::Vacant(entry) = self.tables.entry(table_name) {
+                added_table_names.push(entry.key().clone());
+                entry.insert(table);
+            }
+        }
+
+        for (sequence_name, sequence) in reloaded.sequences {
+            self.sequences.entry(sequence_name).or_insert(sequence);
         }
+        self.offline_tables = reloaded.offline_tables;
 
-        Ok(Self { tables, database_filepath: PathBuf::from(database_filepath), tables_dir })
+        self.schema_modified_at = reloaded.schema_modified_at;
+        added_table_names.sort();
+        Ok(added_table_names)
+    }
+
+    /// True if database file was changed by someone else since it was last read or written
+    pub fn schema_changed_on_disk(&self) -> bool {
+        Self::modified_at(&self.database_filepath) != self.schema_modified_at
+    }
+
+    fn modified_at(database_filepath: &Path) -> Option<SystemTime> {
+        fs::metadata(database_filepath).and_then(|metadata| metadata.modified()).ok()
     }
 
     pub fn create(database_filepath: &Path, tables_dir_path: &Path) -> Result<(), MetaCommandError> {
@@ -67,7 +242,7 @@ impl Database {
             }
         }
 
-        writeln!(database_file, "{}", tables_dir.display())?;
+        writeln!(database_file, "{}", Self::relative_tables_dir(&database_filepath, &tables_dir)?.display())?;
         // ideally we should check if it is succesfull, should handle in "cascade" file
         // manager
 
@@ -84,49 +259,243 @@ impl Database {
         for table_name in table_names {
             database.drop_table(table_name).map_err(MetaCommandError::ExecutionError)?;
         }
+        for (_name, sequence) in database.sequences.drain() {
+            sequence.destroy()?;
+        }
 
         // TODO: use cascade file manager to panic from unrecoverable errors with correct message
         fs::remove_file(database_filepath).map_err(MetaCommandError::IoError)?;
         Ok(())
     }
 
-    pub fn close(self) {
-        self.flush_schema();
+    /// Moves database file to `new_database_filepath`, and table, index and sequence files to
+    /// `new_tables_dir` if it is given. New database file is written to a temporary file first and
+    /// renamed, so tables dir line is replaced at once. Moved files are recorded in rename journal,
+    /// and are moved back if database file cannot be written.
+    pub fn move_to(database_filepath: &Path, new_database_filepath: &Path, new_tables_dir: Option<&Path>) -> Result<(), MetaCommandError> {
+        let same_database_file = new_database_filepath.exists() &&
+            fs::canonicalize(database_filepath)? == fs::canonicalize(new_database_filepath)?;
+        if new_database_filepath.exists() && !same_database_file {
+            return Err(MetaCommandError::DatabaseFileAlreadyExist(PathBuf::from(new_database_filepath)));
+        }
+
+        let database = Self::from(database_filepath)?;
+        let tables_dir = database.tables_dir.clone();
+        let filepaths: Vec<PathBuf> = database.tables.values()
+            .flat_map(|table| table.filepaths())
+            .chain(database.sequences.values().map(|sequence| sequence.filepath().to_path_buf()))
+            .collect();
+        database.close().map_err(MetaCommandError::ExecutionError)?;
+
+        let new_tables_dir = new_tables_dir.map_or_else(|| tables_dir.clone(), PathBuf::from);
+        fs::create_dir_all(&new_tables_dir)?;
+        let moves = match fs::canonicalize(&tables_dir)? == fs::canonicalize(&new_tables_dir)? {
+            true => vec![],
+            false => filepaths.into_iter()
+                .filter(|filepath| filepath.exists())
+                .map(|filepath| {
+                    let new_filepath = new_tables_dir.join(filepath.file_name().unwrap());
+                    (filepath, new_filepath)
+                })
+                .collect(),
+        };
+        if let Some((_, new_filepath)) = moves.iter().find(|(_, new_filepath)| new_filepath.exists()) {
+            return Err(MetaCommandError::TablesDirFileAlreadyExist(new_filepath.clone()));
+        }
+
+        let new_database_name = new_database_filepath.file_stem().unwrap_or_default().to_string_lossy();
+        let journal = RenameJournal::new(&tables_dir, MOVE_JOURNAL_NAME, &new_database_name, moves);
+        journal.apply()?;
+
+        if let Err(error) = Self::write_moved_database_file(database_filepath, new_database_filepath, &new_tables_dir) {
+            journal.rollback()?;
+            return Err(error.into())
+        }
+        if !same_database_file {
+            fs::remove_file(database_filepath)?;
+        }
+        journal.commit()?;
+        Ok(())
+    }
+
+    /// Relative tables dir is resolved from dir of database file, so that database can be moved
+    /// together with its tables dir. Database files written before that stored tables dir relative
+    /// to working dir, such path is used if there is no tables dir next to database file.
+    fn resolve_tables_dir(database_filepath: &Path, stored_tables_dir: &Path) -> PathBuf {
+        if stored_tables_dir.is_absolute() {
+            return PathBuf::from(stored_tables_dir)
+        }
+
+        let database_dir = database_filepath.parent().unwrap_or(Path::new(""));
+        let tables_dir = database_dir.join(stored_tables_dir);
+        if !tables_dir.is_dir() && stored_tables_dir.is_dir() {
+            PathBuf::from(stored_tables_dir)
+        } else {
+            tables_dir
+        }
+    }
+
+    /// Tables dir path to write into database file: relative to database file dir if tables dir
+    /// is inside of it, absolute otherwise
+    fn relative_tables_dir(database_filepath: &Path, tables_dir: &Path) -> io::Result<PathBuf> {
+        let database_dir = database_filepath.parent().unwrap_or(Path::new(""));
+        match tables_dir.strip_prefix(database_dir) {
+            Ok(relative_tables_dir) if !relative_tables_dir.as_os_str().is_empty() => Ok(PathBuf::from(relative_tables_dir)),
+            Ok(_) => Ok(PathBuf::from(".")),
+            Err(_) => fs::canonicalize(tables_dir),
+        }
+    }
+
+    fn write_moved_database_file(database_filepath: &Path, new_database_filepath: &Path, new_tables_dir: &Path) -> io::Result<()> {
+        let contents = fs::read_to_string(database_filepath)?;
+        let schema_lines = contents.split_once('\n').map_or("", |(_tables_dir_line, schema_lines)| schema_lines);
+
+        let mut temporary_filepath = new_database_filepath.as_os_str().to_owned();
+        temporary_filepath.push(".tmp");
+        let mut temporary_file = File::create(&temporary_filepath)?;
+        writeln!(temporary_file, "{}", Self::relative_tables_dir(new_database_filepath, new_tables_dir)?.display())?;
+        temporary_file.write_all(schema_lines.as_bytes())?;
+        temporary_file.sync_data()?;
+
+        fs::rename(&temporary_filepath, new_database_filepath)
+    }
+
+    /// Writes schema and all cached table pages to disk and waits until they are
+    /// synced, so that no data is lost if process is killed afterwards.
+    pub fn sync(&mut self) -> Result<(), ExecutionError> {
+        // offline tables are missing from schema, and damaged files are kept as they were found
+        if self.salvaged {
+            return Ok(())
+        }
+        self.write_schema()?;
+        for table in self.tables.values_mut() {
+            table.sync()?;
+        }
+        Ok(())
+    }
+
+    pub fn close(mut self) -> Result<(), ExecutionError> {
+        self.sync()
+    }
+
+    /// Files in tables dir which were created by database, but are not referenced by schema,
+    /// e.g. swap files or leftovers of interrupted `ALTER TABLE`. Subdirs are not scanned.
+    /// Salvaged database reports no files, since files of offline tables are not known.
+    pub fn orphaned_files(&self) -> io::Result<Vec<PathBuf>> {
+        if self.salvaged {
+            return Ok(vec![])
+        }
+        let referenced_filepaths: HashSet<PathBuf> = self.tables.values()
+            .flat_map(|table| table.filepaths())
+            .chain(self.sequences.values().map(|sequence| sequence.filepath().to_path_buf()))
+            .collect();
+
+        let mut orphaned_files = vec![];
+        for entry in fs::read_dir(&self.tables_dir)? {
+            let path = entry?.path();
+            let created_by_database = path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| DATABASE_FILE_EXTENSIONS.contains(&extension));
+
+            if path.is_file() && created_by_database && !referenced_filepaths.contains(&path) {
+                orphaned_files.push(path);
+            }
+        }
+
+        orphaned_files.sort();
+        Ok(orphaned_files)
+    }
+
+    /// Removes orphaned files and returns their paths
+    pub fn delete_orphaned_files(&self) -> io::Result<Vec<PathBuf>> {
+        let orphaned_files = self.orphaned_files()?;
+        for path in &orphaned_files {
+            fs::remove_file(path)?;
+        }
+        Ok(orphaned_files)
+    }
+
+    /// Moves orphaned files into `quarantine` subdir of tables dir and returns their new paths
+    pub fn quarantine_orphaned_files(&self) -> io::Result<Vec<PathBuf>> {
+        let quarantine_dir = self.tables_dir.join(QUARANTINE_DIR_NAME);
+        fs::create_dir_all(&quarantine_dir)?;
+
+        let mut quarantined_files = vec![];
+        for path in self.orphaned_files()? {
+            let quarantined_path = quarantine_dir.join(path.file_name().unwrap());
+            fs::rename(&path, &quarantined_path)?;
+            quarantined_files.push(quarantined_path);
+        }
+        Ok(quarantined_files)
     }
 
     pub fn parse_schema_line(tables_dir: &Path, table_definition_line: &str) -> Result<Table, MetaCommandError> {
-        let parser::TableSchemaDefinitionLine { name, row_count, column_definitions, indexes_definitions } =
+        let parser::TableSchemaDefinitionLine { name, row_count, null_counts, column_definitions, indexes_definitions, options } =
             parser::parse_schema_line(table_definition_line)
             .map_err(|parser_error| MetaCommandError::ParseError(parser_error.to_string()))?;
 
         let table_filepath = Self::table_filepath(tables_dir, &name);
 
-        Ok(Table::new(table_filepath, &name, row_count, &column_definitions, indexes_definitions)?)
+        Ok(Table::new(table_filepath, &name, row_count, null_counts, &column_definitions, indexes_definitions, options)?)
     }
 
-    // TODO: return result instead of unwrapping and handle err (probably via logging)
-    fn flush_schema(&self) {
-        let mut database_file = OpenOptions::new()
+    fn flush_schema(&mut self) -> Result<(), ExecutionError> {
+        if self.batch_in_progress {
+            self.schema_flush_pending = true;
+        } else {
+            self.write_schema()?;
+        }
+        Ok(())
+    }
+
+    // schema is written into swap file which then replaces database file, so failed write, e.g. when
+    // there is no space left on device, leaves previous schema intact instead of a truncated one
+    fn write_schema(&mut self) -> Result<(), ExecutionError> {
+        let mut swap_filepath = self.database_filepath.clone().into_os_string();
+        swap_filepath.push(SCHEMA_SWAP_EXTENSION);
+        let swap_filepath = PathBuf::from(swap_filepath);
+
+        let written = OpenOptions::new()
             .write(true)
+            .create(true)
             .truncate(true)
-            .open(&self.database_filepath).unwrap();
+            .open(&swap_filepath)
+            .and_then(|mut swap_file| {
+                self.write_schema_into(&mut swap_file)?;
+                swap_file.sync_all()
+            })
+            .and_then(|()| fs::rename(&swap_filepath, &self.database_filepath));
+        match written {
+            Ok(()) => {
+                self.schema_modified_at = Self::modified_at(&self.database_filepath);
+                Ok(())
+            },
+            Err(error) => match fs::remove_file(&swap_filepath) {
+                Ok(()) => Err(error.into()),
+                Err(cleanup_error) => Err(ExecutionError::SwapCleanupFailed { error, cleanup_error }),
+            },
+        }
+    }
 
-        writeln!(database_file, "{}", self.tables_dir.to_str().unwrap()).unwrap();
+    fn write_schema_into(&self, database_file: &mut File) -> io::Result<()> {
+        writeln!(database_file, "{}", self.stored_tables_dir.display())?;
         for (table_name, table) in &self.tables {
-            write!(database_file, "{}", table_name).unwrap();
-            write!(database_file, " {}", table.row_count).unwrap();
+            if Self::is_temporary_table_name(table_name) { continue }
+
+            write!(database_file, "{}", table_name)?;
+            write!(database_file, " {}", table.row_count)?;
             for i in 0..table.column_types().len() {
-                write!(database_file, " {} {}", table.column_names()[i], table.column_types()[i]).unwrap();
+                write!(database_file, " {} {}", table.column_names()[i], table.column_types()[i])?;
                 for constraint in &table.column_constraints()[i] {
-                    write!(database_file, " {}", constraint).unwrap();
+                    write!(database_file, " {}", constraint)?;
                 }
 
                 if i < table.column_types().len() - 1 {
-                    write!(database_file, ",").unwrap();
+                    write!(database_file, ",")?;
                 }
             }
 
-            write!(database_file, ";").unwrap();
+            write!(database_file, ";")?;
 
             let indexes: Vec<_> =
                 table.column_indexes().iter().enumerate()
@@ -136,56 +505,582 @@ impl Database {
 
             for index_number in 0..indexes.len() {
                 let (column_number, index_ref) = indexes[index_number];
-                write!(database_file, " {} {}", column_number, index_ref.name).unwrap();
+                write!(database_file, " {} {}", column_number, index_ref.name)?;
                 if index_number < indexes.len() - 1 {
-                    write!(database_file, ",").unwrap();
+                    write!(database_file, ",")?;
                 }
             }
 
-            write!(database_file, ";").unwrap();
-            writeln!(database_file).unwrap();
+            write!(database_file, ";")?;
+
+            let option_names = table.options().names();
+            if !option_names.is_empty() {
+                write!(database_file, " {}", option_names.join(", "))?;
+            }
+            write!(database_file, ";")?;
+
+            for null_count in table.null_counts() {
+                write!(database_file, " {}", null_count)?;
+            }
+            writeln!(database_file, ";")?;
         }
+
+        for sequence in self.sequences.values() {
+            writeln!(database_file, "{}{}", SEQUENCE_SCHEMA_PREFIX, sequence.name)?;
+        }
+        for user in self.users.values() {
+            writeln!(database_file, "{}{}", USER_SCHEMA_PREFIX, user.to_schema_line())?;
+        }
+
+        Ok(())
     }
 
     pub fn execute(&mut self, command: Command) -> Result<Option<QueryResult>, ExecutionError> {
+        self.statement_deadline = self.session.statement_timeout.map(|timeout| Instant::now() + timeout);
+        self.session.last_statement_warnings = warning::check_command(&command);
+        self.session.last_statement_pagination = None;
+        let schema_change = SchemaChangeKind::of(&command)
+            .map(|(kind, table_name, new_table_name)| (kind, self.table_schema(&table_name), new_table_name));
+        let result = self.check_statement_policy(&command)
+            .and_then(|()| self.check_access(&command))
+            .and_then(|()| self.execute_command(command))
+            .map_err(|error| match error.is_storage_full() {
+                true => ExecutionError::StorageFull(Box::new(error)),
+                false => error,
+            });
+
+        self.session.last_statement_stats = StatementStats::default();
+        for (table_name, table) in self.tables.iter_mut() {
+            let table_stats = table.take_stats();
+            if table_stats.damaged_rows_skipped > 0 {
+                self.session.last_statement_warnings.push(
+                    Warning::DamagedRowsSkipped { table_name: table_name.clone(), row_count: table_stats.damaged_rows_skipped }
+                );
+            }
+            self.session.last_statement_stats += table_stats;
+        }
+
+        // `CREATE TABLE IF NOT EXISTS` of existing table changes nothing
+        let schema_change = schema_change.filter(|(kind, before, _)| *kind != SchemaChangeKind::CreateTable || before.is_none());
+        if let (Ok(_), Some((kind, before, table_name))) = (&result, schema_change) {
+            let schema_change = SchemaChange { kind, before, after: self.table_schema(&table_name) };
+            for schema_hook in self.schema_hooks.iter_mut() {
+                schema_hook(&schema_change);
+            }
+        }
+
+        result
+    }
+
+    /// Sets user whose privileges are checked on each statement, `None` switches back to database owner
+    pub fn set_current_user(&mut self, user_name: Option<&str>) -> Result<(), ExecutionError> {
+        if let Some(user_name) = user_name {
+            if !self.users.contains_key(user_name) {
+                return Err(ExecutionError::UserNotExist(user_name.to_string()))
+            }
+        }
+        self.session.current_user = user_name.map(str::to_string);
+        Ok(())
+    }
+
+    pub fn current_user(&self) -> Option<&str> {
+        self.session.current_user.as_deref()
+    }
+
+    /// Exchanges session of database with the given one, so that statements are executed in it
+    pub(crate) fn swap_session(&mut self, session: &mut Session) {
+        std::mem::swap(&mut self.session, session);
+    }
+
+    /// Restricts statements which can be executed, `None` allows all of them
+    pub fn set_statement_policy(&mut self, statement_policy: Option<StatementPolicy>) {
+        self.session.statement_policy = statement_policy;
+    }
+
+    /// Adds callback which is called after each statement which created, dropped or altered a table,
+    /// or created or dropped its index. Hooks are called in order they were added.
+    pub fn add_schema_hook(&mut self, schema_hook: impl FnMut(&SchemaChange) + Send + 'static) {
+        self.schema_hooks.push(Box::new(schema_hook));
+    }
+
+    pub fn clear_schema_hooks(&mut self) {
+        self.schema_hooks.clear();
+    }
+
+    /// Limits growth of table files, quotas are checked when a table needs a new page, so
+    /// files which are already bigger are not truncated, but cannot grow further
+    pub fn set_size_quota(&mut self, size_quota: SizeQuota) {
+        self.size_quota = size_quota;
+    }
+
+    /// Non-fatal issues found in the last executed statement
+    pub fn last_statement_warnings(&self) -> &[Warning] {
+        &self.session.last_statement_warnings
+    }
+
+    /// Replaces calls of macros created with `CREATE MACRO` in statement tokens, it should be done before they are parsed
+    pub fn expand_macros(&self, tokens: Vec<Token>) -> Result<Vec<Token>, MacroError> {
+        sql_macro::expand(tokens, &self.session.macros)
+    }
+
+    /// Total number of matching rows of the last executed select with `LIMIT` or `OFFSET`
+    pub fn last_statement_pagination(&self) -> Option<Pagination> {
+        self.session.last_statement_pagination
+    }
+
+    pub fn filepath(&self) -> &Path {
+        &self.database_filepath
+    }
+
+    /// Name of database file without extension
+    pub fn name(&self) -> String {
+        self.database_filepath.file_stem()
+            .map(|file_stem| file_stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Tables and sequences as they are written to database file
+    pub fn catalog(&self) -> Catalog {
+        let tables = self.tables.iter()
+            .filter(|(table_name, _table)| !Self::is_temporary_table_name(table_name))
+            .map(|(table_name, table)| Self::build_table_schema(table_name, table))
+            .collect();
+
+        let mut catalog = Catalog { tables, sequences: self.sequences.keys().cloned().collect() };
+        catalog.sort();
+        catalog
+    }
+
+    fn table_schema(&self, table_name: &str) -> Option<TableSchema> {
+        self.tables.get(table_name).map(|table| Self::build_table_schema(table_name, table))
+    }
+
+    fn build_table_schema(table_name: &str, table: &Table) -> TableSchema {
+        let columns = (0..table.column_types().len())
+            .map(|i| ColumnSchema {
+                name: table.column_names()[i].clone(),
+                kind: table.column_types()[i],
+                constraints: table.column_constraints()[i].clone(),
+            })
+            .collect();
+        let indexes = table.column_indexes().iter()
+            .zip(table.column_names())
+            .filter_map(|(index, column_name)| index.as_ref().map(|index| (index.name.clone(), column_name.clone())))
+            .collect();
+        TableSchema { name: table_name.to_string(), columns, indexes, options: table.options() }
+    }
+
+    /// Resources used by the last executed statement, both successful and failed one.
+    /// Usage of tables dropped or rebuilt by the statement is not included.
+    pub fn last_statement_stats(&self) -> StatementStats {
+        self.session.last_statement_stats
+    }
+
+    /// Stats of indexes of the given table or of all tables, ordered by table name
+    pub fn index_stats(&self, table_name: Option<&str>) -> Result<Vec<(String, HashIndexStats)>, ExecutionError> {
+        let mut table_names: Vec<&String> = match table_name {
+            Some(table_name) => vec![
+                self.tables.get_key_value(table_name).ok_or(ExecutionError::TableNotExist(table_name.to_string()))?.0
+            ],
+            None => self.tables.keys().collect(),
+        };
+        table_names.sort();
+
+        let mut index_stats = vec![];
+        for table_name in table_names {
+            for index in self.tables[table_name].column_indexes().iter().flatten() {
+                index_stats.push((table_name.clone(), index.stats().map_err(TableError::HashIndexError)?));
+            }
+        }
+        Ok(index_stats)
+    }
+
+    /// Bytes and decoded contents of table file page, or of hash index bucket if index name is given
+    pub fn page_dump(&mut self, table_name: &str, index_name: Option<&str>, number: u64) -> Result<String, ExecutionError> {
+        let table = self.get_mut_table(table_name)?;
+        let dump = match index_name {
+            Some(index_name) => table.bucket_dump(index_name, number)?,
+            None => table.page_dump(number)?,
+        };
+        Ok(dump)
+    }
+
+    fn execute_command(&mut self, command: Command) -> Result<Option<QueryResult>, ExecutionError> {
         match command {
-            Command::CreateTable { table_name, columns } => self.create_table(table_name, columns),
-            Command::DropTable { table_name } => self.drop_table(table_name),
+            Command::CreateTable { table_name, columns, options, if_not_exists } => {
+                Self::check_table_name_not_reserved(&table_name)?;
+                if if_not_exists && self.tables.contains_key(&table_name.to_string()) {
+                    return Ok(None)
+                }
+                self.create_table(table_name, columns, options)
+            },
+            Command::DropTable { table_name } => {
+                self.check_table_not_referenced(&table_name, None, false)?;
+                let result = self.drop_table(table_name.clone())?;
+                self.rename_grants(&table_name.to_string(), None)?;
+                Ok(result)
+            },
             Command::Select { table_name, column_names, where_clause } => self.select_rows(table_name, column_names, where_clause),
-            Command::InsertInto { table_name, column_names, values } => self.insert_rows(table_name, column_names, values),
-            Command::Update { table_name, field_assignments, where_clause } => self.update_rows(table_name, field_assignments, where_clause),
-            Command::Delete { table_name, where_clause } => self.delete_rows(table_name, where_clause),
-            Command::RenameTable { table_name, new_table_name } => self.rename_table(table_name, new_table_name),
-            Command::RenameTableColumn { table_name, column_name, new_column_name } =>
-                self.rename_table_column(table_name, column_name, new_column_name),
+            Command::SelectCrossJoin { table_names, column_names, where_clause } =>
+                self.select_cross_join(table_names, column_names, where_clause),
+            Command::InsertInto { table_name, column_names, values } => self.insert_rows(table_name, column_names, values, None),
+            Command::Update { table_name, field_assignments, where_clause } => self.update_rows(table_name, field_assignments, where_clause, None),
+            Command::Delete { table_name, where_clause } => self.delete_rows(table_name, where_clause, None),
+            Command::Undelete { table_name, where_clause } => self.undelete_rows(table_name, where_clause),
+            Command::RenameTable { table_name, new_table_name } => {
+                Self::check_table_name_not_reserved(&new_table_name)?;
+                self.check_table_not_referenced(&table_name, None, true)?;
+                let result = self.rename_table(table_name.clone(), new_table_name.clone())?;
+                self.rename_grants(&table_name.to_string(), Some(&new_table_name.to_string()))?;
+                Ok(result)
+            },
+            Command::RenameTableColumn { table_name, column_name, new_column_name } => {
+                self.check_table_not_referenced(&table_name, Some(&column_name), true)?;
+                self.rename_table_column(table_name, column_name, new_column_name)
+            },
             Command::AddTableColumn { table_name, column_definition } => self.add_table_column(table_name, column_definition),
             Command::AddColumnConstraint { table_name, column_name, constraint } =>
                 self.add_table_column_constraint(table_name, column_name, constraint),
             Command::DropColumnConstraint { table_name, column_name, constraint } =>
                 self.drop_table_column_constraint(table_name, column_name, constraint),
-            Command::DropTableColumn { table_name, column_name } => self.drop_table_column(table_name, column_name),
+            Command::DropNamedConstraint { table_name, constraint_name } => self.drop_named_constraint(table_name, constraint_name),
+            Command::SetTableOptions { table_name, options } => self.set_table_options(table_name, options),
+            Command::DropTableColumn { table_name, column_name } => {
+                self.check_table_not_referenced(&table_name, Some(&column_name), true)?;
+                self.drop_table_column(table_name, column_name)
+            },
             Command::CreateIndex { table_name, index_name, column_name } => self.create_table_index(index_name, table_name, column_name),
             Command::DropIndex { table_name, index_name } => self.drop_table_index(index_name, table_name),
             Command::VacuumTable { table_name } => self.vacuum_table(&table_name),
+            Command::CheckTable { table_name } => Ok(Some(self.get_mut_table_by_sql_value(&table_name)?.check()?)),
+            Command::ShowStorage { table_name } => Ok(Some(self.get_mut_table_by_sql_value(&table_name)?.storage_stats()?)),
+            Command::CreateSequence { sequence_name } => self.create_sequence(sequence_name),
+            Command::DropSequence { sequence_name } => self.drop_sequence(sequence_name),
+            Command::SelectChanges { table_name, since_lsn } => self.select_changes(table_name, since_lsn),
+            Command::SelectSeries { column_names, start, stop, step, where_clause } =>
+                Ok(Some(series::select_series(&column_names, start, stop, step, where_clause, self.statement_deadline)?)),
+            Command::SetStatementTimeout { milliseconds } => self.set_statement_timeout(milliseconds),
+            Command::SetRandomSeed { seed } => {
+                self.session.random = Random::new(seed);
+                Ok(None)
+            },
+            Command::CreateUser { user_name } => self.create_user(user_name),
+            Command::DropUser { user_name } => self.drop_user(user_name),
+            Command::Grant { privileges, table_name, user_name } => self.grant(&privileges, table_name, user_name, true),
+            Command::Revoke { privileges, table_name, user_name } => self.grant(&privileges, table_name, user_name, false),
+            Command::Upsert { insert, conflict_column_name, action } => self.upsert_rows(*insert, conflict_column_name, action, None),
+            Command::Returning { command, column_names: returning } => match *command {
+                Command::InsertInto { table_name, column_names, values } => self.insert_rows(table_name, column_names, values, Some(&returning)),
+                Command::Upsert { insert, conflict_column_name, action } =>
+                    self.upsert_rows(*insert, conflict_column_name, action, Some(&returning)),
+                Command::Update { table_name, field_assignments, where_clause } =>
+                    self.update_rows(table_name, field_assignments, where_clause, Some(&returning)),
+                Command::Delete { table_name, where_clause } => self.delete_rows(table_name, where_clause, Some(&returning)),
+                command => Err(ExecutionError::ClauseNotSupported { clause: "RETURNING", statement_name: command.statement_name() }),
+            },
+            Command::SelectOrdered { select, order_by } => {
+                let mut result = self.select_result(*select, "ORDER BY")?;
+                ordering::sort(&mut result, &order_by, &mut self.session.random)?;
+                Ok(Some(result))
+            },
+            Command::SelectIndexHinted { select, index_hint } => match *select {
+                Command::Select { table_name, column_names, where_clause } =>
+                    self.select_rows_with_index_hint(table_name, column_names, where_clause, index_hint),
+                command => {
+                    let clause = match index_hint {
+                        IndexHint::Use(_) => "USE INDEX",
+                        IndexHint::Ignore(_) => "IGNORE INDEX",
+                    };
+                    Err(ExecutionError::ClauseNotSupported { clause, statement_name: command.statement_name() })
+                },
+            },
+            Command::SelectLimited { select, limit, offset } => {
+                let mut result = self.select_result(*select, "LIMIT")?;
+                self.session.last_statement_pagination = Some(result.paginate(limit, offset));
+                Ok(Some(result))
+            },
+            Command::CopyFrom { table_name, file_path } => self.copy_from(table_name, &file_path),
+            Command::CopyTo { select, file_path } => self.copy_to(*select, &file_path),
+            Command::DeclareCursor { cursor_name, select } => self.declare_cursor(cursor_name, *select),
+            Command::Fetch { cursor_name, direction, count } => {
+                let cursor = self.session.cursors.get_mut(&cursor_name.to_string())
+                    .ok_or_else(|| ExecutionError::CursorNotExist(cursor_name.to_string()))?;
+                Ok(Some(cursor.fetch(direction, count)))
+            },
+            Command::CloseCursor { cursor_name } => match self.session.cursors.remove(&cursor_name.to_string()) {
+                Some(_) => Ok(None),
+                None => Err(ExecutionError::CursorNotExist(cursor_name.to_string())),
+            },
+            Command::CreateMacro { macro_name, parameter_names, body } => self.create_macro(macro_name, parameter_names, body),
             Command::Void => Ok(None),
         }
     }
 
-    fn create_table(&mut self, table_name: SqlValue, columns: Vec<ColumnDefinition>) -> Result<Option<QueryResult>, ExecutionError> {
+    // statement wrapped by a clause which works on selected rows, such as `ORDER BY`, should read
+    // and return them, it is checked before execution, so that other statements change nothing
+    fn select_result(&mut self, select: Command, clause: &'static str) -> Result<QueryResult, ExecutionError> {
+        let not_supported = ExecutionError::ClauseNotSupported { clause, statement_name: select.statement_name() };
+        if StatementKind::of(&select) != StatementKind::Read {
+            return Err(not_supported)
+        }
+        self.execute_command(select)?.ok_or(not_supported)
+    }
+
+    fn create_macro(&mut self, macro_name: SqlValue, parameter_names: Vec<SqlValue>, body: Vec<Token>) -> Result<Option<QueryResult>, ExecutionError> {
+        match self.session.macros.entry(macro_name.to_string().to_lowercase()) {
+            Entry::Occupied(_) => Err(ExecutionError::MacroAlreadyExist(macro_name.to_string())),
+            Entry::Vacant(entry) => {
+                entry.insert(Macro::new(parameter_names.iter().map(ToString::to_string).collect(), body));
+                Ok(None)
+            },
+        }
+    }
+
+    fn declare_cursor(&mut self, cursor_name: SqlValue, select: Command) -> Result<Option<QueryResult>, ExecutionError> {
+        let cursor_name = cursor_name.to_string();
+        if self.session.cursors.contains_key(&cursor_name) {
+            return Err(ExecutionError::CursorAlreadyExist(cursor_name))
+        }
+
+        let result = self.select_result(select, "DECLARE CURSOR")?;
+        self.session.cursors.insert(cursor_name, Cursor::new(result));
+        Ok(None)
+    }
+
+    /// Inserts each record of CSV file as a row, in the same way as `INSERT`. Records are read one
+    /// by one, so file is not loaded into memory. Each row is inserted on its own, so when a record
+    /// fails, rows of the lines before it stay inserted, and the lines after it are not copied.
+    fn copy_from(&mut self, table_name: SqlValue, file_path: &str) -> Result<Option<QueryResult>, ExecutionError> {
+        let column_types = self.get_table_by_sql_value(&table_name)?.column_types().to_vec();
+        let file = File::open(file_path)?;
+
+        for record in csv::Records::new(BufReader::new(file)) {
+            let (line_number, fields) = record?;
+            csv::insert_values(fields, &column_types)
+                .and_then(|values| self.insert_rows(table_name.clone(), None, values, None))
+                .map_err(|error| ExecutionError::CopyLineFailed { line_number, error: Box::new(error) })?;
+        }
+        Ok(None)
+    }
+
+    /// Writes select result to CSV file, a line per row without header. File is overwritten.
+    /// Rows of a single table are written as they are scanned, so they are not kept in memory,
+    /// while sorted, paginated and other selects are collected first. If select fails while
+    /// its rows are written, file keeps the rows written before the failure.
+    fn copy_to(&mut self, select: Command, file_path: &str) -> Result<Option<QueryResult>, ExecutionError> {
+        match select {
+            Command::Select { table_name, column_names, where_clause } if self.tables.contains_key(&table_name.to_string()) => {
+                let where_clause = self.resolve_subqueries(&table_name, where_clause)?;
+                let table = self.get_mut_table_by_sql_value(&table_name)?;
+                let mut writer = BufWriter::new(File::create(file_path)?);
+                table.select_each(column_names, where_clause, |values| writeln!(writer, "{}", csv::format_record(values)))?;
+                writer.flush()?;
+            },
+            select => {
+                let result = self.select_result(select, "COPY ... TO")?;
+                let mut writer = BufWriter::new(File::create(file_path)?);
+                for row in &result.rows {
+                    let values = row.get_sql_values(&result.column_types)?;
+                    writeln!(writer, "{}", csv::format_record(&values))?;
+                }
+                writer.flush()?;
+            },
+        }
+        Ok(None)
+    }
+
+    fn check_statement_policy(&self, command: &Command) -> Result<(), ExecutionError> {
+        if self.salvaged && !matches!(StatementKind::of(command), StatementKind::Read | StatementKind::Session) {
+            return Err(ExecutionError::DatabaseSalvaged(command.statement_name()))
+        }
+        match &self.session.statement_policy {
+            Some(statement_policy) if !statement_policy.allows(command) =>
+                Err(ExecutionError::StatementNotAllowed(command.statement_name())),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_access(&self, command: &Command) -> Result<(), ExecutionError> {
+        // user dropped by other connection while it is current has no privileges, rather than owner ones
+        let user = match self.session.current_user.as_ref() {
+            None => return Ok(()),
+            Some(user_name) => self.users.get(user_name).ok_or_else(|| ExecutionError::UserNotExist(user_name.clone()))?,
+        };
+
+        let required_privileges = access::required_privileges(command)
+            .ok_or_else(|| ExecutionError::OwnerOnlyStatement(user.name.clone()))?;
+        // catalog is readable by every user, as names of tables are not secret
+        let mut missing_privileges = required_privileges.into_iter()
+            .filter(|(privilege, table_name)| !(*privilege == Privilege::Select && system_table::is_system_table_name(table_name)))
+            .filter(|(privilege, table_name)| !user.is_granted(*privilege, table_name));
+        match missing_privileges.next() {
+            None => Ok(()),
+            Some((privilege, table_name)) => Err(ExecutionError::PrivilegeMissing { user_name: user.name.clone(), privilege, table_name }),
+        }
+    }
+
+    fn create_user(&mut self, user_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
+        let user_name = user_name.to_string();
+        if self.users.contains_key(&user_name) {
+            return Err(ExecutionError::UserAlreadyExist(user_name));
+        }
+
+        self.users.insert(user_name.clone(), User::new(&user_name));
+        self.flush_schema()?;
+        Ok(None)
+    }
+
+    fn drop_user(&mut self, user_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
+        let user_name = user_name.to_string();
+        match self.users.remove(&user_name) {
+            None => Err(ExecutionError::UserNotExist(user_name)),
+            Some(_user) => {
+                self.flush_schema()?;
+                Ok(None)
+            },
+        }
+    }
+
+    // grants privileges if `granted` is true, revokes them otherwise
+    fn grant(&mut self, privileges: &[Privilege], table_name: SqlValue, user_name: SqlValue, granted: bool) -> Result<Option<QueryResult>, ExecutionError> {
+        let table_name = self.get_table_by_sql_value(&table_name)?.name().to_string();
+        let user_name = user_name.to_string();
+        let user = self.users.get_mut(&user_name).ok_or(ExecutionError::UserNotExist(user_name))?;
+
+        for privilege in privileges {
+            match granted {
+                true => user.grant(*privilege, &table_name),
+                false => user.revoke(*privilege, &table_name),
+            }
+        }
+        self.flush_schema()?;
+        Ok(None)
+    }
+
+    // grants follow renamed table, and are removed with dropped one
+    fn rename_grants(&mut self, table_name: &str, new_table_name: Option<&str>) -> Result<(), ExecutionError> {
+        let mut grants_changed = false;
+        for user in self.users.values_mut() {
+            grants_changed |= match new_table_name {
+                Some(new_table_name) => user.rename_table(table_name, new_table_name),
+                None => user.forget_table(table_name),
+            };
+        }
+        if grants_changed {
+            self.flush_schema()?;
+        }
+        Ok(())
+    }
+
+    /// Executes commands one by one and returns result for each of them. Failed command
+    /// does not stop the batch. Only schema write is deferred: schema is written to disk once
+    /// after the last command instead of after each schema-changing one, while table pages
+    /// and index buckets are not deferred, they are flushed by their caches as usual.
+    /// If deferred schema write fails, its error is appended after commands results.
+    pub fn execute_batch(&mut self, commands: impl IntoIterator<Item = Command>) -> Vec<Result<Option<QueryResult>, ExecutionError>> {
+        self.batch_in_progress = true;
+        let mut results: Vec<_> = commands.into_iter()
+            .map(|command| self.execute(command))
+            .collect();
+        self.batch_in_progress = false;
+
+        if self.schema_flush_pending {
+            self.schema_flush_pending = false;
+            let committed = self.write_schema()
+                .and_then(|_| self.pending_rename_journals.drain(..).try_for_each(RenameJournal::commit).map_err(ExecutionError::from));
+            if let Err(error) = committed {
+                results.push(Err(error));
+            }
+        }
+
+        results
+    }
+
+    /// Fetches values of a single row by its id, without scanning the table. Returns None if
+    /// there is no row with such id or the row is soft deleted. Row ids are changed by vacuum.
+    /// Lookup is accounted in `last_statement_stats` as a separate statement.
+    pub fn get_row(&mut self, table_name: &str, row_id: u64) -> Result<Option<Vec<SqlValue>>, ExecutionError> {
+        let table = self.get_mut_table(table_name)?;
+        let result = table.get_row(row_id);
+        self.session.last_statement_stats = table.take_stats();
+
+        Ok(result?)
+    }
+
+    /// Fills table with `row_count` rows of random values, used for benchmarks and demos.
+    /// Nullable columns are null in about one of ten rows, numbers are taken below 1000
+    /// and strings are short lowercase words. Rows failing CHECK constraint are generated again.
+    pub fn seed_table(&mut self, table_name: &str, row_count: usize) -> Result<(), ExecutionError> {
+        if self.salvaged {
+            return Err(ExecutionError::DatabaseSalvaged("INSERT"))
+        }
+        let max_file_size = self.max_table_file_size(table_name)?;
+        let table = self.tables.get_mut(table_name)
+            .ok_or_else(|| ExecutionError::TableNotExist(table_name.to_string()))?;
+        table.set_max_file_size(max_file_size);
+        let column_types = table.column_types().to_vec();
+        let column_nullable_flags: Vec<bool> = (0..column_types.len())
+            .map(|column_number| table.column_is_nullable(column_number))
+            .collect();
+
+        for _ in 0..row_count {
+            let mut attempt = 1;
+            loop {
+                let values = column_types.iter().zip(&column_nullable_flags)
+                    .map(|(column_type, nullable)| Self::random_value(&mut self.session.random, *column_type, *nullable))
+                    .collect();
+
+                match table.insert(None, values, None) {
+                    Err(TableError::CheckViolation { .. }) if attempt < SEED_ATTEMPTS_PER_ROW => attempt += 1,
+                    result => {
+                        result?;
+                        break
+                    },
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn random_value(random: &mut Random, column_type: ColumnType, nullable: bool) -> SqlValue {
+        if nullable && random.next_below(SEED_NULL_ONE_IN) == 0 {
+            return SqlValue::Null
+        }
+
+        match column_type {
+            ColumnType::Integer => SqlValue::Integer(random.next_below(SEED_NUMBER_BOUND) as i64),
+            ColumnType::Float => SqlValue::Float(random.next_below(SEED_NUMBER_BOUND * 100) as f64 / 100.0),
+            ColumnType::String => {
+                let length = 1 + random.next_below(SEED_STRING_MAX_LENGTH);
+                let string = (0..length).map(|_| (b'a' + random.next_below(26) as u8) as char).collect();
+                SqlValue::String(string)
+            },
+        }
+    }
+
+    fn create_table(&mut self, table_name: SqlValue, columns: Vec<ColumnDefinition>, options: TableOptions) -> Result<Option<QueryResult>, ExecutionError> {
         let table_name_string = table_name.to_string();
-        let table = self.build_table(&table_name_string, &columns)?;
+        let table = self.build_table(&table_name_string, &columns, options)?;
         self.tables.insert(table_name_string, table);
         Ok(None)
     }
 
-    fn build_table(&self, table_name: &str, columns: &Vec<ColumnDefinition>) -> Result<Table, ExecutionError> {
+    fn build_table(&self, table_name: &str, columns: &Vec<ColumnDefinition>, options: TableOptions) -> Result<Table, ExecutionError> {
         let table_filepath = Self::table_filepath(self.tables_dir.as_path(), table_name);
 
         if self.tables.contains_key(table_name) {
             return Err(ExecutionError::TableAlreadyExist(table_name.to_string()));
         }
+        for column in columns {
+            for constraint in &column.column_constraints {
+                if let Constraint::References(foreign_key) = constraint {
+                    self.check_foreign_key_target(table_name, columns, column.kind, foreign_key)?;
+                }
+            }
+        }
         File::create(table_filepath.as_path())?;
-        match Table::new(table_filepath.clone(), table_name, 0, columns, vec![]) {
+        match Table::new(table_filepath.clone(), table_name, 0, None, columns, vec![], options) {
             Ok(table) => Ok(table),
             Err(create_table_error) => {
                 fs::remove_file(table_filepath.as_path())
@@ -206,62 +1101,454 @@ impl Database {
             None => Err(ExecutionError::TableNotExist(table_name_string)),
             Some(table) => {
                 table.destroy()?;
-                self.flush_schema();
+                self.flush_schema()?;
                 Ok(None)
             },
         }
     }
 
-    fn select_rows(&mut self, table_name: SqlValue, column_names: Vec<SelectColumnName>, where_clause: Option<BinaryCondition>) -> Result<Option<QueryResult>, ExecutionError> {
+    fn select_rows(&mut self, table_name: SqlValue, column_names: Vec<SelectColumnName>, where_clause: Option<Condition>) -> Result<Option<QueryResult>, ExecutionError> {
+        if let Some(rows) = self.system_table_rows(&table_name.to_string()) {
+            if where_clause.as_ref().is_some_and(Condition::has_subqueries) {
+                return Err(TableError::SubqueryNotSupported(table_name.to_string()).into())
+            }
+            return Ok(Some(system_table::select(&column_names, &table_name.to_string(), rows?, where_clause, self.statement_deadline)?))
+        }
+        let where_clause = self.resolve_subqueries(&table_name, where_clause)?;
         let table = self.get_mut_table_by_sql_value(&table_name)?;
 
         Ok(Some(table.select(column_names, where_clause)?))
     }
 
-    fn insert_rows(&mut self, table_name: SqlValue, column_names: Option<Vec<SqlValue>>, values: Vec<SqlValue>) -> Result<Option<QueryResult>, ExecutionError> {
-        let column_names = column_names
+    fn select_rows_with_index_hint(&mut self, table_name: SqlValue, column_names: Vec<SelectColumnName>, where_clause: Option<Condition>,
+                                   index_hint: IndexHint) -> Result<Option<QueryResult>, ExecutionError> {
+        // system tables have no indexes, so any hinted index is missing
+        if system_table::is_system_table_name(&table_name.to_string()) && !self.tables.contains_key(&table_name.to_string()) {
+            let index_name = index_hint.index_names().first().cloned().unwrap_or_default();
+            return Err(TableError::HashIndexMissing { table_name: table_name.to_string(), index_name }.into())
+        }
+        let where_clause = self.resolve_subqueries(&table_name, where_clause)?;
+        let table = self.get_mut_table_by_sql_value(&table_name)?;
+
+        table.set_index_hint(Some(index_hint))?;
+        let result = table.select(column_names, where_clause);
+        table.set_index_hint(None)?;
+        Ok(Some(result?))
+    }
+
+    // EXISTS subqueries are replaced with conditions on columns of the queried table, so the rest
+    // of the statement is executed as usual. Uncorrelated subquery is executed once and is replaced
+    // with a constant, for correlated one rows of subquery table are selected once and checked in memory.
+    fn resolve_subqueries(&mut self, table_name: &SqlValue, where_clause: Option<Condition>) -> Result<Option<Condition>, ExecutionError> {
+        match where_clause {
+            Some(where_clause) if where_clause.has_subqueries() =>
+                Ok(Some(where_clause.replace_subqueries(&mut |subquery| self.resolve_subquery(table_name, subquery))?)),
+            where_clause => Ok(where_clause),
+        }
+    }
+
+    fn resolve_subquery(&mut self, table_name: &SqlValue, subquery: Subquery) -> Result<Condition, ExecutionError> {
+        let deadline = self.statement_deadline;
+        let outer_table = self.get_table_by_sql_value(table_name)?;
+        let outer_table_name = outer_table.name().to_string();
+        let outer_column_names = outer_table.column_names().to_vec();
+
+        let inner_table = self.get_mut_table_by_sql_value(&subquery.table_name)?;
+        let inner_table_name = inner_table.name().to_string();
+        if subquery.where_clause.as_ref().is_some_and(Condition::has_subqueries) {
+            return Err(TableError::SubqueryNotSupported(inner_table_name).into())
+        }
+
+        let referenced_column_names = subquery::outer_column_names(&subquery, (&inner_table_name, inner_table.column_names()),
+                                                                   (&outer_table_name, &outer_column_names));
+        if referenced_column_names.is_empty() {
+            let rows = inner_table.select(vec![SelectColumnName::AllColumns], subquery.where_clause)?;
+            return Ok(subquery::constant(!rows.is_empty()))
+        }
+
+        let inner_rows = inner_table.select(vec![SelectColumnName::AllColumns], None)?;
+        let referenced_columns = referenced_column_names.into_iter()
+            .map(|column_name| SelectColumnName::Name(SqlValue::Identificator(column_name)))
+            .collect();
+        let outer_values = self.get_mut_table_by_sql_value(table_name)?.select(referenced_columns, None)?;
+
+        Ok(subquery::resolve_correlated(subquery, (&inner_table_name, &inner_rows), (&outer_table_name, &outer_values), deadline)?)
+    }
+
+    // each table is scanned once, product of scanned rows is built and filtered in memory
+    fn select_cross_join(&mut self, table_names: Vec<SqlValue>, column_names: Vec<SelectColumnName>,
+                         where_clause: Option<Condition>) -> Result<Option<QueryResult>, ExecutionError> {
+        let mut tables: Vec<(String, QueryResult)> = vec![];
+        for table_name in table_names {
+            let (table_name, rows) = match self.system_table_rows(&table_name.to_string()) {
+                Some(rows) => (table_name.to_string(), rows?),
+                None => {
+                    let table = self.get_mut_table_by_sql_value(&table_name)?;
+                    (table.name().to_string(), table.select(vec![SelectColumnName::AllColumns], None)?)
+                },
+            };
+            if tables.iter().any(|(joined_table_name, _)| *joined_table_name == table_name) {
+                return Err(TableError::TableJoinedTwice(table_name).into())
+            }
+            tables.push((table_name, rows));
+        }
+
+        Ok(Some(cross_join::select_cross_join(&column_names, tables, where_clause, self.statement_deadline)?))
+    }
+
+    // system tables are shadowed by regular tables which were created before the names were reserved
+    fn system_table_rows(&self, table_name: &str) -> Option<Result<QueryResult, ExecutionError>> {
+        if self.tables.contains_key(table_name) {
+            return None
+        }
+        system_table::rows(table_name, &self.catalog(), |table_name| self.tables[table_name].row_count)
+            .map(|rows| rows.map_err(ExecutionError::from))
+    }
+
+    fn select_changes(&self, table_name: SqlValue, since_lsn: u64) -> Result<Option<QueryResult>, ExecutionError> {
+        let table = self.get_table_by_sql_value(&table_name)?;
+
+        Ok(Some(table.changes(since_lsn)?))
+    }
+
+    fn insert_rows(&mut self, table_name: SqlValue, column_names: Option<Vec<SqlValue>>, values: Vec<InsertValue>,
+                   returning: Option<&[SelectColumnName]>) -> Result<Option<QueryResult>, ExecutionError> {
+        // table is checked first to not waste sequence values on insert into nonexistent table
+        self.get_table_by_sql_value(&table_name)?;
+        let values = values.into_iter()
+            .map(|value| self.resolve_insert_value(value))
+            .collect::<Result<Vec<SqlValue>, ExecutionError>>()?;
+
+        let column_names: Option<Vec<String>> = column_names
             .map(|sql_names|
                  sql_names.iter()
                      .map(|sql_name| sql_name.to_string()).collect()
                 );
 
+        let table = self.get_table_by_sql_value(&table_name)?;
+        // columns which are not given get default values
+        let column_values: Vec<(String, SqlValue)> = column_names.as_deref().unwrap_or(table.column_names()).iter().cloned()
+            .zip(values.iter().cloned())
+            .chain(table.column_names().iter().cloned().zip(table.defaults().iter().cloned()))
+            .collect();
+        self.check_references_present(&table_name.to_string(), &column_values)?;
+
         let table = self.get_mut_table_by_sql_value(&table_name)?;
-        table.insert(column_names, values)?;
-        Ok(None)
+        Ok(table.insert(column_names, values, returning)?)
     }
 
-    fn update_rows(&mut self, table_name: SqlValue, field_assignments: Vec<FieldAssignment>, where_clause: Option<BinaryCondition>) -> Result<Option<QueryResult>, ExecutionError> {
+    /// Inserts row unless conflict column already has its value. On conflict either nothing is done,
+    /// or rows with this value are updated. Conflict column should be indexed, so conflicting rows are
+    /// found without a scan. NULL never conflicts, as it is not equal to other NULLs.
+    fn upsert_rows(&mut self, insert: Command, conflict_column_name: SqlValue, action: ConflictAction,
+                   returning: Option<&[SelectColumnName]>) -> Result<Option<QueryResult>, ExecutionError> {
+        let (table_name, column_names, values) = match insert {
+            Command::InsertInto { table_name, column_names, values } => (table_name, column_names, values),
+            command => return Err(ExecutionError::ClauseNotSupported { clause: "ON CONFLICT", statement_name: command.statement_name() }),
+        };
+        // values are resolved once, so sequence value is not issued again on insert
+        self.get_table_by_sql_value(&table_name)?;
+        let values = values.into_iter()
+            .map(|value| self.resolve_insert_value(value))
+            .collect::<Result<Vec<SqlValue>, ExecutionError>>()?;
+
         let table = self.get_mut_table_by_sql_value(&table_name)?;
-        table.update(field_assignments, where_clause)?;
+        let conflict_column_name = conflict_column_name.to_string();
+        let conflict_column_number = table.column_number_result(&conflict_column_name)?;
+        let conflict_value = match &column_names {
+            Some(column_names) => column_names.iter()
+                .position(|column_name| column_name.to_string() == conflict_column_name)
+                .and_then(|position| values.get(position).cloned()),
+            None => values.get(conflict_column_number).cloned(),
+        }
+            .unwrap_or_else(|| table.defaults()[conflict_column_number].clone());
+
+        let conflicting = conflict_value != SqlValue::Null && table.indexed_value_present(&conflict_column_name, &conflict_value)?;
+        match (conflicting, action) {
+            (false, _) => {
+                let values = values.into_iter().map(InsertValue::from).collect();
+                self.insert_rows(table_name, column_names, values, returning)
+            },
+            (true, ConflictAction::Nothing) => Ok(returning.map(|column_names| table.empty_result(column_names)).transpose()?),
+            (true, ConflictAction::Update(field_assignments)) => {
+                let where_clause = BinaryCondition {
+                    left_value: SqlValue::Identificator(conflict_column_name),
+                    right_value: conflict_value,
+                    operator: CmpOperator::Equals,
+                };
+                self.update_rows(table_name, field_assignments, Some(where_clause.into()), returning)
+            },
+        }
+    }
+
+    fn resolve_insert_value(&mut self, value: InsertValue) -> Result<SqlValue, ExecutionError> {
+        match value {
+            InsertValue::Value(value) => Ok(value),
+            InsertValue::NextVal(sequence_name) => {
+                let sequence_name = sequence_name.to_string();
+                let sequence = self.sequences.get(&sequence_name)
+                    .ok_or(ExecutionError::SequenceNotExist(sequence_name))?;
+
+                Ok(SqlValue::Integer(sequence.next_value()?))
+            },
+            InsertValue::Random => Ok(SqlValue::Integer(self.session.random.next_i64())),
+        }
+    }
+
+    fn create_sequence(&mut self, sequence_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
+        let sequence_name = sequence_name.to_string();
+        if self.sequences.contains_key(&sequence_name) {
+            return Err(ExecutionError::SequenceAlreadyExist(sequence_name));
+        }
+
+        let sequence = Sequence::create(self.tables_dir.as_path(), &sequence_name)?;
+        self.sequences.insert(sequence_name, sequence);
+        self.flush_schema()?;
         Ok(None)
     }
 
-    fn delete_rows(&mut self, table_name: SqlValue, where_clause: Option<BinaryCondition>) -> Result<Option<QueryResult>, ExecutionError> {
+    fn drop_sequence(&mut self, sequence_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
+        let sequence_name = sequence_name.to_string();
+
+        match self.sequences.remove(&sequence_name) {
+            None => Err(ExecutionError::SequenceNotExist(sequence_name)),
+            Some(sequence) => {
+                sequence.destroy()?;
+                self.flush_schema()?;
+                Ok(None)
+            },
+        }
+    }
+
+    fn update_rows(&mut self, table_name: SqlValue, field_assignments: Vec<FieldAssignment>, where_clause: Option<Condition>,
+                   returning: Option<&[SelectColumnName]>) -> Result<Option<QueryResult>, ExecutionError> {
+        let where_clause = self.resolve_subqueries(&table_name, where_clause)?;
+        let column_values: Vec<(String, SqlValue)> = field_assignments.iter()
+            .map(|assignment| (assignment.column_name.clone(), assignment.value.clone()))
+            .collect();
+        self.check_references_present(&table_name.to_string(), &column_values)?;
+        self.check_rows_not_referenced(&table_name.to_string(), &where_clause, Some(&column_values))?;
+
         let table = self.get_mut_table_by_sql_value(&table_name)?;
+        Ok(table.update(field_assignments, where_clause, returning)?)
+    }
 
-        table.delete(where_clause)?;
+    fn delete_rows(&mut self, table_name: SqlValue, where_clause: Option<Condition>,
+                   returning: Option<&[SelectColumnName]>) -> Result<Option<QueryResult>, ExecutionError> {
+        let where_clause = self.resolve_subqueries(&table_name, where_clause)?;
+        self.check_rows_not_referenced(&table_name.to_string(), &where_clause, None)?;
+        let table = self.get_mut_table_by_sql_value(&table_name)?;
+
+        Ok(table.delete(where_clause, returning)?)
+    }
+
+    fn undelete_rows(&mut self, table_name: SqlValue, where_clause: Option<Condition>) -> Result<Option<QueryResult>, ExecutionError> {
+        let where_clause = self.resolve_subqueries(&table_name, where_clause)?;
+        let table = self.get_mut_table_by_sql_value(&table_name)?;
+
+        table.undelete(where_clause)?;
         Ok(None)
     }
 
+    // Referenced column should be present in referenced table, or in the table being created if
+    // it references itself, and should have the same type as foreign key column
+    fn check_foreign_key_target(&self, table_name: &str, columns: &[ColumnDefinition], column_type: ColumnType,
+                                foreign_key: &ForeignKey) -> Result<(), ExecutionError> {
+        let target_column_type = match self.tables.get(&foreign_key.table_name) {
+            _ if foreign_key.table_name == table_name => columns.iter()
+                .find(|column| column.name.to_string() == foreign_key.column_name)
+                .map(|column| column.kind),
+            Some(target_table) => target_table.column_number_result(&foreign_key.column_name).ok()
+                .map(|column_number| target_table.column_types()[column_number]),
+            None => None,
+        };
+
+        match target_column_type {
+            Some(target_column_type) if target_column_type == column_type => Ok(()),
+            _ => Err(ExecutionError::ForeignKeyTargetInvalid(foreign_key.clone())),
+        }
+    }
+
+    fn check_existing_values_referenced(&mut self, table_name: &SqlValue, column_name: &str, foreign_key: &ForeignKey) -> Result<(), ExecutionError> {
+        let table = self.get_mut_table_by_sql_value(table_name)?;
+        let column_number = table.column_number_result(column_name)?;
+        let column_definitions = table.column_definitions();
+        let table_name = table.name().to_string();
+        self.check_foreign_key_target(&table_name, &column_definitions, column_definitions[column_number].kind, foreign_key)?;
+
+        let column = SelectColumnName::Name(SqlValue::Identificator(column_name.to_string()));
+        let rows = self.get_mut_table(&table_name)?.select(vec![column], None)?;
+        for row in &rows.rows {
+            let value = row.get_sql_values(&rows.column_types).map_err(TableError::CannotGetCell)?.remove(0);
+            if value != SqlValue::Null && !self.column_contains(&foreign_key.table_name, &foreign_key.column_name, &value)? {
+                return Err(ExecutionError::ForeignKeyViolation {
+                    table_name, column_name: column_name.to_string(), foreign_key: foreign_key.clone(), value,
+                })
+            }
+        }
+        Ok(())
+    }
+
+    // Each assigned foreign key value is looked up in referenced table, lookup uses index of
+    // referenced column if it has one
+    fn check_references_present(&mut self, table_name: &str, column_values: &[(String, SqlValue)]) -> Result<(), ExecutionError> {
+        let foreign_keys: Vec<(String, ForeignKey)> = self.get_table(table_name)?.foreign_keys()
+            .map(|(column_name, foreign_key)| (column_name.to_string(), foreign_key.clone()))
+            .collect();
+
+        for (column_name, foreign_key) in foreign_keys {
+            let value = match column_values.iter().find(|(assigned_column_name, _)| *assigned_column_name == column_name) {
+                None | Some((_, SqlValue::Null)) => continue,
+                Some((_, value)) => value,
+            };
+            if !self.column_contains(&foreign_key.table_name, &foreign_key.column_name, value)? {
+                return Err(ExecutionError::ForeignKeyViolation {
+                    table_name: table_name.to_string(), column_name, foreign_key, value: value.clone(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    // Rows matched by where clause are selected before they are deleted or updated, and their
+    // referenced values are looked up in referencing tables. On update only values of assigned
+    // columns are checked, unless they are assigned the same value.
+    fn check_rows_not_referenced(&mut self, table_name: &str, where_clause: &Option<Condition>,
+                                 assigned_values: Option<&[(String, SqlValue)]>) -> Result<(), ExecutionError> {
+        let referencing_foreign_keys: Vec<(String, String, ForeignKey)> = self.referencing_foreign_keys(table_name).into_iter()
+            .filter(|(_, _, foreign_key)| assigned_values.is_none_or(|assigned_values| {
+                assigned_values.iter().any(|(column_name, _)| *column_name == foreign_key.column_name)
+            }))
+            .collect();
+        if referencing_foreign_keys.is_empty() {
+            return Ok(())
+        }
+
+        let referenced_columns = referencing_foreign_keys.iter()
+            .map(|(_, _, foreign_key)| SelectColumnName::Name(SqlValue::Identificator(foreign_key.column_name.clone())))
+            .collect();
+        let rows = self.get_mut_table(table_name)?.select(referenced_columns, where_clause.clone())?;
+        for row in &rows.rows {
+            let values = row.get_sql_values(&rows.column_types).map_err(TableError::CannotGetCell)?;
+            for ((referencing_table_name, referencing_column_name, foreign_key), value) in referencing_foreign_keys.iter().zip(values) {
+                let assigned_value = assigned_values.and_then(|assigned_values| {
+                    assigned_values.iter().find(|(column_name, _)| *column_name == foreign_key.column_name)
+                });
+                if value == SqlValue::Null || assigned_value.is_some_and(|(_, assigned_value)| *assigned_value == value) {
+                    continue
+                }
+
+                if self.column_contains(referencing_table_name, referencing_column_name, &value)? {
+                    return Err(ExecutionError::ForeignKeyReferenced {
+                        table_name: table_name.to_string(),
+                        column_name: foreign_key.column_name.clone(),
+                        value,
+                        referencing_table_name: referencing_table_name.clone(),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Table, or only its given column, cannot be dropped or renamed while it is referenced, since
+    // foreign keys would point to nothing. Dropped table can reference itself.
+    fn check_table_not_referenced(&self, table_name: &SqlValue, column_name: Option<&SqlValue>,
+                                  include_self_references: bool) -> Result<(), ExecutionError> {
+        let table_name = table_name.to_string();
+        let column_name = column_name.map(SqlValue::to_string);
+        let referencing_foreign_key = self.referencing_foreign_keys(&table_name).into_iter()
+            .filter(|(referencing_table_name, _, _)| include_self_references || *referencing_table_name != table_name)
+            .find(|(_, _, foreign_key)| column_name.as_ref().is_none_or(|column_name| *column_name == foreign_key.column_name));
+
+        match referencing_foreign_key {
+            None => Ok(()),
+            Some((referencing_table_name, _, foreign_key)) =>
+                Err(ExecutionError::TableReferenced { table_name, column_name: foreign_key.column_name, referencing_table_name }),
+        }
+    }
+
+    // Foreign keys referencing given table with referencing table and column names, ordered by them
+    fn referencing_foreign_keys(&self, table_name: &str) -> Vec<(String, String, ForeignKey)> {
+        let mut referencing_foreign_keys: Vec<(String, String, ForeignKey)> = self.tables.iter()
+            .filter(|(referencing_table_name, _)| !Self::is_temporary_table_name(referencing_table_name))
+            .flat_map(|(referencing_table_name, table)| {
+                table.foreign_keys()
+                    .filter(|(_, foreign_key)| foreign_key.table_name == table_name)
+                    .map(|(column_name, foreign_key)| (referencing_table_name.clone(), column_name.to_string(), foreign_key.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        referencing_foreign_keys.sort_by(|left, right| (&left.0, &left.1).cmp(&(&right.0, &right.1)));
+        referencing_foreign_keys
+    }
+
+    fn column_contains(&mut self, table_name: &str, column_name: &str, value: &SqlValue) -> Result<bool, ExecutionError> {
+        let table = self.get_mut_table(table_name)?;
+        let column_number = table.column_number_result(column_name)?;
+        // value of another type is rejected by insert or update itself
+        if !table.column_types()[column_number].matches_value(value) {
+            return Ok(true)
+        }
+
+        // unquoted string values are identificators, they should not be compared as column names
+        let value = match value {
+            SqlValue::Identificator(string) => SqlValue::String(string.clone()),
+            value => value.clone(),
+        };
+        let column = SqlValue::Identificator(column_name.to_string());
+        let condition = BinaryCondition { left_value: column.clone(), right_value: value, operator: CmpOperator::Equals };
+        let rows = table.select(vec![SelectColumnName::Name(column)], Some(condition.into()))?;
+        Ok(!rows.is_empty())
+    }
+
+    // Files are moved under a journal, which is committed only after schema with the new name is
+    // written. Temporary table names are never written to schema, so such renames are committed
+    // right after files are moved.
     fn rename_table(&mut self, table_name: SqlValue, new_table_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
         let table_name_string = table_name.to_string();
         let new_table_name_string = new_table_name.to_string();
         let new_table_filepath = Self::table_filepath(self.tables_dir.as_path(), new_table_name_string.as_str());
 
+        if self.tables.contains_key(new_table_name_string.as_str()) {
+            return Err(ExecutionError::TableAlreadyExist(new_table_name_string));
+        }
         let mut table = match self.tables.remove(table_name_string.as_str()) {
             None => return Err(ExecutionError::TableNotExist(table_name_string)),
             Some(table) => table,
         };
-        match table.rename(&new_table_name_string, new_table_filepath.as_path()) {
-            Err(io_error) => {
-                self.tables.insert(table_name_string, table);
-                Err(io_error.into())
-            },
-            Ok(_) => {
-                self.tables.insert(new_table_name_string, table);
-                Ok(None)
+
+        let moves = table.rename_moves(&new_table_name_string, new_table_filepath.as_path());
+        let journal = RenameJournal::new(self.tables_dir.as_path(), &table_name_string, &new_table_name_string, moves);
+        if let Err(io_error) = journal.apply() {
+            self.tables.insert(table_name_string, table);
+            return Err(io_error.into())
+        }
+
+        table.rename(&new_table_name_string, new_table_filepath.as_path())?;
+        self.tables.insert(new_table_name_string.clone(), table);
+
+        if !Self::is_temporary_table_name(&table_name_string) && !Self::is_temporary_table_name(&new_table_name_string) {
+            self.flush_schema()?;
+            if self.batch_in_progress {
+                self.pending_rename_journals.push(journal);
+                return Ok(None)
             }
         }
+        journal.commit()?;
+        Ok(None)
+    }
+
+    // Rename is committed if schema uses new table name, otherwise it is interrupted and is rolled back
+    fn recover_interrupted_renames(tables_dir: &Path, schema_table_names: &HashSet<String>) -> io::Result<()> {
+        for journal in RenameJournal::load_all(tables_dir)? {
+            if schema_table_names.contains(&journal.new_name) && !schema_table_names.contains(&journal.old_name) {
+                journal.commit()?;
+            } else {
+                journal.rollback()?;
+            }
+        }
+        Ok(())
     }
 
     fn rename_table_column(&mut self, table_name: SqlValue, column_name: SqlValue, new_column_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
@@ -272,18 +1559,21 @@ impl Database {
         table.rename_column(column_name_string, new_column_name_string)?;
 
         // TODO: use result, and rename column back if flush is not possible
-        self.flush_schema();
+        self.flush_schema()?;
         Ok(None)
     }
 
     fn add_table_column_constraint(&mut self, table_name: SqlValue, column_name: SqlValue, constraint: Constraint) -> Result<Option<QueryResult>, ExecutionError> {
         let column_name_string = column_name.to_string();
 
+        if let Constraint::References(foreign_key) = &constraint {
+            self.check_existing_values_referenced(&table_name, &column_name_string, foreign_key)?;
+        }
         let table = self.get_mut_table_by_sql_value(&table_name)?;
         table.add_column_constraint(column_name_string, constraint)?;
 
         // TODO: use result, and rename column back if flush is not possible
-        self.flush_schema();
+        self.flush_schema()?;
         Ok(None)
     }
 
@@ -294,17 +1584,77 @@ impl Database {
         table.drop_column_constraint(column_name_string, constraint)?;
 
         // TODO: use result, and rename column back if flush is not possible
-        self.flush_schema();
+        self.flush_schema()?;
+        Ok(None)
+    }
+
+    fn drop_named_constraint(&mut self, table_name: SqlValue, constraint_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
+        let table = self.get_mut_table_by_sql_value(&table_name)?;
+        table.drop_named_constraint(&constraint_name.to_string())?;
+
+        self.flush_schema()?;
         Ok(None)
     }
 
+    // compressed strings change layout of table file, so table is rebuilt as for a new column,
+    // other options are switched in place
+    fn set_table_options(&mut self, table_name: SqlValue, options: Vec<(String, bool)>) -> Result<Option<QueryResult>, ExecutionError> {
+        let table = self.get_table_by_sql_value(&table_name)?;
+        let mut new_options = table.options();
+        for (option_name, enabled) in &options {
+            new_options.set(option_name, *enabled)?;
+        }
+
+        if new_options.compression == table.options().compression {
+            self.get_mut_table_by_sql_value(&table_name)?.set_options(new_options)?;
+            self.flush_schema()?;
+            return Ok(None)
+        }
+        table.check_no_soft_deleted_rows()?;
+        table.check_change_log_is_empty()?;
+
+        let column_definitions = table.column_definitions();
+        let table_column_types = table.column_types().to_vec();
+        let temp_new_table_name = Self::temporary_table_name(&table_name);
+        let mut new_table = self.build_table(&temp_new_table_name.to_string(), &column_definitions, new_options)?;
+        table.clone_indexes_to(&mut new_table)?;
+        self.tables.insert(temp_new_table_name.to_string(), new_table);
+
+        match self.move_records_to_new_table_and_swap_tables(&table_name, &temp_new_table_name, &table_column_types) {
+            Ok(result) => Ok(result),
+            Err(move_error) => {
+                self.drop_table(temp_new_table_name.clone())
+                    .unwrap_or_else(|error| panic!("error rebuilding table {} with changed compression: {}, \
+                                      and was unable to rollback: cleanup temporary table {} failed: {}, \
+                                      consider dropping in manually",
+                                      table_name, move_error, temp_new_table_name, error));
+                Err(move_error)
+            }
+        }
+    }
+
+    fn move_records_to_new_table_and_swap_tables(&mut self, target_table_name: &SqlValue, temp_new_table_name: &SqlValue,
+                                                 table_column_types: &[ColumnType]) -> Result<Option<QueryResult>, ExecutionError> {
+        let all_rows_query_option = self.select_rows(target_table_name.clone(), vec![SelectColumnName::AllColumns], None)?;
+        let new_table = self.get_mut_table_by_sql_value(temp_new_table_name)?;
+
+        if let Some(all_rows_query) = all_rows_query_option {
+            for row in all_rows_query.rows {
+                new_table.insert(None, row.get_sql_values(table_column_types)?, None)?;
+            }
+        }
+
+        self.swap_tables_and_drop_old_table(target_table_name, temp_new_table_name)
+    }
+
     fn add_table_column(&mut self, table_name: SqlValue, column_definition: ColumnDefinition) -> Result<Option<QueryResult>, ExecutionError> {
         let table = self.get_table_by_sql_value(&table_name)?;
+        table.check_no_soft_deleted_rows()?;
         let mut new_column_definitions = table.column_definitions();
         let table_column_types = table.column_types().to_vec();
         new_column_definitions.push(column_definition);
         let temp_new_table_name = Self::temporary_table_name(&table_name);
-        let mut new_table = self.build_table(&temp_new_table_name.to_string(), &new_column_definitions)?;
+        let mut new_table = self.build_table(&temp_new_table_name.to_string(), &new_column_definitions, table.options())?;
         table.clone_indexes_to(&mut new_table)?;
         self.tables.insert(temp_new_table_name.to_string(), new_table);
 
@@ -346,7 +1696,7 @@ impl Database {
             for row in all_rows_query.rows {
                 let mut sql_values = row.get_sql_values(table_column_types)?;
                 sql_values.push(SqlValue::Null);
-                new_table.insert(None, sql_values)?;
+                new_table.insert(None, sql_values, None)?;
             }
         }
 
@@ -392,18 +1742,19 @@ impl Database {
 
     fn drop_old_table_and_flush_schema(&mut self, old_table_name: &SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
         self.drop_table(old_table_name.clone())?;
-        self.flush_schema(); //TODO: full rollback on flush error
+        self.flush_schema()?; //TODO: full rollback on flush error
         Ok(None)
     }
 
     fn drop_table_column(&mut self, table_name: SqlValue, column_name: SqlValue) -> Result<Option<QueryResult>, ExecutionError> {
         let table = self.get_table_by_sql_value(&table_name)?;
+        table.check_no_soft_deleted_rows()?;
         let dropped_column_number = table.column_number_result(column_name.to_string().as_str())?;
         let mut new_column_definitions = table.column_definitions();
         let table_column_types = table.column_types().to_vec();
         new_column_definitions.remove(dropped_column_number);
         let temp_new_table_name = Self::temporary_table_name(&table_name);
-        let mut new_table = self.build_table(&temp_new_table_name.to_string(), &new_column_definitions)?;
+        let mut new_table = self.build_table(&temp_new_table_name.to_string(), &new_column_definitions, table.options())?;
         table.clone_indexes_without_one_column_to(&mut new_table, dropped_column_number)?;
         self.tables.insert(temp_new_table_name.to_string(), new_table);
 
@@ -429,7 +1780,7 @@ impl Database {
             for row in all_rows_query.rows {
                 let mut sql_values = row.get_sql_values(table_column_types)?;
                 sql_values.remove(drop_index);
-                new_table.insert(None, sql_values)?;
+                new_table.insert(None, sql_values, None)?;
             }
         }
 
@@ -442,6 +1793,15 @@ impl Database {
         Ok(None)
     }
 
+    // zero disables timeout, same as in postgres
+    fn set_statement_timeout(&mut self, milliseconds: u64) -> Result<Option<QueryResult>, ExecutionError> {
+        self.session.statement_timeout = match milliseconds {
+            0 => None,
+            milliseconds => Some(Duration::from_millis(milliseconds)),
+        };
+        Ok(None)
+    }
+
     fn get_table_by_sql_value(&self, table_name: &SqlValue) -> Result<&Table, ExecutionError> {
         let table_name_string = table_name.to_string();
         self.get_table(&table_name_string)
@@ -453,19 +1813,49 @@ impl Database {
     }
 
     fn get_mut_table(&mut self, table_name: &str) -> Result<&mut Table, ExecutionError> {
+        let max_file_size = self.max_table_file_size(table_name)?;
+        let table_not_exist = self.table_not_exist(table_name);
         match self.tables.get_mut(table_name) {
-            None => Err(ExecutionError::TableNotExist(table_name.to_string())),
-            Some(existing_table) => Ok(existing_table),
+            None => Err(table_not_exist),
+            Some(existing_table) => {
+                existing_table.set_statement_deadline(self.statement_deadline);
+                existing_table.set_max_file_size(max_file_size);
+                Ok(existing_table)
+            },
         }
     }
 
+    // table can take the smaller of table quota and space which other tables left from database quota
+    fn max_table_file_size(&self, table_name: &str) -> io::Result<Option<u64>> {
+        let max_database_size = match self.size_quota.max_database_size {
+            None => return Ok(self.size_quota.max_table_size),
+            Some(max_database_size) => max_database_size,
+        };
+
+        let mut other_tables_size = 0;
+        for (other_table_name, table) in &self.tables {
+            if other_table_name != table_name {
+                other_tables_size += table.file_size()?;
+            }
+        }
+        let space_left = max_database_size.saturating_sub(other_tables_size);
+        Ok(Some(self.size_quota.max_table_size.map_or(space_left, |max_table_size| max_table_size.min(space_left))))
+    }
+
     fn get_table(&self, table_name: &str) -> Result<&Table, ExecutionError> {
         match self.tables.get(table_name) {
-            None => Err(ExecutionError::TableNotExist(table_name.to_string())),
+            None => Err(self.table_not_exist(table_name)),
             Some(existing_table) => Ok(existing_table),
         }
     }
 
+    fn table_not_exist(&self, table_name: &str) -> ExecutionError {
+        match self.offline_tables.get(table_name) {
+            Some(reason) => ExecutionError::TableOffline { table_name: table_name.to_string(), reason: reason.clone() },
+            None => ExecutionError::TableNotExist(table_name.to_string()),
+        }
+    }
+
     fn table_filepath(tables_dir: &Path, table_name: &str) -> PathBuf {
         let mut path = tables_dir.join(table_name);
         path.set_extension(TABLE_EXTENSION);
@@ -473,6 +1863,21 @@ impl Database {
     }
 
     fn temporary_table_name(table_name: &SqlValue) -> SqlValue {
-        SqlValue::String(format!("{}-{}", table_name, get_timestamp()))
+        SqlValue::Identificator(format!("{}{}_{}", TEMPORARY_TABLE_PREFIX, table_name, get_timestamp()))
+    }
+
+    fn is_temporary_table_name(table_name: &str) -> bool {
+        table_name.starts_with(TEMPORARY_TABLE_PREFIX)
+    }
+
+    fn check_table_name_not_reserved(table_name: &SqlValue) -> Result<(), ExecutionError> {
+        let table_name = table_name.to_string();
+        if system_table::is_system_table_name(&table_name) {
+            return Err(ExecutionError::SystemTableNameReserved(table_name))
+        }
+        match Self::is_temporary_table_name(&table_name) {
+            true => Err(ExecutionError::TableNameReserved(table_name)),
+            false => Ok(()),
+        }
     }
 }
@@ -5,6 +5,9 @@ use std::io;
 use crate::serialize::SerDeError;
 use crate::pager::PagerError;
 use crate::table::error::TableError;
+use crate::table::{ColumnType, ForeignKey};
+use crate::lexer::SqlValue;
+use crate::access::Privilege;
 
 #[derive(Debug)]
 pub enum ExecutionError {
@@ -14,6 +17,32 @@ pub enum ExecutionError {
     PagerError(PagerError),
     IoError(io::Error),
     TableError(TableError),
+    SequenceAlreadyExist(String),
+    SequenceNotExist(String),
+    TableNameReserved(String),
+    SystemTableNameReserved(String),
+    ForeignKeyTargetInvalid(ForeignKey),
+    ForeignKeyViolation { table_name: String, column_name: String, foreign_key: ForeignKey, value: SqlValue },
+    ForeignKeyReferenced { table_name: String, column_name: String, value: SqlValue, referencing_table_name: String },
+    TableReferenced { table_name: String, column_name: String, referencing_table_name: String },
+    UserAlreadyExist(String),
+    UserNotExist(String),
+    PrivilegeMissing { user_name: String, privilege: Privilege, table_name: String },
+    OwnerOnlyStatement(String),
+    StatementNotAllowed(&'static str),
+    TableOffline { table_name: String, reason: String },
+    DatabaseSalvaged(&'static str),
+    ClauseNotSupported { clause: &'static str, statement_name: &'static str },
+    StorageFull(Box<ExecutionError>),
+    CursorAlreadyExist(String),
+    CursorNotExist(String),
+    MacroAlreadyExist(String),
+    OrderColumnNotSelected(String),
+    CsvQuoteUnclosed(usize),
+    CsvValuesCountInvalid { expected: usize, actual: usize },
+    CsvValueInvalid { value: String, column_type: ColumnType },
+    CopyLineFailed { line_number: usize, error: Box<ExecutionError> },
+    SwapCleanupFailed { error: io::Error, cleanup_error: io::Error },
 }
 
 impl fmt::Display for ExecutionError {
@@ -25,6 +54,50 @@ impl fmt::Display for ExecutionError {
             Self::PagerError(pager_error) => pager_error.to_string(),
             Self::IoError(io_error) => io_error.to_string(),
             Self::TableError(table_error) => table_error.to_string(),
+            Self::SequenceAlreadyExist(sequence_name) => format!("sequence '{}' already exists", sequence_name),
+            Self::SequenceNotExist(sequence_name) => format!("sequence '{}' not exists", sequence_name),
+            Self::TableNameReserved(table_name) =>
+                format!("table name '{}' is reserved for temporary tables, consider using another name", table_name),
+            Self::SystemTableNameReserved(table_name) =>
+                format!("table name '{}' is taken by a system table, consider using another name", table_name),
+            Self::ForeignKeyTargetInvalid(foreign_key) =>
+                format!("cannot reference {}: table or column does not exist, or column type is different", foreign_key),
+            Self::ForeignKeyViolation { table_name, column_name, foreign_key, value } =>
+                format!("value {} of column '{}' in table '{}' is not present in referenced {}",
+                        value, column_name, table_name, foreign_key),
+            Self::ForeignKeyReferenced { table_name, column_name, value, referencing_table_name } =>
+                format!("value {} of column '{}' in table '{}' is still referenced from table '{}'",
+                        value, column_name, table_name, referencing_table_name),
+            Self::TableReferenced { table_name, column_name, referencing_table_name } =>
+                format!("column '{}' of table '{}' is referenced from table '{}', drop its foreign key first",
+                        column_name, table_name, referencing_table_name),
+            Self::UserAlreadyExist(user_name) => format!("user '{}' already exists", user_name),
+            Self::UserNotExist(user_name) => format!("user '{}' not exists", user_name),
+            Self::CursorAlreadyExist(cursor_name) => format!("cursor '{}' already exists, close it first", cursor_name),
+            Self::CursorNotExist(cursor_name) => format!("cursor '{}' not exists", cursor_name),
+            Self::MacroAlreadyExist(macro_name) => format!("macro '{}' already exists", macro_name),
+            Self::OrderColumnNotSelected(column_name) =>
+                format!("cannot order by '{}': only selected columns can be used in ORDER BY", column_name),
+            Self::CsvQuoteUnclosed(line_number) => format!("quote opened on line {} is not closed until end of file", line_number),
+            Self::CsvValuesCountInvalid { expected, actual } => format!("expected {} values, got {}", expected, actual),
+            Self::CsvValueInvalid { value, column_type } => format!("cannot read '{}' as {} value", value, column_type),
+            Self::CopyLineFailed { line_number, error } => format!("cannot copy line {}: {}", line_number, error),
+            Self::PrivilegeMissing { user_name, privilege, table_name } =>
+                format!("user '{}' has no {} privilege on table '{}'", user_name, privilege, table_name),
+            Self::OwnerOnlyStatement(user_name) =>
+                format!("user '{}' cannot execute this statement, it is allowed only to database owner", user_name),
+            Self::StatementNotAllowed(statement_name) => format!("{} statement is not allowed by connection policy", statement_name),
+            Self::TableOffline { table_name, reason } =>
+                format!("table '{}' is offline, it could not be opened by salvage connect: {}", table_name, reason),
+            Self::ClauseNotSupported { clause, statement_name } =>
+                format!("{} cannot be used with {} statement", clause, statement_name),
+            Self::DatabaseSalvaged(statement_name) =>
+                format!("{} statement cannot be executed, database connected with `--salvage` is read-only", statement_name),
+            Self::StorageFull(error) =>
+                format!("no space left on device: {}; free disk space (dropping or vacuuming tables also helps) \
+                        and run the statement again", error),
+            Self::SwapCleanupFailed { error, cleanup_error } =>
+                format!("{}, and schema swap file could not be removed: {}", error, cleanup_error),
         };
 
         write!(f, "{}", message)
@@ -59,7 +132,45 @@ impl Error for ExecutionError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             Self::SerDeError(ser_de_error) => Some(ser_de_error),
+            Self::PagerError(pager_error) => Some(pager_error),
+            Self::IoError(io_error) | Self::SwapCleanupFailed { error: io_error, .. } => Some(io_error),
+            Self::TableError(table_error) => Some(table_error),
+            Self::StorageFull(error) | Self::CopyLineFailed { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
 }
+
+impl ExecutionError {
+    /// True if error is caused by a write which failed since there is no space left on device
+    pub fn is_storage_full(&self) -> bool {
+        let mut source: Option<&(dyn Error + 'static)> = Some(self);
+        while let Some(error) = source {
+            if error.downcast_ref::<io::Error>().is_some_and(|io_error| io_error.kind() == io::ErrorKind::StorageFull) {
+                return true
+            }
+            source = error.source();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_full_is_found_in_error_sources() {
+        let storage_full = || io::Error::from(io::ErrorKind::StorageFull);
+        let insert_error = ExecutionError::TableError(TableError::CannotInsertRow(PagerError::IoError(storage_full())));
+        assert!(insert_error.is_storage_full());
+        assert!(ExecutionError::IoError(storage_full()).is_storage_full());
+        assert!(ExecutionError::IoError(io::Error::from_raw_os_error(28)).is_storage_full());
+        let cleanup_error = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(ExecutionError::SwapCleanupFailed { error: storage_full(), cleanup_error }.is_storage_full());
+
+        let other_io_error = ExecutionError::TableError(TableError::IoError(io::Error::from(io::ErrorKind::PermissionDenied)));
+        assert!(!other_io_error.is_storage_full());
+        assert!(!ExecutionError::TableNotExist("users".to_string()).is_storage_full());
+    }
+}
@@ -0,0 +1,392 @@
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+
+use crate::lexer::SqlValue;
+use crate::table::ColumnType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarFunction {
+    Upper,
+    Lower,
+    Length,
+    Substr,
+    Coalesce,
+    IfNull,
+    Abs,
+    Round,
+    Mod,
+    Min,
+    Max,
+}
+
+#[derive(Debug)]
+pub enum FunctionError {
+    ArgumentCountInvalid { function: ScalarFunction, count: usize },
+    ArgumentInvalid { function: ScalarFunction, value: SqlValue },
+    ArgumentTypesDiffer { function: ScalarFunction, types: (ColumnType, ColumnType) },
+    ArgumentTypeInvalid { function: ScalarFunction, column_type: ColumnType },
+}
+
+impl fmt::Display for FunctionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ArgumentCountInvalid { function, count } => {
+                let arity = function.arity();
+                match *arity.end() {
+                    end if end == *arity.start() => write!(f, "{}() expects {} argument(s), got {}", function, arity.start(), count),
+                    usize::MAX => write!(f, "{}() expects at least {} argument(s), got {}", function, arity.start(), count),
+                    end => write!(f, "{}() expects {} to {} arguments, got {}", function, arity.start(), end, count),
+                }
+            },
+            Self::ArgumentInvalid { function, value } => write!(f, "{}() cannot be applied to {}", function, value),
+            Self::ArgumentTypesDiffer { function, types: (left, right) } =>
+                write!(f, "{}() arguments should have the same type, got {} and {}", function, left, right),
+            Self::ArgumentTypeInvalid { function, column_type } => write!(f, "{}() cannot be applied to {} values", function, column_type),
+        }
+    }
+}
+
+impl Error for FunctionError { }
+
+impl fmt::Display for ScalarFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl ScalarFunction {
+    pub fn from_name(name: &str) -> Option<ScalarFunction> {
+        match name.to_uppercase().as_str() {
+            "UPPER" => Some(Self::Upper),
+            "LOWER" => Some(Self::Lower),
+            "LENGTH" => Some(Self::Length),
+            "SUBSTR" => Some(Self::Substr),
+            "COALESCE" => Some(Self::Coalesce),
+            "IFNULL" => Some(Self::IfNull),
+            "ABS" => Some(Self::Abs),
+            "ROUND" => Some(Self::Round),
+            "MOD" => Some(Self::Mod),
+            "MIN" => Some(Self::Min),
+            "MAX" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Upper => "UPPER",
+            Self::Lower => "LOWER",
+            Self::Length => "LENGTH",
+            Self::Substr => "SUBSTR",
+            Self::Coalesce => "COALESCE",
+            Self::IfNull => "IFNULL",
+            Self::Abs => "ABS",
+            Self::Round => "ROUND",
+            Self::Mod => "MOD",
+            Self::Min => "MIN",
+            Self::Max => "MAX",
+        }
+    }
+
+    fn arity(&self) -> RangeInclusive<usize> {
+        match self {
+            Self::Upper | Self::Lower | Self::Length => 1..=1,
+            Self::Substr => 2..=3,
+            Self::Coalesce => 1..=usize::MAX,
+            Self::IfNull | Self::Mod => 2..=2,
+            Self::Abs => 1..=1,
+            Self::Round => 1..=2,
+            Self::Min | Self::Max => 2..=usize::MAX,
+        }
+    }
+
+    /// `argument_types` are `None` for NULL literals. `COALESCE` and `IFNULL` return one of their
+    /// arguments, so all of them should have the same type. Numeric functions of integers are integers,
+    /// `MOD`, `MIN` and `MAX` are floats if any of their arguments is a float.
+    pub fn result_type(&self, argument_types: &[Option<ColumnType>]) -> Result<ColumnType, FunctionError> {
+        match self {
+            Self::Upper | Self::Lower | Self::Substr => Ok(ColumnType::String),
+            Self::Length => Ok(ColumnType::Integer),
+            Self::Coalesce | Self::IfNull => {
+                let mut types = argument_types.iter().flatten();
+                let result_type = types.next().copied().unwrap_or(ColumnType::String);
+                match types.find(|column_type| **column_type != result_type) {
+                    Some(other_type) => Err(FunctionError::ArgumentTypesDiffer { function: *self, types: (result_type, *other_type) }),
+                    None => Ok(result_type),
+                }
+            },
+            Self::Abs | Self::Round => {
+                if let Some(Some(column_type @ (ColumnType::Float | ColumnType::String))) = argument_types.get(1) {
+                    return Err(FunctionError::ArgumentTypeInvalid { function: *self, column_type: *column_type })
+                }
+                self.numeric_result_type(&argument_types[..1])
+            },
+            Self::Mod | Self::Min | Self::Max => self.numeric_result_type(argument_types),
+        }
+    }
+
+    fn numeric_result_type(&self, argument_types: &[Option<ColumnType>]) -> Result<ColumnType, FunctionError> {
+        let mut result_type = ColumnType::Integer;
+        for column_type in argument_types.iter().flatten() {
+            match column_type {
+                ColumnType::String => return Err(FunctionError::ArgumentTypeInvalid { function: *self, column_type: *column_type }),
+                ColumnType::Float => result_type = ColumnType::Float,
+                ColumnType::Integer => {},
+            }
+        }
+        Ok(result_type)
+    }
+
+    /// `COALESCE` returns its first argument which is not NULL, `IFNULL(value, default)` is the same
+    /// as `COALESCE` of two arguments. Other functions of NULL are NULL.
+    pub fn apply(&self, arguments: &[SqlValue]) -> Result<SqlValue, FunctionError> {
+        self.check_argument_count(arguments.len())?;
+
+        match self {
+            Self::Coalesce | Self::IfNull => {
+                let value = arguments.iter().find(|argument| !matches!(argument, SqlValue::Null));
+                Ok(value.cloned().unwrap_or(SqlValue::Null))
+            },
+            Self::Upper | Self::Lower | Self::Length | Self::Substr => self.apply_string(arguments),
+            Self::Abs | Self::Round | Self::Mod | Self::Min | Self::Max => self.apply_numeric(arguments),
+        }
+    }
+
+    /// `LENGTH` counts chars, `SUBSTR(string, start[, length])` takes chars starting from `start`,
+    /// which is counted from 1
+    fn apply_string(&self, arguments: &[SqlValue]) -> Result<SqlValue, FunctionError> {
+        let string = match &arguments[0] {
+            SqlValue::Null => return Ok(SqlValue::Null),
+            SqlValue::String(string) => string,
+            value => return Err(FunctionError::ArgumentInvalid { function: *self, value: value.clone() }),
+        };
+
+        match self {
+            Self::Upper => Ok(SqlValue::String(string.to_uppercase())),
+            Self::Lower => Ok(SqlValue::String(string.to_lowercase())),
+            Self::Length => Ok(SqlValue::Integer(string.chars().count() as i64)),
+            Self::Substr => {
+                let start = self.integer_argument(&arguments[1])?;
+                let length = match arguments.get(2) {
+                    None => None,
+                    Some(value) => match self.integer_argument(value)? {
+                        length if length < 0 => return Err(FunctionError::ArgumentInvalid { function: *self, value: value.clone() }),
+                        length => Some(length),
+                    },
+                };
+
+                // chars before the first one are counted in length, like in standard sql
+                let end = length.map(|length| start.saturating_add(length));
+                let skipped = start.max(1) - 1;
+                let taken = end.map_or(usize::MAX, |end| (end - 1 - skipped).max(0) as usize);
+                Ok(SqlValue::String(string.chars().skip(skipped as usize).take(taken).collect()))
+            },
+            _ => unreachable!("{} is not a string function", self),
+        }
+    }
+
+    /// `ROUND(number[, digits])` rounds floats half away from zero, negative digits count is the same
+    /// as zero, so integers are not changed. `MOD` is a remainder with the sign of dividend, and
+    /// `MOD` by zero is NULL.
+    fn apply_numeric(&self, arguments: &[SqlValue]) -> Result<SqlValue, FunctionError> {
+        if arguments.iter().any(|argument| matches!(argument, SqlValue::Null)) {
+            return Ok(SqlValue::Null)
+        }
+        if let Some(value) = arguments.iter().find(|argument| !matches!(argument, SqlValue::Integer(_) | SqlValue::Float(_))) {
+            return Err(FunctionError::ArgumentInvalid { function: *self, value: value.clone() })
+        }
+        let all_integers = arguments.iter().all(|argument| matches!(argument, SqlValue::Integer(_)));
+
+        match self {
+            Self::Abs => match &arguments[0] {
+                SqlValue::Integer(integer) => integer.checked_abs().map(SqlValue::Integer)
+                    .ok_or_else(|| FunctionError::ArgumentInvalid { function: *self, value: arguments[0].clone() }),
+                value => Ok(SqlValue::Float(Self::float_argument(value).abs())),
+            },
+            Self::Round => {
+                let digits = match arguments.get(1) {
+                    None => 0,
+                    Some(value) => self.integer_argument(value)?.clamp(0, f64::DIGITS as i64),
+                };
+                match &arguments[0] {
+                    SqlValue::Integer(integer) => Ok(SqlValue::Integer(*integer)),
+                    value => {
+                        let float = Self::float_argument(value);
+                        let scale = 10f64.powi(digits as i32);
+                        // very large floats have no fraction digits to round
+                        let rounded = (float * scale).round() / scale;
+                        Ok(SqlValue::Float(if rounded.is_finite() { rounded } else { float }))
+                    },
+                }
+            },
+            Self::Mod => match (&arguments[0], &arguments[1]) {
+                (_, SqlValue::Integer(0)) => Ok(SqlValue::Null),
+                (SqlValue::Integer(dividend), SqlValue::Integer(divisor)) => Ok(SqlValue::Integer(dividend.wrapping_rem(*divisor))),
+                (dividend, divisor) => match Self::float_argument(divisor) {
+                    0.0 => Ok(SqlValue::Null),
+                    divisor => Ok(SqlValue::Float(Self::float_argument(dividend) % divisor)),
+                },
+            },
+            Self::Min | Self::Max if all_integers => {
+                let integers = arguments.iter().map(|argument| match argument {
+                    SqlValue::Integer(integer) => *integer,
+                    _ => unreachable!("arguments are checked to be integers"),
+                });
+                let integer = match self {
+                    Self::Min => integers.min(),
+                    _ => integers.max(),
+                };
+                Ok(SqlValue::Integer(integer.unwrap()))
+            },
+            Self::Min | Self::Max => {
+                let floats = arguments.iter().map(Self::float_argument);
+                let float = match self {
+                    Self::Min => floats.fold(f64::INFINITY, f64::min),
+                    _ => floats.fold(f64::NEG_INFINITY, f64::max),
+                };
+                Ok(SqlValue::Float(float))
+            },
+            _ => unreachable!("{} is not a numeric function", self),
+        }
+    }
+
+    fn float_argument(value: &SqlValue) -> f64 {
+        match value {
+            SqlValue::Integer(integer) => *integer as f64,
+            SqlValue::Float(float) => *float,
+            _ => unreachable!("arguments of numeric functions are checked to be numbers"),
+        }
+    }
+
+    pub fn check_argument_count(&self, count: usize) -> Result<(), FunctionError> {
+        match self.arity().contains(&count) {
+            true => Ok(()),
+            false => Err(FunctionError::ArgumentCountInvalid { function: *self, count }),
+        }
+    }
+
+    fn integer_argument(&self, value: &SqlValue) -> Result<i64, FunctionError> {
+        match value {
+            SqlValue::Integer(integer) => Ok(*integer),
+            value => Err(FunctionError::ArgumentInvalid { function: *self, value: value.clone() }),
+        }
+    }
+}
+
+/// Function applied to values, which are either literals, column names or other function calls
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub function: ScalarFunction,
+    pub arguments: Vec<SqlValue>,
+}
+
+impl fmt::Display for FunctionCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // string arguments are quoted, so call is not confused with one on a column
+        let arguments: Vec<String> = self.arguments.iter()
+            .map(|argument| match argument {
+                SqlValue::String(string) => format!("\"{}\"", string),
+                _ => argument.to_string(),
+            })
+            .collect();
+        write!(f, "{}({})", self.function, arguments.join(", "))
+    }
+}
+
+impl Hash for FunctionCall {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.function.hash(state);
+        self.arguments.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> SqlValue {
+        SqlValue::String(value.to_string())
+    }
+
+    #[test]
+    fn apply_string_functions() {
+        assert_eq!(ScalarFunction::Upper.apply(&[string("John ж")]).unwrap(), string("JOHN Ж"));
+        assert_eq!(ScalarFunction::Lower.apply(&[string("John")]).unwrap(), string("john"));
+        assert_eq!(ScalarFunction::Length.apply(&[string("жук")]).unwrap(), SqlValue::Integer(3));
+        assert_eq!(ScalarFunction::Length.apply(&[SqlValue::Null]).unwrap(), SqlValue::Null);
+
+        let substr = |arguments: &[SqlValue]| ScalarFunction::Substr.apply(arguments).unwrap();
+        assert_eq!(substr(&[string("abcdef"), SqlValue::Integer(2)]), string("bcdef"));
+        assert_eq!(substr(&[string("abcdef"), SqlValue::Integer(2), SqlValue::Integer(3)]), string("bcd"));
+        assert_eq!(substr(&[string("abcdef"), SqlValue::Integer(0), SqlValue::Integer(2)]), string("a"));
+        assert_eq!(substr(&[string("abc"), SqlValue::Integer(5)]), string(""));
+
+        assert!(matches!(
+                ScalarFunction::Upper.apply(&[SqlValue::Integer(1)]),
+                Err(FunctionError::ArgumentInvalid { function: ScalarFunction::Upper, .. })
+                ));
+        assert!(matches!(
+                ScalarFunction::Substr.apply(&[string("abc")]),
+                Err(FunctionError::ArgumentCountInvalid { count: 1, .. })
+                ));
+        assert!(matches!(
+                ScalarFunction::Substr.apply(&[string("abc"), SqlValue::Integer(1), SqlValue::Integer(-1)]),
+                Err(FunctionError::ArgumentInvalid { .. })
+                ));
+    }
+
+    #[test]
+    fn apply_numeric_functions() {
+        let apply = |function: ScalarFunction, arguments: &[SqlValue]| function.apply(arguments).unwrap();
+        assert_eq!(apply(ScalarFunction::Abs, &[SqlValue::Integer(-3)]), SqlValue::Integer(3));
+        assert_eq!(apply(ScalarFunction::Abs, &[SqlValue::Float(-1.5)]), SqlValue::Float(1.5));
+        assert_eq!(apply(ScalarFunction::Abs, &[SqlValue::Null]), SqlValue::Null);
+        assert!(ScalarFunction::Abs.apply(&[SqlValue::Integer(i64::MIN)]).is_err());
+
+        assert_eq!(apply(ScalarFunction::Round, &[SqlValue::Float(2.5)]), SqlValue::Float(3.0));
+        assert_eq!(apply(ScalarFunction::Round, &[SqlValue::Float(-1.256), SqlValue::Integer(2)]), SqlValue::Float(-1.26));
+        assert_eq!(apply(ScalarFunction::Round, &[SqlValue::Integer(7), SqlValue::Integer(-1)]), SqlValue::Integer(7));
+        assert_eq!(apply(ScalarFunction::Round, &[SqlValue::Float(1e300), SqlValue::Integer(15)]), SqlValue::Float(1e300));
+
+        assert_eq!(apply(ScalarFunction::Mod, &[SqlValue::Integer(-7), SqlValue::Integer(3)]), SqlValue::Integer(-1));
+        assert_eq!(apply(ScalarFunction::Mod, &[SqlValue::Float(7.5), SqlValue::Integer(2)]), SqlValue::Float(1.5));
+        assert_eq!(apply(ScalarFunction::Mod, &[SqlValue::Integer(7), SqlValue::Integer(0)]), SqlValue::Null);
+        assert_eq!(apply(ScalarFunction::Mod, &[SqlValue::Integer(i64::MIN), SqlValue::Integer(-1)]), SqlValue::Integer(0));
+
+        assert_eq!(apply(ScalarFunction::Min, &[SqlValue::Integer(3), SqlValue::Integer(-2), SqlValue::Integer(5)]), SqlValue::Integer(-2));
+        assert_eq!(apply(ScalarFunction::Max, &[SqlValue::Integer(3), SqlValue::Float(3.5)]), SqlValue::Float(3.5));
+        assert_eq!(apply(ScalarFunction::Max, &[SqlValue::Integer(3), SqlValue::Null]), SqlValue::Null);
+        assert!(matches!(
+                ScalarFunction::Min.apply(&[SqlValue::Integer(1), string("a")]),
+                Err(FunctionError::ArgumentInvalid { function: ScalarFunction::Min, .. })
+                ));
+
+        assert_eq!(ScalarFunction::Abs.result_type(&[Some(ColumnType::Float)]).unwrap(), ColumnType::Float);
+        assert_eq!(ScalarFunction::Round.result_type(&[Some(ColumnType::Integer), None]).unwrap(), ColumnType::Integer);
+        assert_eq!(ScalarFunction::Max.result_type(&[Some(ColumnType::Integer), Some(ColumnType::Float)]).unwrap(), ColumnType::Float);
+        assert!(matches!(
+                ScalarFunction::Mod.result_type(&[Some(ColumnType::String), Some(ColumnType::Integer)]),
+                Err(FunctionError::ArgumentTypeInvalid { column_type: ColumnType::String, .. })
+                ));
+        assert!(ScalarFunction::Round.result_type(&[Some(ColumnType::Float), Some(ColumnType::Float)]).is_err());
+    }
+
+    #[test]
+    fn apply_coalesce() {
+        let coalesce = |arguments: &[SqlValue]| ScalarFunction::Coalesce.apply(arguments).unwrap();
+        assert_eq!(coalesce(&[SqlValue::Null, SqlValue::Integer(2), SqlValue::Integer(3)]), SqlValue::Integer(2));
+        assert_eq!(coalesce(&[string("a"), SqlValue::Null]), string("a"));
+        assert_eq!(coalesce(&[SqlValue::Null, SqlValue::Null]), SqlValue::Null);
+        assert_eq!(ScalarFunction::IfNull.apply(&[SqlValue::Null, string("none")]).unwrap(), string("none"));
+        assert!(ScalarFunction::IfNull.apply(&[SqlValue::Null]).is_err());
+
+        let types = [None, Some(ColumnType::Integer), None, Some(ColumnType::Integer)];
+        assert_eq!(ScalarFunction::Coalesce.result_type(&types).unwrap(), ColumnType::Integer);
+        assert!(matches!(
+                ScalarFunction::Coalesce.result_type(&[Some(ColumnType::Integer), Some(ColumnType::String)]),
+                Err(FunctionError::ArgumentTypesDiffer { types: (ColumnType::Integer, ColumnType::String), .. })
+                ));
+    }
+}
@@ -0,0 +1,89 @@
+//! Entry points for fuzzers, e.g. `cargo fuzz` targets can call them with arbitrary bytes.
+//! Any error returned by lexer, parser or database is an expected outcome here, only panics are bugs.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::command::Command;
+use crate::database::Database;
+use crate::{lexer, parser};
+
+const FUZZ_DATABASE_NAME: &str = "fuzz.db";
+// generous enough for small tables, but keeps huge generate_series() and such from hanging the fuzzer
+const FUZZ_STATEMENT_TIMEOUT_MILLISECONDS: u64 = 1000;
+
+static FUZZ_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Lexes and parses input both as metacommand and as SQL statement, without executing anything.
+pub fn parse_bytes(data: &[u8]) {
+    let input = String::from_utf8_lossy(data);
+    let _ = parser::parse_meta_command(&input);
+
+    if let Ok(tokens) = lexer::to_tokens(&input) {
+        let _ = parser::parse_statement(tokens.iter());
+    }
+}
+
+/// Executes each line of input as SQL statement against a fresh database, which is created
+/// in temp dir and removed afterwards. Only errors of database setup and cleanup are returned.
+pub fn execute_bytes(data: &[u8]) -> io::Result<()> {
+    let fuzz_dir = fuzz_dir_path();
+    fs::create_dir_all(&fuzz_dir)?;
+    let result = execute_in_database(&fuzz_dir, &String::from_utf8_lossy(data));
+    fs::remove_dir_all(&fuzz_dir)?;
+    result
+}
+
+fn execute_in_database(fuzz_dir: &Path, input: &str) -> io::Result<()> {
+    let database_path = fuzz_dir.join(FUZZ_DATABASE_NAME);
+    Database::create(&database_path, fuzz_dir).map_err(io::Error::other)?;
+    let mut database = Database::from(&database_path).map_err(io::Error::other)?;
+    database.execute(Command::SetStatementTimeout { milliseconds: FUZZ_STATEMENT_TIMEOUT_MILLISECONDS })
+        .map_err(io::Error::other)?;
+
+    for line in input.lines() {
+        let command = match lexer::to_tokens(line) {
+            Ok(tokens) => parser::parse_statement(tokens.iter()).ok(),
+            Err(_) => None,
+        };
+
+        if let Some(command) = command {
+            let _ = database.execute(command);
+        }
+    }
+
+    let _ = database.close();
+    Ok(())
+}
+
+fn fuzz_dir_path() -> PathBuf {
+    let counter = FUZZ_DIR_COUNTER.fetch_add(1, Ordering::Relaxed);
+    env::temp_dir().join(format!("yarrd-fuzz-{}-{}", process::id(), counter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzz_inputs_do_not_panic() {
+        let long_string = format!("\"{}\"", "x".repeat(300));
+        let inputs = [
+            "".to_string(),
+            "é\"ж\" ( , \" select".to_string(),
+            format!("create table t (id int, name string)\ninsert into t values (1, {})\nvacuum t", long_string),
+            "create table t (id int)\ndelete from t\nvacuum t\nselect * from generate_series(1, 9223372036854775807)".to_string(),
+            "create table u (name string, id int)\ninsert into u (id) values (1, 2)\ninsert into u values (1)".to_string(),
+        ];
+
+        for input in inputs {
+            parse_bytes(input.as_bytes());
+            execute_bytes(input.as_bytes()).unwrap();
+        }
+        parse_bytes(&[0xff, 0xfe, b'(', 0]);
+    }
+}
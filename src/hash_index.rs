@@ -1,24 +1,68 @@
 use crate::lexer::SqlValue;
+use crate::hash_index::bucket_cache::BucketCache;
 use crate::hash_index::error::HashIndexError;
 use crate::hash_index::hash_bucket::{HashBucket, HashRow};
 use crate::serialize::SerDeError;
 
 use std::path::{PathBuf, Path};
 use std::fs::{self, OpenOptions, File};
-use std::io::{Seek, SeekFrom, Write, Read};
+use std::io::{Seek, SeekFrom, Write};
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::cell::{Cell, RefCell};
 use std::hash::{Hash, Hasher};
+use std::fmt;
 
 pub mod error;
+mod bucket_cache;
 mod hash_bucket;
 
+pub const HASH_INDEX_EXTENSION: &str = "hash";
+// deleted slots of a full bucket, fewer of them cannot make bucket chains long
+const REINDEX_MIN_DELETED_ENTRIES: usize = hash_bucket::ROWS_IN_BUCKET;
+
 #[derive(Debug)]
 pub struct HashIndex {
     pub name: String,
     hash_index_filepath: PathBuf,
-    hash_index_file: File,
+    buckets: BucketCache,
     swap_hash_index_filepath: PathBuf, // this is used to rebuild index and swap it with original
     base_buckets_count: usize,
+    // changes deferred until the end of statement, index is borrowed immutably during table scan
+    pending_changes: RefCell<PendingChanges>,
+    // entries count of each hashed value, values with colliding hashes are counted as one key
+    key_entries: RefCell<HashMap<u64, usize>>,
+    // slots of deleted rows which are not reused yet, they keep bucket chains long after bulk deletes
+    deleted_entries: Cell<usize>,
+}
+
+// deferred changes grouped by primary bucket number, so each bucket chain is read and written
+// once per statement, rather than once per changed row
+#[derive(Debug, Default)]
+struct PendingChanges {
+    deleted_row_ids: BTreeMap<u64, Vec<u64>>,
+    inserted_rows: BTreeMap<u64, Vec<(u64, u64)>>,
+}
+
+/// Index shape, as reported by `.index_stats`
+#[derive(Debug, PartialEq)]
+pub struct HashIndexStats {
+    pub name: String,
+    pub keys: usize,
+    pub entries: usize,
+    pub primary_buckets: u64,
+    pub overflow_buckets: u64,
+    pub longest_chain: u64,
+    pub load_factor: f64,
+    pub deleted_entries: usize,
+}
+
+impl fmt::Display for HashIndexStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} keys, {} entries, {} deleted, {} buckets + {} overflow, longest chain {} buckets, load factor {:.2}",
+               self.name, self.keys, self.entries, self.deleted_entries, self.primary_buckets, self.overflow_buckets,
+               self.longest_chain, self.load_factor)
+    }
 }
 
 impl HashIndex {
@@ -26,35 +70,105 @@ impl HashIndex {
         let hash_index_filepath = Self::build_hash_index_filepath(tables_dir, table_name, name.as_str());
         let swap_filepath = Self::build_swap_hash_index_filepath(tables_dir, table_name, name.as_str());
 
-        let hash_index_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(hash_index_filepath.as_path())?;
+        let buckets = BucketCache::new(Self::open_hash_index_file(hash_index_filepath.as_path())?)?;
+        let base_buckets_count = buckets.get(0)?.primary_buckets_count()? as usize;
 
-        let base_buckets_count = HashBucket::new(&hash_index_file, 0)?.primary_buckets_count()? as usize;
-
-        if hash_index_file.metadata()?.len() < (base_buckets_count * hash_bucket::BUCKET_SIZE) as u64 {
-            hash_index_file.set_len((base_buckets_count * hash_bucket::BUCKET_SIZE) as u64)?;
+        if buckets.file().metadata()?.len() < (base_buckets_count * hash_bucket::BUCKET_SIZE) as u64 {
+            buckets.file().set_len((base_buckets_count * hash_bucket::BUCKET_SIZE) as u64)?;
         }
 
-        Ok(Self {
-            hash_index_file,
+        let hash_index = Self {
+            buckets,
             hash_index_filepath,
             base_buckets_count,
             name,
             swap_hash_index_filepath: swap_filepath,
-        })
+            pending_changes: RefCell::new(PendingChanges::default()),
+            key_entries: RefCell::new(HashMap::new()),
+            deleted_entries: Cell::new(0),
+        };
+        for bucket in hash_index.each_bucket()? {
+            let bucket = bucket?;
+            for hash_row in bucket.all_index_rows() {
+                hash_index.count_entry(hash_row?.hashed_value);
+            }
+            hash_index.deleted_entries.set(hash_index.deleted_entries.get() + bucket.deleted_row_count());
+        }
+
+        Ok(hash_index)
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn filepath(&self) -> &Path {
+        &self.hash_index_filepath
+    }
+
+    /// Number of rows which probe by the value would return, including hash collisions
+    pub fn estimated_row_count(&self, column_value: &SqlValue) -> usize {
+        let hashed_value = Self::hash_sql_value(column_value);
+        self.key_entries.borrow().get(&hashed_value).copied().unwrap_or(0)
+    }
+
+    pub fn stats(&self) -> Result<HashIndexStats, HashIndexError> {
+        let total_buckets = self.buckets.file().metadata()?.len() / hash_bucket::BUCKET_SIZE_U64;
+        let primary_buckets = self.base_buckets_count as u64;
+        let longest_chain = (0..primary_buckets)
+            .map(|bucket_number| HashBucket::bucket_iter_with_overflow_buckets(bucket_number, &self.buckets).count() as u64)
+            .max()
+            .unwrap_or(0);
+        let key_entries = self.key_entries.borrow();
+        let entries = key_entries.values().sum::<usize>();
+
+        Ok(HashIndexStats {
+            name: self.name.clone(),
+            keys: key_entries.len(),
+            entries,
+            primary_buckets,
+            overflow_buckets: total_buckets - primary_buckets,
+            longest_chain,
+            load_factor: entries as f64 / (hash_bucket::ROWS_IN_BUCKET as u64 * primary_buckets) as f64,
+            deleted_entries: self.deleted_entries.get(),
+        })
+    }
+
+    /// Human readable content of index file bucket, as shown by `.pagedump`
+    pub fn bucket_dump(&self, bucket_number: u64) -> Result<String, HashIndexError> {
+        let total_buckets = self.buckets.file().metadata()?.len() / hash_bucket::BUCKET_SIZE_U64;
+        // bucket right after the end of file would be allocated by load
+        if bucket_number >= total_buckets {
+            return Err(HashIndexError::UnexpectedBucketNumber(bucket_number))
+        }
+
+        let bucket = self.buckets.get(bucket_number)?;
+        let kind = match bucket_number < self.base_buckets_count as u64 {
+            true => "primary",
+            false => "overflow",
+        };
+        let next_bucket = match bucket.overflow_bucket_number()? {
+            Some(overflow_bucket_number) => format!("overflow bucket {}", overflow_bucket_number),
+            None => "no overflow bucket".to_string(),
+        };
+        let mut lines = vec![format!("bucket {} of {} in index '{}': {}, {}", bucket_number, total_buckets, self.name, kind, next_bucket)];
+        lines.extend(bucket.dump_lines());
+        Ok(lines.join("\n"))
+    }
+
+    /// Index should be rebuilt once slots of deleted rows outnumber entries, since lookups read them
+    /// as well. Small indexes are never rebuilt, their chains are short anyway.
+    pub fn is_mostly_deleted(&self) -> bool {
+        let deleted_entries = self.deleted_entries.get();
+        deleted_entries >= REINDEX_MIN_DELETED_ENTRIES && deleted_entries > self.key_entries.borrow().values().sum::<usize>()
+    }
+
+    /// Row ids of entries with the same value hash, rows of other values with colliding hashes can
+    /// be found as well, so caller should check the value of each found row
     pub fn find_row_ids(&self, column_value: &SqlValue) -> impl Iterator<Item = Result<u64, HashIndexError>> + '_ {
         let hashed_value = Self::hash_sql_value(column_value);
 
-        Self::matching_buckets(&self.hash_index_file, self.base_buckets_count as u64, hashed_value)
+        Self::matching_buckets(&self.buckets, self.base_buckets_count as u64, hashed_value)
             .flat_map(move |bucket| bucket.find_database_rows(hashed_value))
     }
 
@@ -71,7 +185,10 @@ impl HashIndex {
             }) {
                 Err(HashIndexError::RowAlreadyExists(column_value.clone(), row_id))
             } else {
-                Self::insert_row_to_file(&self.hash_index_file, hashed_value, row_id, self.base_buckets_count)
+                let reused_deleted = Self::insert_row_to_file(&self.buckets, hashed_value, row_id, self.base_buckets_count)?;
+                self.uncount_deleted(reused_deleted as usize);
+                self.count_entry(hashed_value);
+                Ok(())
             }
     }
 
@@ -80,37 +197,87 @@ impl HashIndex {
         let hashed_new_value = Self::hash_sql_value(new_column_value);
 
         let row_id = self.delete_row_from_file(hashed_old_value, row_id)?;
-        Self::insert_row_to_file(&self.hash_index_file, hashed_new_value, row_id, self.base_buckets_count)
+        self.uncount_entry(hashed_old_value);
+        let reused_deleted = Self::insert_row_to_file(&self.buckets, hashed_new_value, row_id, self.base_buckets_count)?;
+        self.uncount_deleted(reused_deleted as usize);
+        self.count_entry(hashed_new_value);
+        Ok(())
     }
 
     pub fn delete_row(&self, row_id: u64, column_value: &SqlValue) -> Result<(), HashIndexError> {
         let hashed_value = Self::hash_sql_value(column_value);
 
         self.delete_row_from_file(hashed_value, row_id)?;
+        self.uncount_entry(hashed_value);
         Ok(())
     }
 
-    pub fn destroy(self) -> Result<(), HashIndexError> {
-        self.drop_swap_file_if_present()?;
-        fs::remove_file(self.hash_index_filepath)?;
+    /// Same as `update_row`, but change is kept in memory until `apply_pending_changes` is called
+    pub fn defer_update_row(&self, row_id: u64, old_column_value: &SqlValue, new_column_value: &SqlValue) {
+        self.defer_delete_row(row_id, old_column_value);
+
+        let hashed_new_value = Self::hash_sql_value(new_column_value);
+        self.count_entry(hashed_new_value);
+        self.pending_changes.borrow_mut().inserted_rows
+            .entry(hashed_new_value % self.base_buckets_count as u64)
+            .or_default()
+            .push((hashed_new_value, row_id));
+    }
+
+    /// Same as `delete_row`, but change is kept in memory until `apply_pending_changes` is called
+    pub fn defer_delete_row(&self, row_id: u64, column_value: &SqlValue) {
+        let hashed_value = Self::hash_sql_value(column_value);
+        self.uncount_entry(hashed_value);
+        self.pending_changes.borrow_mut().deleted_row_ids
+            .entry(hashed_value % self.base_buckets_count as u64)
+            .or_default()
+            .push(row_id);
+    }
+
+    /// Writes deferred changes bucket by bucket, deletions go first, so updated row which stays in
+    /// the same bucket does not take an extra slot
+    pub fn apply_pending_changes(&self) -> Result<(), HashIndexError> {
+        let pending_changes = self.pending_changes.take();
+
+        for (bucket_number, row_ids) in pending_changes.deleted_row_ids {
+            self.delete_rows_from_bucket_chain(bucket_number, row_ids)?;
+        }
+        for (bucket_number, rows) in pending_changes.inserted_rows {
+            let reused_deleted = Self::insert_rows_to_bucket_chain(&self.buckets, bucket_number, rows)?;
+            self.uncount_deleted(reused_deleted);
+        }
+
         Ok(())
     }
 
-    pub fn adjust_filepaths(&mut self, new_table_name: &str, tables_dir: &Path) -> Result<(), HashIndexError> {
+    pub fn destroy(mut self) -> Result<(), HashIndexError> {
         self.drop_swap_file_if_present()?;
+        self.buckets.clear()?;
+        fs::remove_file(&self.hash_index_filepath)?;
+        Ok(())
+    }
 
-        let new_hash_index_filepath = Self::build_hash_index_filepath(tables_dir, new_table_name, &self.name);
-        let new_swap_filepath = Self::build_swap_hash_index_filepath(tables_dir, new_table_name, &self.name);
+    /// Path index file gets after table is renamed
+    pub fn renamed_table_filepath(&self, tables_dir: &Path, new_table_name: &str) -> PathBuf {
+        Self::build_hash_index_filepath(tables_dir, new_table_name, &self.name)
+    }
 
-        // TODO: this should be rollbackable via cascade file manager
-        fs::rename(self.hash_index_filepath.as_path(), new_hash_index_filepath.as_path())?;
+    /// Points index to files of renamed table, index file itself should already be moved
+    pub fn set_table_name(&mut self, new_table_name: &str, tables_dir: &Path) -> Result<(), HashIndexError> {
+        self.drop_swap_file_if_present()?;
 
-        self.hash_index_filepath = new_hash_index_filepath;
-        self.swap_hash_index_filepath = new_swap_filepath;
+        self.hash_index_filepath = Self::build_hash_index_filepath(tables_dir, new_table_name, &self.name);
+        self.swap_hash_index_filepath = Self::build_swap_hash_index_filepath(tables_dir, new_table_name, &self.name);
 
         Ok(())
     }
 
+    pub fn sync(&self) -> Result<(), HashIndexError> {
+        self.buckets.flush()?;
+        self.buckets.file().sync_data()?;
+        Ok(())
+    }
+
     fn drop_swap_file_if_present(&self) -> Result<(), HashIndexError> {
         match fs::remove_file(self.swap_hash_index_filepath.as_path()) {
             Ok(()) => Ok(()),
@@ -119,98 +286,213 @@ impl HashIndex {
         }
     }
 
-    fn insert_row_to_file(file: &File, hashed_value: u64, row_id: u64, base_buckets_count: usize) -> Result<(), HashIndexError> {
+    // returns true if row took a slot of deleted row
+    fn insert_row_to_file(buckets: &BucketCache, hashed_value: u64, row_id: u64, base_buckets_count: usize) -> Result<bool, HashIndexError> {
         let bucket_with_new_row =
-            Self::matching_buckets(file, base_buckets_count as u64, hashed_value)
+            Self::matching_buckets(buckets, base_buckets_count as u64, hashed_value)
             .map(|mut bucket| {
                 match bucket.insert_row(hashed_value, row_id) {
-                    Err(HashIndexError::BucketIsFull)  => Ok(false), // this bucket is full, need to continue iteration
-                    Ok(_) => Ok(true), // insertion successful no need to continue iteration
+                    Err(HashIndexError::BucketIsFull)  => Ok(None), // this bucket is full, need to continue iteration
+                    Ok(reused_deleted) => { // insertion successful no need to continue iteration
+                        buckets.put(bucket)?;
+                        Ok(Some(reused_deleted))
+                    },
                     Err(other_error)  => Err(other_error), // serialization error, can't insert
                 }
             })
-            .find(|insertion_result| insertion_result.is_err() || insertion_result.as_ref().unwrap() == &true);
+            .find(|insertion_result| insertion_result.is_err() || insertion_result.as_ref().unwrap().is_some());
 
         match bucket_with_new_row {
-            Some(Ok(_)) => Ok(()),
+            Some(Ok(reused_deleted)) => Ok(reused_deleted.unwrap_or(false)),
             Some(Err(error)) => Err(error),
             None => {
-                Self::matching_buckets(file, base_buckets_count as u64, hashed_value)
+                let last_bucket = Self::matching_buckets(buckets, base_buckets_count as u64, hashed_value)
                     .last()
-                    .unwrap() // matching buckets is guaranteed to return at least one bucket
-                    .spawn_overflow_bucket()?
-                    .insert_row(hashed_value, row_id)
+                    .unwrap(); // matching buckets is guaranteed to return at least one bucket
+                let mut overflow_bucket = buckets.spawn_overflow_bucket(last_bucket)?;
+                overflow_bucket.insert_row(hashed_value, row_id)?;
+                buckets.put(overflow_bucket)?;
+                Ok(false)
             }
         }
     }
 
+    // returns number of rows which took slots of deleted rows
+    fn insert_rows_to_bucket_chain(buckets: &BucketCache, bucket_number: u64, rows: Vec<(u64, u64)>) -> Result<usize, HashIndexError> {
+        let mut rows = rows.into_iter().peekable();
+        let mut bucket_chain = HashBucket::bucket_iter_with_overflow_buckets(bucket_number, buckets);
+        let mut bucket = bucket_chain.next().unwrap(); // chain always has at least primary bucket
+        let mut reused_deleted = 0;
+
+        loop {
+            while let Some((hashed_value, row_id)) = rows.peek() {
+                match bucket.insert_row(*hashed_value, *row_id) {
+                    Ok(reused) => {
+                        reused_deleted += reused as usize;
+                        rows.next();
+                    },
+                    Err(HashIndexError::BucketIsFull) => break,
+                    Err(other_error) => return Err(other_error),
+                }
+            }
+            if rows.peek().is_none() {
+                buckets.put(bucket)?;
+                return Ok(reused_deleted)
+            }
+            bucket = match bucket_chain.next() {
+                Some(next_bucket) => {
+                    buckets.put(bucket)?;
+                    next_bucket
+                },
+                None => buckets.spawn_overflow_bucket(bucket)?,
+            };
+        }
+    }
+
+    fn delete_rows_from_bucket_chain(&self, bucket_number: u64, mut row_ids: Vec<u64>) -> Result<(), HashIndexError> {
+        for mut bucket in HashBucket::bucket_iter_with_overflow_buckets(bucket_number, &self.buckets) {
+            let mut remaining_row_ids = vec![];
+            for row_id in row_ids {
+                match bucket.delete_row(row_id)? {
+                    Some(_) => self.deleted_entries.set(self.deleted_entries.get() + 1),
+                    None => remaining_row_ids.push(row_id),
+                }
+            }
+            self.buckets.put(bucket)?;
+
+            row_ids = remaining_row_ids;
+            if row_ids.is_empty() { return Ok(()) }
+        }
+
+        Err(HashIndexError::RowDoesNotExists(row_ids[0]))
+    }
+
     fn delete_row_from_file(&self, hashed_old_value: u64, row_id: u64) -> Result<u64, HashIndexError> {
         let last_deleted_row =
-            Self::matching_buckets(&self.hash_index_file, self.base_buckets_count as u64, hashed_old_value)
-            .map(|mut bucket| bucket.delete_row(row_id))
+            Self::matching_buckets(&self.buckets, self.base_buckets_count as u64, hashed_old_value)
+            .map(|mut bucket| {
+                let deleted_row_id = bucket.delete_row(row_id)?;
+                self.buckets.put(bucket)?;
+                Ok(deleted_row_id)
+            })
             .find(|deletion_result| deletion_result.is_err() || deletion_result.as_ref().unwrap().is_some());
 
         match last_deleted_row {
-            Some(Ok(_)) => Ok(row_id),
+            Some(Ok(_)) => {
+                self.deleted_entries.set(self.deleted_entries.get() + 1);
+                Ok(row_id)
+            },
             Some(Err(error)) => Err(error),
             None => Err(HashIndexError::RowDoesNotExists(row_id)),
         }
     }
 
     pub fn clear(&mut self) -> Result<(), HashIndexError> {
-        self.hash_index_file.set_len(0)?;
-        self.hash_index_file.rewind()?;
+        self.key_entries.get_mut().clear();
+        self.deleted_entries.set(0);
+        self.buckets.clear()?;
+        self.buckets.file().set_len(0)?;
         Ok(())
     }
 
+    /// Rebuilds index with twice as many buckets in swap file, which then replaces index file.
+    /// Rename is atomic, so interrupted rebuild leaves either old or new index intact, and
+    /// leftover swap file is overwritten by the next rebuild. Swap file of failed rebuild is removed
+    /// right away, so it does not hold disk space if rebuild failed since the disk is full.
     pub fn increase_buckets_count(&mut self) -> Result<(), HashIndexError> {
-        let mut swap_hash_index_file = OpenOptions::new()
+        self.rebuild(self.base_buckets_count * 2)
+    }
+
+    /// Rebuilds index with the same number of buckets, so that slots of deleted rows are dropped
+    /// and bucket chains are as short as entries allow
+    pub fn reindex(&mut self) -> Result<(), HashIndexError> {
+        self.rebuild(self.base_buckets_count)
+    }
+
+    fn rebuild(&mut self, buckets_count: usize) -> Result<(), HashIndexError> {
+        let swap_buckets_count = self.build_swap_index_file(buckets_count)
+            .map_err(|error| match fs::remove_file(self.swap_hash_index_filepath.as_path()) {
+                Ok(()) => error,
+                Err(cleanup_error) => HashIndexError::SwapCleanupFailed { error: Box::new(error), cleanup_error },
+            })?;
+
+        fs::rename(self.swap_hash_index_filepath.as_path(), self.hash_index_filepath.as_path())?;
+        // all current rows are in the renamed file now, so cached buckets of the old one are outdated
+        self.buckets.clear()?;
+        self.buckets = BucketCache::new(Self::open_hash_index_file(self.hash_index_filepath.as_path())?)?;
+        self.base_buckets_count = swap_buckets_count;
+        self.deleted_entries.set(0);
+
+        Ok(())
+    }
+
+    fn build_swap_index_file(&self, buckets_count: usize) -> Result<usize, HashIndexError> {
+        let swap_hash_index_file = OpenOptions::new()
             .write(true)
             .read(true)
             .create(true)
+            .truncate(true)
             .open(self.swap_hash_index_filepath.as_path())?;
 
-        swap_hash_index_file.set_len(self.base_buckets_count as u64 * 2 * hash_bucket::BUCKET_SIZE_U64)?;
+        swap_hash_index_file.set_len(buckets_count as u64 * hash_bucket::BUCKET_SIZE_U64)?;
 
-        for hash_row_result in self.each_row() {
-            let hash_row = hash_row_result.as_ref().unwrap();
-            Self::insert_row_to_file(&swap_hash_index_file, hash_row.hashed_value, hash_row.row_id, self.base_buckets_count * 2)?
+        let swap_buckets = BucketCache::new(swap_hash_index_file)?;
+        for hash_row_result in self.each_row()? {
+            let hash_row = hash_row_result?;
+            Self::insert_row_to_file(&swap_buckets, hash_row.hashed_value, hash_row.row_id, buckets_count)?;
         }
+        swap_buckets.flush()?;
 
+        let mut swap_hash_index_file = swap_buckets.file();
         swap_hash_index_file.seek(SeekFrom::Start(hash_bucket::TOTAL_BUCKETS_ADDRESS as u64))?;
-        swap_hash_index_file.write_all(&(self.base_buckets_count * 2).to_le_bytes())?;
+        swap_hash_index_file.write_all(&buckets_count.to_le_bytes())?;
+        swap_hash_index_file.sync_data()?;
 
-        let total_buckets = swap_hash_index_file.metadata()?.len() / hash_bucket::BUCKET_SIZE_U64;
-        self.hash_index_file.set_len(0)?;
-        swap_hash_index_file.rewind()?;
+        Ok(buckets_count)
+    }
 
-        for bucket_number in 0..total_buckets {
-            let mut bytes = [0u8; hash_bucket::BUCKET_SIZE];
-            swap_hash_index_file.read_exact(&mut bytes)?;
+    fn each_row(&self) -> Result<impl Iterator<Item = Result<HashRow, HashIndexError>> + '_, HashIndexError> {
+        Ok(self.each_bucket()?
+            .flat_map(|bucket| match bucket {
+                Ok(bucket) => bucket.all_index_rows(),
+                Err(error) => vec![Err(error)],
+            }))
+    }
 
-            self.hash_index_file.seek(SeekFrom::Start(hash_bucket::BUCKET_SIZE_U64 * bucket_number))?;
-            self.hash_index_file.write_all(&bytes[..])?;
-        }
+    fn each_bucket(&self) -> Result<impl Iterator<Item = Result<HashBucket, HashIndexError>> + '_, HashIndexError> {
+        let total_buckets = self.buckets.file().metadata()?.len() / hash_bucket::BUCKET_SIZE_U64;
+        Ok((0..total_buckets)
+            .map(|bucket_number| self.buckets.get(bucket_number)))
+    }
 
-        self.base_buckets_count *= 2;
+    fn matching_buckets(buckets: &BucketCache, base_buckets_count: u64, hashed_value: u64) -> impl Iterator<Item = HashBucket> + '_ {
+        let primary_bucket_number = hashed_value % base_buckets_count;
+        HashBucket::bucket_iter_with_overflow_buckets(primary_bucket_number, buckets)
+    }
 
-        Ok(())
+    fn count_entry(&self, hashed_value: u64) {
+        *self.key_entries.borrow_mut().entry(hashed_value).or_default() += 1;
     }
 
-    fn each_row(&self) -> impl Iterator<Item = Result<HashRow, HashIndexError>> + '_ {
-        self.each_bucket()
-            .flat_map(|bucket| bucket.unwrap().all_index_rows())
+    fn uncount_deleted(&self, reused_deleted: usize) {
+        self.deleted_entries.set(self.deleted_entries.get().saturating_sub(reused_deleted));
     }
 
-    fn each_bucket(&self) -> impl Iterator<Item = Result<HashBucket, HashIndexError>> + '_ {
-        let total_buckets = self.hash_index_file.metadata().unwrap().len() / hash_bucket::BUCKET_SIZE_U64;
-        (0..total_buckets)
-            .map(|bucket_number| HashBucket::new(&self.hash_index_file, bucket_number))
+    fn uncount_entry(&self, hashed_value: u64) {
+        let mut key_entries = self.key_entries.borrow_mut();
+        if let Some(entries) = key_entries.get_mut(&hashed_value) {
+            *entries -= 1;
+            if *entries == 0 { key_entries.remove(&hashed_value); }
+        }
     }
 
-    fn matching_buckets(hash_index_file: &File, base_buckets_count: u64, hashed_value: u64) -> impl Iterator<Item = HashBucket> + '_ {
-        let primary_bucket_number = hashed_value % base_buckets_count;
-        HashBucket::bucket_iter_with_overflow_buckets(primary_bucket_number, hash_index_file)
+    fn open_hash_index_file(filepath: &Path) -> Result<File, HashIndexError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(filepath)?;
+        Ok(file)
     }
 
     fn hash_sql_value(value: &SqlValue) -> u64 {
@@ -221,13 +503,13 @@ impl HashIndex {
 
     fn build_hash_index_filepath(tables_dir: &Path, table_name: &str, index_name: &str) -> PathBuf {
         let mut filepath = tables_dir.to_path_buf();
-        filepath.push(format!("{}-{}.hash", table_name, index_name));
+        filepath.push(format!("{}-{}.{}", table_name, index_name, HASH_INDEX_EXTENSION));
         filepath
     }
 
     fn build_swap_hash_index_filepath(tables_dir: &Path, table_name: &str, index_name: &str) -> PathBuf {
         let mut filepath = tables_dir.to_path_buf();
-        filepath.push(format!("{}-{}-swap.hash", table_name, index_name));
+        filepath.push(format!("{}-{}-swap.{}", table_name, index_name, HASH_INDEX_EXTENSION));
         filepath
     }
 }
@@ -324,6 +606,10 @@ mod tests {
 
         assert_eq!(index_file.file_path.metadata().unwrap().len(), 512 * 5);
 
+        let overflow_blob = index_file.read_u64(504).expect("cannot read overflow bucket number blob");
+        assert_eq!(u64::from_le_bytes(overflow_blob), 0); // modified bucket is still cached
+
+        index.sync().unwrap();
         let overflow_blob = index_file.read_u64(504).expect("cannot read overflow bucket number blob");
         let overflow_pointer = u64::from_le_bytes(overflow_blob);
         assert_eq!(overflow_pointer, 4); // 5th bucket is overflow bucket of a first bucket
@@ -351,9 +637,16 @@ mod tests {
         let mut index = HashIndex::new(tables_dir_path.as_path(), "users", "u2".to_string())
             .expect("hash index should be creatable from seed file");
 
+        // swap file left by interrupted rebuild
+        let swap_filepath = tables_dir_path.join("users-u2-swap.hash");
+        fs::write(&swap_filepath, [1u8; 512 * 3]).unwrap();
+
         assert_eq!(index.insert_row(&SqlValue::Integer(1), 999, 28).is_ok(), true);
         assert_eq!(index.find_row_ids(&SqlValue::Integer(1)).last().unwrap().unwrap(), 999u64);
+        assert_eq!(index.find_row_ids(&SqlValue::Integer(1)).count(), 21);
+        assert!(!swap_filepath.exists());
 
+        index.sync().unwrap();
         assert_eq!(index_file.file_path.metadata().unwrap().len(), 512 * 2);
 
         let overflow_blob = index_file.read_u64(504).expect("cannot read overflow bucket number blob");
@@ -401,4 +694,142 @@ mod tests {
         let mut ids_with_1 = index.find_row_ids(&SqlValue::Integer(1));
         assert_eq!(ids_with_1.next().is_none(), true);
     }
+
+    #[test]
+    fn deferred_changes_are_applied_together() {
+        let (index_file, tables_dir_path) = create_index_file("users", "ui2");
+
+        let hash_1 = calculate_hash(&1i64).to_le_bytes();
+        let mut contents: Vec<u8> = vec![];
+
+        for row_id in 0..40u64 {
+            contents.push(1);
+            contents.extend_from_slice(&hash_1);
+            contents.extend_from_slice(&row_id.to_le_bytes());
+            if row_id == 27 { contents.resize(512, 0) } // first bucket is full, the rest go to overflow one
+        }
+
+        contents.resize(1024, 0);
+        contents[496] = 1; // total buckets count
+        contents[504] = 1; // overflow bucket pointer
+
+        index_file.write_bytes(&contents)
+            .expect("seed contents should be writable to index file");
+
+        let index = HashIndex::new(tables_dir_path.as_path(), "users", "ui2".to_string())
+            .expect("hash index should be creatable from seed file");
+
+        for row_id in 0..40 {
+            if row_id % 2 == 0 {
+                index.defer_update_row(row_id, &SqlValue::Integer(1), &SqlValue::Integer(3));
+            } else {
+                index.defer_delete_row(row_id, &SqlValue::Integer(1));
+            }
+        }
+        assert_eq!(index.find_row_ids(&SqlValue::Integer(1)).count(), 40);
+        assert_eq!(index.find_row_ids(&SqlValue::Integer(3)).count(), 0);
+
+        index.apply_pending_changes().unwrap();
+        assert_eq!(index.find_row_ids(&SqlValue::Integer(1)).count(), 0);
+        let ids_with_3: Vec<u64> = index.find_row_ids(&SqlValue::Integer(3)).map(Result::unwrap).collect();
+        assert_eq!(ids_with_3, (0..40).step_by(2).collect::<Vec<u64>>());
+
+        index.defer_delete_row(99, &SqlValue::Integer(3));
+        assert!(matches!(index.apply_pending_changes(), Err(HashIndexError::RowDoesNotExists(99))));
+    }
+
+    #[test]
+    fn evicted_buckets_are_written_to_file() {
+        let (index_file, tables_dir_path) = create_index_file("users", "ui3");
+
+        let mut contents = vec![0u8; 512 * 64]; // more buckets than cache can hold
+        contents[496] = 64; // total buckets count
+        index_file.write_bytes(&contents)
+            .expect("seed contents should be writable to index file");
+
+        let mut index = HashIndex::new(tables_dir_path.as_path(), "users", "ui3".to_string())
+            .expect("hash index should be creatable from seed file");
+        for value in 0..200 {
+            index.insert_row(&SqlValue::Integer(value), value as u64, 0).unwrap();
+        }
+        for value in 0..200 {
+            assert_eq!(index.find_row_ids(&SqlValue::Integer(value)).next().unwrap().unwrap(), value as u64);
+        }
+        drop(index);
+
+        let index = HashIndex::new(tables_dir_path.as_path(), "users", "ui3".to_string()).unwrap();
+        for value in 0..200 {
+            assert_eq!(index.find_row_ids(&SqlValue::Integer(value)).next().unwrap().unwrap(), value as u64);
+        }
+    }
+
+    #[test]
+    fn stats_and_estimated_row_count() {
+        let (_index_file, tables_dir_path) = create_index_file("users", "ui4");
+        let mut index = HashIndex::new(tables_dir_path.as_path(), "users", "ui4".to_string()).unwrap();
+
+        for row_id in 0..30 { // more than bucket can hold
+            index.insert_row(&SqlValue::Integer(1), row_id, 0).unwrap();
+        }
+        index.insert_row(&SqlValue::Integer(2), 99, 0).unwrap();
+
+        assert_eq!(index.estimated_row_count(&SqlValue::Integer(1)), 30);
+        assert_eq!(index.estimated_row_count(&SqlValue::Integer(3)), 0);
+        assert_eq!(index.stats().unwrap(), HashIndexStats {
+            name: "ui4".to_string(),
+            keys: 2,
+            entries: 31,
+            primary_buckets: 1,
+            overflow_buckets: 1,
+            longest_chain: 2,
+            load_factor: 31.0 / 29.0,
+            deleted_entries: 0,
+        });
+        assert_eq!(index.stats().unwrap().to_string(),
+                   "ui4: 2 keys, 31 entries, 0 deleted, 1 buckets + 1 overflow, longest chain 2 buckets, load factor 1.07");
+
+        index.delete_row(99, &SqlValue::Integer(2)).unwrap();
+        index.defer_update_row(0, &SqlValue::Integer(1), &SqlValue::Integer(3));
+        index.apply_pending_changes().unwrap();
+        assert_eq!(index.estimated_row_count(&SqlValue::Integer(1)), 29);
+        assert_eq!(index.stats().unwrap().keys, 2);
+        // updated row takes its own deleted slot
+        assert_eq!(index.stats().unwrap().deleted_entries, 1);
+        drop(index);
+
+        // counts are restored from index file
+        let index = HashIndex::new(tables_dir_path.as_path(), "users", "ui4".to_string()).unwrap();
+        assert_eq!(index.estimated_row_count(&SqlValue::Integer(1)), 29);
+        assert_eq!(index.estimated_row_count(&SqlValue::Integer(3)), 1);
+        assert_eq!(index.stats().unwrap().deleted_entries, 1);
+    }
+
+    #[test]
+    fn reindex_drops_deleted_entries() {
+        let (_index_file, tables_dir_path) = create_index_file("users", "ui5");
+        let mut index = HashIndex::new(tables_dir_path.as_path(), "users", "ui5".to_string()).unwrap();
+
+        for row_id in 0..100 {
+            index.insert_row(&SqlValue::Integer(1), row_id, 0).unwrap();
+        }
+        for row_id in 10..40 {
+            index.defer_delete_row(row_id, &SqlValue::Integer(1));
+        }
+        index.apply_pending_changes().unwrap();
+        assert_eq!(index.stats().unwrap().deleted_entries, 30);
+        assert!(!index.is_mostly_deleted());
+
+        for row_id in 40..100 {
+            index.delete_row(row_id, &SqlValue::Integer(1)).unwrap();
+        }
+        assert!(index.is_mostly_deleted());
+        assert_eq!(index.stats().unwrap().longest_chain, 4);
+
+        index.reindex().unwrap();
+        let stats = index.stats().unwrap();
+        assert_eq!((stats.entries, stats.deleted_entries, stats.primary_buckets, stats.longest_chain), (10, 0, 1, 1));
+        let mut row_ids: Vec<u64> = index.find_row_ids(&SqlValue::Integer(1)).map(Result::unwrap).collect();
+        row_ids.sort();
+        assert_eq!(row_ids, (0..10).collect::<Vec<u64>>());
+    }
 }
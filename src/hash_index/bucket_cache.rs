@@ -0,0 +1,83 @@
+use crate::hash_index::error::HashIndexError;
+use crate::hash_index::hash_bucket::{HashBucket, BUCKET_SIZE_U64};
+use crate::pager::lru::Lru;
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io;
+
+const BUCKET_CACHE_SIZE: usize = 32;
+
+/// Recently used buckets of hash index file. Buckets are handed out as copies, so index can be
+/// probed while it is borrowed immutably. Modified copies are put back to cache and are written
+/// to the file when they are evicted, or when cache is flushed.
+#[derive(Debug)]
+pub struct BucketCache {
+    file: File,
+    buckets: RefCell<Lru<u64, HashBucket>>,
+}
+
+impl BucketCache {
+    pub fn new(file: File) -> Result<BucketCache, HashIndexError> {
+        let buckets = RefCell::new(Lru::new(BUCKET_CACHE_SIZE)?);
+        Ok(Self { file, buckets })
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn get(&self, bucket_number: u64) -> Result<HashBucket, HashIndexError> {
+        if let Some(bucket) = self.buckets.borrow_mut().get(&bucket_number) {
+            return Ok(bucket.clone())
+        }
+
+        let bucket = HashBucket::load(&self.file, bucket_number)?;
+        self.cache(bucket.clone())?;
+        Ok(bucket)
+    }
+
+    pub fn put(&self, bucket: HashBucket) -> Result<(), io::Error> {
+        if !bucket.is_modified() { return Ok(()) }
+        self.cache(bucket)
+    }
+
+    /// Allocates new bucket at the end of file and links it to the given last bucket of a chain
+    pub fn spawn_overflow_bucket(&self, mut bucket: HashBucket) -> Result<HashBucket, HashIndexError> {
+        let overflow_bucket_number = self.file.metadata()?.len() / BUCKET_SIZE_U64;
+        bucket.set_overflow_bucket_pointer(overflow_bucket_number)?;
+        self.put(bucket)?;
+        self.get(overflow_bucket_number)
+    }
+
+    /// Writes all modified buckets to the file, buckets stay in cache
+    pub fn flush(&self) -> Result<(), io::Error> {
+        for (_bucket_number, bucket) in self.buckets.borrow_mut().iter_mut() {
+            bucket.write(&self.file)?;
+        }
+        Ok(())
+    }
+
+    /// Forgets cached buckets without writing them, should be called when file is rewritten
+    pub fn clear(&mut self) -> Result<(), HashIndexError> {
+        *self.buckets.get_mut() = Lru::new(BUCKET_CACHE_SIZE)?;
+        Ok(())
+    }
+
+    fn cache(&self, bucket: HashBucket) -> Result<(), io::Error> {
+        let evicted = self.buckets.borrow_mut().set(bucket.bucket_number(), bucket);
+        if let Some((_bucket_number, mut evicted_bucket)) = evicted {
+            evicted_bucket.write(&self.file)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BucketCache {
+    fn drop(&mut self) {
+        // drop cannot return an error, call `flush` beforehand to handle it
+        if let Err(error) = self.flush() {
+            eprintln!("failed to flush hash index buckets: {}", error);
+        }
+    }
+}
@@ -1,21 +1,27 @@
+use crate::hash_index::bucket_cache::BucketCache;
 use crate::hash_index::error::HashIndexError;
 use crate::serialize::SerDeError;
+use crate::helpers::hex_dump;
 
 use std::fs::File;
 use std::io::{self, Seek, SeekFrom, Write, Read};
 use std::cmp::Ordering;
 
-pub const ROW_SIZE: usize = 1 + 8 + 8; // is deleted flag + hashed value + disk row number
+pub const ROW_SIZE: usize = 1 + 8 + 8; // presence flag + hashed value + disk row number
 pub const BUCKET_SIZE: usize = 512;
 pub const BUCKET_SIZE_U64: u64 = BUCKET_SIZE as u64;
 pub const ROWS_IN_BUCKET: usize = BUCKET_SIZE / ROW_SIZE - 1; // leave some space for overflow pointer
 pub const TOTAL_BUCKETS_ADDRESS: usize = BUCKET_SIZE - 16; // 8 bytes for total buckets count in first bucket
 const OVERFLOW_BUCKET_ADDRESS: usize = BUCKET_SIZE - 8; // rows end at 493th byte, and we use 8 bytes
                                                         // for a pointer to overflow bucket at the end of page
+const EMPTY_FLAG: u8 = 0;
+const PRESENT_FLAG: u8 = 1;
+// slot of deleted row, it is reused by inserts like an empty one, but is counted to decide when index
+// should be rebuilt; older index files have empty flag there instead
+const DELETED_FLAG: u8 = 2;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HashBucket {
-    hash_index_file: File,
     bucket_number: u64,
     modified: bool,
     bytes: [u8; BUCKET_SIZE],
@@ -30,8 +36,9 @@ pub struct HashRow {
 }
 
 impl HashBucket {
-    pub fn new(file: &File, bucket_number: u64) -> Result<HashBucket, HashIndexError> {
-        let mut hash_index_file = file.try_clone()?;
+    /// Reads bucket from index file, bucket right after the end of file is allocated
+    pub fn load(file: &File, bucket_number: u64) -> Result<HashBucket, HashIndexError> {
+        let mut hash_index_file = file;
         let file_len = hash_index_file.metadata()?.len();
         let bucket_starts_at = BUCKET_SIZE as u64 * bucket_number;
 
@@ -50,7 +57,15 @@ impl HashBucket {
         let mut bytes = [0u8; BUCKET_SIZE];
         hash_index_file.read_exact(&mut bytes)?;
 
-        Ok(Self { hash_index_file, bucket_number, bytes, modified: false })
+        Ok(Self { bucket_number, bytes, modified: false })
+    }
+
+    pub fn bucket_number(&self) -> u64 {
+        self.bucket_number
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
     }
 
     pub fn all_index_rows(&self) -> Vec<Result<HashRow, HashIndexError>> {
@@ -79,23 +94,51 @@ impl HashBucket {
                     hash_row_id: global_row_number,
                 })
             })
-            .filter(|hash_row| hash_row.as_ref().unwrap().presence_flag == 1)
+            .filter(|hash_row| hash_row.as_ref().unwrap().presence_flag == PRESENT_FLAG)
             .collect()
     }
 
+    /// Non-empty slots with their flag, hashed value and row id, followed by hex of slot bytes
+    pub fn dump_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for row_number in 0..ROWS_IN_BUCKET {
+            let row_starts_at = row_number * ROW_SIZE;
+            let slot = &self.bytes[row_starts_at..row_starts_at + ROW_SIZE];
+            let status = match slot[0] {
+                EMPTY_FLAG => continue,
+                PRESENT_FLAG => "present",
+                DELETED_FLAG => "deleted",
+                _ => "unknown flag",
+            };
+            let hashed_value = u64::from_le_bytes(slot[1..9].try_into().unwrap());
+            let row_id = u64::from_le_bytes(slot[9..17].try_into().unwrap());
+            lines.push(format!("slot {}: {}, hash {:016x}, row {}", row_number, status, hashed_value, row_id));
+            lines.extend(hex_dump(slot, row_starts_at));
+        }
+        lines
+    }
+
+    pub fn deleted_row_count(&self) -> usize {
+        (0..ROWS_IN_BUCKET)
+            .filter(|row_number| self.bytes[row_number * ROW_SIZE] == DELETED_FLAG)
+            .count()
+    }
+
     pub fn delete_row(&mut self, row_id: u64) -> Result<Option<u64>, HashIndexError> {
         let found_hash_row = self.all_index_rows()
             .into_iter()
             .find(|result| {
                 result.is_err() ||
-                    matches!(result, Ok(index_row) if index_row.presence_flag == 1 && index_row.row_id == row_id)
+                    matches!(result, Ok(index_row) if index_row.presence_flag == PRESENT_FLAG && index_row.row_id == row_id)
             });
 
         match found_hash_row {
             None => Ok(None),
             Some(Ok(row)) => {
-                let row_starts_at = row.hash_row_id as usize * ROW_SIZE;
-                self.bytes[row_starts_at] = 0;
+                // hash row id is numbered across all buckets, row offset is taken within this one
+                let bucket_row_number = row.hash_row_id - self.bucket_number * ROWS_IN_BUCKET as u64;
+                let row_starts_at = bucket_row_number as usize * ROW_SIZE;
+                self.bytes[row_starts_at] = DELETED_FLAG;
                 self.modified = true;
                 Ok(Some(row_id))
             }
@@ -108,25 +151,26 @@ impl HashBucket {
             .into_iter()
             .filter(|result| {
                     result.is_err() ||
-                        matches!(result, Ok(index_row) if index_row.presence_flag == 1 && index_row.hashed_value == hashed_value)
+                        matches!(result, Ok(index_row) if index_row.presence_flag == PRESENT_FLAG && index_row.hashed_value == hashed_value)
             })
             .map(|result| result.map(|index_row| index_row.row_id))
             .collect()
     }
 
-    pub fn insert_row(&mut self, hashed_value: u64, row_id: u64) -> Result<(), HashIndexError> {
+    /// Inserts row into the first free slot, returns true if it was a slot of deleted row
+    pub fn insert_row(&mut self, hashed_value: u64, row_id: u64) -> Result<bool, HashIndexError> {
         for row_number in 0..ROWS_IN_BUCKET {
             let row_starts_at = row_number * ROW_SIZE;
             match self.bytes[row_starts_at] {
-                0 => {
-                    self.bytes[row_starts_at] = 1;
+                flag @ (EMPTY_FLAG | DELETED_FLAG) => {
+                    self.bytes[row_starts_at] = PRESENT_FLAG;
                     let hashed_value_blob = hashed_value.to_le_bytes();
                     let row_id_blob = row_id.to_le_bytes();
                     (&mut self.bytes[(row_starts_at + 1)..]).write_all(&hashed_value_blob)?;
                     (&mut self.bytes[(row_starts_at + 9)..]).write_all(&row_id_blob)?;
                     self.modified = true;
 
-                    return Ok(())
+                    return Ok(flag == DELETED_FLAG)
                 },
                 _ => continue
             }
@@ -135,13 +179,6 @@ impl HashBucket {
         Err(HashIndexError::BucketIsFull)
     }
 
-    pub fn spawn_overflow_bucket(mut self) -> Result<HashBucket, HashIndexError> {
-        let bucket_starts_at = self.hash_index_file.metadata()?.len();
-        let overflow_bucket_number = bucket_starts_at / BUCKET_SIZE as u64;
-        self.set_overflow_bucket_pointer(overflow_bucket_number)?;
-        Self::new(&self.hash_index_file, overflow_bucket_number)
-    }
-
     pub fn overflow_bucket_number(&self) -> Result<Option<u64>, HashIndexError> {
         let mut u64_blob: [u8; 8] = [0; 8];
 
@@ -162,8 +199,8 @@ impl HashBucket {
         Ok(())
     }
 
-    pub fn bucket_iter_with_overflow_buckets(bucket_number: u64, file: &File) -> impl Iterator<Item = HashBucket> + '_ {
-        HashBucketChainIter { file, next_bucket_number: Some(bucket_number) }
+    pub fn bucket_iter_with_overflow_buckets(bucket_number: u64, buckets: &BucketCache) -> impl Iterator<Item = HashBucket> + '_ {
+        HashBucketChainIter { buckets, next_bucket_number: Some(bucket_number) }
     }
 
     pub fn primary_buckets_count(&self) -> Result<u64, HashIndexError> {
@@ -176,26 +213,21 @@ impl HashBucket {
         Ok(u64::from_le_bytes(u64_blob))
     }
 
-    fn flush(&mut self) -> Result<(), io::Error> {
+    pub fn write(&mut self, file: &File) -> Result<(), io::Error> {
         if !self.modified { return Ok(()) }
 
-        self.hash_index_file.seek(SeekFrom::Start(BUCKET_SIZE as u64 * self.bucket_number))?;
-        self.hash_index_file.write_all(&self.bytes[..])?;
+        let mut hash_index_file = file;
+        hash_index_file.seek(SeekFrom::Start(BUCKET_SIZE as u64 * self.bucket_number))?;
+        hash_index_file.write_all(&self.bytes[..])?;
+        self.modified = false;
 
         Ok(())
     }
 }
 
-impl Drop for HashBucket {
-    fn drop(&mut self) {
-        // TODO: flush err should be handled somehow (probably via WAL or error logs)
-        self.flush().unwrap();
-    }
-}
-
 struct HashBucketChainIter<'a> {
     next_bucket_number: Option<u64>,
-    file: &'a File,
+    buckets: &'a BucketCache,
 }
 
 impl<'a> Iterator for HashBucketChainIter<'a> {
@@ -205,7 +237,7 @@ impl<'a> Iterator for HashBucketChainIter<'a> {
         if self.next_bucket_number.is_none() {
             None
         } else {
-            match HashBucket::new(self.file, self.next_bucket_number.unwrap()) {
+            match self.buckets.get(self.next_bucket_number.unwrap()) {
                 Ok(bucket) => {
                     // we assume that hash index data is valid, so bucket number won't throw an error
                     self.next_bucket_number = bucket.overflow_bucket_number().unwrap();
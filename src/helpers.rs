@@ -6,3 +6,17 @@ pub fn get_timestamp() -> u128 {
         .unwrap()
         .as_nanos()
 }
+
+/// Lines of 16 bytes with page offset, hex values and printable ascii chars, as in `hexdump -C`
+pub fn hex_dump(bytes: &[u8], start_offset: usize) -> Vec<String> {
+    bytes.chunks(16)
+        .enumerate()
+        .map(|(line_number, line)| {
+            let hex: Vec<String> = line.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = line.iter()
+                .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+                .collect();
+            format!("{:04x}  {:<47}  |{}|", start_offset + line_number * 16, hex.join(" "), ascii)
+        })
+        .collect()
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::helpers::get_timestamp;
+use crate::meta_command_error::MetaCommandError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub statement: String,
+    /// Seconds since unix epoch
+    pub executed_at: u64,
+    pub succeeded: bool,
+}
+
+impl HistoryEntry {
+    fn status(&self) -> &'static str {
+        if self.succeeded { "ok" } else { "error" }
+    }
+}
+
+/// Statements executed in the cmd interface, numbered from 1. If history file is set, entries of
+/// previous sessions are loaded from it, and each new entry is appended to it as a
+/// `TIMESTAMP<tab>STATUS<tab>STATEMENT` line.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    file: Option<File>,
+}
+
+impl History {
+    /// Loads entries from history file and appends current session entries to them,
+    /// so that they keep the same numbers. File is created if it does not exist.
+    pub fn set_file(&mut self, path: &Path) -> Result<(), MetaCommandError> {
+        let file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+
+        let mut entries = vec![];
+        for (line_number, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line?;
+            let entry = Self::parse_line(&line)
+                .ok_or(MetaCommandError::HistoryFileCorrupted(PathBuf::from(path), line_number + 1))?;
+            entries.push(entry);
+        }
+
+        let mut file = file;
+        for entry in &self.entries {
+            Self::write_line(&mut file, entry)?;
+        }
+        entries.append(&mut self.entries);
+
+        self.entries = entries;
+        self.file = Some(file);
+        Ok(())
+    }
+
+    pub fn push(&mut self, statement: &str, succeeded: bool) -> Result<(), io::Error> {
+        let entry = HistoryEntry {
+            statement: statement.to_string(),
+            executed_at: (get_timestamp() / 1_000_000_000) as u64,
+            succeeded,
+        };
+
+        let write_result = match self.file.as_mut() {
+            Some(file) => Self::write_line(file, &entry),
+            None => Ok(()),
+        };
+        self.entries.push(entry);
+        write_result
+    }
+
+    /// Returns entry by its number, entries are numbered from 1
+    pub fn get(&self, number: usize) -> Option<&HistoryEntry> {
+        number.checked_sub(1).and_then(|index| self.entries.get(index))
+    }
+
+    /// Last `count` entries (all if not given) with their numbers, oldest first
+    pub fn last(&self, count: Option<usize>) -> impl Iterator<Item = (usize, &HistoryEntry)> {
+        let skip = count.map_or(0, |count| self.entries.len().saturating_sub(count));
+        self.entries.iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(index, entry)| (index + 1, entry))
+    }
+
+    /// Expands `!!` to the last statement and `!N` to statement number N.
+    /// Returns `None` if input is not a history shortcut.
+    pub fn expand(&self, input: &str) -> Option<Result<String, MetaCommandError>> {
+        let reference = input.strip_prefix('!')?;
+        let entry = match reference {
+            "!" => self.entries.last(),
+            number => match number.parse() {
+                Ok(number) => self.get(number),
+                Err(_) => return None,
+            },
+        };
+
+        Some(entry
+             .map(|entry| entry.statement.clone())
+             .ok_or(MetaCommandError::HistoryEntryMissing(input.to_string())))
+    }
+
+    fn parse_line(line: &str) -> Option<HistoryEntry> {
+        let mut parts = line.splitn(3, '\t');
+        let executed_at = parts.next()?.parse().ok()?;
+        let succeeded = match parts.next()? {
+            "ok" => true,
+            "error" => false,
+            _ => return None,
+        };
+        let statement = parts.next()?.to_string();
+        Some(HistoryEntry { statement, executed_at, succeeded })
+    }
+
+    fn write_line(file: &mut File, entry: &HistoryEntry) -> Result<(), io::Error> {
+        writeln!(file, "{}\t{}\t{}", entry.executed_at, entry.status(), entry.statement)
+    }
+}
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}  {:<5}  {}", self.executed_at, self.status(), self.statement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_file::TempFile;
+
+    #[test]
+    fn push_list_and_expand() {
+        let mut history = History::default();
+        assert!(matches!(history.expand("!!"), Some(Err(MetaCommandError::HistoryEntryMissing(_)))));
+
+        history.push("select * from users", true).unwrap();
+        history.push("select * from nobody", false).unwrap();
+        history.push("select id from users", true).unwrap();
+
+        let last_two: Vec<(usize, &str, bool)> = history.last(Some(2))
+            .map(|(number, entry)| (number, entry.statement.as_str(), entry.succeeded))
+            .collect();
+        assert_eq!(last_two, vec![(2, "select * from nobody", false), (3, "select id from users", true)]);
+        assert_eq!(history.last(None).count(), 3);
+        assert_eq!(history.last(Some(10)).count(), 3);
+
+        assert_eq!(history.expand("!!").unwrap().unwrap(), "select id from users");
+        assert_eq!(history.expand("!1").unwrap().unwrap(), "select * from users");
+        assert!(matches!(history.expand("!0"), Some(Err(MetaCommandError::HistoryEntryMissing(_)))));
+        assert!(matches!(history.expand("!4"), Some(Err(MetaCommandError::HistoryEntryMissing(_)))));
+        assert!(history.expand("!= 1").is_none());
+        assert!(history.expand("select 1").is_none());
+    }
+
+    #[test]
+    fn entries_are_kept_in_file() {
+        let history_file = TempFile::new("history").unwrap();
+
+        let mut history = History::default();
+        history.push("select * from users", true).unwrap();
+        history.set_file(history_file.path()).unwrap();
+        history.push("select * from nobody", false).unwrap();
+        drop(history);
+
+        let mut history = History::default();
+        history.push("select id from users", true).unwrap();
+        history.set_file(history_file.path()).unwrap();
+
+        let statements: Vec<&str> = history.last(None).map(|(_, entry)| entry.statement.as_str()).collect();
+        assert_eq!(statements, vec!["select * from users", "select * from nobody", "select id from users"]);
+        assert!(!history.get(2).unwrap().succeeded);
+
+        history_file.write_bytes(b"not a history line\n").unwrap();
+        assert!(matches!(
+                History::default().set_file(history_file.path()),
+                Err(MetaCommandError::HistoryFileCorrupted(_, 1))
+                ));
+    }
+}
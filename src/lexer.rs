@@ -1,8 +1,11 @@
+use std::cmp::Ordering;
 use std::error::Error;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
-#[derive(Debug, PartialEq)]
+use crate::function::FunctionCall;
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     LeftParenthesis,
     RightParenthesis,
@@ -36,10 +39,40 @@ pub enum Token {
     Values,
     Is,
     Not,
+    And,
+    Or,
+    In,
+    Exists,
     Constraint,
     Default,
     Check,
+    References,
+    Show,
+    Storage,
     Vacuum,
+    With,
+    Undelete,
+    Sequence,
+    User,
+    Grant,
+    Revoke,
+    NextVal,
+    Random,
+    Since,
+    Declare,
+    Cursor,
+    For,
+    Fetch,
+    Close,
+    Order,
+    By,
+    Copy,
+    Returning,
+    Limit,
+    Offset,
+    Unset,
+    Macro,
+    As,
     IntegerType, // TODO: maybe extract types to separate enum
     StringType,
     FloatType,
@@ -82,10 +115,40 @@ impl fmt::Display for Token {
             Self::Values => "VALUES",
             Self::Is => "IS",
             Self::Not => "NOT",
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::In => "IN",
+            Self::Exists => "EXISTS",
             Self::Vacuum => "VACUUM",
+            Self::With => "WITH",
+            Self::Undelete => "UNDELETE",
+            Self::Sequence => "SEQUENCE",
+            Self::User => "USER",
+            Self::Grant => "GRANT",
+            Self::Revoke => "REVOKE",
+            Self::NextVal => "NEXTVAL",
+            Self::Random => "RANDOM",
+            Self::Since => "SINCE",
+            Self::Declare => "DECLARE",
+            Self::Cursor => "CURSOR",
+            Self::For => "FOR",
+            Self::Fetch => "FETCH",
+            Self::Close => "CLOSE",
+            Self::Order => "ORDER",
+            Self::By => "BY",
+            Self::Copy => "COPY",
+            Self::Returning => "RETURNING",
+            Self::Limit => "LIMIT",
+            Self::Offset => "OFFSET",
+            Self::Unset => "UNSET",
+            Self::Macro => "MACRO",
+            Self::As => "AS",
             Self::Constraint => "CONSTRAINT",
             Self::Default => "DEFAULT",
             Self::Check => "CHECK",
+            Self::References => "REFERENCES",
+            Self::Show => "SHOW",
+            Self::Storage => "STORAGE",
             Self::IntegerType => "int",
             Self::StringType => "string",
             Self::FloatType => "float",
@@ -122,6 +185,7 @@ pub enum SqlValue {
     Float(f64),
     Identificator(String),
     Null,
+    Function(Box<FunctionCall>),
 }
 
 impl fmt::Display for SqlValue {
@@ -131,6 +195,7 @@ impl fmt::Display for SqlValue {
             Self::Integer(integer) => write!(f, "{}", integer),
             Self::Float(float) => write!(f, "{:e}", float),
             Self::Null => write!(f, "NULL"),
+            Self::Function(function_call) => write!(f, "{}", function_call),
         }
     }
 }
@@ -138,15 +203,69 @@ impl fmt::Display for SqlValue {
 impl Hash for SqlValue {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
-            Self::Float(_) => (),
+            Self::Float(_) | Self::Null => (),
             Self::String(string) => string.hash(state),
             Self::Integer(int) => int.hash(state),
             Self::Identificator(string) => string.hash(state),
-            Self::Null => Self::Null.hash(state),
+            Self::Function(function_call) => function_call.hash(state),
         }
     }
 }
 
+impl SqlValue {
+    /// Value itself, or arguments of function call including nested calls arguments,
+    /// in the same order as they appear in statement
+    pub fn operands(&self) -> Vec<&SqlValue> {
+        match self {
+            Self::Function(function_call) => function_call.arguments.iter().flat_map(SqlValue::operands).collect(),
+            _ => vec![self],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut SqlValue> {
+        match self {
+            Self::Function(function_call) => function_call.arguments.iter_mut().flat_map(SqlValue::operands_mut).collect(),
+            _ => vec![self],
+        }
+    }
+
+    /// Order of values of any types, used to sort values of different types deterministically.
+    /// As in SQLite, NULL is less than any number and numbers are less than strings. Integers
+    /// and floats are compared by exact numeric value, so `1` equals `1.0`, and NaN is greater
+    /// than any other number. Strings are compared by bytes, identificators are compared as
+    /// strings, and function calls, which are never stored in rows, are greater than everything.
+    pub fn total_cmp(&self, other: &SqlValue) -> Ordering {
+        self.type_rank().cmp(&other.type_rank()).then_with(|| match (self, other) {
+            (Self::Integer(left), Self::Integer(right)) => left.cmp(right),
+            (Self::Float(left), Self::Float(right)) =>
+                left.partial_cmp(right).unwrap_or_else(|| left.is_nan().cmp(&right.is_nan())),
+            (Self::Integer(left), Self::Float(right)) => Self::cmp_integer_float(*left, *right),
+            (Self::Float(left), Self::Integer(right)) => Self::cmp_integer_float(*right, *left).reverse(),
+            (Self::String(left) | Self::Identificator(left), Self::String(right) | Self::Identificator(right)) => left.cmp(right),
+            _ => Ordering::Equal,
+        })
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Integer(_) | Self::Float(_) => 1,
+            Self::String(_) | Self::Identificator(_) => 2,
+            Self::Function(_) => 3,
+        }
+    }
+
+    // integer is not converted to float, since large integers would lose precision
+    fn cmp_integer_float(integer: i64, float: f64) -> Ordering {
+        const TWO_POW_63: f64 = 9_223_372_036_854_775_808.0;
+        if float.is_nan() || float >= TWO_POW_63 { return Ordering::Less }
+        if float < -TWO_POW_63 { return Ordering::Greater }
+
+        let whole = float.trunc();
+        integer.cmp(&(whole as i64)).then_with(|| 0.0_f64.partial_cmp(&(float - whole)).unwrap())
+    }
+}
+
 impl Token {
     pub fn is_junk(&self) -> bool {
         matches!(self, Token::Unknown(_))
@@ -155,9 +274,10 @@ impl Token {
 
 pub fn to_tokens(input: &str) -> Result<Vec<Token>, LexerError> {
     if input.matches('"').count() % 2 != 0 { return Err(LexerError::IncompleteString) };
-    let input_chars_length = input.chars().count();
+    let input_length = input.len();
 
-    let tokens: Vec<Token> = input.chars().enumerate()
+    // separators are byte offsets, so non-ascii chars are never split while slicing input
+    let tokens: Vec<Token> = input.char_indices()
         .fold((vec![0], false), |(mut separate_at, mut inside_string), (i, c)| {
             match c {
                 '"' => {
@@ -177,8 +297,8 @@ pub fn to_tokens(input: &str) -> Result<Vec<Token>, LexerError> {
                 _ => { },
             }
 
-            if i == input_chars_length - 1 && separate_at[separate_at.len() - 1] != input_chars_length {
-                separate_at.push(input_chars_length);
+            if i + c.len_utf8() == input_length && separate_at[separate_at.len() - 1] != input_length {
+                separate_at.push(input_length);
             }
 
             (separate_at, inside_string)
@@ -199,7 +319,7 @@ pub fn to_tokens(input: &str) -> Result<Vec<Token>, LexerError> {
 }
 
 fn parse_token(str_token: &str) -> Token {
-    if str_token.starts_with('"') && str_token.ends_with('"') {
+    if str_token.len() > 1 && str_token.starts_with('"') && str_token.ends_with('"') {
         return Token::Value(SqlValue::String(str_token[1..str_token.len()-1].to_string()))
     };
 
@@ -236,10 +356,40 @@ fn parse_token(str_token: &str) -> Token {
         "values" => Token::Values,
         "is" => Token::Is,
         "not" => Token::Not,
+        "and" => Token::And,
+        "or" => Token::Or,
+        "in" => Token::In,
+        "exists" => Token::Exists,
         "vacuum" => Token::Vacuum,
+        "with" => Token::With,
+        "undelete" => Token::Undelete,
+        "sequence" => Token::Sequence,
+        "user" => Token::User,
+        "grant" => Token::Grant,
+        "revoke" => Token::Revoke,
+        "nextval" => Token::NextVal,
+        "random" => Token::Random,
+        "since" => Token::Since,
+        "declare" => Token::Declare,
+        "cursor" => Token::Cursor,
+        "for" => Token::For,
+        "fetch" => Token::Fetch,
+        "close" => Token::Close,
+        "order" => Token::Order,
+        "by" => Token::By,
+        "copy" => Token::Copy,
+        "returning" => Token::Returning,
+        "limit" => Token::Limit,
+        "offset" => Token::Offset,
+        "unset" => Token::Unset,
+        "macro" => Token::Macro,
+        "as" => Token::As,
         "constraint" => Token::Constraint,
         "default" => Token::Default,
         "check" => Token::Check,
+        "references" => Token::References,
+        "show" => Token::Show,
+        "storage" => Token::Storage,
         "int" => Token::IntegerType,
         "float" => Token::FloatType,
         "string" => Token::StringType,
@@ -314,4 +464,43 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn non_ascii_chars_parse() {
+        assert_eq!(
+            to_tokens("select имя from \"таблица, é\"").unwrap(),
+            vec![
+                Token::Select, Token::Value(SqlValue::Identificator("имя".into())), Token::From,
+                Token::Value(SqlValue::String("таблица, é".into())),
+            ]
+        );
+        assert_eq!(
+            to_tokens("é\"\"ж").unwrap(),
+            vec![
+                Token::Value(SqlValue::Identificator("é".into())), Token::Value(SqlValue::String("".into())),
+                Token::Value(SqlValue::Identificator("ж".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn sql_values_total_order() {
+        let mut values = vec![
+            SqlValue::String("b".into()), SqlValue::Float(f64::NAN), SqlValue::Integer(2), SqlValue::Null,
+            SqlValue::Float(1.5), SqlValue::String("a".into()), SqlValue::Integer(i64::MAX), SqlValue::Float(-0.5),
+            SqlValue::Float(f64::NEG_INFINITY), SqlValue::Integer(1),
+        ];
+        values.sort_by(SqlValue::total_cmp);
+        assert_eq!(values.iter().map(ToString::to_string).collect::<Vec<_>>(), [
+            "NULL", "-inf", "-5e-1", "1", "1.5e0", "2", "9223372036854775807", "NaN", "a", "b",
+        ]);
+
+        assert_eq!(SqlValue::Integer(1).total_cmp(&SqlValue::Float(1.0)), Ordering::Equal);
+        assert_eq!(SqlValue::Integer(0).total_cmp(&SqlValue::Float(-0.0)), Ordering::Equal);
+        assert_eq!(SqlValue::Float(0.0).total_cmp(&SqlValue::Float(-0.0)), Ordering::Equal);
+        // converted to float, i64::MAX would be equal to 2^63
+        assert_eq!(SqlValue::Integer(i64::MAX).total_cmp(&SqlValue::Float(9_223_372_036_854_775_808.0)), Ordering::Less);
+        assert_eq!(SqlValue::Integer(-2).total_cmp(&SqlValue::Float(-1.5)), Ordering::Less);
+        assert_eq!(SqlValue::Identificator("a".into()).total_cmp(&SqlValue::String("a".into())), Ordering::Equal);
+    }
 }
@@ -0,0 +1,47 @@
+pub mod table;
+pub mod lexer;
+pub mod command;
+pub mod meta_command;
+pub mod parser;
+pub mod database;
+pub mod row; // TODO: maybe put it inside database or table?
+pub mod query_result;
+pub mod binary_condition;
+pub mod condition;
+pub mod connection;
+pub mod execution_error;
+pub mod meta_command_error;
+pub mod cmp_operator;
+pub mod output;
+pub mod statement_stats;
+pub mod plan_cache;
+pub mod warning;
+pub mod fuzz;
+pub mod history;
+pub mod schema_diff;
+pub mod access;
+pub mod function;
+pub mod shared_database;
+pub mod schema_hook;
+pub mod sql_macro;
+mod row_check;
+mod serialize;
+mod pager;
+mod helpers;
+mod hash_index;
+mod query_rewriter;
+mod sequence;
+mod rename_journal;
+mod random;
+mod series;
+mod cross_join;
+mod system_table;
+mod subquery;
+mod cursor;
+mod session;
+mod ordering;
+mod csv;
+mod script;
+
+#[cfg(test)]
+mod temp_file;
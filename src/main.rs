@@ -1,33 +1,16 @@
+use std::env;
 use std::io::{self, Write};
+use std::time::Instant;
 
-use crate::meta_command::MetaCommandResult;
-use crate::database::Database;
-use crate::meta_command_error::MetaCommandError;
-use crate::connection::Connection;
-
-mod table;
-mod lexer;
-mod command;
-mod meta_command;
-mod parser;
-mod database;
-mod row; // TODO: maybe put it inside database or table?
-mod query_result;
-mod binary_condition;
-mod row_check;
-mod connection;
-mod execution_error;
-mod meta_command_error;
-mod serialize;
-mod pager;
-mod cmp_operator;
-mod helpers;
-mod hash_index;
-
-#[cfg(test)]
-mod temp_file;
-
-const PROMPT: &str = "yarrd> ";
+use yarrd::meta_command::MetaCommandResult;
+use yarrd::meta_command_error::MetaCommandError;
+use yarrd::connection::Connection;
+use yarrd::database::Database;
+use yarrd::output::{self, OutputSettings, OutputTarget, Response};
+use yarrd::plan_cache::PlanCache;
+use yarrd::{parser, lexer};
+
+const JSON_LINES_FLAG: &str = "--json";
 
 fn main() {
     if let Err(error) = run() {
@@ -36,61 +19,155 @@ fn main() {
 }
 
 fn run() -> Result<(), MetaCommandError> {
+    let json_lines = env::args().skip(1).any(|argument| argument == JSON_LINES_FLAG);
     let mut buffer = String::new();
     let stdin = io::stdin();
     let mut connection = Connection::blank();
+    let mut plan_cache = PlanCache::default();
 
     loop {
         buffer.clear();
-        print_prompt();
-
-        stdin.read_line(&mut buffer)?;
-        let input = buffer.trim();
+        if !json_lines {
+            print_prompt(&connection.prompt());
+        }
 
-        match parser::parse_meta_command(input).execute(&mut connection) {
-            MetaCommandResult::Exit => break,
-            MetaCommandResult::Ok => {
-                println!("OK");
-                continue
+        // end of input, e.g. when driving process closes stdin
+        if stdin.read_line(&mut buffer)? == 0 { break }
+        let input = match connection.history.expand(buffer.trim()) {
+            None => buffer.trim().to_string(),
+            Some(Ok(statement)) => {
+                if !json_lines {
+                    println!("{}", statement);
+                }
+                statement
             },
-            MetaCommandResult::Err(error) => {
-                println!("error executing meta command: {}", error);
+            Some(Err(error)) => {
+                print_response(Response::error(error.to_string()), &connection.output_settings, &mut connection.output_target, json_lines);
                 continue
             },
+        };
+        let input = input.as_str();
+
+        let response = match parser::parse_meta_command(input).execute(&mut connection) {
+            MetaCommandResult::Exit => break,
+            MetaCommandResult::Ok => Response::message("OK".to_string()),
+            MetaCommandResult::Output(output) => Response::message(output),
+            MetaCommandResult::Err(error) => Response::error(format!("error executing meta command: {}", error)),
             MetaCommandResult::None => {
-                match connection.get_mut_database() {
-                    Some(database) => parse_and_execute_sql_statement(input, database),
-                    None => println!("cannot exectute statement: no database connected"),
-                }
+                // database is locked only while statement is executed, history is written afterwards
+                let response = match connection.get_mut_database() {
+                    Some(mut database) => {
+                        // tables added to database file by other tools are picked up before statement is run
+                        let reloaded = match database.schema_changed_on_disk() {
+                            true => database.reload().map(|_| ()),
+                            false => Ok(()),
+                        };
+                        match reloaded {
+                            Ok(()) => Some(parse_and_execute_sql_statement(input, &mut database, &mut plan_cache)),
+                            Err(error) => Some(Response::error(format!("cannot reload changed database file: {}", error))),
+                        }
+                    },
+                    None => None,
+                };
+                let response = match response {
+                    Some(response) => {
+                        if !input.is_empty() {
+                            if let Err(error) = connection.history.push(input, response.error.is_none()) {
+                                println!("cannot write history: {}", error);
+                            }
+                        }
+                        response
+                    },
+                    None => Response::error("cannot exectute statement: no database connected".to_string()),
+                };
+
+                let mut output_target = std::mem::take(&mut connection.output_target);
+                print_response(response, &connection.output_settings, &mut output_target, json_lines);
+                connection.output_target = output_target.next_statement_target();
+                continue
             },
         };
+        print_response(response, &connection.output_settings, &mut connection.output_target, json_lines);
     };
 
-    connection.close();
+    if let Err(error) = connection.close() {
+        println!("error closing database: {}", error);
+    }
     Ok(())
 }
 
-fn parse_and_execute_sql_statement(input: &str, database: &mut Database) {
+fn parse_and_execute_sql_statement(input: &str, database: &mut Database, plan_cache: &mut PlanCache) -> Response {
     let tokens = match lexer::to_tokens(input) {
         Ok(tokens) => tokens,
-        Err(message) => {
-            println!("cannot parse statement: {}", message);
-            return
-        },
+        Err(message) => return Response::error(format!("cannot parse statement: {}", message)),
+    };
+    let tokens = match database.expand_macros(tokens) {
+        Ok(tokens) => tokens,
+        Err(error) => return Response::error(format!("cannot expand macro: {}", error)),
+    };
+
+    let parse_result = match plan_cache.get(&tokens) {
+        Some(command) => Ok(command),
+        None => parser::parse_statement(tokens.iter())
+            .inspect(|command| plan_cache.insert(&tokens, command)),
+    };
+    let command = match parse_result {
+        Ok(command) => command,
+        Err(error) => return Response::error(format!("error parsing statement: {}", error)),
+    };
+
+    let started_at = Instant::now();
+    let execution_result = database.execute(command);
+    let elapsed = started_at.elapsed();
+
+    let mut response = match execution_result {
+        Ok(Some(result)) => Response { result: Some(result), ..Default::default() },
+        Ok(None) => Response::message("OK".to_string()),
+        Err(message) => Response::error(format!("cannot execute statement: {}", message)),
     };
+    response.warnings = database.last_statement_warnings().iter().map(ToString::to_string).collect();
+    response.pagination = database.last_statement_pagination();
+    response.timer = Some((elapsed, database.last_statement_stats()));
+    response
+}
+
+// in json lines mode each response is printed to stdout as a single line, regardless of output target
+fn print_response(response: Response, output_settings: &OutputSettings, output_target: &mut OutputTarget, json_lines: bool) {
+    if json_lines {
+        println!("{}", output::format_json_line(&response));
+        return
+    }
 
-    match parser::parse_statement(tokens.iter()) {
-        Err(error) => println!("error parsing statement: {}", error),
-        Ok(command) => {
-            match database.execute(command) {
-                Ok(result) => println!("{:?}", result),
-                Err(message) => println!("cannot execute statement: {}", message),
-            }
-        },
+    if let Some(result) = &response.result {
+        match output::format_query_result(result, output_settings) {
+            Ok(formatted_result) => {
+                if let Err(error) = output_target.write(&formatted_result) {
+                    println!("cannot write query result: {}", error);
+                }
+            },
+            Err(error) => println!("cannot display query result: {}", error),
+        }
+    }
+    if let Some(pagination) = &response.pagination {
+        println!("{}", pagination);
+    }
+    if let Some(message) = &response.message {
+        println!("{}", message);
+    }
+    if let Some(error) = &response.error {
+        println!("{}", error);
+    }
+    for warning in &response.warnings {
+        println!("warning: {}", warning);
+    }
+    if let Some((elapsed, stats)) = &response.timer {
+        if let Some(timer) = output::format_timer(*elapsed, stats, output_settings) {
+            println!("{}", timer);
+        }
     }
 }
 
-fn print_prompt() {
-    print!("{}", PROMPT);
+fn print_prompt(prompt: &str) {
+    print!("{}", prompt);
     io::stdout().flush().expect("error flushing the prompt");
 }
@@ -1,7 +1,12 @@
 use crate::database::Database;
 use crate::meta_command_error::MetaCommandError;
 use crate::connection::Connection;
+use crate::shared_database::SharedDatabase;
+use crate::schema_diff::Catalog;
+use crate::output::{self, TimerMode, OutputMode, OutputTarget};
+use crate::script;
 
+use std::fs::{self, File};
 use std::path::PathBuf;
 
 pub enum MetaCommand {
@@ -11,8 +16,31 @@ pub enum MetaCommand {
     Exit,
     Createdb { db_path: PathBuf, tables_dir_path: PathBuf },
     Dropdb(PathBuf),
-    Connect(PathBuf),
+    Movedb { new_db_path: PathBuf, new_tables_dir_path: Option<PathBuf> },
+    /// Salvage connect opens damaged database read-only, see `Database::salvage`
+    Connect { db_path: PathBuf, salvage: bool },
     CloseConnection,
+    ShowColumnTypes(bool),
+    Timer(TimerMode),
+    CollectGarbage(GarbageAction),
+    RedirectOutput { path: Option<PathBuf>, once: bool },
+    SetOutputMode(OutputMode),
+    Seed { table_name: String, row_count: usize },
+    IndexStats(Option<String>),
+    /// Page of table file, or bucket of hash index file if index name is given
+    PageDump { table_name: String, index_name: Option<String>, number: u64 },
+    User(Option<String>),
+    History(Option<usize>),
+    HistoryFile(PathBuf),
+    Diff(PathBuf),
+    Reload,
+    Read { path: PathBuf, continue_on_error: bool },
+}
+
+pub enum GarbageAction {
+    List,
+    Delete,
+    Quarantine,
 }
 
 impl MetaCommand {
@@ -32,28 +60,243 @@ impl MetaCommand {
                 if connection.is_active() {
                     return MetaCommandResult::Err(MetaCommandError::ConnectionPresent);
                 }
+                if SharedDatabase::is_open(&db_path) {
+                    return MetaCommandResult::Err(MetaCommandError::DatabaseInUse(db_path));
+                }
 
                 match Database::drop(&db_path) {
                     Ok(()) => MetaCommandResult::Ok,
                     Err(error) => MetaCommandResult::Err(error),
                 }
             },
-            Self::Connect(db_path) => {
+            Self::Movedb { new_db_path, new_tables_dir_path } => {
+                let db_path = match connection.get_mut_database() {
+                    Some(database) => database.filepath().to_path_buf(),
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+                if connection.database_connection_count() > 1 {
+                    return MetaCommandResult::Err(MetaCommandError::DatabaseInUse(db_path));
+                }
+
+                if let Err(error) = connection.close() {
+                    return MetaCommandResult::Err(error);
+                }
+                let (move_result, connect_path) = match Database::move_to(&db_path, &new_db_path, new_tables_dir_path.as_deref()) {
+                    Ok(()) => (Ok(()), new_db_path),
+                    Err(error) => (Err(error), db_path),
+                };
+                match move_result.and(connection.from(&connect_path)) {
+                    Ok(()) => MetaCommandResult::Ok,
+                    Err(error) => MetaCommandResult::Err(error),
+                }
+            },
+            Self::Connect { db_path, salvage: false } => {
                 match connection.from(&db_path) {
                     Ok(_) => MetaCommandResult::Ok,
                     Err(error) => MetaCommandResult::Err(error),
                 }
             },
+            Self::Connect { db_path, salvage: true } => {
+                if let Err(error) = connection.salvage(&db_path) {
+                    return MetaCommandResult::Err(error)
+                }
+                let database = connection.get_mut_database().unwrap();
+                let offline_lines: Vec<String> = database.offline_tables().iter()
+                    .map(|(table_name, reason)| format!("{}: {}", table_name, reason))
+                    .collect();
+                match offline_lines.is_empty() {
+                    true => MetaCommandResult::Output("all tables are opened, database is read-only".to_string()),
+                    false => MetaCommandResult::Output(
+                        format!("tables which could not be opened are offline, database is read-only:\n{}", offline_lines.join("\n"))
+                    ),
+                }
+            },
             Self::CloseConnection => {
-                connection.close();
+                match connection.close() {
+                    Ok(()) => MetaCommandResult::Ok,
+                    Err(error) => MetaCommandResult::Err(error),
+                }
+            },
+            Self::ShowColumnTypes(show_column_types) => {
+                connection.output_settings.show_column_types = show_column_types;
+                MetaCommandResult::Ok
+            },
+            Self::Timer(timer_mode) => {
+                connection.output_settings.timer = timer_mode;
                 MetaCommandResult::Ok
-            }
+            },
+            Self::SetOutputMode(mode) => {
+                connection.output_settings.mode = mode;
+                MetaCommandResult::Ok
+            },
+            Self::RedirectOutput { path, once } => {
+                let file = match path.map(File::create).transpose() {
+                    Ok(file) => file,
+                    Err(error) => return MetaCommandResult::Err(error.into()),
+                };
+
+                connection.output_target = match (file, once) {
+                    (None, _) => OutputTarget::Stdout,
+                    (Some(file), false) => OutputTarget::File(file),
+                    (Some(file), true) => OutputTarget::Once(file),
+                };
+                MetaCommandResult::Ok
+            },
+            Self::Seed { table_name, row_count } => {
+                let mut database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                match database.seed_table(&table_name, row_count) {
+                    Ok(()) => MetaCommandResult::Output(format!("inserted {} rows into '{}'", row_count, table_name)),
+                    Err(error) => MetaCommandResult::Err(MetaCommandError::ExecutionError(error)),
+                }
+            },
+            Self::IndexStats(table_name) => {
+                let database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                match database.index_stats(table_name.as_deref()) {
+                    Ok(index_stats) if index_stats.is_empty() => MetaCommandResult::Output("no indexes found".to_string()),
+                    Ok(index_stats) => {
+                        let stats_lines: Vec<String> = index_stats.iter()
+                            .map(|(table_name, stats)| format!("{}.{}", table_name, stats))
+                            .collect();
+                        MetaCommandResult::Output(stats_lines.join("\n"))
+                    },
+                    Err(error) => MetaCommandResult::Err(MetaCommandError::ExecutionError(error)),
+                }
+            },
+            Self::PageDump { table_name, index_name, number } => {
+                let mut database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                match database.page_dump(&table_name, index_name.as_deref(), number) {
+                    Ok(dump) => MetaCommandResult::Output(dump),
+                    Err(error) => MetaCommandResult::Err(MetaCommandError::ExecutionError(error)),
+                }
+            },
+            Self::User(user_name) => {
+                let mut database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                match database.set_current_user(user_name.as_deref()) {
+                    Ok(()) => MetaCommandResult::Ok,
+                    Err(error) => MetaCommandResult::Err(MetaCommandError::ExecutionError(error)),
+                }
+            },
+            Self::History(count) => {
+                let entry_lines: Vec<String> = connection.history.last(count)
+                    .map(|(number, entry)| format!("{:>5}  {}", number, entry))
+                    .collect();
+                match entry_lines.is_empty() {
+                    true => MetaCommandResult::Output("history is empty".to_string()),
+                    false => MetaCommandResult::Output(entry_lines.join("\n")),
+                }
+            },
+            Self::HistoryFile(path) => {
+                match connection.history.set_file(&path) {
+                    Ok(()) => MetaCommandResult::Ok,
+                    Err(error) => MetaCommandResult::Err(error),
+                }
+            },
+            Self::Diff(other_db_path) => {
+                let database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                let other_catalog = match Catalog::load(&other_db_path) {
+                    Ok(catalog) => catalog,
+                    Err(error) => return MetaCommandResult::Err(error),
+                };
+                let statements = database.catalog().diff(&other_catalog);
+                match statements.is_empty() {
+                    true => MetaCommandResult::Output("schemas are the same".to_string()),
+                    false => MetaCommandResult::Output(statements.join("\n")),
+                }
+            },
+            Self::Reload => {
+                let mut database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                match database.reload() {
+                    Ok(added_table_names) if added_table_names.is_empty() => MetaCommandResult::Output("no new tables found".to_string()),
+                    Ok(added_table_names) => MetaCommandResult::Output(format!("added tables: {}", added_table_names.join(", "))),
+                    Err(error) => MetaCommandResult::Err(error),
+                }
+            },
+            Self::Read { path, continue_on_error } => {
+                let script = match fs::read_to_string(&path) {
+                    Ok(script) => script,
+                    Err(error) => return MetaCommandResult::Err(error.into()),
+                };
+                let mut database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                let mut output = vec![];
+                let mut failures = vec![];
+                let statements = script::split_statements(&script);
+                for (executed_count, statement) in statements.iter().enumerate() {
+                    match script::execute_statement(&mut database, &statement.text) {
+                        Ok(Some(result)) => match output::format_query_result(&result, &connection.output_settings) {
+                            Ok(formatted_result) => output.push(formatted_result.trim_end().to_string()),
+                            Err(error) => output.push(format!("cannot display query result: {}", error)),
+                        },
+                        Ok(None) => {},
+                        Err(error) if continue_on_error => failures.push(format!("{}:{}: {}", path.display(), statement.line_number, error)),
+                        Err(error) => return MetaCommandResult::Err(MetaCommandError::ScriptStatementFailed {
+                            path, line_number: statement.line_number, error, executed_count,
+                        }),
+                    }
+                }
+
+                output.push(format!("executed {} statements from '{}'", statements.len() - failures.len(), path.display()));
+                if !failures.is_empty() {
+                    output.push(format!("{} statements failed:", failures.len()));
+                    output.extend(failures);
+                }
+                MetaCommandResult::Output(output.join("\n"))
+            },
+            Self::CollectGarbage(action) => {
+                let database = match connection.get_mut_database() {
+                    Some(database) => database,
+                    None => return MetaCommandResult::Err(MetaCommandError::ConnectionMissing),
+                };
+
+                let (files_result, message) = match action {
+                    GarbageAction::List => (database.orphaned_files(), "orphaned files, run `.gc delete` or `.gc quarantine` to clean them up:"),
+                    GarbageAction::Delete => (database.delete_orphaned_files(), "deleted orphaned files:"),
+                    GarbageAction::Quarantine => (database.quarantine_orphaned_files(), "quarantined orphaned files:"),
+                };
+
+                match files_result {
+                    Ok(files) if files.is_empty() => MetaCommandResult::Output("no orphaned files found".to_string()),
+                    Ok(files) => {
+                        let file_lines: Vec<String> = files.iter().map(|path| path.display().to_string()).collect();
+                        MetaCommandResult::Output(format!("{}\n{}", message, file_lines.join("\n")))
+                    },
+                    Err(error) => MetaCommandResult::Err(error.into()),
+                }
+            },
         }
     }
 }
 
 pub enum MetaCommandResult {
     Ok,
+    Output(String),
     None,
     Exit,
     Err(MetaCommandError),
@@ -106,15 +349,118 @@ mod tests {
             tables_dir_path: PathBuf::from(format!("{}/some_tables", temp_dir.to_str().unwrap())),
         }.execute(&mut connection);
 
-        let connect = MetaCommand::Connect(db_path).execute(&mut connection);
+        let connect = MetaCommand::Connect { db_path, salvage: false }.execute(&mut connection);
 
         assert!(matches!(connect, MetaCommandResult::Ok));
         assert_eq!(connection.is_active(), true);
+        assert_eq!(connection.prompt(), "yarrd(new_db)> ");
 
         let disconnect = MetaCommand::CloseConnection.execute(&mut connection);
 
         assert!(matches!(disconnect, MetaCommandResult::Ok));
         assert_eq!(connection.is_active(), false);
+        assert_eq!(connection.prompt(), "yarrd> ");
+    }
+
+    #[test]
+    fn database_used_by_other_connection() {
+        let (temp_dir, _temp_file) = create_temp_dir();
+        let db_path = temp_dir.join("shared_db");
+        let mut connection = Connection::blank();
+        let mut other_connection = Connection::blank();
+
+        MetaCommand::Createdb { db_path: db_path.clone(), tables_dir_path: temp_dir.join("shared_db_tables") }
+            .execute(&mut connection);
+        assert!(matches!(MetaCommand::Connect { db_path: db_path.clone(), salvage: false }.execute(&mut connection), MetaCommandResult::Ok));
+        assert!(matches!(MetaCommand::Connect { db_path: db_path.clone(), salvage: false }.execute(&mut other_connection), MetaCommandResult::Ok));
+        assert_eq!(connection.database_connection_count(), 2);
+
+        let movedb = MetaCommand::Movedb { new_db_path: temp_dir.join("moved_db"), new_tables_dir_path: None };
+        assert!(matches!(movedb.execute(&mut connection), MetaCommandResult::Err(MetaCommandError::DatabaseInUse(_))));
+        let mut salvage_connection = Connection::blank();
+        let salvage = MetaCommand::Connect { db_path: db_path.clone(), salvage: true };
+        assert!(matches!(salvage.execute(&mut salvage_connection), MetaCommandResult::Err(MetaCommandError::DatabaseInUse(_))));
+
+        assert!(matches!(MetaCommand::CloseConnection.execute(&mut connection), MetaCommandResult::Ok));
+        let dropdb = MetaCommand::Dropdb(db_path.clone());
+        assert!(matches!(dropdb.execute(&mut connection), MetaCommandResult::Err(MetaCommandError::DatabaseInUse(_))));
+
+        assert!(matches!(MetaCommand::CloseConnection.execute(&mut other_connection), MetaCommandResult::Ok));
+        assert!(matches!(MetaCommand::Dropdb(db_path).execute(&mut connection), MetaCommandResult::Ok));
+    }
+
+    #[test]
+    fn read_script() {
+        let (temp_dir, _temp_file) = create_temp_dir();
+        let db_path = temp_dir.join("script_db");
+        let script_path = temp_dir.join("script.sql");
+        let mut connection = Connection::blank();
+        MetaCommand::Createdb { db_path: db_path.clone(), tables_dir_path: temp_dir.join("script_db_tables") }
+            .execute(&mut connection);
+        MetaCommand::Connect { db_path, salvage: false }.execute(&mut connection);
+
+        fs::write(&script_path, "CREATE TABLE users (\n  id INT\n);\nINSERT INTO users (id) VALUES (1)\n\n\
+                                 INSERT INTO userz (id) VALUES (2)\nINSERT INTO users (id) VALUES (3); SELECT * FROM users").unwrap();
+        let read = |continue_on_error| MetaCommand::Read { path: script_path.clone(), continue_on_error };
+        match read(false).execute(&mut connection) {
+            MetaCommandResult::Err(MetaCommandError::ScriptStatementFailed { line_number: 6, executed_count: 2, .. }) => {},
+            _ => panic!("expected script to stop at line 6"),
+        }
+
+        fs::write(&script_path, "INSERT INTO userz (id) VALUES (2)\nINSERT INTO users (id) VALUES (3)\nSELECT * FROM users\nSELEKT").unwrap();
+        match read(true).execute(&mut connection) {
+            MetaCommandResult::Output(output) => {
+                let failures: Vec<&str> = output.lines().filter(|line| line.contains("script.sql:")).collect();
+                assert_eq!(failures.len(), 2);
+                assert!(failures[0].ends_with("script.sql:1: cannot execute statement: table 'userz' not exists"));
+                assert!(failures[1].contains("script.sql:4: "));
+                assert!(output.contains("executed 2 statements"));
+                assert!(output.lines().any(|line| line == "3"));
+            },
+            _ => panic!("expected script to continue after errors"),
+        }
+    }
+
+    #[test]
+    fn connect_database_moved_with_tables_dir() {
+        let (temp_dir, _temp_file) = create_temp_dir();
+        let app_dir = temp_dir.join("app");
+        std::fs::create_dir(&app_dir).unwrap();
+        let mut connection = Connection::blank();
+
+        MetaCommand::Createdb { db_path: app_dir.join("app_db"), tables_dir_path: app_dir.join("app_db_tables") }
+            .execute(&mut connection);
+        let database_file_contents = std::fs::read_to_string(app_dir.join("app_db")).unwrap();
+        assert_eq!(database_file_contents, "app_db_tables\n");
+
+        let moved_app_dir = temp_dir.join("moved_app");
+        std::fs::rename(&app_dir, &moved_app_dir).unwrap();
+
+        let connect = MetaCommand::Connect { db_path: moved_app_dir.join("app_db"), salvage: false }.execute(&mut connection);
+        assert!(matches!(connect, MetaCommandResult::Ok));
+    }
+
+    #[test]
+    fn redirect_output() {
+        let (temp_dir, _temp_file) = create_temp_dir();
+        let output_path = temp_dir.join("results.txt");
+        let mut connection = Connection::blank();
+
+        let once = MetaCommand::RedirectOutput { path: Some(output_path.clone()), once: true }.execute(&mut connection);
+        assert!(matches!(once, MetaCommandResult::Ok));
+        assert!(matches!(connection.output_target, OutputTarget::Once(_)));
+
+        connection.output_target.write("id\n").unwrap();
+        let output_target = std::mem::take(&mut connection.output_target);
+        assert!(matches!(output_target.next_statement_target(), OutputTarget::Stdout));
+        assert_eq!(std::fs::read_to_string(&output_path).unwrap(), "id\n");
+
+        MetaCommand::RedirectOutput { path: Some(output_path), once: false }.execute(&mut connection);
+        let output_target = std::mem::take(&mut connection.output_target);
+        assert!(matches!(output_target.next_statement_target(), OutputTarget::File(_)));
+
+        MetaCommand::RedirectOutput { path: None, once: false }.execute(&mut connection);
+        assert!(matches!(connection.output_target, OutputTarget::Stdout));
     }
 
     fn create_temp_dir() -> (PathBuf, TempFile) {
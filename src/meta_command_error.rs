@@ -11,11 +11,17 @@ pub enum MetaCommandError {
     IoError(io::Error),
     DatabaseFileAlreadyExist(PathBuf),
     DatabaseTablesDirNotExist(PathBuf),
+    TablesDirFileAlreadyExist(PathBuf),
     TableError(TableError),
     ParseError(String),
     UnknownCommand(String),
     ExecutionError(ExecutionError),
     ConnectionPresent,
+    ConnectionMissing,
+    DatabaseInUse(PathBuf),
+    HistoryEntryMissing(String),
+    HistoryFileCorrupted(PathBuf, usize),
+    ScriptStatementFailed { path: PathBuf, line_number: usize, error: String, executed_count: usize },
 }
 
 impl fmt::Display for MetaCommandError {
@@ -28,12 +34,23 @@ impl fmt::Display for MetaCommandError {
             Self::DatabaseFileAlreadyExist(tables_dir) =>
                 format!("cannot create database file at '{}': file already exist",
                         tables_dir.to_str().unwrap()),
+            Self::TablesDirFileAlreadyExist(filepath) =>
+                format!("cannot move database files: '{}' already exist", filepath.display()),
             Self::TableError(table_error) => table_error.to_string(),
             Self::ParseError(parser_error) => format!("failed to parse metacommand: {}", parser_error),
             Self::UnknownCommand(input) => format!("unknown metacommand: {}", input),
             Self::ExecutionError(exec_error) => format!("failed to execute metacommand: {}", exec_error),
             Self::ConnectionPresent => "this metacommand cannot be executed while \
-                                     connected to existing database, consider running `.close`".to_string()
+                                     connected to existing database, consider running `.close`".to_string(),
+            Self::ConnectionMissing => "this metacommand requires a connected database, consider running `.connect`".to_string(),
+            Self::DatabaseInUse(database_filepath) =>
+                format!("database '{}' is used by other connections, they should be closed first", database_filepath.display()),
+            Self::HistoryEntryMissing(reference) => format!("no statement in history for '{}'", reference),
+            Self::HistoryFileCorrupted(path, line_number) =>
+                format!("cannot read history file '{}': line {} is malformed", path.display(), line_number),
+            Self::ScriptStatementFailed { path, line_number, error, executed_count } =>
+                format!("{}:{}: {}\n{} statements before it were executed, statements after it were not, \
+                        use `--continue-on-error` to execute them anyway", path.display(), line_number, error, executed_count),
         };
         write!(f, "{}", message)
     }
@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+
+use crate::command::{NullsOrder, OrderingKey, OrderingTerm};
+use crate::execution_error::ExecutionError;
+use crate::lexer::SqlValue;
+use crate::query_result::QueryResult;
+use crate::random::Random;
+
+/// Sorts rows of select result by `ORDER BY` terms. Ordering columns must be selected, column
+/// name may be qualified with table name. Values are compared with `SqlValue::total_cmp`, so
+/// values of different types are ordered too. Sort is stable, so rows which are equal by all
+/// terms keep the order in which they were selected. `RANDOM()` term draws a number from `random`
+/// for each row, so `ORDER BY RANDOM() LIMIT n` takes a sample of n rows.
+pub fn sort(result: &mut QueryResult, order_by: &[OrderingTerm], random: &mut Random) -> Result<(), ExecutionError> {
+    let column_indexes = order_by.iter()
+        .map(|term| match &term.key {
+            OrderingKey::Column(column_name) => column_index(result, &column_name.to_string()).map(Some),
+            OrderingKey::Random => Ok(None),
+        })
+        .collect::<Result<Vec<Option<usize>>, ExecutionError>>()?;
+
+    // cells are decoded once, not on every comparison
+    let mut keyed_rows = Vec::with_capacity(result.len());
+    for row in result.rows.drain(..) {
+        let key = column_indexes.iter()
+            .map(|column_index| match column_index {
+                Some(column_index) => row.get_cell_sql_value(&result.column_types, *column_index),
+                None => Ok(SqlValue::Integer(random.next_i64())),
+            })
+            .collect::<Result<Vec<SqlValue>, _>>()?;
+        keyed_rows.push((key, row));
+    }
+
+    keyed_rows.sort_by(|(left, _), (right, _)| {
+        order_by.iter()
+            .zip(left.iter().zip(right))
+            .map(|(term, (left, right))| compare(left, right, term))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    result.rows = keyed_rows.into_iter().map(|(_, row)| row).collect();
+    Ok(())
+}
+
+fn column_index(result: &QueryResult, column_name: &str) -> Result<usize, ExecutionError> {
+    let unqualified_name = column_name.rsplit_once('.').map_or(column_name, |(_, name)| name);
+    result.column_names.iter()
+        .position(|name| name == column_name)
+        .or_else(|| result.column_names.iter().position(|name| name == unqualified_name))
+        .ok_or_else(|| ExecutionError::OrderColumnNotSelected(column_name.to_string()))
+}
+
+// NULL placement does not depend on direction, so it is decided before values are compared
+fn compare(left: &SqlValue, right: &SqlValue, term: &OrderingTerm) -> Ordering {
+    let nulls_first = term.nulls == NullsOrder::First;
+    match (left, right) {
+        (SqlValue::Null, SqlValue::Null) => Ordering::Equal,
+        (SqlValue::Null, _) => if nulls_first { Ordering::Less } else { Ordering::Greater },
+        (_, SqlValue::Null) => if nulls_first { Ordering::Greater } else { Ordering::Less },
+        _ if term.descending => right.total_cmp(left),
+        _ => left.total_cmp(right),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::Row;
+    use crate::table::ColumnType;
+
+    fn term(descending: bool, nulls: NullsOrder) -> Vec<OrderingTerm> {
+        vec![OrderingTerm { key: OrderingKey::Column(SqlValue::Identificator("users.age".to_string())), descending, nulls }]
+    }
+
+    fn sorted(values: &[SqlValue], order_by: &[OrderingTerm]) -> Vec<SqlValue> {
+        let column_types = vec![ColumnType::Integer];
+        let rows = values.iter().map(|value| Row::from_sql_values(&[value.clone()], &column_types).unwrap()).collect();
+        let mut result = QueryResult { column_types, column_names: vec!["age".to_string()], column_nullable_flags: vec![true], rows };
+        sort(&mut result, order_by, &mut Random::new(0)).unwrap();
+        result.rows.iter().map(|row| row.get_cell_sql_value(&result.column_types, 0).unwrap()).collect()
+    }
+
+    #[test]
+    fn nulls_placement() {
+        let values = [SqlValue::Integer(2), SqlValue::Null, SqlValue::Integer(1), SqlValue::Null, SqlValue::Integer(3)];
+        let (one, two, three, null) = (SqlValue::Integer(1), SqlValue::Integer(2), SqlValue::Integer(3), SqlValue::Null);
+
+        assert_eq!(sorted(&values, &term(false, NullsOrder::Last)), [one.clone(), two.clone(), three.clone(), null.clone(), null.clone()]);
+        assert_eq!(sorted(&values, &term(false, NullsOrder::First)), [null.clone(), null.clone(), one.clone(), two.clone(), three.clone()]);
+        assert_eq!(sorted(&values, &term(true, NullsOrder::First)), [null.clone(), null.clone(), three.clone(), two.clone(), one.clone()]);
+        assert_eq!(sorted(&values, &term(true, NullsOrder::Last)), [three, two, one, null.clone(), null]);
+    }
+
+    #[test]
+    fn column_not_selected() {
+        let mut result = QueryResult { column_types: vec![], column_names: vec![], column_nullable_flags: vec![], rows: vec![] };
+        assert!(matches!(sort(&mut result, &term(false, NullsOrder::Last), &mut Random::new(0)), Err(ExecutionError::OrderColumnNotSelected(_))));
+    }
+
+    #[test]
+    fn random_order() {
+        let values: Vec<SqlValue> = (0..20).map(SqlValue::Integer).collect();
+        let random_term = |descending| vec![OrderingTerm { key: OrderingKey::Random, descending, nulls: NullsOrder::Last }];
+
+        let shuffled = sorted(&values, &random_term(false));
+        assert_ne!(shuffled, values);
+        let mut sorted_back = shuffled.clone();
+        sorted_back.sort_by(SqlValue::total_cmp);
+        assert_eq!(sorted_back, values);
+        // the same seed gives the same order
+        assert_eq!(sorted(&values, &random_term(false)), shuffled);
+    }
+}
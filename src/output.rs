@@ -0,0 +1,350 @@
+use crate::query_result::{Pagination, QueryResult};
+use crate::lexer::SqlValue;
+use crate::serialize::SerDeError;
+use crate::statement_stats::StatementStats;
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::time::Duration;
+
+const COLUMN_SEPARATOR: &str = " | ";
+const NOT_NULL_SUFFIX: &str = " NOT NULL";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TimerMode {
+    #[default]
+    Off,
+    On,
+    Verbose,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum OutputMode {
+    #[default]
+    Table,
+    Insert(String), // holds table name used in rendered statements
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OutputSettings {
+    pub show_column_types: bool,
+    pub timer: TimerMode,
+    pub mode: OutputMode,
+}
+
+/// Everything cmd interface prints for a line of input
+#[derive(Debug, Default)]
+pub struct Response {
+    /// `OK` or metacommand output
+    pub message: Option<String>,
+    pub result: Option<QueryResult>,
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+    /// set for select with `LIMIT` or `OFFSET`
+    pub pagination: Option<Pagination>,
+    /// run time and resources used by statement, metacommands are not timed
+    pub timer: Option<(Duration, StatementStats)>,
+}
+
+impl Response {
+    pub fn message(message: String) -> Self {
+        Self { message: Some(message), ..Default::default() }
+    }
+
+    pub fn error(error: String) -> Self {
+        Self { error: Some(error), ..Default::default() }
+    }
+}
+
+/// Where query results are written, set with `.output` and `.once`
+#[derive(Debug, Default)]
+pub enum OutputTarget {
+    #[default]
+    Stdout,
+    File(File),
+    Once(File), // is switched back to stdout after the next statement
+}
+
+impl OutputTarget {
+    pub fn write(&mut self, output: &str) -> io::Result<()> {
+        match self {
+            Self::Stdout => {
+                print!("{}", output);
+                Ok(())
+            },
+            Self::File(file) | Self::Once(file) => file.write_all(output.as_bytes()),
+        }
+    }
+
+    /// Target for the statement following the current one
+    pub fn next_statement_target(self) -> Self {
+        match self {
+            Self::Once(_) => Self::Stdout,
+            target => target,
+        }
+    }
+}
+
+/// Renders query result in the output mode from settings
+pub fn format_query_result(result: &QueryResult, settings: &OutputSettings) -> Result<String, SerDeError> {
+    match &settings.mode {
+        OutputMode::Table => format_table(result, settings),
+        OutputMode::Insert(table_name) => format_insert_statements(result, table_name),
+    }
+}
+
+/// Renders query result as a table: header with column names, optional line with
+/// column types, and a line per each row. Columns are padded to the widest cell.
+fn format_table(result: &QueryResult, settings: &OutputSettings) -> Result<String, SerDeError> {
+    let mut lines: Vec<Vec<String>> = vec![result.column_names.clone()];
+
+    if settings.show_column_types {
+        let type_line = (0..result.column_count())
+            .map(|i| {
+                let suffix = if result.column_is_nullable(i).unwrap_or(true) { "" } else { NOT_NULL_SUFFIX };
+                format!("{}{}", result.column_types[i], suffix)
+            })
+            .collect();
+        lines.push(type_line);
+    }
+    let header_lines_count = lines.len();
+
+    for row in result.rows.iter() {
+        let values = row.get_sql_values(&result.column_types)?;
+        lines.push(values.iter().map(format_value).collect());
+    }
+
+    let widths: Vec<usize> = (0..result.column_count())
+        .map(|i| lines.iter().map(|line| line[i].chars().count()).max().unwrap_or(0))
+        .collect();
+
+    let mut output = String::new();
+    for (line_number, line) in lines.iter().enumerate() {
+        output.push_str(&format_line(line, &widths));
+        output.push('\n');
+
+        if line_number == header_lines_count - 1 {
+            let delimiter: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+            output.push_str(&delimiter.join("-+-"));
+            output.push('\n');
+        }
+    }
+
+    Ok(output)
+}
+
+/// Renders each row as `INSERT INTO` statement, so result can be loaded into another database
+fn format_insert_statements(result: &QueryResult, table_name: &str) -> Result<String, SerDeError> {
+    let column_names = result.column_names.join(", ");
+
+    let mut output = String::new();
+    for row in result.rows.iter() {
+        let values: Vec<String> = row.get_sql_values(&result.column_types)?
+            .iter()
+            .map(|value| match value {
+                SqlValue::String(string) => format!("\"{}\"", string),
+                // debug format keeps fraction for whole floats, so value is not parsed back as integer
+                SqlValue::Float(float) => format!("{:?}", float),
+                _ => value.to_string(),
+            })
+            .collect();
+        output.push_str(&format!("INSERT INTO {} ({}) VALUES ({})\n", table_name, column_names, values.join(", ")));
+    }
+
+    Ok(output)
+}
+
+pub fn format_value(value: &SqlValue) -> String {
+    match value {
+        SqlValue::Float(float) => float.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+/// Renders statement run time, verbose mode also adds resources used by the statement.
+/// Returns nothing if timer is off.
+pub fn format_timer(elapsed: Duration, stats: &StatementStats, settings: &OutputSettings) -> Option<String> {
+    match settings.timer {
+        TimerMode::Off => None,
+        TimerMode::On => Some(format!("Run Time: {:.6}s", elapsed.as_secs_f64())),
+        TimerMode::Verbose => Some(format!("Run Time: {:.6}s, {}", elapsed.as_secs_f64(), stats)),
+    }
+}
+
+/// Renders response as a single line JSON object, so cmd interface can be driven by another program:
+/// `{"ok":true,"columns":["id"],"types":["INT"],"rows":[[1]],"warnings":[],"elapsed":0.0001,"stats":{...}}`.
+/// Keys of missing parts are omitted, except `ok` and `warnings`. Timer is always included for statements.
+pub fn format_json_line(response: &Response) -> String {
+    let mut error = response.error.clone();
+    let mut fields = vec![];
+
+    if let Some(message) = &response.message {
+        fields.push(format!("\"message\":{}", json_string(message)));
+    }
+    if let Some(result) = &response.result {
+        match format_json_rows(result) {
+            Ok(rows) => {
+                let column_names: Vec<String> = result.column_names.iter().map(|column_name| json_string(column_name)).collect();
+                let column_types: Vec<String> = result.column_types.iter().map(|column_type| json_string(&column_type.to_string())).collect();
+                fields.push(format!("\"columns\":[{}]", column_names.join(",")));
+                fields.push(format!("\"types\":[{}]", column_types.join(",")));
+                fields.push(format!("\"rows\":[{}]", rows.join(",")));
+            },
+            Err(serde_error) => error = Some(format!("cannot display query result: {}", serde_error)),
+        }
+    }
+    if let Some(error) = &error {
+        fields.push(format!("\"error\":{}", json_string(error)));
+    }
+    if let Some(pagination) = &response.pagination {
+        fields.push(format!("\"pagination\":{{\"offset\":{},\"total_count\":{},\"has_more\":{}}}",
+                            pagination.offset, pagination.total_count, pagination.has_more));
+    }
+    let warnings: Vec<String> = response.warnings.iter().map(|warning| json_string(warning)).collect();
+    fields.push(format!("\"warnings\":[{}]", warnings.join(",")));
+    if let Some((elapsed, stats)) = &response.timer {
+        fields.push(format!("\"elapsed\":{:?}", elapsed.as_secs_f64()));
+        fields.push(format!("\"stats\":{{\"pages_read\":{},\"pages_written\":{},\"rows_scanned\":{},\"index_probes\":{}}}",
+                            stats.pages_read, stats.pages_written, stats.rows_scanned, stats.index_probes));
+    }
+
+    format!("{{\"ok\":{},{}}}", error.is_none(), fields.join(","))
+}
+
+fn format_json_rows(result: &QueryResult) -> Result<Vec<String>, SerDeError> {
+    let mut rows = vec![];
+    for row in result.rows.iter() {
+        let values: Vec<String> = row.get_sql_values(&result.column_types)?
+            .iter()
+            .map(|value| match value {
+                SqlValue::Integer(int) => int.to_string(),
+                // debug format keeps fraction for whole floats, json has no infinity
+                SqlValue::Float(float) if float.is_finite() => format!("{:?}", float),
+                SqlValue::Float(_) | SqlValue::Null => "null".to_string(),
+                SqlValue::String(string) | SqlValue::Identificator(string) => json_string(string),
+                SqlValue::Function(function_call) => json_string(&function_call.to_string()),
+            })
+            .collect();
+        rows.push(format!("[{}]", values.join(",")));
+    }
+    Ok(rows)
+}
+
+fn json_string(string: &str) -> String {
+    let mut output = String::from("\"");
+    for char in string.chars() {
+        match char {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            char if char.is_control() => output.push_str(&format!("\\u{:04x}", char as u32)),
+            char => output.push(char),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn format_line(cells: &[String], widths: &[usize]) -> String {
+    let padded: Vec<String> = cells.iter().zip(widths.iter())
+        .map(|(cell, width)| format!("{:width$}", cell, width = width))
+        .collect();
+
+    padded.join(COLUMN_SEPARATOR).trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::ColumnType;
+    use crate::row::Row;
+
+    fn users_result() -> QueryResult {
+        let column_types = vec![ColumnType::Integer, ColumnType::String];
+        let rows = vec![
+            Row::from_sql_values(&[SqlValue::Integer(1), SqlValue::String("john".to_string())], &column_types).unwrap(),
+            Row::from_sql_values(&[SqlValue::Integer(20), SqlValue::Null], &column_types).unwrap(),
+        ];
+
+        QueryResult {
+            column_names: vec!["id".to_string(), "name".to_string()],
+            column_nullable_flags: vec![false, true],
+            column_types,
+            rows,
+        }
+    }
+
+    #[test]
+    fn format_table() {
+        let output = format_query_result(&users_result(), &OutputSettings::default()).unwrap();
+
+        assert_eq!(output, "id | name\n---+-----\n1  | john\n20 | NULL\n");
+    }
+
+    #[test]
+    fn format_table_with_types() {
+        let settings = OutputSettings { show_column_types: true, ..Default::default() };
+        let output = format_query_result(&users_result(), &settings).unwrap();
+
+        assert_eq!(output, "id           | name\nINT NOT NULL | STRING\n-------------+-------\n1            | john\n20           | NULL\n");
+    }
+
+    #[test]
+    fn format_insert_mode() {
+        let settings = OutputSettings { mode: OutputMode::Insert("people".to_string()), ..Default::default() };
+        let output = format_query_result(&users_result(), &settings).unwrap();
+
+        assert_eq!(
+            output,
+            "INSERT INTO people (id, name) VALUES (1, \"john\")\nINSERT INTO people (id, name) VALUES (20, NULL)\n"
+        );
+    }
+
+    #[test]
+    fn format_json_lines() {
+        let response = Response {
+            result: Some(users_result()),
+            warnings: vec!["'id = NULL' is never true".to_string()],
+            timer: Some((Duration::from_millis(1500), StatementStats { rows_scanned: 2, ..Default::default() })),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_json_line(&response),
+            "{\"ok\":true,\"columns\":[\"id\",\"name\"],\"types\":[\"INT\",\"STRING\"],\"rows\":[[1,\"john\"],[20,null]],\
+             \"warnings\":[\"'id = NULL' is never true\"],\"elapsed\":1.5,\
+             \"stats\":{\"pages_read\":0,\"pages_written\":0,\"rows_scanned\":2,\"index_probes\":0}}"
+        );
+
+        assert_eq!(format_json_line(&Response::message("OK".to_string())), "{\"ok\":true,\"message\":\"OK\",\"warnings\":[]}");
+        assert_eq!(
+            format_json_line(&Response::error("table \"users\"\tnot exists\n".to_string())),
+            "{\"ok\":false,\"error\":\"table \\\"users\\\"\\tnot exists\\n\",\"warnings\":[]}"
+        );
+
+        let response = Response {
+            result: Some(users_result()),
+            pagination: Some(Pagination { offset: 2, total_count: 5, has_more: true }),
+            ..Default::default()
+        };
+        assert!(format_json_line(&response).ends_with("\"pagination\":{\"offset\":2,\"total_count\":5,\"has_more\":true},\"warnings\":[]}"));
+    }
+
+    #[test]
+    fn format_timer_modes() {
+        let elapsed = Duration::from_millis(1500);
+        let stats = StatementStats { pages_read: 2, rows_scanned: 10, index_probes: 1, ..Default::default() };
+
+        assert_eq!(format_timer(elapsed, &stats, &OutputSettings::default()), None);
+
+        let settings = OutputSettings { timer: TimerMode::On, ..Default::default() };
+        assert_eq!(format_timer(elapsed, &stats, &settings), Some("Run Time: 1.500000s".to_string()));
+
+        let settings = OutputSettings { timer: TimerMode::Verbose, ..Default::default() };
+        assert_eq!(
+            format_timer(elapsed, &stats, &settings),
+            Some("Run Time: 1.500000s, pages read: 2, pages written: 0, rows scanned: 10, index probes: 1".to_string())
+        );
+    }
+}
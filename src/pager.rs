@@ -3,15 +3,22 @@ use std::fmt;
 use std::io::{self, Seek, SeekFrom, Write, Read};
 use std::fs::{OpenOptions, File};
 use std::path::Path;
+use std::sync::Arc;
 
 use lru::{Lru, LruError};
 use page::{Page, PAGE_SIZE};
+use compression::StringCompression;
 use crate::row::Row;
+use crate::statement_stats::StatementStats;
+use crate::table::ColumnType;
 
-mod lru;
+pub(crate) mod lru;
 pub mod page;
+pub mod compression;
 
 const PAGE_CACHE_SIZE: usize = 10;
+// pages read by sequential scans are cached separately, so a scan does not evict pages used by lookups
+const SCAN_RING_SIZE: usize = 2;
 const MAX_ROW_SIZE: usize = PAGE_SIZE - 1; // wee need at least 1 byte for deleted row flag on the page
 
 #[derive(Debug)]
@@ -19,7 +26,9 @@ pub enum PagerError {
     IoError(io::Error),
     LruError(LruError),
     PageIsFull,
+    PageDictionaryIsFull,
     RowIsTooBig(usize),
+    FileSizeLimitReached(u64),
 }
 
 impl fmt::Display for PagerError {
@@ -28,8 +37,12 @@ impl fmt::Display for PagerError {
             Self::IoError(io_error) => write!(f, "{}", io_error),
             Self::LruError(lru_error) => write!(f, "{}", lru_error),
             Self::PageIsFull => write!(f, "cannot append row to page: page is full"),
+            Self::PageDictionaryIsFull =>
+                write!(f, "cannot write row to page: there is no space left on page for its compressed strings"),
             Self::RowIsTooBig(row_size) =>
                 write!(f, "tried to build a row which size is {} bytes, but max row size is {}", row_size, MAX_ROW_SIZE),
+            Self::FileSizeLimitReached(max_file_size) =>
+                write!(f, "cannot allocate a page, table file is limited to {} bytes", max_file_size),
         }
     }
 }
@@ -46,17 +59,40 @@ impl From<LruError> for PagerError {
     }
 }
 
-impl Error for PagerError { }
+impl Error for PagerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::IoError(io_error) => Some(io_error),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Pager {
     page_cache: Lru<u64, Page>,
+    scan_ring: Lru<u64, Page>,
     row_size: usize,
+    // string cells of rows are replaced with codes of page dictionary entries
+    compression: Option<Arc<StringCompression>>,
     table_file: File,
+    // new page is not allocated if file would become bigger than that
+    max_file_size: Option<u64>,
+    stats: StatementStats,
 }
 
 impl Pager {
     pub fn new(table_filepath: &Path, row_size: usize) -> Result<Pager, PagerError> {
+        Self::open(table_filepath, row_size, None)
+    }
+
+    /// Pager which stores each distinct string value of a page once, in the end of the page
+    pub fn with_string_compression(table_filepath: &Path, column_types: &[ColumnType]) -> Result<Pager, PagerError> {
+        let compression = StringCompression::new(column_types);
+        Self::open(table_filepath, compression.row_size(), Some(Arc::new(compression)))
+    }
+
+    fn open(table_filepath: &Path, row_size: usize, compression: Option<Arc<StringCompression>>) -> Result<Pager, PagerError> {
         if row_size > MAX_ROW_SIZE {
             return Err(PagerError::RowIsTooBig(row_size))
         }
@@ -66,8 +102,9 @@ impl Pager {
             .write(true)
             .open(table_filepath)?;
         let page_cache = Lru::new(PAGE_CACHE_SIZE)?;
+        let scan_ring = Lru::new(SCAN_RING_SIZE)?;
 
-        Ok(Pager { page_cache, row_size, table_file })
+        Ok(Pager { page_cache, scan_ring, row_size, compression, table_file, max_file_size: None, stats: StatementStats::default() })
     }
 
     pub fn get_row(&mut self, row_id: u64) -> Result<Option<Row>, PagerError> {
@@ -77,6 +114,19 @@ impl Pager {
         Ok(page.get_row(row_number))
     }
 
+    /// Same as `get_row`, but page which is not cached yet is put into scan ring instead of page cache.
+    /// Should be used when rows are read one after another, so scans keep only a couple of their pages in memory.
+    pub fn get_scanned_row(&mut self, row_id: u64) -> Result<Option<Row>, PagerError> {
+        let row_number = self.page_row_number(row_id);
+        Ok(self.get_scanned_page(self.page_id(row_id))?.get_row(row_number))
+    }
+
+    /// Same as `get_scanned_row`, but row is read into given one, returns false if there is no row with such id
+    pub fn read_scanned_row(&mut self, row_id: u64, row: &mut Row) -> Result<bool, PagerError> {
+        let row_number = self.page_row_number(row_id);
+        Ok(self.get_scanned_page(self.page_id(row_id))?.read_row_into(row_number, row))
+    }
+
     pub fn delete_row(&mut self, row_id: u64) -> Result<(), PagerError> {
         let row_number = self.page_row_number(row_id);
         let page = self.get_page_by_row_id(row_id)?;
@@ -85,15 +135,15 @@ impl Pager {
         Ok(())
     }
 
-    pub fn insert_row(&mut self, row: Row) -> Result<u64, PagerError> {
+    pub fn insert_row(&mut self, row: &Row) -> Result<u64, PagerError> {
+        let rows_per_page = self.rows_per_page() as u64;
         let (page_id, page) = self.get_last_page_with_page_id()?;
-        let rows_per_page = Page::calculate_row_count(row.byte_len()) as u64;
 
-         match page.insert_row(&row) {
+         match page.insert_row(row) {
              Ok(page_row_id) => Ok(rows_per_page * page_id + page_row_id),
              Err(_err) => {
                 let page_id = self.allocate_new_page()?;
-                let page_row_id = self.get_page(page_id)?.insert_row(&row)?;
+                let page_row_id = self.get_page(page_id)?.insert_row(row)?;
                 Ok(rows_per_page * page_id + page_row_id)
              },
          }
@@ -102,19 +152,20 @@ impl Pager {
     pub fn update_row(&mut self, row_id: u64, row: &Row) -> Result<u64, PagerError> {
         let page_row_number = self.page_row_number(row_id);
         let page_id = self.page_id(row_id);
+        let rows_per_page = self.rows_per_page() as u64;
         let page = self.get_page(page_id)?;
-        let rows_per_page = Page::calculate_row_count(row.byte_len()) as u64;
 
-        page.update_row(page_row_number, row);
+        page.update_row(page_row_number, row)?;
         Ok(rows_per_page * page_id + page_row_number as u64)
     }
 
     pub fn vacuum(&mut self) -> Result<(), PagerError> {
-        let semi_free_page_id = 0;
+        let mut semi_free_page_id = 0;
         loop {
             self.truncate_trailing_blank_pages()?;
 
-            let (semi_free_page_id, last_page_id) = match self.next_semi_free_page_id(semi_free_page_id)? {
+            let last_page_id;
+            (semi_free_page_id, last_page_id) = match self.next_semi_free_page_id(semi_free_page_id)? {
                 Some((id, last_page_id)) => (id, last_page_id),
                 None => break,
             };
@@ -122,9 +173,16 @@ impl Pager {
             if semi_free_page_id >= last_page_id { break };
 
             let last_page = self.get_page(last_page_id)?;
-            if let Some(movable_row) = last_page.drain_first_row() {
+            if let Some((row_number, movable_row)) = last_page.drain_first_row() {
                 let semi_free_page = self.get_page(semi_free_page_id)?;
-                semi_free_page.insert_row(&movable_row)?;
+                match semi_free_page.insert_row(&movable_row) {
+                    // strings of the row may not fit into dictionary of page with free slots
+                    Err(PagerError::PageIsFull) => {
+                        self.get_page(last_page_id)?.update_row(row_number, &movable_row)?;
+                        semi_free_page_id += 1;
+                    },
+                    result => { result?; },
+                }
                 // TODO: update index
             }
         }
@@ -132,19 +190,72 @@ impl Pager {
         Ok(())
     }
 
+    /// Writes all modified cached pages to the table file and syncs it, pages stay in cache
+    pub fn sync(&mut self) -> Result<(), PagerError> {
+        for (page_id, page) in self.page_cache.iter_mut().chain(self.scan_ring.iter_mut()) {
+            if !page.modified { continue }
+            self.table_file.seek(SeekFrom::Start(PAGE_SIZE as u64 * *page_id))?;
+            self.table_file.write_all(page.as_bytes())?;
+            page.modified = false;
+            self.stats.pages_written += 1;
+        }
+
+        self.table_file.sync_data()?;
+        Ok(())
+    }
+
+    /// Returns pages read and written since the previous call
+    pub fn take_stats(&mut self) -> StatementStats {
+        std::mem::take(&mut self.stats)
+    }
+
     fn get_page_by_row_id(&mut self, row_id: u64) -> Result<&mut Page, PagerError> {
         let page_id = self.page_id(row_id);
         self.get_page(page_id)
     }
 
+    fn get_scanned_page(&mut self, page_id: u64) -> Result<&mut Page, PagerError> {
+        if self.page_cache.contains_key(&page_id) {
+            return Ok(self.page_cache.get_mut(&page_id).unwrap())
+        }
+
+        if !self.scan_ring.contains_key(&page_id) {
+            let bytes = Self::load_page_bytes(&mut self.table_file, page_id)?;
+            self.stats.pages_read += 1;
+            let dropped = self.scan_ring.set(page_id, Self::build_page(self.row_size, &self.compression, bytes));
+            if Self::flush(&mut self.table_file, dropped)? {
+                self.stats.pages_written += 1;
+            }
+        }
+        Ok(self.scan_ring.get_mut(&page_id).unwrap())
+    }
+
+    // page found in scan ring is moved to page cache
     fn get_page(&mut self, page_id: u64) -> Result<&mut Page, PagerError> {
         match self.page_cache.contains_key(&page_id) {
             true => Ok(self.page_cache.get_mut(&page_id).unwrap()),
             false => {
-                let bytes = Self::load_page_bytes(&mut self.table_file, page_id)?;
-                let page = Page::new(self.row_size, bytes);
-                let dropped = self.page_cache.set(page_id, page);
-                Self::flush(&mut self.table_file, dropped)?;
+                let (page, from_scan_ring) = match self.scan_ring.remove(&page_id) {
+                    Some(page) => (page, true),
+                    None => {
+                        let bytes = Self::load_page_bytes(&mut self.table_file, page_id)?;
+                        self.stats.pages_read += 1;
+                        (Self::build_page(self.row_size, &self.compression, bytes), false)
+                    },
+                };
+                if let Some((dropped_page_id, dropped_page)) = self.page_cache.set(page_id, page) {
+                    match Self::write_page(&mut self.table_file, dropped_page_id, &dropped_page) {
+                        Ok(true) => self.stats.pages_written += 1,
+                        Ok(false) => {},
+                        Err(error) => {
+                            // page which cannot be written, e.g. on full disk, is kept, so its changes are not lost
+                            let page = self.page_cache.remove(&page_id).unwrap();
+                            if from_scan_ring { self.scan_ring.set(page_id, page); }
+                            self.page_cache.set(dropped_page_id, dropped_page);
+                            return Err(error.into())
+                        },
+                    }
+                }
                 let page = self.page_cache.get_mut(&page_id).unwrap();
                 Ok(page)
             }
@@ -165,16 +276,49 @@ impl Pager {
         Ok(None)
     }
 
+    /// Page as it is seen by statements, so changes which are not written to file yet are included.
+    /// Page is read as during scans, and does not push other pages out of cache.
+    pub fn scanned_page(&mut self, page_id: u64) -> Result<Option<&Page>, PagerError> {
+        if page_id >= self.page_count()? {
+            return Ok(None)
+        }
+        self.get_scanned_page(page_id).map(|page| Some(&*page))
+    }
+
+    pub fn page_count(&self) -> io::Result<u64> {
+        Ok(self.last_page_id()?.map_or(0, |last_page_id| last_page_id + 1))
+    }
+
+    pub fn rows_per_page(&self) -> usize {
+        match &self.compression {
+            Some(compression) => Page::calculate_compressed_row_count(compression),
+            None => Page::calculate_row_count(self.row_size),
+        }
+    }
+
+    pub fn row_size(&self) -> usize {
+        self.row_size
+    }
+
+    pub fn file_size(&self) -> io::Result<u64> {
+        Ok(self.table_file.metadata()?.len())
+    }
+
+    pub fn set_max_file_size(&mut self, max_file_size: Option<u64>) {
+        self.max_file_size = max_file_size;
+    }
+
     pub fn max_rows(&self) -> u64 {
         match self.last_page_id().unwrap() { // TODO: check if it is successful
             None => 0,
-            Some(last_page_id) => (last_page_id + 1) * Page::calculate_row_count(self.row_size) as u64,
+            Some(last_page_id) => (last_page_id + 1) * self.rows_per_page() as u64,
         }
     }
 
     fn truncate_trailing_blank_pages(&mut self) -> Result<(), PagerError> {
-        loop {
-            let (page_id, page) = self.get_last_page_with_page_id()?;
+        // last page is looked up without allocating, otherwise empty file would never stop growing and shrinking
+        while let Some(page_id) = self.last_page_id()? {
+            let page = self.get_page(page_id)?;
             if page.is_blank() {
                 self.remove_page_from_cache(page_id)?;
                 self.truncate_last_page_in_file()?;
@@ -202,9 +346,20 @@ impl Pager {
         }
     }
 
-    fn allocate_new_page(&mut self) -> io::Result<u64> {
+    fn allocate_new_page(&mut self) -> Result<u64, PagerError> {
         let table_file_size = self.table_file.metadata()?.len();
-        self.table_file.set_len(table_file_size + PAGE_SIZE as u64)?;
+        if let Some(max_file_size) = self.max_file_size {
+            if table_file_size + PAGE_SIZE as u64 > max_file_size {
+                return Err(PagerError::FileSizeLimitReached(max_file_size))
+            }
+        }
+        // page is written instead of extending file length, so full disk is detected here rather than
+        // when page is flushed
+        self.table_file.seek(SeekFrom::Start(table_file_size))?;
+        if let Err(error) = self.table_file.write_all(&[0u8; PAGE_SIZE]) {
+            self.table_file.set_len(table_file_size)?;
+            return Err(error.into())
+        }
         Ok(self.last_page_id()?.unwrap())
     }
 
@@ -214,6 +369,13 @@ impl Pager {
         Ok(())
     }
 
+    fn build_page(row_size: usize, compression: &Option<Arc<StringCompression>>, bytes: [u8; PAGE_SIZE]) -> Page {
+        match compression {
+            Some(compression) => Page::with_compression(compression.clone(), bytes),
+            None => Page::new(row_size, bytes),
+        }
+    }
+
     fn load_page_bytes(file: &mut File, page_id: u64) -> Result<[u8; PAGE_SIZE], PagerError> {
         file.seek(SeekFrom::Start(PAGE_SIZE as u64 * page_id))?;
         let mut bytes = [0u8; PAGE_SIZE];
@@ -223,24 +385,33 @@ impl Pager {
 
     fn flush_all(&mut self) -> Result<(), io::Error> {
         let page_cache = std::mem::take(&mut self.page_cache);
-        for page_data in page_cache {
-            Self::flush(&mut self.table_file, page_data)?
+        let scan_ring = std::mem::take(&mut self.scan_ring);
+        for page_data in page_cache.into_iter().chain(scan_ring) {
+            Self::flush(&mut self.table_file, page_data)?;
         }
         Ok(())
     }
 
-    fn flush(file: &mut File, page_data: Option<(u64, Page)>) -> Result<(), io::Error> {
-        if let Some((page_id, page)) = page_data {
-            if !page.modified { return Ok(()) }
-            file.seek(SeekFrom::Start(PAGE_SIZE as u64 * page_id))?;
-            file.write_all(page.as_bytes())?;
+    // returns true if page was modified and has been written to the file
+    fn flush(file: &mut File, page_data: Option<(u64, Page)>) -> Result<bool, io::Error> {
+        match page_data {
+            Some((page_id, page)) => Self::write_page(file, page_id, &page),
+            None => Ok(false),
         }
-        Ok(())
+    }
+
+    fn write_page(file: &mut File, page_id: u64, page: &Page) -> Result<bool, io::Error> {
+        if !page.modified { return Ok(false) }
+        file.seek(SeekFrom::Start(PAGE_SIZE as u64 * page_id))?;
+        file.write_all(page.as_bytes())?;
+        Ok(true)
     }
 
     fn remove_page_from_cache(&mut self, page_id: u64) -> Result<(), io::Error> {
-        if let Some(page) = self.page_cache.remove(&page_id) {
-            Self::flush(&mut self.table_file, Some((page_id, page)))?
+        if let Some(page) = self.page_cache.remove(&page_id).or_else(|| self.scan_ring.remove(&page_id)) {
+            if Self::flush(&mut self.table_file, Some((page_id, page)))? {
+                self.stats.pages_written += 1;
+            }
         }
 
         Ok(())
@@ -248,18 +419,20 @@ impl Pager {
 
     // this should use primary index later on
     fn page_id(&self, row_id: u64) -> u64 {
-        row_id / Page::calculate_row_count(self.row_size) as u64
+        row_id / self.rows_per_page() as u64
     }
 
     fn page_row_number(&self, row_id: u64) -> usize {
-        (row_id % Page::calculate_row_count(self.row_size) as u64) as usize
+        (row_id % self.rows_per_page() as u64) as usize
     }
 }
 
 impl Drop for Pager {
     fn drop(&mut self) {
-        // TODO: flush_all err should be handled somehow (probably via WAL or error logs)
-        self.flush_all().unwrap();
+        // drop cannot return an error, call `sync` beforehand to handle it
+        if let Err(error) = self.flush_all() {
+            eprintln!("failed to flush table pages: {}", error);
+        }
     }
 }
 
@@ -319,4 +492,25 @@ mod tests {
         assert_eq!(pager.get_page_by_row_id(0).unwrap().modified, true);
         assert_eq!(pager.get_page_by_row_id(505).unwrap().modified, false);
     }
+
+    #[test]
+    fn scans_do_not_evict_cached_pages() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let page_count = PAGE_CACHE_SIZE as u64 * 2;
+        table_file.write_bytes(&vec![255u8; PAGE_SIZE * page_count as usize]).unwrap();
+        let mut pager = Pager::new(table_file.path(), 8).unwrap();
+        let rows_per_page = pager.rows_per_page() as u64;
+
+        pager.get_row(0).unwrap();
+        pager.delete_row(rows_per_page).unwrap();
+        for row_id in 0..(rows_per_page * page_count) {
+            pager.get_scanned_row(row_id).unwrap();
+        }
+        assert_eq!(pager.take_stats().pages_read, page_count);
+        assert!(pager.get_scanned_row(rows_per_page).unwrap().is_none());
+
+        pager.get_row(0).unwrap();
+        pager.get_row(rows_per_page * (page_count - 1)).unwrap(); // last scanned page is moved from scan ring
+        assert_eq!(pager.take_stats().pages_read, 0);
+    }
 }
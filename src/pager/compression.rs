@@ -0,0 +1,187 @@
+use crate::row::{Row, STRING_SIZE};
+use crate::table::ColumnType;
+
+use super::page::PAGE_SIZE;
+
+// string cell is stored in page slot as a number of page dictionary entry
+const CODE_SIZE: usize = 2;
+// each dictionary entry is followed by its length
+const ENTRY_LENGTH_SIZE: usize = 2;
+/// Number of dictionary entries is stored right after free row bitmask
+pub const DICTIONARY_HEADER_SIZE: usize = 2;
+
+/// Layout of rows which string cells are stored in page dictionary. Row slot keeps all other bytes
+/// as they are, and a code of dictionary entry instead of each string cell. Rows are restored
+/// byte to byte, so compression is not seen outside of pager.
+#[derive(Debug)]
+pub struct StringCompression {
+    row_size: usize,
+    // offsets of string cells in row, in ascending order
+    cell_offsets: Vec<usize>,
+}
+
+impl StringCompression {
+    pub fn new(column_types: &[ColumnType]) -> StringCompression {
+        StringCompression { row_size: Row::calculate_row_size(column_types), cell_offsets: Row::string_cell_offsets(column_types) }
+    }
+
+    pub fn row_size(&self) -> usize {
+        self.row_size
+    }
+
+    pub fn slot_size(&self) -> usize {
+        self.row_size - self.cell_offsets.len() * (STRING_SIZE - CODE_SIZE)
+    }
+
+    /// Page bytes which are kept free when row is inserted into non-empty page, so that
+    /// string values of any row can be changed later at least once
+    pub fn headroom(&self) -> usize {
+        self.cell_offsets.len() * (STRING_SIZE + ENTRY_LENGTH_SIZE)
+    }
+
+    /// Contents of string cells, trailing zero bytes of each cell are not included
+    pub fn cell_contents<'a>(&self, row_bytes: &'a [u8]) -> Vec<&'a [u8]> {
+        self.cell_offsets.iter()
+            .map(|offset| {
+                let cell = &row_bytes[*offset..*offset + STRING_SIZE];
+                let content_len = cell.iter().rposition(|byte| *byte != 0).map_or(0, |position| position + 1);
+                &cell[..content_len]
+            })
+            .collect()
+    }
+
+    pub fn encode(&self, row_bytes: &[u8], codes: &[u16], slot: &mut [u8]) {
+        let mut row_offset = 0;
+        let mut slot_offset = 0;
+        for (cell_offset, code) in self.cell_offsets.iter().zip(codes) {
+            let plain_len = cell_offset - row_offset;
+            slot[slot_offset..slot_offset + plain_len].copy_from_slice(&row_bytes[row_offset..*cell_offset]);
+            slot_offset += plain_len;
+            slot[slot_offset..slot_offset + CODE_SIZE].copy_from_slice(&code.to_le_bytes());
+            slot_offset += CODE_SIZE;
+            row_offset = cell_offset + STRING_SIZE;
+        }
+        slot[slot_offset..].copy_from_slice(&row_bytes[row_offset..]);
+    }
+
+    pub fn decode(&self, slot: &[u8], dictionary: &PageDictionary, row_bytes: &mut Vec<u8>) {
+        row_bytes.clear();
+        let mut slot_offset = 0;
+        let mut row_offset = 0;
+        for cell_offset in &self.cell_offsets {
+            let plain_len = cell_offset - row_offset;
+            row_bytes.extend_from_slice(&slot[slot_offset..slot_offset + plain_len]);
+            slot_offset += plain_len;
+            let code = u16::from_le_bytes([slot[slot_offset], slot[slot_offset + 1]]);
+            slot_offset += CODE_SIZE;
+            let content = dictionary.entry(code);
+            row_bytes.extend_from_slice(content);
+            row_bytes.resize(row_bytes.len() + STRING_SIZE - content.len(), 0);
+            row_offset = cell_offset + STRING_SIZE;
+        }
+        row_bytes.extend_from_slice(&slot[slot_offset..]);
+    }
+}
+
+/// String values of page rows, each distinct value is stored once. Entries are placed at the end
+/// of page and grow towards row slots, each entry is followed by its length.
+#[derive(Debug, Default)]
+pub struct PageDictionary {
+    entries: Vec<Vec<u8>>,
+    // offset of the last added entry, page bytes after it are taken by dictionary
+    start: usize,
+}
+
+impl PageDictionary {
+    pub fn load(bytes: &[u8; PAGE_SIZE], header_offset: usize) -> PageDictionary {
+        let entry_count = u16::from_le_bytes([bytes[header_offset], bytes[header_offset + 1]]);
+        let min_start = header_offset + DICTIONARY_HEADER_SIZE;
+        let mut entries = vec![];
+        let mut start = PAGE_SIZE;
+        for _ in 0..entry_count {
+            // entries of damaged page are cut off, so its rows still can be read
+            if start < min_start + ENTRY_LENGTH_SIZE { break }
+            let len = u16::from_le_bytes([bytes[start - 2], bytes[start - 1]]) as usize;
+            if len > STRING_SIZE || start < min_start + ENTRY_LENGTH_SIZE + len { break }
+            start -= ENTRY_LENGTH_SIZE + len;
+            entries.push(bytes[start..start + len].to_vec());
+        }
+        PageDictionary { entries, start }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn entries(&self) -> &[Vec<u8>] {
+        &self.entries
+    }
+
+    pub fn code(&self, content: &[u8]) -> Option<u16> {
+        self.entries.iter().position(|entry| entry == content).map(|position| position as u16)
+    }
+
+    pub fn entry(&self, code: u16) -> &[u8] {
+        self.entries.get(code as usize).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn entry_size(content: &[u8]) -> usize {
+        content.len() + ENTRY_LENGTH_SIZE
+    }
+
+    /// Writes new entry to page bytes, returns its code
+    pub fn push(&mut self, bytes: &mut [u8; PAGE_SIZE], header_offset: usize, content: &[u8]) -> u16 {
+        let len = content.len();
+        self.start -= Self::entry_size(content);
+        bytes[self.start..self.start + len].copy_from_slice(content);
+        bytes[self.start + len..self.start + len + ENTRY_LENGTH_SIZE].copy_from_slice(&(len as u16).to_le_bytes());
+        self.entries.push(content.to_vec());
+        Self::write_entry_count(bytes, header_offset, self.entries.len());
+        self.entries.len() as u16 - 1
+    }
+
+    pub fn clear(&mut self, bytes: &mut [u8; PAGE_SIZE], header_offset: usize) {
+        self.entries.clear();
+        self.start = PAGE_SIZE;
+        Self::write_entry_count(bytes, header_offset, 0);
+    }
+
+    fn write_entry_count(bytes: &mut [u8; PAGE_SIZE], header_offset: usize, entry_count: usize) {
+        bytes[header_offset..header_offset + DICTIONARY_HEADER_SIZE].copy_from_slice(&(entry_count as u16).to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::SqlValue;
+
+    #[test]
+    fn rows_are_restored() {
+        let column_types = [ColumnType::Integer, ColumnType::String, ColumnType::String];
+        let compression = StringCompression::new(&column_types);
+        assert_eq!(compression.slot_size(), 1 + 8 + 2 + 2);
+
+        let row = Row::from_sql_values(
+            &[SqlValue::Integer(7), SqlValue::String("active".to_string()), SqlValue::Null],
+            &column_types,
+        ).unwrap();
+        let contents = compression.cell_contents(row.as_bytes());
+        assert_eq!(contents[0], b"\x06active");
+        assert!(contents[1].is_empty());
+
+        let mut bytes = [0u8; PAGE_SIZE];
+        let mut dictionary = PageDictionary::default();
+        dictionary.clear(&mut bytes, 1);
+        let codes: Vec<u16> = contents.iter().map(|content| dictionary.push(&mut bytes, 1, content)).collect();
+        let mut slot = vec![0u8; compression.slot_size()];
+        compression.encode(row.as_bytes(), &codes, &mut slot);
+
+        let dictionary = PageDictionary::load(&bytes, 1);
+        assert_eq!(dictionary.start(), PAGE_SIZE - 2 * ENTRY_LENGTH_SIZE - 7);
+        assert_eq!(dictionary.code(b"\x06active"), Some(0));
+        let mut row_bytes = vec![];
+        compression.decode(&slot, &dictionary, &mut row_bytes);
+        assert_eq!(row_bytes, row.as_bytes());
+    }
+}
@@ -120,6 +120,16 @@ impl<K: Eq + Hash + Copy, V> Lru<K, V> {
         }
     }
 
+    // iterates over cached values without changing their usage order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.use_sequence.iter_mut().filter_map(|node| {
+            match (&node.key, &mut node.value) {
+                (Some(key), Some(value)) => Some((key, value)),
+                _ => None,
+            }
+        })
+    }
+
     fn bump_key(&mut self, key_index: usize) {
         self.skip_key(key_index);
         self.drag_key_before_current(key_index);
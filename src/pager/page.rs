@@ -1,13 +1,36 @@
+use std::sync::Arc;
+
 use crate::row::Row;
 use crate::pager::PagerError;
+use super::compression::{PageDictionary, StringCompression, DICTIONARY_HEADER_SIZE};
 
 pub const PAGE_SIZE: usize = 4096;
 
+/// Parts of page bytes, as shown by `.pagedump`
+pub struct PageLayout<'a> {
+    pub free_row_bitmask: &'a [u8],
+    /// Entries of page with compressed strings, in order of their codes
+    pub dictionary: Option<&'a [Vec<u8>]>,
+    pub slot_size: usize,
+    pub slot_count: usize,
+    pub occupied_slots: Vec<PageSlot<'a>>,
+}
+
+pub struct PageSlot<'a> {
+    pub row_number: usize,
+    pub offset: usize,
+    pub bytes: &'a [u8],
+    /// Row restored from slot bytes, it differs from them only if strings are compressed
+    pub row: Row,
+}
+
 #[derive(Debug)]
 pub struct Page {
     bytes: [u8; PAGE_SIZE],
+    // size of row slot, which is smaller than row for pages with compressed strings
     row_size: usize,
     free_row_bitmask_size: usize,
+    compressed_strings: Option<(Arc<StringCompression>, PageDictionary)>,
     pub modified: bool,
 }
 
@@ -15,7 +38,15 @@ impl Page {
     pub fn new(row_size: usize, bytes: [u8; PAGE_SIZE]) -> Page {
         let row_count = Self::calculate_row_count(row_size);
         let free_row_bitmask_size = Self::free_row_bitmask_size(row_count);
-        Self { row_size, free_row_bitmask_size, bytes, modified: false }
+        Self { row_size, free_row_bitmask_size, bytes, compressed_strings: None, modified: false }
+    }
+
+    /// Page which rows have string cells replaced with codes of page dictionary entries
+    pub fn with_compression(compression: Arc<StringCompression>, bytes: [u8; PAGE_SIZE]) -> Page {
+        let row_size = compression.slot_size();
+        let free_row_bitmask_size = Self::free_row_bitmask_size(Self::calculate_compressed_row_count(&compression));
+        let dictionary = PageDictionary::load(&bytes, free_row_bitmask_size);
+        Self { row_size, free_row_bitmask_size, bytes, compressed_strings: Some((compression, dictionary)), modified: false }
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -24,12 +55,30 @@ impl Page {
 
     pub fn get_row(&self, page_row_number: usize) -> Option<Row> {
         let row_offset = self.row_offset(page_row_number);
-        match self.row_is_blank(page_row_number) {
-            true => None,
-            false => Some(Row::from_bytes(self.bytes[row_offset..row_offset + self.row_size].to_vec())),
+        match (self.row_is_blank(page_row_number), &self.compressed_strings) {
+            (true, _) => None,
+            (false, None) => Some(Row::from_bytes(self.bytes[row_offset..row_offset + self.row_size].to_vec())),
+            (false, Some((compression, dictionary))) => {
+                let mut row_bytes = Vec::with_capacity(compression.row_size());
+                compression.decode(&self.bytes[row_offset..row_offset + self.row_size], dictionary, &mut row_bytes);
+                Some(Row::from_bytes(row_bytes))
+            },
         }
     }
 
+    /// Same as `get_row`, but row bytes are copied into given row, returns false if row is blank
+    pub fn read_row_into(&self, page_row_number: usize, row: &mut Row) -> bool {
+        let row_offset = self.row_offset(page_row_number);
+        if self.row_is_blank(page_row_number) { return false }
+
+        let slot = &self.bytes[row_offset..row_offset + self.row_size];
+        match &self.compressed_strings {
+            None => row.copy_from_bytes(slot),
+            Some((compression, dictionary)) => compression.decode(slot, dictionary, row.bytes_mut()),
+        }
+        true
+    }
+
     #[cfg(test)]
     pub fn get_first_row(&self) -> Option<Row> {
         match self.first_occupied_row_number() {
@@ -38,11 +87,10 @@ impl Page {
         }
     }
 
-    pub fn drain_first_row(&mut self) -> Option<Row> {
-        match self.first_occupied_row_number() {
-            None => None,
-            Some(i) => self.drain_row(i)
-        }
+    /// Removes first row from page, returns it with its number
+    pub fn drain_first_row(&mut self) -> Option<(usize, Row)> {
+        let row_number = self.first_occupied_row_number()?;
+        self.drain_row(row_number).map(|row| (row_number, row))
     }
 
     pub fn delete_row(&mut self, page_row_number: usize) {
@@ -51,19 +99,104 @@ impl Page {
     }
 
     pub fn insert_row(&mut self, row: &Row) -> Result<u64, PagerError> {
-        match self.free_row_number() {
-            Some(free_row_number) => {
-                self.update_row(free_row_number, row);
-                Ok(free_row_number as u64)
-            },
-            None => Err(PagerError::PageIsFull),
+        let free_row_number = self.free_row_number().ok_or(PagerError::PageIsFull)?;
+        let headroom = match (&self.compressed_strings, self.is_blank()) {
+            (Some((compression, _)), false) => compression.headroom(),
+            _ => 0,
+        };
+        match self.write_row(free_row_number, row, headroom) {
+            true => Ok(free_row_number as u64),
+            false => Err(PagerError::PageIsFull),
         }
     }
 
-    pub fn update_row(&mut self, page_row_number: usize, row: &Row) {
-        self.mut_row_bytes(page_row_number).copy_from_slice(row.as_bytes());
+    pub fn update_row(&mut self, page_row_number: usize, row: &Row) -> Result<(), PagerError> {
+        match self.write_row(page_row_number, row, 0) {
+            true => Ok(()),
+            false => Err(PagerError::PageDictionaryIsFull),
+        }
+    }
+
+    // row which strings do not fit into page dictionary is not written, unused entries are
+    // dropped from dictionary before giving up
+    fn write_row(&mut self, page_row_number: usize, row: &Row, headroom: usize) -> bool {
+        if self.compressed_strings.is_none() {
+            self.mut_row_bytes(page_row_number).copy_from_slice(row.as_bytes());
+        } else if !self.write_compressed_row(page_row_number, row, headroom) {
+            let old_row = self.get_row(page_row_number);
+            self.compact_dictionary(page_row_number);
+            if !self.write_compressed_row(page_row_number, row, headroom) {
+                // old values were in dictionary before compaction, so they fit again
+                if let Some(old_row) = old_row { self.write_compressed_row(page_row_number, &old_row, 0); }
+                return false
+            }
+        }
         self.flag_row_presence_status(page_row_number, true);
         self.modified = true;
+        true
+    }
+
+    fn write_compressed_row(&mut self, page_row_number: usize, row: &Row, headroom: usize) -> bool {
+        let header_offset = self.free_row_bitmask_size;
+        let rows_end = self.occupied_rows_end().max(self.row_offset(page_row_number + 1));
+        let (compression, dictionary) = self.compressed_strings.as_mut().unwrap();
+        let contents = compression.cell_contents(row.as_bytes());
+
+        let mut new_contents: Vec<&[u8]> = vec![];
+        for content in &contents {
+            if dictionary.code(content).is_none() && !new_contents.contains(content) {
+                new_contents.push(content);
+            }
+        }
+        let new_entries_size: usize = new_contents.iter().map(|content| PageDictionary::entry_size(content)).sum();
+        if rows_end + headroom + new_entries_size > dictionary.start() { return false }
+
+        let codes: Vec<u16> = contents.iter()
+            .map(|content| dictionary.code(content).unwrap_or_else(|| dictionary.push(&mut self.bytes, header_offset, content)))
+            .collect();
+        let compression = compression.clone();
+        compression.encode(row.as_bytes(), &codes, self.mut_row_bytes(page_row_number));
+        true
+    }
+
+    // rows are written again with a new dictionary, which has only their values,
+    // except for values of the row which is going to be overwritten
+    fn compact_dictionary(&mut self, overwritten_row_number: usize) {
+        let rows: Vec<(usize, Row)> = (0..self.row_count())
+            .filter(|row_number| *row_number != overwritten_row_number)
+            .filter_map(|row_number| self.get_row(row_number).map(|row| (row_number, row)))
+            .collect();
+        let header_offset = self.free_row_bitmask_size;
+        if let Some((_, dictionary)) = self.compressed_strings.as_mut() {
+            dictionary.clear(&mut self.bytes, header_offset);
+        }
+        for (row_number, row) in rows {
+            self.write_compressed_row(row_number, &row, 0);
+        }
+        self.modified = true;
+    }
+
+    // end of the last occupied row slot, dictionary cannot grow past it
+    fn occupied_rows_end(&self) -> usize {
+        (0..self.row_count()).rev()
+            .find(|row_number| !self.row_is_blank(*row_number))
+            .map_or(self.row_offset(0), |row_number| self.row_offset(row_number + 1))
+    }
+
+    pub fn layout(&self) -> PageLayout<'_> {
+        let occupied_slots = (0..self.row_count())
+            .filter_map(|row_number| {
+                let offset = self.row_offset(row_number);
+                self.get_row(row_number).map(|row| PageSlot { row_number, offset, bytes: &self.bytes[offset..offset + self.row_size], row })
+            })
+            .collect();
+        PageLayout {
+            free_row_bitmask: self.free_row_bitmask(),
+            dictionary: self.compressed_strings.as_ref().map(|(_, dictionary)| dictionary.entries()),
+            slot_size: self.row_size,
+            slot_count: self.row_count(),
+            occupied_slots,
+        }
     }
 
     pub fn has_free_rows(&self) -> bool {
@@ -97,11 +230,20 @@ impl Page {
     }
 
     fn row_offset(&self, page_row_number: usize) -> usize {
-        self.free_row_bitmask_size + page_row_number * self.row_size
+        let header_size = match self.compressed_strings {
+            Some(_) => DICTIONARY_HEADER_SIZE,
+            None => 0,
+        };
+        self.free_row_bitmask_size + header_size + page_row_number * self.row_size
     }
 
+    // slots of page with compressed strings which are taken by dictionary are not free
     fn free_row_number(&self) -> Option<usize> {
-        self.first_row_number(false)
+        let free_row_number = self.first_row_number(false)?;
+        match &self.compressed_strings {
+            Some((_, dictionary)) if self.row_offset(free_row_number + 1) > dictionary.start() => None,
+            _ => Some(free_row_number),
+        }
     }
 
     fn first_occupied_row_number(&self) -> Option<usize> {
@@ -135,12 +277,20 @@ impl Page {
     }
 
     fn row_count(&self) -> usize {
-        Self::calculate_row_count(self.row_size)
+        match &self.compressed_strings {
+            Some((compression, _)) => Self::calculate_compressed_row_count(compression),
+            None => Self::calculate_row_count(self.row_size),
+        }
     }
 
     pub fn calculate_row_count(row_size: usize) -> usize {
         PAGE_SIZE * 8 / (row_size * 8 + 1)
     }
+
+    /// Number of row slots, rows which strings take too much space leave some of them unused
+    pub fn calculate_compressed_row_count(compression: &StringCompression) -> usize {
+        (PAGE_SIZE - DICTIONARY_HEADER_SIZE) * 8 / (compression.slot_size() * 8 + 1)
+    }
 }
 
 #[cfg(test)]
@@ -197,6 +347,43 @@ mod tests {
         assert_eq!(page.free_row_number(), None);
     }
 
+    #[test]
+    fn compressed_strings() {
+        use crate::lexer::SqlValue;
+        use crate::table::ColumnType;
+
+        let column_types = [ColumnType::Integer, ColumnType::String];
+        let compression = Arc::new(StringCompression::new(&column_types));
+        let mut page = Page::with_compression(compression.clone(), [0u8; PAGE_SIZE]);
+        let row = |id, status: &str| Row::from_sql_values(&[SqlValue::Integer(id), SqlValue::String(status.to_string())], &column_types).unwrap();
+
+        let mut row_count = 0;
+        while page.insert_row(&row(row_count, ["active", "blocked"][row_count as usize % 2])).is_ok() {
+            row_count += 1;
+        }
+        // plain page holds 15 such rows, compressed one takes 11 bytes, and space for one more value is kept
+        assert_eq!(Page::calculate_compressed_row_count(&compression), 368);
+        assert_eq!(row_count, 342);
+        let reloaded_page = Page::with_compression(compression.clone(), page.as_bytes().try_into().unwrap());
+        assert_eq!(reloaded_page.get_row(3).unwrap().as_bytes(), row(3, "blocked").as_bytes());
+
+        // values are added to dictionary in place of slots which are not taken
+        page.delete_row(row_count as usize - 1);
+        page.update_row(0, &row(0, "suspended")).unwrap();
+        assert_eq!(page.get_row(0).unwrap().as_bytes(), row(0, "suspended").as_bytes());
+
+        // values which are no longer used are dropped when dictionary is full
+        for row_number in 0..(row_count as usize - 1) {
+            page.update_row(row_number, &row(row_number as i64, "active")).unwrap();
+        }
+        let long_status = "a".repeat(200);
+        page.update_row(0, &row(0, &long_status)).unwrap();
+        assert_eq!(page.get_row(0).unwrap().as_bytes(), row(0, &long_status).as_bytes());
+        assert_eq!(page.get_row(1).unwrap().as_bytes(), row(1, "active").as_bytes());
+        assert!(matches!(page.update_row(1, &row(1, &"b".repeat(200))), Err(PagerError::PageDictionaryIsFull)));
+        assert_eq!(page.get_row(1).unwrap().as_bytes(), row(1, "active").as_bytes());
+    }
+
     #[test]
     fn get_first_row() {
         let mut bytes = [0u8; PAGE_SIZE];
@@ -1,21 +1,31 @@
 use std::path::{Path, PathBuf};
+use std::vec;
 
 use crate::command::Command;
-use crate::meta_command::MetaCommand;
+use crate::meta_command::{MetaCommand, GarbageAction};
 use crate::meta_command_error::MetaCommandError;
 use crate::lexer;
 use crate::lexer::Token;
 use crate::command::ColumnDefinition;
+use crate::table::TableOptions;
+use crate::output::{TimerMode, OutputMode};
 use crate::parser::error::ParserError;
 use create::parse_create_statement;
 use drop::parse_drop_statement;
 use insert::parse_insert_statement;
 use update::parse_update_statement;
-use select::parse_select_statement;
+use select::{parse_select_statement, parse_returning_column_names};
 use delete::parse_delete_statement;
+use undelete::parse_undelete_statement;
 use alter::parse_alter_statement;
 use vacuum::parse_vacuum_statement;
-use crate::parser::shared::{parse_column_definition, parse_index_name};
+use check::parse_check_statement;
+use show::parse_show_statement;
+use set::parse_set_statement;
+use grant::{parse_grant_statement, parse_revoke_statement};
+use cursor::{parse_declare_statement, parse_fetch_statement, parse_close_statement};
+use copy::parse_copy_statement;
+use crate::parser::shared::{parse_column_definition, parse_index_name, position_outside_parentheses};
 
 mod create;
 mod drop;
@@ -24,20 +34,31 @@ mod select;
 mod where_clause;
 mod update;
 mod delete;
+mod undelete;
 mod alter;
 mod vacuum;
+mod check;
+mod show;
+mod set;
+mod grant;
+mod cursor;
+mod copy;
 mod error;
 mod shared;
 
 const DEFAULT_PATH: &str = ".";
 const CURRENT_FOLDER_PATH: &str = ".";
 const DEFAULT_TABLES_DIR_SUFFIX: &str = "_tables";
+const DEFAULT_INSERT_TABLE_NAME: &str = "new_table";
+const CONTINUE_ON_ERROR_OPTION: &str = "--continue-on-error";
 
 pub struct TableSchemaDefinitionLine {
     pub name: String,
     pub row_count: usize,
+    pub null_counts: Option<Vec<usize>>,
     pub column_definitions: Vec<ColumnDefinition>,
     pub indexes_definitions: Vec<(usize, String)>,
+    pub options: TableOptions,
 }
 
 pub fn parse_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
@@ -47,12 +68,22 @@ where
     let command = match token.next() {
         Some(Token::Create) => parse_create_statement(&mut token)?,
         Some(Token::Drop) => parse_drop_statement(&mut token)?,
-        Some(Token::Insert) => parse_insert_statement(&mut token)?,
+        Some(Token::Insert) => parse_returning_statement(&mut token, |token| parse_insert_statement(token))?,
         Some(Token::Select) => parse_select_statement(&mut token)?,
-        Some(Token::Update) => parse_update_statement(&mut token)?,
-        Some(Token::Delete) => parse_delete_statement(&mut token)?,
+        Some(Token::Update) => parse_returning_statement(&mut token, |token| parse_update_statement(token))?,
+        Some(Token::Delete) => parse_returning_statement(&mut token, |token| parse_delete_statement(token))?,
+        Some(Token::Undelete) => parse_undelete_statement(&mut token)?,
         Some(Token::Alter) => parse_alter_statement(&mut token)?,
         Some(Token::Vacuum) => parse_vacuum_statement(&mut token)?,
+        Some(Token::Check) => parse_check_statement(&mut token)?,
+        Some(Token::Show) => parse_show_statement(&mut token)?,
+        Some(Token::Set) => parse_set_statement(&mut token)?,
+        Some(Token::Grant) => parse_grant_statement(&mut token)?,
+        Some(Token::Revoke) => parse_revoke_statement(&mut token)?,
+        Some(Token::Declare) => parse_declare_statement(&mut token)?,
+        Some(Token::Fetch) => parse_fetch_statement(&mut token)?,
+        Some(Token::Close) => parse_close_statement(&mut token)?,
+        Some(Token::Copy) => parse_copy_statement(&mut token)?,
         Some(command) => return Err(ParserError::UnknownCommand(command)),
         _ => return Ok(Command::Void),
     };
@@ -65,6 +96,32 @@ where
     }
 }
 
+/// returning_statement = (insert_statement | update_statement | delete_statement) [returning_clause]
+fn parse_returning_statement<'a, I, F>(token: I, parse_statement: F) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>,
+    F: FnOnce(&mut vec::IntoIter<&'a Token>) -> Result<Command, ParserError<'a>>,
+{
+    let mut tokens: Vec<&Token> = token.collect();
+    let returning_tokens = position_outside_parentheses(&tokens, &Token::Returning)
+        .map(|returning_position| tokens.split_off(returning_position).split_off(1));
+
+    let mut token = tokens.into_iter();
+    let command = parse_statement(&mut token)?;
+    let remainder = token.collect::<Vec<&Token>>();
+    if !remainder.is_empty() {
+        return Err(ParserError::ExcessTokens(remainder))
+    }
+
+    match returning_tokens {
+        Some(returning_tokens) => {
+            let column_names = parse_returning_column_names(returning_tokens.into_iter())?;
+            Ok(Command::Returning { command: Box::new(command), column_names })
+        },
+        None => Ok(command),
+    }
+}
+
 pub fn parse_meta_command(input: &str) -> MetaCommand {
     if input.starts_with('.') {
         if input.starts_with(".createdb") {
@@ -77,15 +134,75 @@ pub fn parse_meta_command(input: &str) -> MetaCommand {
                 Ok(dropdb_meta_command) => return dropdb_meta_command,
                 Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
             }
+        } else if input.starts_with(".movedb") {
+            match parse_movedb(input) {
+                Ok(movedb_meta_command) => return movedb_meta_command,
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
         } else if input.starts_with(".connect") {
             match parse_connect(input) {
                 Ok(connect_meta_command) => return connect_meta_command,
                 Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
             }
+        } else if input.starts_with(".types") {
+            match parse_switch(input) {
+                Ok(show_column_types) => return MetaCommand::ShowColumnTypes(show_column_types),
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".timer") {
+            match parse_timer_mode(input) {
+                Ok(timer_mode) => return MetaCommand::Timer(timer_mode),
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".output") || input.starts_with(".once") {
+            match parse_output_redirect(input) {
+                Ok(redirect_meta_command) => return redirect_meta_command,
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".mode") {
+            match parse_output_mode(input) {
+                Ok(mode) => return MetaCommand::SetOutputMode(mode),
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".seed") {
+            match parse_seed(input) {
+                Ok(seed_meta_command) => return seed_meta_command,
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".pagedump") {
+            match parse_pagedump(input) {
+                Ok(pagedump_meta_command) => return pagedump_meta_command,
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".index_stats") {
+            return parse_index_stats(input)
+        } else if input.starts_with(".user") {
+            return parse_user(input)
+        } else if input.starts_with(".history") {
+            match parse_history(input) {
+                Ok(history_meta_command) => return history_meta_command,
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".read") {
+            match parse_read(input) {
+                Ok(read_meta_command) => return read_meta_command,
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".diff") {
+            match parse_diff(input) {
+                Ok(diff_meta_command) => return diff_meta_command,
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
+        } else if input.starts_with(".gc") {
+            match parse_garbage_action(input) {
+                Ok(action) => return MetaCommand::CollectGarbage(action),
+                Err(error) => return MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(error.to_string())),
+            }
         }
 
         match input.trim() {
             ".close" => MetaCommand::CloseConnection,
+            ".reload" => MetaCommand::Reload,
             ".exit" | ".quit" => MetaCommand::Exit,
             _ => MetaCommand::Unknown(input.to_string()),
         }
@@ -94,44 +211,70 @@ pub fn parse_meta_command(input: &str) -> MetaCommand {
     }
 }
 
-pub fn parse_schema_line(table_definition_line: &str) -> Result<TableSchemaDefinitionLine, ParserError> {
+/// Schema line consists of sections separated by semicolon:
+/// `name row_count column definitions; index definitions; table options; null counts`,
+/// sections after column definitions can be empty or omitted.
+pub fn parse_schema_line(table_definition_line: &str) -> Result<TableSchemaDefinitionLine, ParserError<'_>> {
     let tokens = lexer::to_tokens(table_definition_line).map_err(ParserError::LexerError)?;
     let mut token_iter = tokens.iter();
     let table_name = token_iter.next().ok_or(ParserError::TableNameMissing)?.to_string();
     let row_count_string = token_iter.next().ok_or(ParserError::RowCountMissing)?.to_string();
     let row_count = row_count_string.parse::<usize>().map_err(|_| ParserError::RowCountInvalid(row_count_string))?;
 
-    let mut indexes_definitions = vec![];
-    let mut column_definitions = vec![];
+    let remaining_tokens: Vec<&Token> = token_iter.collect();
+    let mut sections = remaining_tokens.split(|token| **token == Token::Semicolon);
+    let columns_section = sections.next().unwrap_or(&[]);
+    let indexes_section = sections.next().unwrap_or(&[]);
+    let options_section = sections.next().unwrap_or(&[]);
+    let null_counts_section = sections.next().unwrap_or(&[]);
 
+    let mut column_definitions = vec![];
+    let mut columns_iter = columns_section.iter().copied();
     loop {
-        let (column_definition, last_token) = parse_column_definition(&mut token_iter)
+        let (column_definition, last_token) = parse_column_definition(&mut columns_iter)
             .map_err(|parser_error| ParserError::InvalidSchemaDefinition(parser_error.to_string()))?;
 
         column_definitions.push(column_definition);
 
         match last_token {
             Some(Token::Comma) => continue,
-            Some(Token::Semicolon) => break,
-            None => return Ok(TableSchemaDefinitionLine { name: table_name, row_count, column_definitions, indexes_definitions }),
+            None => break,
             _ => return Err(ParserError::CommaExpected("column_definitions")),
         }
     }
 
-    loop {
-        let (i, index_name, last_token) = parse_index_definition(&mut token_iter)
+    let mut indexes_definitions = vec![];
+    let mut indexes_iter = indexes_section.iter().copied();
+    while !indexes_section.is_empty() {
+        let (i, index_name, last_token) = parse_index_definition(&mut indexes_iter)
             .map_err(|parser_error| ParserError::InvalidSchemaDefinition(parser_error.to_string()))?;
 
         indexes_definitions.push((i, index_name));
 
         match last_token {
             Some(Token::Comma) => continue,
-            Some(Token::Semicolon) => break,
             None => break,
             _ => return Err(ParserError::CommaExpected("index_definitions")),
         }
     }
-    Ok(TableSchemaDefinitionLine { name: table_name, row_count, column_definitions, indexes_definitions })
+
+    let mut options = TableOptions::default();
+    for option_token in options_section.iter().filter(|token| ***token != Token::Comma) {
+        options.set(&option_token.to_string(), true)
+            .map_err(|table_error| ParserError::InvalidSchemaDefinition(table_error.to_string()))?;
+    }
+
+    let null_counts = match null_counts_section.is_empty() {
+        true => None,
+        false => Some(
+            null_counts_section.iter()
+            .map(|token| parse_int(std::iter::once(*token)))
+            .collect::<Result<Vec<usize>, ParserError>>()
+            .map_err(|parser_error| ParserError::InvalidSchemaDefinition(parser_error.to_string()))?
+        ),
+    };
+
+    Ok(TableSchemaDefinitionLine { name: table_name, row_count, null_counts, column_definitions, indexes_definitions, options })
 }
 
 pub fn parse_index_definition<'a, I>(mut token: I) -> Result<(usize, String, Option<&'a Token>), ParserError<'a>>
@@ -193,14 +336,177 @@ pub fn parse_dropdb(input: &str) -> Result<MetaCommand, ParserError> {
     Ok(MetaCommand::Dropdb(db_path))
 }
 
+pub fn parse_movedb(input: &str) -> Result<MetaCommand, ParserError<'_>> {
+    let mut input_iterator = input.split_whitespace();
+    input_iterator.next(); // skip ".movedb"
+
+    let new_db_path = pathify(input_iterator.next().ok_or(ParserError::DatabasePathMissing)?);
+    let new_tables_dir_path = input_iterator.next().map(pathify);
+
+    Ok(MetaCommand::Movedb { new_db_path, new_tables_dir_path })
+}
+
+/// `.connect [--salvage] PATH`
 pub fn parse_connect(input: &str) -> Result<MetaCommand, ParserError> {
     let mut input_iterator = input.splitn(2, ' ');
     input_iterator.next(); // skip ".connect"
 
-    let db_path_str = input_iterator.next().ok_or(ParserError::DatabasePathMissing)?;
+    let mut db_path_str = input_iterator.next().ok_or(ParserError::DatabasePathMissing)?;
+    let salvage = match db_path_str.strip_prefix("--salvage ") {
+        Some(salvaged_db_path_str) => {
+            db_path_str = salvaged_db_path_str;
+            true
+        },
+        None => false,
+    };
     let db_path = PathBuf::from(db_path_str);
 
-    Ok(MetaCommand::Connect(db_path))
+    Ok(MetaCommand::Connect { db_path, salvage })
+}
+
+pub fn parse_diff(input: &str) -> Result<MetaCommand, ParserError<'_>> {
+    let mut input_iterator = input.splitn(2, ' ');
+    input_iterator.next(); // skip ".diff"
+
+    let db_path_str = input_iterator.next().map(str::trim).filter(|path| !path.is_empty())
+        .ok_or(ParserError::DatabasePathMissing)?;
+
+    Ok(MetaCommand::Diff(PathBuf::from(db_path_str)))
+}
+
+pub fn parse_switch(input: &str) -> Result<bool, ParserError<'_>> {
+    let mut input_iterator = input.splitn(2, ' ');
+    input_iterator.next(); // skip metacommand name
+
+    match input_iterator.next().map(str::trim) {
+        Some("on") => Ok(true),
+        Some("off") => Ok(false),
+        Some(value) => Err(ParserError::SwitchValueInvalid(value)),
+        None => Err(ParserError::SwitchValueMissing),
+    }
+}
+
+pub fn parse_timer_mode(input: &str) -> Result<TimerMode, ParserError<'_>> {
+    match input.split_once(' ').map(|(_, value)| value.trim()) {
+        Some("verbose") => Ok(TimerMode::Verbose),
+        _ => match parse_switch(input)? {
+            true => Ok(TimerMode::On),
+            false => Ok(TimerMode::Off),
+        },
+    }
+}
+
+pub fn parse_garbage_action(input: &str) -> Result<GarbageAction, ParserError<'_>> {
+    match input.split_once(' ').map(|(_, value)| value.trim()) {
+        None => Ok(GarbageAction::List),
+        Some("delete") => Ok(GarbageAction::Delete),
+        Some("quarantine") => Ok(GarbageAction::Quarantine),
+        Some(value) => Err(ParserError::GarbageActionInvalid(value)),
+    }
+}
+
+/// `.mode table` or `.mode insert [TABLE_NAME]`
+pub fn parse_output_mode(input: &str) -> Result<OutputMode, ParserError<'_>> {
+    let mut input_iterator = input.split_whitespace();
+    input_iterator.next(); // skip ".mode"
+
+    match input_iterator.next() {
+        Some("table") => Ok(OutputMode::Table),
+        Some("insert") => {
+            let table_name = input_iterator.next().unwrap_or(DEFAULT_INSERT_TABLE_NAME);
+            Ok(OutputMode::Insert(table_name.to_string()))
+        },
+        Some(value) => Err(ParserError::OutputModeInvalid(value)),
+        None => Err(ParserError::OutputModeMissing),
+    }
+}
+
+/// `.index_stats [TABLE_NAME]`
+pub fn parse_index_stats(input: &str) -> MetaCommand {
+    let table_name = input.split_whitespace().nth(1); // skip ".index_stats"
+    MetaCommand::IndexStats(table_name.map(|table_name| table_name.to_string()))
+}
+
+/// `.user [USER_NAME]`, without user name switches back to database owner
+pub fn parse_user(input: &str) -> MetaCommand {
+    let user_name = input.split_whitespace().nth(1); // skip ".user"
+    MetaCommand::User(user_name.map(|user_name| user_name.to_string()))
+}
+
+/// `.history [COUNT]` lists executed statements, `.history file PATH` keeps them in a file
+pub fn parse_history(input: &str) -> Result<MetaCommand, ParserError<'_>> {
+    let mut input_iterator = input.split_whitespace();
+    input_iterator.next(); // skip ".history"
+
+    match (input_iterator.next(), input_iterator.next()) {
+        (None, _) => Ok(MetaCommand::History(None)),
+        (Some("file"), Some(path)) => Ok(MetaCommand::HistoryFile(PathBuf::from(path))),
+        (Some("file"), None) => Err(ParserError::HistoryFilePathMissing),
+        (Some(count), _) => {
+            let count = count.parse().map_err(|_| ParserError::HistoryCountInvalid(count))?;
+            Ok(MetaCommand::History(Some(count)))
+        },
+    }
+}
+
+/// `.read PATH [--continue-on-error]`
+pub fn parse_read(input: &str) -> Result<MetaCommand, ParserError<'_>> {
+    let mut path = None;
+    let mut continue_on_error = false;
+    for argument in input.split_whitespace().skip(1) { // skip ".read"
+        match argument {
+            CONTINUE_ON_ERROR_OPTION => continue_on_error = true,
+            option if option.starts_with("--") => return Err(ParserError::ReadOptionInvalid(option)),
+            _ if path.is_some() => return Err(ParserError::ReadOptionInvalid(argument)),
+            _ => path = Some(PathBuf::from(argument)),
+        }
+    }
+
+    let path = path.ok_or(ParserError::ScriptPathMissing)?;
+    Ok(MetaCommand::Read { path, continue_on_error })
+}
+
+/// `.seed TABLE_NAME ROW_COUNT`
+pub fn parse_seed(input: &str) -> Result<MetaCommand, ParserError<'_>> {
+    let mut input_iterator = input.split_whitespace();
+    input_iterator.next(); // skip ".seed"
+
+    match (input_iterator.next(), input_iterator.next()) {
+        (Some(table_name), Some(row_count)) => {
+            let row_count = row_count.parse().map_err(|_| ParserError::SeedRowCountInvalid(row_count))?;
+            Ok(MetaCommand::Seed { table_name: table_name.to_string(), row_count })
+        },
+        _ => Err(ParserError::SeedArgumentsMissing),
+    }
+}
+
+/// `.pagedump TABLE_NAME PAGE_ID` or `.pagedump TABLE_NAME INDEX_NAME BUCKET_NUMBER`
+pub fn parse_pagedump(input: &str) -> Result<MetaCommand, ParserError<'_>> {
+    let arguments: Vec<&str> = input.split_whitespace().skip(1).collect(); // skip ".pagedump"
+
+    let (table_name, index_name, number) = match arguments.as_slice() {
+        [table_name, page_id] => (table_name, None, page_id),
+        [table_name, index_name, bucket_number] => (table_name, Some(index_name.to_string()), bucket_number),
+        _ => return Err(ParserError::PageDumpArgumentsMissing),
+    };
+    let number = number.parse().map_err(|_| ParserError::PageDumpNumberInvalid(number))?;
+    Ok(MetaCommand::PageDump { table_name: table_name.to_string(), index_name, number })
+}
+
+/// `.output FILE` redirects all following results to file, `.output` without file
+/// switches back to stdout, `.once FILE` redirects result of the next statement only
+pub fn parse_output_redirect(input: &str) -> Result<MetaCommand, ParserError<'_>> {
+    let once = input.starts_with(".once");
+    let path = input.split_once(' ')
+        .map(|(_, path)| path.trim())
+        .filter(|path| !path.is_empty())
+        .map(pathify);
+
+    if once && path.is_none() {
+        return Err(ParserError::OutputPathMissing)
+    }
+
+    Ok(MetaCommand::RedirectOutput { path, once })
 }
 
 fn pathify(string: &str) -> PathBuf {
@@ -219,9 +525,11 @@ fn pathify(string: &str) -> PathBuf {
 mod tests {
     use super::*;
     use crate::lexer::SqlValue;
-    use crate::table::{ColumnType, Constraint};
+    use crate::table::{ColumnType, Constraint, ForeignKey};
     use crate::binary_condition::BinaryCondition;
+    use crate::condition::Condition;
     use crate::cmp_operator::CmpOperator;
+    use crate::command::{ConflictAction, FetchDirection, IndexHint, InsertValue, NullsOrder, OrderingKey, SelectColumnName};
 
     #[test]
     fn insert_with_column_names() {
@@ -260,6 +568,101 @@ mod tests {
         assert!(parse_statement(input.iter()).is_ok());
     }
 
+    #[test]
+    fn create_table_with_foreign_key() {
+        let foreign_key = Constraint::References(ForeignKey { table_name: "users".to_string(), column_name: "id".to_string() });
+
+        let tokens = lexer::to_tokens("create table orders (user_id int not null references users(id), total float)").unwrap();
+        match parse_statement(tokens.iter()) {
+            Ok(Command::CreateTable { columns, .. }) =>
+                assert_eq!(columns[0].column_constraints, vec![Constraint::NotNull, foreign_key.clone()]),
+            result => panic!("expected create table, got {:?}", result),
+        }
+
+        let tokens = lexer::to_tokens("alter table orders add constraint references users (id) (user_id)").unwrap();
+        assert!(matches!(
+                    parse_statement(tokens.iter()),
+                    Ok(Command::AddColumnConstraint { constraint, .. }) if constraint == foreign_key
+                ));
+
+        let tokens = lexer::to_tokens("create table orders (user_id int references users)").unwrap();
+        assert!(parse_statement(tokens.iter()).is_err());
+    }
+
+    #[test]
+    fn create_table_if_not_exists() {
+        let tokens = lexer::to_tokens("CREATE TABLE IF NOT EXISTS users (id INT) WITH (soft_delete)").unwrap();
+        assert!(matches!(
+                    parse_statement(tokens.iter()),
+                    Ok(Command::CreateTable { table_name: SqlValue::Identificator(name), if_not_exists: true, options: TableOptions { soft_delete: true, .. }, .. })
+                        if name == "users"
+                ));
+        let tokens = lexer::to_tokens("create table if (id int)").unwrap();
+        assert!(matches!(
+                    parse_statement(tokens.iter()),
+                    Ok(Command::CreateTable { table_name: SqlValue::Identificator(name), if_not_exists: false, .. }) if name == "if"
+                ));
+
+        let tokens = lexer::to_tokens("CREATE TABLE IF NOT users (id INT)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::IfNotExistsInvalid(_))));
+        let tokens = lexer::to_tokens("CREATE TABLE IF NOT").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::IfNotExistsMissing)));
+    }
+
+    #[test]
+    fn alter_table_options() {
+        let tokens = lexer::to_tokens("ALTER TABLE users SET (soft_delete, checksums = false, compression = 1)").unwrap();
+        let options = match parse_statement(tokens.iter()) {
+            Ok(Command::SetTableOptions { options, .. }) => options,
+            result => panic!("unexpected parse result {:?}", result),
+        };
+        assert_eq!(options, vec![("soft_delete".to_string(), true), ("checksums".to_string(), false), ("compression".to_string(), true)]);
+        let tokens = lexer::to_tokens("ALTER TABLE users UNSET (change_log)").unwrap();
+        assert!(matches!(
+                    parse_statement(tokens.iter()),
+                    Ok(Command::SetTableOptions { options, .. }) if options == vec![("change_log".to_string(), false)]
+                ));
+
+        let tokens = lexer::to_tokens("ALTER TABLE users SET (soft_delete = yes)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::TableOptionValueInvalid(_))));
+        let tokens = lexer::to_tokens("ALTER TABLE users SET (soft_delete =").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::TableOptionValueMissing)));
+        let tokens = lexer::to_tokens("ALTER TABLE users UNSET (soft_delete = true)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::RightParenthesisExpected(Token::Equals, _))));
+        let tokens = lexer::to_tokens("ALTER TABLE users SET (ttl = 1)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::TableOptionUnknown(_))));
+    }
+
+    #[test]
+    fn create_table_with_options() {
+        let input = vec![
+                Token::Create, Token::Table, Token::Value(SqlValue::Identificator("table_name".into())),
+                Token::LeftParenthesis,
+                Token::Value(SqlValue::Identificator("id".into())), Token::IntegerType,
+                Token::RightParenthesis,
+                Token::With, Token::LeftParenthesis,
+                Token::Value(SqlValue::Identificator("soft_delete".into())),
+                Token::RightParenthesis,
+           ];
+
+        assert!(matches!(
+                    parse_statement(input.iter()),
+                    Ok(Command::CreateTable { options: TableOptions { soft_delete: true, .. }, .. })
+                ));
+
+        let input = vec![
+                Token::Create, Token::Table, Token::Value(SqlValue::Identificator("table_name".into())),
+                Token::LeftParenthesis,
+                Token::Value(SqlValue::Identificator("id".into())), Token::IntegerType,
+                Token::RightParenthesis,
+                Token::With, Token::LeftParenthesis,
+                Token::Value(SqlValue::Identificator("unknown_option".into())),
+                Token::RightParenthesis,
+           ];
+
+        assert!(matches!(parse_statement(input.iter()), Err(ParserError::TableOptionUnknown(_))));
+    }
+
     #[test]
     fn select_columns() {
         let input = vec![
@@ -286,6 +689,102 @@ mod tests {
         assert!(parse_statement(input.iter()).is_ok());
     }
 
+    #[test]
+    fn select_where_boolean_operators() {
+        let binary_condition = |column_name: &str, operator, value| Condition::Binary(BinaryCondition {
+            left_value: SqlValue::Identificator(column_name.to_string()),
+            right_value: value,
+            operator,
+        });
+        let age_over_18 = binary_condition("age", CmpOperator::Greater, SqlValue::Integer(18));
+        let name_is_bob = binary_condition("name", CmpOperator::Equals, SqlValue::String("bob".to_string()));
+        let deleted = binary_condition("deleted", CmpOperator::Equals, SqlValue::Integer(1));
+
+        let tokens = lexer::to_tokens("select * from users where age > 18 AND name = \"bob\" or not deleted = 1").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Select { where_clause: Some(where_clause), .. }) if where_clause == Condition::or(
+                    Condition::and(age_over_18.clone(), name_is_bob.clone()),
+                    Condition::negate(deleted.clone()),
+                )));
+
+        let tokens = lexer::to_tokens("delete from users where age > 18 and (name = \"bob\" or deleted = 1)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Delete { where_clause: Some(where_clause), .. }) if where_clause == Condition::and(
+                    age_over_18,
+                    Condition::or(name_is_bob, deleted),
+                )));
+
+        let tokens = lexer::to_tokens("select * from users where (age > 18 or deleted = 1").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::RightParenthesisMissing(_))));
+
+        let tokens = lexer::to_tokens("select * from users where age > 18 and").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::LvalueMissing)));
+
+        let tokens = lexer::to_tokens("select * from users where age > 18 name = 1").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ConditionEndExpected(_))));
+    }
+
+    #[test]
+    fn select_where_in_list() {
+        let id = SqlValue::Identificator("id".to_string());
+        let id_in_list = Condition::in_list(id.clone(), vec![SqlValue::Integer(1), SqlValue::Integer(2)]);
+        assert_eq!(id_in_list, Condition::or(
+            BinaryCondition { left_value: id.clone(), right_value: SqlValue::Integer(1), operator: CmpOperator::Equals }.into(),
+            BinaryCondition { left_value: id.clone(), right_value: SqlValue::Integer(2), operator: CmpOperator::Equals }.into(),
+        ));
+
+        let tokens = lexer::to_tokens("select * from users where id in (1, 2) and not id in (2)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Select { where_clause: Some(where_clause), .. }) if where_clause == Condition::and(
+                    id_in_list.clone(),
+                    Condition::negate(Condition::in_list(id.clone(), vec![SqlValue::Integer(2)])),
+                )));
+
+        let tokens = lexer::to_tokens("delete from users where id not in (1, 2)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Delete { where_clause: Some(where_clause), .. }) if where_clause == Condition::negate(id_in_list)));
+
+        let tokens = lexer::to_tokens("select * from users where id in ()").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ColumnValueInvalid(Token::RightParenthesis))));
+
+        let tokens = lexer::to_tokens("select * from users where id in (1, 2").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::RightParenthesisMissing(_))));
+
+        let tokens = lexer::to_tokens("select * from users where id not = 1").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::OperatorInvalid(Token::Equals))));
+    }
+
+    #[test]
+    fn select_where_exists() {
+        let tokens = lexer::to_tokens("select * from users where not exists (select count(*) from orders where user_id = users.id) and id > 1").unwrap();
+        let user_has_orders = Condition::exists(SqlValue::Identificator("orders".to_string()), Some(BinaryCondition {
+            left_value: SqlValue::Identificator("user_id".to_string()),
+            right_value: SqlValue::Identificator("users.id".to_string()),
+            operator: CmpOperator::Equals,
+        }.into()));
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Select { where_clause: Some(where_clause), .. }) if where_clause == Condition::and(
+                    Condition::negate(user_has_orders),
+                    BinaryCondition { left_value: SqlValue::Identificator("id".to_string()), right_value: SqlValue::Integer(1), operator: CmpOperator::Greater }.into(),
+                )));
+
+        let tokens = lexer::to_tokens("delete from users where exists (select * from orders)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Delete { where_clause: Some(Condition::Exists(subquery)), .. }) if subquery.where_clause.is_none()));
+
+        let tokens = lexer::to_tokens("select * from users where exists (orders)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::SubquerySelectExpected(_))));
+
+        let tokens = lexer::to_tokens("select * from users where exists (select * from orders where user_id = 1").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::RightParenthesisMissing(_))));
+    }
+
     #[test]
     fn select_all_columns() {
         let input = vec![
@@ -354,6 +853,438 @@ mod tests {
         assert!(parse_statement(input.iter()).is_ok());
     }
 
+    #[test]
+    fn undelete_rows_where() {
+        let input = vec![
+                Token::Undelete, Token::From,
+                Token::Value(SqlValue::Identificator("table_name".into())),
+                Token::Where, Token::Value(SqlValue::String("id".into())), Token::Equals,
+                Token::Value(SqlValue::Integer(10))
+           ];
+
+        assert!(matches!(parse_statement(input.iter()), Ok(Command::Undelete { where_clause: Some(_), .. })));
+    }
+
+    #[test]
+    fn create_and_drop_sequence() {
+        let input = vec![
+                Token::Create, Token::Sequence, Token::Value(SqlValue::Identificator("user_ids".into())),
+           ];
+        assert!(matches!(parse_statement(input.iter()), Ok(Command::CreateSequence { .. })));
+
+        let input = vec![
+                Token::Drop, Token::Sequence, Token::Value(SqlValue::Identificator("user_ids".into())),
+           ];
+        assert!(matches!(parse_statement(input.iter()), Ok(Command::DropSequence { .. })));
+
+        for sequence_name in [SqlValue::Identificator("..".into()), SqlValue::Identificator("ids.table".into()), SqlValue::String("../ids".into())] {
+            let input = vec![Token::Create, Token::Sequence, Token::Value(sequence_name)];
+            assert!(matches!(parse_statement(input.iter()), Err(ParserError::SequenceNameInvalid(_))));
+        }
+    }
+
+    #[test]
+    fn insert_next_value() {
+        let tokens = lexer::to_tokens("insert into users (id, name) values (nextval(user_ids), \"john\")").unwrap();
+
+        match parse_statement(tokens.iter()) {
+            Ok(Command::InsertInto { values, .. }) => {
+                assert!(matches!(values[0], InsertValue::NextVal(SqlValue::Identificator(ref name)) if name == "user_ids"));
+                assert!(matches!(values[1], InsertValue::Value(SqlValue::String(_))));
+            },
+            result => panic!("expected insert command, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn insert_random_value() {
+        let tokens = lexer::to_tokens("insert into users (id) values (RANDOM())").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::InsertInto { values, .. }) if matches!(values[0], InsertValue::Random)));
+
+        let tokens = lexer::to_tokens("insert into users (id) values (random)").unwrap();
+        assert!(parse_statement(tokens.iter()).is_err());
+    }
+
+    #[test]
+    fn select_series() {
+        let tokens = lexer::to_tokens("select * from generate_series(1, 10) where value > 5").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectSeries { start: 1, stop: 10, step: 1, where_clause: Some(_), .. })
+                ));
+
+        let tokens = lexer::to_tokens("select value from generate_series(10, -10, -2)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectSeries { start: 10, stop: -10, step: -2, where_clause: None, .. })
+                ));
+
+        let tokens = lexer::to_tokens("select * from generate_series(1, 10, 0)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::SeriesStepZero)));
+
+        let tokens = lexer::to_tokens("select * from generate_series(1)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::SeriesArgumentInvalid(_))));
+    }
+
+    #[test]
+    fn select_count() {
+        let tokens = lexer::to_tokens("select count(*), COUNT(name) from users").unwrap();
+        match parse_statement(tokens.iter()) {
+            Ok(Command::Select { column_names, .. }) => {
+                assert!(matches!(column_names[0], SelectColumnName::CountAll));
+                assert!(matches!(&column_names[1], SelectColumnName::Count(SqlValue::Identificator(name)) if name == "name"));
+            },
+            result => panic!("expected select, got {:?}", result),
+        }
+
+        let tokens = lexer::to_tokens("select count from users").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Select { column_names, .. }) if matches!(column_names[0], SelectColumnName::Name(_))
+                ));
+
+        let tokens = lexer::to_tokens("select count(1) from users").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ColumnNameInvalid(_))));
+    }
+
+    #[test]
+    fn select_cross_join() {
+        let tokens = lexer::to_tokens("select * from users, posts where users.id = posts.user_id").unwrap();
+        match parse_statement(tokens.iter()) {
+            Ok(Command::SelectCrossJoin { table_names, where_clause: Some(_), .. }) => {
+                assert_eq!(table_names, vec![
+                           SqlValue::Identificator("users".to_string()),
+                           SqlValue::Identificator("posts".to_string()),
+                ]);
+            },
+            result => panic!("expected cross join, got {:?}", result),
+        }
+
+        let tokens = lexer::to_tokens("select * from users, posts, comments").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectCrossJoin { table_names, where_clause: None, .. }) if table_names.len() == 3
+                ));
+
+        let tokens = lexer::to_tokens("select * from users,").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::TableNameMissing)));
+    }
+
+    #[test]
+    fn check_table() {
+        let tokens = lexer::to_tokens("check table users").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::CheckTable { table_name: SqlValue::Identificator(name) }) if name == "users"
+                ));
+
+        let tokens = lexer::to_tokens("check users").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CheckTypeUnknown(_))));
+
+        let tokens = lexer::to_tokens("check").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CheckTypeMissing)));
+    }
+
+    #[test]
+    fn show_storage() {
+        let tokens = lexer::to_tokens("show storage users").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::ShowStorage { table_name: SqlValue::Identificator(name) }) if name == "users"
+                ));
+
+        let tokens = lexer::to_tokens("show users").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ShowTypeUnknown(_))));
+
+        let tokens = lexer::to_tokens("show").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ShowTypeMissing)));
+    }
+
+    #[test]
+    fn select_changes() {
+        let tokens = lexer::to_tokens("select * from changes(users) since 10").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::SelectChanges { since_lsn: 10, .. })));
+
+        let tokens = lexer::to_tokens("select * from changes(users)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::SelectChanges { since_lsn: 0, .. })));
+
+        let tokens = lexer::to_tokens("select id from changes(users)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ChangesColumnsInvalid)));
+
+        let tokens = lexer::to_tokens("select * from changes where id = 1").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::Select { .. })));
+    }
+
+    #[test]
+    fn select_qualified_columns() {
+        let tokens = lexer::to_tokens("select users.*, users.id from users").unwrap();
+        match parse_statement(tokens.iter()) {
+            Ok(Command::Select { column_names, .. }) => {
+                assert!(matches!(&column_names[0], SelectColumnName::TableAllColumns(SqlValue::Identificator(name)) if name == "users"));
+                assert!(matches!(&column_names[1], SelectColumnName::Name(SqlValue::Identificator(name)) if name == "users.id"));
+            },
+            result => panic!("unexpected parse result {:?}", result),
+        }
+
+        let tokens = lexer::to_tokens("select users. from users").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ColumnNameInvalid(_))));
+    }
+
+    #[test]
+    fn function_calls() {
+        let tokens = lexer::to_tokens("select upper(name), id from users where length(substr(name, 2)) > 3").unwrap();
+        match parse_statement(tokens.iter()) {
+            Ok(Command::Select { column_names, where_clause: Some(where_clause), .. }) => {
+                assert!(matches!(&column_names[0], SelectColumnName::Name(function_call) if function_call.to_string() == "UPPER(name)"));
+                assert!(matches!(&column_names[1], SelectColumnName::Name(SqlValue::Identificator(name)) if name == "id"));
+                assert_eq!(where_clause.to_string(), "LENGTH(SUBSTR(name, 2)) > 3");
+            },
+            result => panic!("unexpected parse result {:?}", result),
+        }
+
+        let tokens = lexer::to_tokens("select * from users where lower(name) in (\"john\")").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::Select { .. })));
+
+        let tokens = lexer::to_tokens("select reverse(name) from users").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::FunctionUnknown("reverse"))));
+
+        let tokens = lexer::to_tokens("select * from users where substr(name) = \"j\"").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::FunctionError(_))));
+
+        let tokens = lexer::to_tokens("select * from users where upper(name = \"j\"").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::RightParenthesisExpected(_, "function arguments"))));
+    }
+
+    #[test]
+    fn set_statement_timeout() {
+        let tokens = lexer::to_tokens("set statement_timeout = 100").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::SetStatementTimeout { milliseconds: 100 })));
+
+        let tokens = lexer::to_tokens("SET random_seed = 42").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::SetRandomSeed { seed: 42 })));
+
+        let tokens = lexer::to_tokens("set page_size = 100").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::SettingUnknown(_))));
+
+        let tokens = lexer::to_tokens("set statement_timeout 100").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::EqualsExpected(_))));
+    }
+
+    #[test]
+    fn cursor_statements() {
+        let tokens = lexer::to_tokens("DECLARE recent CURSOR FOR SELECT id FROM users WHERE id > 10").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::DeclareCursor { cursor_name: SqlValue::Identificator(name), select })
+                    if name == "recent" && matches!(*select, Command::Select { where_clause: Some(_), .. })
+                ));
+
+        let tokens = lexer::to_tokens("fetch 100 from recent").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Fetch { direction: FetchDirection::Forward, count: Some(100), .. })
+                ));
+        let tokens = lexer::to_tokens("FETCH BACKWARD ALL FROM recent").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Fetch { direction: FetchDirection::Backward, count: None, .. })
+                ));
+        let tokens = lexer::to_tokens("CLOSE recent").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::CloseCursor { .. })));
+
+        let tokens = lexer::to_tokens("DECLARE recent CURSOR FOR DELETE FROM users").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CursorSelectExpected(_))));
+        let tokens = lexer::to_tokens("FETCH -1 FROM recent").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::FetchCountInvalid(_))));
+        let tokens = lexer::to_tokens("FETCH 10 recent").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::FromExpected(_))));
+    }
+
+    #[test]
+    fn create_macro() {
+        let tokens = lexer::to_tokens("CREATE MACRO adult(a) AS (a >= 18)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::CreateMacro { macro_name: SqlValue::Identificator(name), parameter_names, body })
+                    if name == "adult" && parameter_names.len() == 1 && body == lexer::to_tokens("(a >= 18)").unwrap()
+                ));
+        let tokens = lexer::to_tokens("create macro active() as deleted = 0").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::CreateMacro { parameter_names, .. }) if parameter_names.is_empty()));
+
+        let tokens = lexer::to_tokens("CREATE MACRO adult(a, 1) AS (a >= 18)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::MacroParameterInvalid(_))));
+        let tokens = lexer::to_tokens("CREATE MACRO adult(a) (a >= 18)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::AsExpected(_))));
+        let tokens = lexer::to_tokens("CREATE MACRO adult(a) AS").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::MacroBodyMissing)));
+    }
+
+    #[test]
+    fn returning_clause() {
+        let tokens = lexer::to_tokens("DELETE FROM users WHERE age > 90 RETURNING id, UPPER(name)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Returning { command, column_names })
+                    if matches!(*command, Command::Delete { where_clause: Some(_), .. }) && column_names.len() == 2
+                ));
+        let tokens = lexer::to_tokens("insert into users (id, name) values (1, \"john\") returning *").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Returning { command, column_names })
+                    if matches!(*command, Command::InsertInto { .. }) && matches!(column_names[..], [SelectColumnName::AllColumns])
+                ));
+        let tokens = lexer::to_tokens("UPDATE users SET age = 1 WHERE EXISTS (SELECT id FROM orders) RETURNING users.*").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Ok(Command::Returning { .. })));
+
+        let tokens = lexer::to_tokens("DELETE FROM users RETURNING COUNT(*)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ReturningAggregate)));
+        let tokens = lexer::to_tokens("DELETE FROM users RETURNING id name").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ReturningColumnNamesInvalid(_))));
+        let tokens = lexer::to_tokens("DELETE FROM users RETURNING").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ColumnNameMissing)));
+        let tokens = lexer::to_tokens("INSERT INTO users (id) VALUES (1) 2 RETURNING id").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::OnConflictExpected(_))));
+    }
+
+    #[test]
+    fn select_index_hint() {
+        let tokens = lexer::to_tokens("SELECT * FROM users USE INDEX (users_id, users_name) WHERE id = 1 LIMIT 1").unwrap();
+        let select = match parse_statement(tokens.iter()) {
+            Ok(Command::SelectLimited { select, .. }) => *select,
+            result => panic!("expected limited select, got {:?}", result),
+        };
+        assert!(matches!(
+                select,
+                Command::SelectIndexHinted { select, index_hint: IndexHint::Use(index_names) }
+                    if matches!(*select, Command::Select { where_clause: Some(_), .. }) && index_names == ["users_id", "users_name"]
+                ));
+        let tokens = lexer::to_tokens("select * from users ignore index (users_id)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectIndexHinted { index_hint: IndexHint::Ignore(index_names), .. }) if index_names == ["users_id"]
+                ));
+
+        let tokens = lexer::to_tokens("SELECT * FROM users USE (users_id)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::IndexKeywordExpected(_))));
+        let tokens = lexer::to_tokens("SELECT * FROM users IGNORE INDEX ()").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::IndexNameInvalid(_))));
+        let tokens = lexer::to_tokens("SELECT * FROM users, posts USE INDEX (users_id)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::WhereExpected(_))));
+    }
+
+    #[test]
+    fn select_limit_offset() {
+        let tokens = lexer::to_tokens("SELECT id FROM users ORDER BY id LIMIT 10 OFFSET 20").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectLimited { select, limit: Some(10), offset: 20 }) if matches!(*select, Command::SelectOrdered { .. })
+                ));
+        let tokens = lexer::to_tokens("select id from users where exists (select id from orders) offset 5").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectLimited { select, limit: None, offset: 5 }) if matches!(*select, Command::Select { .. })
+                ));
+
+        let tokens = lexer::to_tokens("SELECT id FROM users LIMIT").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::IntegerMissing)));
+        let tokens = lexer::to_tokens("SELECT id FROM users LIMIT -1").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::IntegerExpected(_))));
+        let tokens = lexer::to_tokens("SELECT id FROM users OFFSET 1 LIMIT 2").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ExcessTokens(_))));
+        let tokens = lexer::to_tokens("SELECT id FROM users LIMIT 1 2").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::OffsetExpected(_))));
+    }
+
+    #[test]
+    fn insert_on_conflict() {
+        let tokens = lexer::to_tokens("INSERT INTO users (id, name) VALUES (1, \"john\") ON CONFLICT (id) DO NOTHING").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Upsert { insert, conflict_column_name: SqlValue::Identificator(name), action: ConflictAction::Nothing })
+                    if matches!(*insert, Command::InsertInto { .. }) && name == "id"
+                ));
+        let tokens = lexer::to_tokens("insert into users (id, name) values (1, \"john\") on conflict (id) do update set name = \"john\", age = 2 returning *").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::Returning { command, .. })
+                    if matches!(&*command, Command::Upsert { action: ConflictAction::Update(field_assignments), .. } if field_assignments.len() == 2)
+                ));
+
+        let tokens = lexer::to_tokens("INSERT INTO users (id) VALUES (1) ON CONFLICT id DO NOTHING").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::LeftParenthesisExpected(..))));
+        let tokens = lexer::to_tokens("INSERT INTO users (id) VALUES (1) ON CONFLICT (id) DO DELETE").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ConflictActionInvalid(_))));
+        let tokens = lexer::to_tokens("INSERT INTO users (id) VALUES (1) ON CONFLICT (id) DO UPDATE SET id = 2 WHERE id = 1").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ConflictUpdateWhereUnsupported)));
+        let tokens = lexer::to_tokens("INSERT INTO users (id) VALUES (1) ON").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ConflictMissing)));
+    }
+
+    #[test]
+    fn copy_statements() {
+        let tokens = lexer::to_tokens("COPY users FROM \"users.csv\"").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::CopyFrom { table_name: SqlValue::Identificator(name), file_path }) if name == "users" && file_path == "users.csv"
+                ));
+        let tokens = lexer::to_tokens("copy users to \"users.csv\"").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::CopyTo { select, .. }) if matches!(*select, Command::Select { where_clause: None, .. })
+                ));
+        let tokens = lexer::to_tokens("COPY (SELECT id FROM users WHERE EXISTS (SELECT id FROM orders) ORDER BY id) TO \"ids.csv\"").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::CopyTo { select, file_path }) if matches!(*select, Command::SelectOrdered { .. }) && file_path == "ids.csv"
+                ));
+
+        let tokens = lexer::to_tokens("COPY (SELECT id FROM users) FROM \"ids.csv\"").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CopyToExpected(_))));
+        let tokens = lexer::to_tokens("COPY (DELETE FROM users) TO \"ids.csv\"").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CopySelectExpected(_))));
+        let tokens = lexer::to_tokens("COPY (SELECT id FROM users TO \"ids.csv\"").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::RightParenthesisMissing(_))));
+        let tokens = lexer::to_tokens("COPY users FROM users_csv").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CopyFilePathInvalid(_))));
+        let tokens = lexer::to_tokens("COPY users").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CopyDirectionMissing)));
+    }
+
+    #[test]
+    fn select_order_by() {
+        let tokens = lexer::to_tokens("SELECT id, age FROM users WHERE id > 1 ORDER BY age DESC, id NULLS FIRST").unwrap();
+        match parse_statement(tokens.iter()) {
+            Ok(Command::SelectOrdered { select, order_by }) => {
+                assert!(matches!(*select, Command::Select { where_clause: Some(_), .. }));
+                assert_eq!(order_by.len(), 2);
+                assert!(order_by[0].descending && order_by[0].nulls == NullsOrder::First);
+                assert!(!order_by[1].descending && order_by[1].nulls == NullsOrder::First);
+            },
+            other => panic!("expected ordered select, got {:?}", other),
+        }
+
+        let tokens = lexer::to_tokens("select id from users order by id asc nulls last").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectOrdered { order_by, .. }) if !order_by[0].descending && order_by[0].nulls == NullsOrder::Last
+                ));
+
+        let tokens = lexer::to_tokens("SELECT id FROM users ORDER BY RANDOM() LIMIT 2").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::SelectLimited { select, .. }) if matches!(&*select, Command::SelectOrdered { order_by, .. } if order_by[0].key == OrderingKey::Random)
+                ));
+
+        let tokens = lexer::to_tokens("SELECT id FROM users ORDER id").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::OrderByExpected(_))));
+        let tokens = lexer::to_tokens("SELECT id FROM users ORDER BY id NULLS").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::NullsOrderMissing)));
+        let tokens = lexer::to_tokens("SELECT id FROM users ORDER BY id NULLS LATER").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::NullsOrderInvalid(_))));
+        let tokens = lexer::to_tokens("SELECT id FROM users ORDER BY id DESC ASC").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::OrderingTermInvalid(_))));
+    }
+
     #[test]
     fn alter_rename_table() {
         let input = vec![
@@ -432,6 +1363,46 @@ mod tests {
         assert!(parse_statement(input.iter()).is_ok());
     }
 
+    #[test]
+    fn named_constraints() {
+        let tokens = lexer::to_tokens("create table users (id int, constraint positive_age check (age > 0), age int constraint adult check (age >= 18))").unwrap();
+        let positive_age = Constraint::NamedCheck("positive_age".to_string(), BinaryCondition {
+            left_value: SqlValue::Identificator("age".to_string()),
+            right_value: SqlValue::Integer(0),
+            operator: CmpOperator::Greater,
+        });
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::CreateTable { columns, .. })
+                    if columns[0].column_constraints.is_empty() &&
+                        matches!(&columns[1].column_constraints[..], [Constraint::NamedCheck(adult, _), constraint] if adult == "adult" && *constraint == positive_age)
+                ));
+
+        let tokens = lexer::to_tokens("ALTER TABLE users ADD CONSTRAINT positive_age CHECK (0 < age)").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::AddColumnConstraint { column_name: SqlValue::Identificator(column_name), constraint: Constraint::NamedCheck(..), .. })
+                    if column_name == "age"
+                ));
+        let tokens = lexer::to_tokens("ALTER TABLE users DROP CONSTRAINT positive_age").unwrap();
+        assert!(matches!(
+                parse_statement(tokens.iter()),
+                Ok(Command::DropNamedConstraint { constraint_name: SqlValue::Identificator(constraint_name), .. }) if constraint_name == "positive_age"
+                ));
+
+        let tokens = lexer::to_tokens("create table users (id int, constraint positive_age check (age > 0))").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::TableConstraintColumnUnknown { .. })));
+        let tokens = lexer::to_tokens("ALTER TABLE users ADD CONSTRAINT always_true CHECK (1 = 1)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::CheckColumnMissing(_))));
+        let tokens = lexer::to_tokens("ALTER TABLE users ADD CONSTRAINT not_null NOT NULL").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::InvalidConstraint(_))));
+        let tokens = lexer::to_tokens("ALTER TABLE users DROP CONSTRAINT positive_age CHECK (age > 0)").unwrap();
+        assert!(matches!(parse_statement(tokens.iter()), Err(ParserError::ExcessTokens(_))));
+
+        let schema_line = parse_schema_line("users 0 id int, age int constraint positive_age check (age > 0);;;").unwrap();
+        assert_eq!(schema_line.column_definitions[1].column_constraints, vec![positive_age]);
+    }
+
     #[test]
     fn create_index() {
         let input = vec![
@@ -540,18 +1511,25 @@ mod tests {
                 ));
 
         match parse_meta_command(".connect foo") {
-            MetaCommand::Connect(db_path) => {
+            MetaCommand::Connect { db_path, salvage: false } => {
                 assert_eq!(db_path, PathBuf::from("foo"));
             },
             _ => panic!("Expected '.connect foo' to be parsed to Createdb"),
         }
 
         match parse_meta_command(".connect /foo/bar") {
-            MetaCommand::Connect(db_path) => {
+            MetaCommand::Connect { db_path, salvage: false } => {
                 assert_eq!(db_path, PathBuf::from("/foo/bar"));
             },
             _ => panic!("Expected '.connect /foo/bar' to be parsed to Createdb"),
         }
+
+        match parse_meta_command(".connect --salvage /foo/bar") {
+            MetaCommand::Connect { db_path, salvage: true } => {
+                assert_eq!(db_path, PathBuf::from("/foo/bar"));
+            },
+            _ => panic!("Expected '.connect --salvage /foo/bar' to be parsed to salvage Connect"),
+        }
     }
 
     #[test]
@@ -567,9 +1545,191 @@ mod tests {
                 ));
     }
 
+    #[test]
+    fn types() {
+        assert!(matches!(parse_meta_command(".types on"), MetaCommand::ShowColumnTypes(true)));
+        assert!(matches!(parse_meta_command(".types off"), MetaCommand::ShowColumnTypes(false)));
+        assert!(matches!(
+                    parse_meta_command(".types"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+        assert!(matches!(
+                    parse_meta_command(".types yes"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn history() {
+        assert!(matches!(parse_meta_command(".history"), MetaCommand::History(None)));
+        assert!(matches!(parse_meta_command(".history 10"), MetaCommand::History(Some(10))));
+        assert!(matches!(
+                    parse_meta_command(".history file yarrd_history"),
+                    MetaCommand::HistoryFile(path) if path == PathBuf::from("yarrd_history")
+                ));
+        assert!(matches!(
+                    parse_meta_command(".history file"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+        assert!(matches!(
+                    parse_meta_command(".history last"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn movedb() {
+        assert!(matches!(
+                    parse_meta_command(".movedb /data/app.db"),
+                    MetaCommand::Movedb { new_db_path, new_tables_dir_path: None } if new_db_path == PathBuf::from("/data/app.db")
+                ));
+        assert!(matches!(
+                    parse_meta_command(".movedb app.db app_tables"),
+                    MetaCommand::Movedb { new_db_path, new_tables_dir_path: Some(tables_dir) }
+                        if new_db_path == PathBuf::from("./app.db") && tables_dir == PathBuf::from("./app_tables")
+                ));
+        assert!(matches!(
+                    parse_meta_command(".movedb"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn user() {
+        assert!(matches!(parse_meta_command(".user"), MetaCommand::User(None)));
+        assert!(matches!(parse_meta_command(".user alice"), MetaCommand::User(Some(name)) if name == "alice"));
+    }
+
+    #[test]
+    fn reload() {
+        assert!(matches!(parse_meta_command(".reload"), MetaCommand::Reload));
+        assert!(matches!(
+                    parse_meta_command(".read dump.sql --continue-on-error"),
+                    MetaCommand::Read { path, continue_on_error: true } if path == PathBuf::from("dump.sql")
+                ));
+        assert!(matches!(parse_meta_command(".read dump.sql"), MetaCommand::Read { continue_on_error: false, .. }));
+        assert!(matches!(parse_meta_command(".read"), MetaCommand::MetacommandWithWrongArgs(_)));
+        assert!(matches!(parse_meta_command(".read dump.sql --quiet"), MetaCommand::MetacommandWithWrongArgs(_)));
+        assert!(matches!(parse_meta_command(".reload now"), MetaCommand::Unknown(_)));
+    }
+
+    #[test]
+    fn diff() {
+        assert!(matches!(
+                    parse_meta_command(".diff other.db"),
+                    MetaCommand::Diff(path) if path == PathBuf::from("other.db")
+                ));
+        assert!(matches!(
+                    parse_meta_command(".diff"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn timer() {
+        assert!(matches!(parse_meta_command(".timer on"), MetaCommand::Timer(TimerMode::On)));
+        assert!(matches!(parse_meta_command(".timer off"), MetaCommand::Timer(TimerMode::Off)));
+        assert!(matches!(parse_meta_command(".timer verbose"), MetaCommand::Timer(TimerMode::Verbose)));
+        assert!(matches!(
+                    parse_meta_command(".timer"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn index_stats() {
+        assert!(matches!(parse_meta_command(".index_stats"), MetaCommand::IndexStats(None)));
+        assert!(matches!(
+                    parse_meta_command(".index_stats users"),
+                    MetaCommand::IndexStats(Some(table_name)) if table_name == "users"
+                ));
+    }
+
+    #[test]
+    fn seed() {
+        assert!(matches!(
+                    parse_meta_command(".seed users 1000"),
+                    MetaCommand::Seed { table_name, row_count: 1000 } if table_name == "users"
+                ));
+        assert!(matches!(
+                    parse_meta_command(".seed users"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+        assert!(matches!(
+                    parse_meta_command(".seed users -5"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn pagedump() {
+        assert!(matches!(
+                    parse_meta_command(".pagedump users 3"),
+                    MetaCommand::PageDump { table_name, index_name: None, number: 3 } if table_name == "users"
+                ));
+        assert!(matches!(
+                    parse_meta_command(".pagedump users people_id 0"),
+                    MetaCommand::PageDump { table_name, index_name: Some(index_name), number: 0 }
+                        if table_name == "users" && index_name == "people_id"
+                ));
+        assert!(matches!(
+                    parse_meta_command(".pagedump users"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+        assert!(matches!(
+                    parse_meta_command(".pagedump users first"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn output_mode() {
+        assert!(matches!(parse_meta_command(".mode table"), MetaCommand::SetOutputMode(OutputMode::Table)));
+        assert!(matches!(
+                    parse_meta_command(".mode insert"),
+                    MetaCommand::SetOutputMode(OutputMode::Insert(table_name)) if table_name == "new_table"
+                ));
+        assert!(matches!(
+                    parse_meta_command(".mode insert people"),
+                    MetaCommand::SetOutputMode(OutputMode::Insert(table_name)) if table_name == "people"
+                ));
+        assert!(matches!(
+                    parse_meta_command(".mode csv"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn output_redirect() {
+        assert!(matches!(
+                    parse_meta_command(".output results.txt"),
+                    MetaCommand::RedirectOutput { path: Some(path), once: false } if path == Path::new("./results.txt")
+                ));
+        assert!(matches!(parse_meta_command(".output"), MetaCommand::RedirectOutput { path: None, once: false }));
+        assert!(matches!(
+                    parse_meta_command(".once /tmp/results.txt"),
+                    MetaCommand::RedirectOutput { path: Some(path), once: true } if path == Path::new("/tmp/results.txt")
+                ));
+        assert!(matches!(
+                    parse_meta_command(".once"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
+    #[test]
+    fn gc() {
+        assert!(matches!(parse_meta_command(".gc"), MetaCommand::CollectGarbage(GarbageAction::List)));
+        assert!(matches!(parse_meta_command(".gc delete"), MetaCommand::CollectGarbage(GarbageAction::Delete)));
+        assert!(matches!(parse_meta_command(".gc quarantine"), MetaCommand::CollectGarbage(GarbageAction::Quarantine)));
+        assert!(matches!(
+                    parse_meta_command(".gc all"),
+                    MetaCommand::MetacommandWithWrongArgs(MetaCommandError::ParseError(_))
+                ));
+    }
+
     #[test]
     fn parse_valid_schema() {
-        let TableSchemaDefinitionLine { name: table_name, row_count, column_definitions, indexes_definitions } =
+        let TableSchemaDefinitionLine { name: table_name, row_count, column_definitions, indexes_definitions, .. } =
             parse_schema_line("users 0 id int not null default 1 check(id > 0), name string").unwrap();
         assert_eq!(table_name, "users");
         assert_eq!(row_count, 0);
@@ -597,7 +1757,7 @@ mod tests {
 
     #[test]
     fn parse_another_valid_schema() {
-        let TableSchemaDefinitionLine { name: table_name, row_count, column_definitions, indexes_definitions } =
+        let TableSchemaDefinitionLine { name: table_name, row_count, column_definitions, indexes_definitions, .. } =
             parse_schema_line("users 2 id int, age int; 1 age_hash;").unwrap();
         assert_eq!(table_name, "users");
         assert_eq!(row_count, 2);
@@ -611,6 +1771,31 @@ mod tests {
         assert_eq!(indexes_definitions[0], (1, "age_hash".to_string()));
     }
 
+    #[test]
+    fn parse_schema_with_empty_sections_and_options() {
+        let TableSchemaDefinitionLine { column_definitions, indexes_definitions, options, null_counts, .. } =
+            parse_schema_line("users 0 id INT, name STRING;;").unwrap();
+        assert_eq!(column_definitions.len(), 2);
+        assert_eq!(indexes_definitions.len(), 0);
+        assert_eq!(options, TableOptions::default());
+        assert_eq!(null_counts, None);
+
+        let TableSchemaDefinitionLine { null_counts, .. } =
+            parse_schema_line("users 3 id INT, name STRING;;; 0 2;").unwrap();
+        assert_eq!(null_counts, Some(vec![0, 2]));
+
+        let TableSchemaDefinitionLine { indexes_definitions, options, .. } =
+            parse_schema_line("users 0 id INT; 0 id_hash; soft_delete, change_log, checksums, compression;").unwrap();
+        assert_eq!(indexes_definitions, vec![(0, "id_hash".to_string())]);
+        assert_eq!(options, TableOptions { soft_delete: true, change_log: true, checksums: true, compression: true });
+
+        assert!(matches!(
+                parse_schema_line("users 0 id INT;; unknown_option;"),
+                Err(ParserError::InvalidSchemaDefinition(_))
+                )
+               );
+    }
+
     #[test]
     fn parse_invalid_schema() {
         assert!(matches!(
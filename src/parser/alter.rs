@@ -1,8 +1,12 @@
+use std::iter;
+
 use crate::command::Command;
 use crate::lexer::Token;
-use crate::table::Constraint;
+use crate::table::{Constraint, TableOptions};
 use crate::parser::error::ParserError;
-use crate::parser::shared::{parse_table_name, parse_column_name, parse_column_definition, parse_constraint_tokens};
+use crate::parser::shared::
+    {parse_table_name, parse_column_name, parse_column_definition, parse_constraint_name, parse_constraint_tokens, parse_table_constraint_tokens,
+    parse_left_parenthesis};
 use crate::lexer::SqlValue;
 
 pub fn parse_alter_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
@@ -32,6 +36,14 @@ where
         },
         Some(Token::Add) => parse_add_entity(token, table_name),
         Some(Token::Drop) => parse_drop_entity(token, table_name),
+        Some(Token::Set) => {
+            let options = parse_table_option_values(token, true)?;
+            Ok(Command::SetTableOptions { table_name, options })
+        },
+        Some(Token::Unset) => {
+            let options = parse_table_option_values(token, false)?;
+            Ok(Command::SetTableOptions { table_name, options })
+        },
         None => Err(ParserError::AlterTableActionMissing),
         Some(token) => Err(ParserError::AlterTableActionUnknown(token)),
     }
@@ -74,7 +86,14 @@ where
             Ok(Command::AddTableColumn { table_name, column_definition })
         },
         Some(Token::Constraint) => {
-            let (column_name, constraint) = parse_column_constraint(&mut token)?;
+            let mut token = token.peekable();
+            let (column_name, constraint) = match token.peek() {
+                Some(Token::Value(SqlValue::Identificator(_))) => {
+                    let constraint_tokens = iter::once(&Token::Constraint).chain(token).collect();
+                    parse_table_constraint_tokens(constraint_tokens)?
+                },
+                _ => parse_column_constraint(&mut token)?,
+            };
             Ok(Command::AddColumnConstraint { table_name, column_name, constraint })
         },
         None => Err(ParserError::AddTypeMissing),
@@ -90,9 +109,9 @@ where
     let mut check_constraint_being_parsed = false;
     loop {
         match token.next() {
-            Some(Token::Check) => {
+            Some(token @ (Token::Check | Token::References)) => {
                 check_constraint_being_parsed = true;
-                constraint_tokens.push(&Token::Check)
+                constraint_tokens.push(token)
             },
             Some(Token::LeftParenthesis) => {
                 if check_constraint_being_parsed {
@@ -130,6 +149,62 @@ where
     Ok((column_name, constraint))
 }
 
+/// set_options = SET (option_name [= TRUE | FALSE | 1 | 0], ...), option without value is enabled
+/// unset_options = UNSET (option_name, ...)
+fn parse_table_option_values<'a, I>(token: I, with_values: bool) -> Result<Vec<(String, bool)>, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut token = token.peekable();
+    let mut options = vec![];
+    parse_left_parenthesis(&mut token, "table options")?;
+
+    loop {
+        let option_name = match token.next() {
+            Some(option_token @ Token::Value(SqlValue::Identificator(option_name))) => {
+                TableOptions::default().set(option_name, true).map_err(|_| ParserError::TableOptionUnknown(option_token))?;
+                option_name.to_string()
+            },
+            Some(option_token) => return Err(ParserError::TableOptionUnknown(option_token)),
+            None => return Err(ParserError::RightParenthesisMissing("table options")),
+        };
+        let enabled = match token.peek() {
+            Some(Token::Equals) if with_values => {
+                token.next();
+                parse_table_option_value(&mut token)?
+            },
+            _ => with_values,
+        };
+        options.push((option_name, enabled));
+
+        match token.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RightParenthesis) => break,
+            Some(token) => return Err(ParserError::RightParenthesisExpected(token, "table options")),
+            None => return Err(ParserError::RightParenthesisMissing("table options")),
+        }
+    }
+
+    Ok(options)
+}
+
+fn parse_table_option_value<'a, I>(mut token: I) -> Result<bool, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(SqlValue::Integer(1))) => Ok(true),
+        Some(Token::Value(SqlValue::Integer(0))) => Ok(false),
+        Some(value_token @ Token::Value(SqlValue::Identificator(value))) => match value.to_lowercase().as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(ParserError::TableOptionValueInvalid(value_token)),
+        },
+        Some(value_token) => Err(ParserError::TableOptionValueInvalid(value_token)),
+        None => Err(ParserError::TableOptionValueMissing),
+    }
+}
+
 fn parse_drop_entity<'a, I>(mut token: I, table_name: SqlValue) -> Result<Command, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
@@ -140,8 +215,17 @@ where
             Ok(Command::DropTableColumn { table_name, column_name })
         },
         Some(Token::Constraint) => {
-            let (column_name, constraint) = parse_column_constraint(&mut token)?;
-            Ok(Command::DropColumnConstraint { table_name, column_name, constraint })
+            let mut token = token.peekable();
+            match token.peek() {
+                Some(Token::Value(SqlValue::Identificator(_))) => {
+                    let constraint_name = parse_constraint_name(&mut token)?;
+                    Ok(Command::DropNamedConstraint { table_name, constraint_name })
+                },
+                _ => {
+                    let (column_name, constraint) = parse_column_constraint(&mut token)?;
+                    Ok(Command::DropColumnConstraint { table_name, column_name, constraint })
+                },
+            }
         }
         None => Err(ParserError::DropTypeMissing),
         Some(token) => Err(ParserError::DropTypeUnknown(token, "COLUMN")),
@@ -0,0 +1,15 @@
+use crate::command::Command;
+use crate::lexer::Token;
+use crate::parser::ParserError;
+use crate::parser::shared::parse_table_name;
+
+pub fn parse_check_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Table) => Ok(Command::CheckTable { table_name: parse_table_name(&mut token)? }),
+        None => Err(ParserError::CheckTypeMissing),
+        Some(token) => Err(ParserError::CheckTypeUnknown(token)),
+    }
+}
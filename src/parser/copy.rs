@@ -0,0 +1,76 @@
+use crate::command::{Command, SelectColumnName};
+use crate::lexer::{Token, SqlValue};
+use crate::parser::ParserError;
+use crate::parser::select::parse_select_statement;
+use crate::parser::shared::parse_table_name;
+
+/// copy_statement = COPY table_name (FROM | TO) file_path | COPY ( select_statement ) TO file_path
+pub fn parse_copy_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut token = (&mut token).peekable();
+    if token.next_if_eq(&&Token::LeftParenthesis).is_some() {
+        let select = parse_copy_select(&mut token)?;
+        match token.next() {
+            Some(Token::To) => {},
+            Some(token) => return Err(ParserError::CopyToExpected(token)),
+            None => return Err(ParserError::CopyToMissing),
+        }
+        let file_path = parse_file_path(&mut token)?;
+        return Ok(Command::CopyTo { select: Box::new(select), file_path })
+    }
+
+    let table_name = parse_table_name(&mut token)?;
+    match token.next() {
+        Some(Token::From) => Ok(Command::CopyFrom { table_name, file_path: parse_file_path(&mut token)? }),
+        Some(Token::To) => {
+            let file_path = parse_file_path(&mut token)?;
+            let select = Command::Select { table_name, column_names: vec![SelectColumnName::AllColumns], where_clause: None };
+            Ok(Command::CopyTo { select: Box::new(select), file_path })
+        },
+        Some(token) => Err(ParserError::CopyDirectionExpected(token)),
+        None => Err(ParserError::CopyDirectionMissing),
+    }
+}
+
+// select tokens are taken up to the matching right parenthesis, since select consumes all tokens it gets
+fn parse_copy_select<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Select) => {},
+        Some(token) => return Err(ParserError::CopySelectExpected(token)),
+        None => return Err(ParserError::CopySelectMissing),
+    }
+
+    let mut depth = 0;
+    let mut select_tokens = vec![];
+    loop {
+        match token.next() {
+            Some(Token::RightParenthesis) if depth == 0 => break,
+            Some(next_token) => {
+                match next_token {
+                    Token::LeftParenthesis => depth += 1,
+                    Token::RightParenthesis => depth -= 1,
+                    _ => {},
+                }
+                select_tokens.push(next_token);
+            },
+            None => return Err(ParserError::RightParenthesisMissing("copied select")),
+        }
+    }
+    parse_select_statement(select_tokens.into_iter())
+}
+
+fn parse_file_path<'a, I>(mut token: I) -> Result<String, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(SqlValue::String(file_path))) if !file_path.is_empty() => Ok(file_path.clone()),
+        Some(token) => Err(ParserError::CopyFilePathInvalid(token)),
+        None => Err(ParserError::CopyFilePathMissing),
+    }
+}
@@ -1,8 +1,12 @@
 use crate::command::{Command, ColumnDefinition};
-use crate::lexer::Token;
+use crate::lexer::{Token, SqlValue};
+use crate::table::TableOptions;
 use crate::parser::error::ParserError;
 use crate::parser::shared::
-    {parse_table_name, parse_column_name, parse_index_name, parse_left_parenthesis, parse_column_definition};
+    {parse_table_name, parse_column_name, parse_index_name, parse_sequence_name, parse_user_name, parse_left_parenthesis, parse_csl_right_parenthesis, parse_column_definition,
+     collect_constraint_tokens, parse_table_constraint_tokens};
+
+const IF: &str = "if";
 
 pub fn parse_create_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
 where
@@ -11,18 +15,107 @@ where
     match token.next() {
         Some(Token::Table) => parse_create_table_statement(token),
         Some(Token::Index) => parse_create_index_statement(token),
+        Some(Token::Sequence) => Ok(Command::CreateSequence { sequence_name: parse_sequence_name(token)? }),
+        Some(Token::User) => Ok(Command::CreateUser { user_name: parse_user_name(token)? }),
+        Some(Token::Macro) => parse_create_macro_statement(token),
         None => Err(ParserError::CreateTypeMissing),
         Some(token) => Err(ParserError::CreateTypeUnknown(token)),
     }
 }
 
-fn parse_create_table_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+/// create_table_statement = TABLE [IF NOT EXISTS] table_name column_definitions [WITH table_options]
+fn parse_create_table_statement<'a, I>(token: I) -> Result<Command, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
-    let table_name = parse_table_name(&mut token)?;
+    let mut token = token.peekable();
+    let mut table_name = parse_table_name(&mut token)?;
+    // IF is not a keyword, so table can still be named `if`
+    let if_not_exists = matches!(&table_name, SqlValue::Identificator(name) if name.eq_ignore_ascii_case(IF)) &&
+        token.next_if_eq(&&Token::Not).is_some();
+    if if_not_exists {
+        match token.next() {
+            Some(Token::Exists) => {},
+            Some(token) => return Err(ParserError::IfNotExistsInvalid(token)),
+            None => return Err(ParserError::IfNotExistsMissing),
+        }
+        table_name = parse_table_name(&mut token)?;
+    }
+
     let column_definitions = parse_column_definitions(&mut token)?;
-    Ok(Command::CreateTable { table_name, columns: column_definitions })
+    let options = match token.peek() {
+        Some(Token::With) => {
+            token.next();
+            parse_table_options(&mut token)?
+        },
+        _ => TableOptions::default(),
+    };
+    Ok(Command::CreateTable { table_name, columns: column_definitions, options, if_not_exists })
+}
+
+/// create_macro_statement = MACRO macro_name ([parameter_name {, parameter_name}]) AS token {token}
+fn parse_create_macro_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let macro_name = match token.next() {
+        Some(Token::Value(name @ SqlValue::Identificator(_))) => name.clone(),
+        Some(token) => return Err(ParserError::MacroNameInvalid(token)),
+        None => return Err(ParserError::MacroNameMissing),
+    };
+
+    parse_left_parenthesis(&mut token, "macro parameters")?;
+    let mut token = token.peekable();
+    let mut parameter_names = vec![];
+    if token.next_if_eq(&&Token::RightParenthesis).is_none() {
+        loop {
+            match token.next() {
+                Some(Token::Value(name @ SqlValue::Identificator(_))) => parameter_names.push(name.clone()),
+                Some(token) => return Err(ParserError::MacroParameterInvalid(token)),
+                None => return Err(ParserError::MacroParameterMissing),
+            }
+            if parse_csl_right_parenthesis(&mut token, "macro parameters")? {
+                break
+            }
+        }
+    }
+
+    match token.next() {
+        Some(Token::As) => {},
+        Some(token) => return Err(ParserError::AsExpected(token)),
+        None => return Err(ParserError::AsMissing),
+    }
+    let body: Vec<Token> = token.cloned().collect();
+    if body.is_empty() {
+        return Err(ParserError::MacroBodyMissing)
+    }
+    Ok(Command::CreateMacro { macro_name, parameter_names, body })
+}
+
+fn parse_table_options<'a, I>(mut token: I) -> Result<TableOptions, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut options = TableOptions::default();
+    parse_left_parenthesis(&mut token, "table options")?;
+
+    loop {
+        match token.next() {
+            Some(option_token @ Token::Value(SqlValue::Identificator(option_name))) =>
+                options.set(option_name, true).map_err(|_| ParserError::TableOptionUnknown(option_token))?,
+            Some(option_token) => return Err(ParserError::TableOptionUnknown(option_token)),
+            None => return Err(ParserError::RightParenthesisMissing("table options")),
+        }
+
+        match token.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RightParenthesis) => break,
+            Some(token) => return Err(ParserError::RightParenthesisExpected(token, "table options")),
+            None => return Err(ParserError::RightParenthesisMissing("table options")),
+        }
+    }
+
+    Ok(options)
 }
 
 fn parse_create_index_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
@@ -43,16 +136,29 @@ where
     }
 }
 
-fn parse_column_definitions<'a, I>(mut token: I) -> Result<Vec<ColumnDefinition>, ParserError<'a>>
+/// column_definitions = ( (column_definition | table_constraint) [, ...] )
+fn parse_column_definitions<'a, I>(token: I) -> Result<Vec<ColumnDefinition>, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
+    let mut token = token.peekable();
     let mut columns = vec![];
+    let mut table_constraints = vec![];
     parse_left_parenthesis(&mut token, "column definitions")?;
 
     loop {
-        let (column, last_token) = parse_column_definition(&mut token)?;
-        columns.push(column);
+        let last_token = match token.peek() {
+            Some(Token::Constraint) => {
+                let (constraint_tokens, last_token) = collect_constraint_tokens(&mut token)?;
+                table_constraints.push(parse_table_constraint_tokens(constraint_tokens)?);
+                last_token
+            },
+            _ => {
+                let (column, last_token) = parse_column_definition(&mut token)?;
+                columns.push(column);
+                last_token
+            },
+        };
 
         match last_token {
             Some(Token::Comma) => continue,
@@ -61,5 +167,15 @@ where
         }
     }
 
+    for (column_name, constraint) in table_constraints {
+        match columns.iter_mut().find(|column| column.name == column_name) {
+            Some(column) => column.column_constraints.push(constraint),
+            None => return Err(ParserError::TableConstraintColumnUnknown {
+                constraint_name: constraint.name().unwrap_or_default().to_string(),
+                column_name: column_name.to_string(),
+            }),
+        }
+    }
+
     Ok(columns)
 }
@@ -0,0 +1,89 @@
+use crate::command::{Command, FetchDirection};
+use crate::lexer::{Token, SqlValue};
+use crate::parser::ParserError;
+use crate::parser::select::parse_select_statement;
+
+const FORWARD: &str = "forward";
+const BACKWARD: &str = "backward";
+const ALL: &str = "all";
+
+/// declare_statement = DECLARE cursor_name CURSOR FOR select_statement
+pub fn parse_declare_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let cursor_name = parse_cursor_name(&mut token)?;
+    match token.next() {
+        Some(Token::Cursor) => {},
+        Some(token) => return Err(ParserError::CursorExpected(token)),
+        None => return Err(ParserError::CursorMissing),
+    }
+    match token.next() {
+        Some(Token::For) => {},
+        Some(token) => return Err(ParserError::ForExpected(token)),
+        None => return Err(ParserError::ForMissing),
+    }
+    match token.next() {
+        Some(Token::Select) => {},
+        Some(token) => return Err(ParserError::CursorSelectExpected(token)),
+        None => return Err(ParserError::CursorSelectMissing),
+    }
+
+    let select = parse_select_statement(&mut token)?;
+    Ok(Command::DeclareCursor { cursor_name, select: Box::new(select) })
+}
+
+/// fetch_statement = FETCH [FORWARD | BACKWARD] (count | ALL) FROM cursor_name
+pub fn parse_fetch_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut next_token = token.next();
+    let direction = match next_token {
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(FORWARD) => {
+            next_token = token.next();
+            FetchDirection::Forward
+        },
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(BACKWARD) => {
+            next_token = token.next();
+            FetchDirection::Backward
+        },
+        _ => FetchDirection::Forward,
+    };
+
+    let count = match next_token {
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(ALL) => None,
+        Some(Token::Value(SqlValue::Integer(count))) if *count >= 0 => Some(*count as usize),
+        Some(token) => return Err(ParserError::FetchCountInvalid(token)),
+        None => return Err(ParserError::FetchCountMissing),
+    };
+
+    match token.next() {
+        Some(Token::From) => {},
+        Some(token) => return Err(ParserError::FromExpected(token)),
+        None => return Err(ParserError::FromMissing),
+    }
+
+    let cursor_name = parse_cursor_name(&mut token)?;
+    Ok(Command::Fetch { cursor_name, direction, count })
+}
+
+/// close_statement = CLOSE cursor_name
+pub fn parse_close_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let cursor_name = parse_cursor_name(&mut token)?;
+    Ok(Command::CloseCursor { cursor_name })
+}
+
+fn parse_cursor_name<'a, I>(mut token: I) -> Result<SqlValue, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(name @ SqlValue::Identificator(_))) => Ok(name.clone()),
+        Some(token) => Err(ParserError::CursorNameInvalid(token)),
+        None => Err(ParserError::CursorNameMissing),
+    }
+}
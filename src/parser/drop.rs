@@ -1,7 +1,7 @@
 use crate::command::Command;
 use crate::lexer::Token;
 use crate::parser::ParserError;
-use crate::parser::shared::{parse_table_name, parse_index_name};
+use crate::parser::shared::{parse_table_name, parse_index_name, parse_sequence_name, parse_user_name};
 
 pub fn parse_drop_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
 where
@@ -10,6 +10,8 @@ where
     match token.next() {
         Some(Token::Table) => parse_drop_table_clause(token),
         Some(Token::Index) => parse_drop_index_clause(token),
+        Some(Token::Sequence) => Ok(Command::DropSequence { sequence_name: parse_sequence_name(token)? }),
+        Some(Token::User) => Ok(Command::DropUser { user_name: parse_user_name(token)? }),
         None => Err(ParserError::DropTypeMissing),
         Some(token) => Err(ParserError::DropTypeUnknown(token, "TABLE")),
     }
@@ -3,6 +3,7 @@ use std::fmt;
 
 use crate::parser::Token;
 use crate::lexer::LexerError;
+use crate::function::FunctionError;
 
 #[derive(Debug)]
 pub enum ParserError<'a> {
@@ -12,6 +13,13 @@ pub enum ParserError<'a> {
     ExcessTokens(Vec<&'a Token>),
     CreateTypeMissing,
     CreateTypeUnknown(&'a Token),
+    MacroNameInvalid(&'a Token),
+    MacroNameMissing,
+    MacroParameterInvalid(&'a Token),
+    MacroParameterMissing,
+    AsExpected(&'a Token),
+    AsMissing,
+    MacroBodyMissing,
     DropTypeMissing,
     DropTypeUnknown(&'a Token, &'static str),
     AddTypeMissing,
@@ -45,11 +53,14 @@ pub enum ParserError<'a> {
     ColumnTypeMissing,
     IndexNameInvalid(&'a Token),
     IndexNameMissing,
+    IndexKeywordExpected(&'a Token),
+    IndexKeywordMissing,
     ValuesKeywordMissing(&'a Token),
     InsertValuesMissing,
     ColumnValueMissing,
     ColumnValueInvalid(&'a Token),
     WhereExpected(&'a Token),
+    ConditionEndExpected(&'a Token),
     SelectColumnNamesInvalid(&'a Token),
     SelectColumnNamesNotFinished,
     LvalueMissing,
@@ -72,7 +83,89 @@ pub enum ParserError<'a> {
     InvalidConstraint(Vec<&'a Token>),
     NoConstraintsGiven,
     MultipleConstraintsGiven,
+    ConstraintNameInvalid(&'a Token),
+    ConstraintNameMissing,
+    CheckColumnMissing(String),
+    TableConstraintColumnUnknown { constraint_name: String, column_name: String },
     InvalidSchemaDefinition(String),
+    SwitchValueInvalid(&'a str),
+    SwitchValueMissing,
+    TableOptionUnknown(&'a Token),
+    TableOptionValueInvalid(&'a Token),
+    TableOptionValueMissing,
+    SequenceNameInvalid(&'a Token),
+    SequenceNameMissing,
+    ChangesColumnsInvalid,
+    SinceExpected(&'a Token),
+    SettingUnknown(&'a Token),
+    SettingMissing,
+    GarbageActionInvalid(&'a str),
+    OutputPathMissing,
+    OutputModeInvalid(&'a str),
+    OutputModeMissing,
+    SeriesArgumentInvalid(&'a Token),
+    SeriesArgumentMissing,
+    SeriesStepZero,
+    SeedArgumentsMissing,
+    CheckTypeMissing,
+    CheckTypeUnknown(&'a Token),
+    ShowTypeMissing,
+    ShowTypeUnknown(&'a Token),
+    SeedRowCountInvalid(&'a str),
+    HistoryFilePathMissing,
+    HistoryCountInvalid(&'a str),
+    ScriptPathMissing,
+    ReadOptionInvalid(&'a str),
+    PageDumpArgumentsMissing,
+    PageDumpNumberInvalid(&'a str),
+    SubquerySelectExpected(&'a Token),
+    SubquerySelectMissing,
+    UserNameInvalid(&'a Token),
+    UserNameMissing,
+    PrivilegeInvalid(&'a Token),
+    PrivilegeMissing,
+    GrantTableExpected(&'a Token),
+    GrantTableMissing,
+    GrantUserExpected(&'a Token, &'static str),
+    GrantUserMissing(&'static str),
+    FunctionUnknown(&'a str),
+    FunctionArgumentInvalid(&'a Token),
+    FunctionArgumentMissing,
+    FunctionError(FunctionError),
+    CursorNameInvalid(&'a Token),
+    CursorNameMissing,
+    CursorExpected(&'a Token),
+    CursorMissing,
+    ForExpected(&'a Token),
+    ForMissing,
+    CursorSelectExpected(&'a Token),
+    CursorSelectMissing,
+    FetchCountInvalid(&'a Token),
+    FetchCountMissing,
+    OrderByExpected(&'a Token),
+    OrderByMissing,
+    OffsetExpected(&'a Token),
+    IfNotExistsInvalid(&'a Token),
+    IfNotExistsMissing,
+    OrderingTermInvalid(&'a Token),
+    OrderingTermMissing,
+    NullsOrderInvalid(&'a Token),
+    NullsOrderMissing,
+    CopyDirectionExpected(&'a Token),
+    CopyDirectionMissing,
+    CopyToExpected(&'a Token),
+    CopyToMissing,
+    CopySelectExpected(&'a Token),
+    CopySelectMissing,
+    CopyFilePathInvalid(&'a Token),
+    CopyFilePathMissing,
+    ReturningColumnNamesInvalid(&'a Token),
+    ReturningAggregate,
+    OnConflictExpected(&'a Token),
+    ConflictMissing,
+    ConflictActionInvalid(&'a Token),
+    ConflictActionMissing,
+    ConflictUpdateWhereUnsupported,
 }
 
 impl<'a> fmt::Display for ParserError<'a> {
@@ -88,6 +181,13 @@ impl<'a> fmt::Display for ParserError<'a> {
             Self::CreateTypeMissing => "CREATE type is not provided".to_string(),
             Self::CreateTypeUnknown(create_type) =>
                 format!("unknown CREATE type '{}', consider using CREATE TABLE", create_type),
+            Self::MacroNameInvalid(name) => format!("{} is not a valid macro name", name),
+            Self::MacroNameMissing => "macro name is not provided".to_string(),
+            Self::MacroParameterInvalid(name) => format!("{} is not a valid macro parameter name", name),
+            Self::MacroParameterMissing => "macro parameter name is not provided".to_string(),
+            Self::AsExpected(token) => format!("expected AS and macro body, got {}", token),
+            Self::AsMissing => "expected AS and macro body".to_string(),
+            Self::MacroBodyMissing => "macro body is not provided".to_string(),
             Self::DropTypeMissing => "DROP type is not provided".to_string(),
             Self::DropTypeUnknown(drop_type, considered) =>
                 format!("unknown DROP type '{}', consider using DROP {}", drop_type, considered),
@@ -132,6 +232,8 @@ impl<'a> fmt::Display for ParserError<'a> {
             Self::ColumnNameMissing => "column name is not provided".to_string(),
             Self::IndexNameInvalid(name) => format!("{} is not a valid index name", name),
             Self::IndexNameMissing => "index name is not provided".to_string(),
+            Self::IndexKeywordExpected(token) => format!("expected INDEX after USE or IGNORE, got {}", token),
+            Self::IndexKeywordMissing => "expected INDEX after USE or IGNORE".to_string(),
             Self::ColumnTypeInvalid(name) => format!("{} is not a valid column type", name),
             Self::ColumnTypeMissing => "column type is not provided".to_string(),
             Self::ValuesKeywordMissing(token) => format!("expected VALUES keyword, got '{}'", token),
@@ -139,6 +241,7 @@ impl<'a> fmt::Display for ParserError<'a> {
             Self::ColumnValueMissing => "column value is not provided".to_string(),
             Self::ColumnValueInvalid(token) => format!("expected column value, got {}", token),
             Self::WhereExpected(token) => format!("expected WHERE or end of statement, got {}", token),
+            Self::ConditionEndExpected(token) => format!("expected AND, OR or end of statement, got {}", token),
             Self::SelectColumnNamesInvalid(token) => format!("column names list is not finished, expected ',' or 'FROM', got {}", token),
             Self::SelectColumnNamesNotFinished => "column names list is not finished, expected ',' or 'FROM'".to_string(),
             Self::LvalueInvalid(token) => format!("expected where left value or identifier, got {}", token),
@@ -160,10 +263,97 @@ impl<'a> fmt::Display for ParserError<'a> {
             Self::LexerError(lexer_error) => format!("{}", lexer_error),
             Self::MultipleConstraintsGiven => "only one constraint is allowed, but several were given".to_string(),
             Self::NoConstraintsGiven => "no constraints were given".to_string(),
+            Self::ConstraintNameInvalid(token) => format!("{} is not a valid constraint name", token),
+            Self::ConstraintNameMissing => "constraint name is not provided".to_string(),
+            Self::CheckColumnMissing(constraint_name) =>
+                format!("condition of constraint '{}' should reference at least one column", constraint_name),
+            Self::TableConstraintColumnUnknown { constraint_name, column_name } =>
+                format!("constraint '{}' references column '{}' which is not defined in table", constraint_name, column_name),
             Self::InvalidConstraint(tokens) =>
                 format!("cannot treat constraint sequence '{:?}'",
                         tokens.iter().map(|t| t.to_string()).collect::<Vec<String>>()),
             Self::InvalidSchemaDefinition(message) => format!("cannot parse schema definition: {}", message),
+            Self::SwitchValueInvalid(value) => format!("expected 'on' or 'off', got '{}'", value),
+            Self::SwitchValueMissing => "expected 'on' or 'off', got nothing".to_string(),
+            Self::TableOptionUnknown(token) => format!("unknown table option '{}'", token),
+            Self::TableOptionValueInvalid(token) =>
+                format!("'{}' is not a valid table option value, expected TRUE, FALSE, 1 or 0", token),
+            Self::TableOptionValueMissing => "table option value is not provided".to_string(),
+            Self::SequenceNameInvalid(name) => format!("{} is not a valid sequence name", name),
+            Self::SequenceNameMissing => "sequence name is not provided".to_string(),
+            Self::ChangesColumnsInvalid => "only '*' can be selected from changes()".to_string(),
+            Self::SinceExpected(token) => format!("expected SINCE or end of statement, got {}", token),
+            Self::SettingUnknown(token) => format!("unknown setting '{}', consider using SET statement_timeout or SET random_seed", token),
+            Self::SettingMissing => "setting name is not provided".to_string(),
+            Self::GarbageActionInvalid(value) => format!("expected 'delete', 'quarantine' or nothing, got '{}'", value),
+            Self::OutputPathMissing => "output file path is not provided".to_string(),
+            Self::OutputModeInvalid(value) => format!("expected 'table' or 'insert', got '{}'", value),
+            Self::OutputModeMissing => "output mode is not provided".to_string(),
+            Self::SeriesArgumentInvalid(token) => format!("expected integer argument of generate_series(), got {}", token),
+            Self::SeriesArgumentMissing => "generate_series() expects start, stop and optional step arguments".to_string(),
+            Self::SeriesStepZero => "generate_series() step cannot be 0".to_string(),
+            Self::CheckTypeMissing => "expected TABLE after CHECK".to_string(),
+            Self::CheckTypeUnknown(token) => format!("expected TABLE after CHECK, got '{}'", token),
+            Self::ShowTypeMissing => "expected STORAGE after SHOW".to_string(),
+            Self::ShowTypeUnknown(token) => format!("expected STORAGE after SHOW, got '{}'", token),
+            Self::SeedArgumentsMissing => "expected table name and row count, e.g. `.seed users 1000`".to_string(),
+            Self::SeedRowCountInvalid(value) => format!("expected row count, got '{}'", value),
+            Self::HistoryFilePathMissing => "expected history file path, e.g. `.history file yarrd_history`".to_string(),
+            Self::SubquerySelectExpected(token) => format!("expected SELECT in EXISTS subquery, got {}", token),
+            Self::SubquerySelectMissing => "expected SELECT in EXISTS subquery, got nothing".to_string(),
+            Self::UserNameInvalid(name) => format!("{} is not a valid user name", name),
+            Self::UserNameMissing => "user name is not provided".to_string(),
+            Self::PrivilegeInvalid(token) => format!("expected SELECT, INSERT, UPDATE or DELETE privilege, got {}", token),
+            Self::PrivilegeMissing => "expected SELECT, INSERT, UPDATE or DELETE privilege, got nothing".to_string(),
+            Self::GrantTableExpected(token) => format!("expected ON table name after privileges, got {}", token),
+            Self::GrantTableMissing => "expected ON table name after privileges, got nothing".to_string(),
+            Self::GrantUserExpected(token, keyword) => format!("expected {} user name after table name, got {}", keyword, token),
+            Self::GrantUserMissing(keyword) => format!("expected {} user name after table name, got nothing", keyword),
+            Self::HistoryCountInvalid(value) => format!("expected number of statements or `file`, got '{}'", value),
+            Self::ScriptPathMissing => "script file path is not provided".to_string(),
+            Self::ReadOptionInvalid(option) => format!("unknown option '{}', only `--continue-on-error` is supported", option),
+            Self::PageDumpArgumentsMissing =>
+                "expected table name and page id, or table name, index name and bucket number, e.g. `.pagedump users 3`".to_string(),
+            Self::PageDumpNumberInvalid(value) => format!("expected page id or bucket number, got '{}'", value),
+            Self::FunctionUnknown(name) => format!("unknown function '{}', see README for the list of supported functions", name),
+            Self::FunctionArgumentInvalid(token) => format!("expected function argument, got {}", token),
+            Self::FunctionArgumentMissing => "function argument is not provided".to_string(),
+            Self::FunctionError(function_error) => format!("{}", function_error),
+            Self::CursorNameInvalid(token) => format!("expected cursor name, got {}", token),
+            Self::CursorNameMissing => "cursor name is not provided".to_string(),
+            Self::CursorExpected(token) => format!("expected CURSOR after cursor name, got {}", token),
+            Self::CursorMissing => "expected CURSOR after cursor name, got nothing".to_string(),
+            Self::ForExpected(token) => format!("expected FOR after CURSOR, got {}", token),
+            Self::ForMissing => "expected FOR after CURSOR, got nothing".to_string(),
+            Self::CursorSelectExpected(token) => format!("cursor can be declared only for SELECT, got {}", token),
+            Self::CursorSelectMissing => "expected SELECT after CURSOR FOR, got nothing".to_string(),
+            Self::FetchCountInvalid(token) => format!("expected number of rows or ALL to fetch, got {}", token),
+            Self::FetchCountMissing => "number of rows to fetch is not provided".to_string(),
+            Self::OrderByExpected(token) => format!("expected BY after ORDER, got {}", token),
+            Self::OrderByMissing => "expected BY after ORDER, got nothing".to_string(),
+            Self::IfNotExistsInvalid(token) => format!("expected EXISTS after IF NOT, got {}", token),
+            Self::IfNotExistsMissing => "expected EXISTS after IF NOT, got nothing".to_string(),
+            Self::OffsetExpected(token) => format!("expected OFFSET or end of statement after LIMIT, got {}", token),
+            Self::OrderingTermInvalid(token) =>
+                format!("expected column name, optionally followed by ASC or DESC and NULLS FIRST or NULLS LAST, got {}", token),
+            Self::OrderingTermMissing => "expected column name to order by, got nothing".to_string(),
+            Self::NullsOrderInvalid(token) => format!("expected FIRST or LAST after NULLS, got {}", token),
+            Self::NullsOrderMissing => "expected FIRST or LAST after NULLS, got nothing".to_string(),
+            Self::CopyDirectionExpected(token) => format!("expected FROM or TO after table name, got {}", token),
+            Self::CopyDirectionMissing => "expected FROM or TO after table name, got nothing".to_string(),
+            Self::CopyToExpected(token) => format!("select can only be copied TO file, got {}", token),
+            Self::CopyToMissing => "expected TO after copied select, got nothing".to_string(),
+            Self::CopySelectExpected(token) => format!("only SELECT can be copied, got {}", token),
+            Self::CopySelectMissing => "expected SELECT after COPY (, got nothing".to_string(),
+            Self::CopyFilePathInvalid(token) => format!("expected quoted file path, got {}", token),
+            Self::CopyFilePathMissing => "file path is not provided".to_string(),
+            Self::ReturningColumnNamesInvalid(token) => format!("expected comma or end of statement after returned column, got {}", token),
+            Self::ReturningAggregate => "aggregates cannot be returned, only columns and function calls".to_string(),
+            Self::OnConflictExpected(token) => format!("expected ON CONFLICT or end of statement, got {}", token),
+            Self::ConflictMissing => "expected CONFLICT after ON, got nothing".to_string(),
+            Self::ConflictActionInvalid(token) => format!("expected DO NOTHING or DO UPDATE SET after conflict column, got {}", token),
+            Self::ConflictActionMissing => "expected DO NOTHING or DO UPDATE SET after conflict column, got nothing".to_string(),
+            Self::ConflictUpdateWhereUnsupported => "WHERE is not supported in DO UPDATE, conflicting rows are always updated".to_string(),
         };
 
         write!(f, "{}", message)
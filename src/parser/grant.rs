@@ -0,0 +1,58 @@
+use crate::access::Privilege;
+use crate::command::Command;
+use crate::lexer::{SqlValue, Token};
+use crate::parser::ParserError;
+use crate::parser::shared::{parse_table_name, parse_user_name};
+
+/// `GRANT privilege, ... ON table TO user`
+pub fn parse_grant_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let (privileges, table_name) = parse_privileges_on_table(&mut token)?;
+    match token.next() {
+        Some(Token::To) => Ok(Command::Grant { privileges, table_name, user_name: parse_user_name(&mut token)? }),
+        Some(token) => Err(ParserError::GrantUserExpected(token, "TO")),
+        None => Err(ParserError::GrantUserMissing("TO")),
+    }
+}
+
+/// `REVOKE privilege, ... ON table FROM user`
+pub fn parse_revoke_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let (privileges, table_name) = parse_privileges_on_table(&mut token)?;
+    match token.next() {
+        Some(Token::From) => Ok(Command::Revoke { privileges, table_name, user_name: parse_user_name(&mut token)? }),
+        Some(token) => Err(ParserError::GrantUserExpected(token, "FROM")),
+        None => Err(ParserError::GrantUserMissing("FROM")),
+    }
+}
+
+fn parse_privileges_on_table<'a, I>(mut token: I) -> Result<(Vec<Privilege>, SqlValue), ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut privileges = vec![];
+    loop {
+        let privilege = match token.next() {
+            Some(Token::Select) => Privilege::Select,
+            Some(Token::Insert) => Privilege::Insert,
+            Some(Token::Update) => Privilege::Update,
+            Some(Token::Delete) => Privilege::Delete,
+            Some(token) => return Err(ParserError::PrivilegeInvalid(token)),
+            None => return Err(ParserError::PrivilegeMissing),
+        };
+        privileges.push(privilege);
+
+        match token.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::On) => break,
+            Some(token) => return Err(ParserError::GrantTableExpected(token)),
+            None => return Err(ParserError::GrantTableMissing),
+        }
+    }
+
+    Ok((privileges, parse_table_name(&mut token)?))
+}
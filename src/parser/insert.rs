@@ -1,8 +1,13 @@
-use crate::command::Command;
+use crate::command::{Command, ConflictAction, InsertValue};
 use crate::lexer::{Token, SqlValue};
 use crate::parser::error::ParserError;
-use crate::parser::shared::{parse_table_name, parse_column_value, parse_left_parenthesis,
-    parse_csl_right_parenthesis, parse_parenthesised_cs_column_names};
+use crate::parser::shared::{parse_table_name, parse_sequence_name, parse_left_parenthesis, parse_right_parenthesis,
+    parse_csl_right_parenthesis, parse_parenthesised_cs_column_names, parse_column_name};
+use crate::parser::update::parse_field_assignments;
+
+const CONFLICT: &str = "conflict";
+const DO: &str = "do";
+const NOTHING: &str = "nothing";
 
 pub fn parse_insert_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
 where
@@ -22,11 +27,49 @@ where
     let table_name = parse_table_name(&mut token)?;
     let column_names = parse_parenthesised_cs_column_names(&mut token)?;
     let values = parse_values_expression(&mut token)?;
+    let insert = Command::InsertInto { table_name, column_names: Some(column_names), values };
 
-    Ok(Command::InsertInto { table_name, column_names: Some(column_names), values })
+    match token.next() {
+        Some(Token::On) => parse_on_conflict(insert, token),
+        Some(token) => Err(ParserError::OnConflictExpected(token)),
+        None => Ok(insert),
+    }
 }
 
-fn parse_values_expression<'a, I>(mut token: I) -> Result<Vec<SqlValue>, ParserError<'a>>
+/// on_conflict = ON CONFLICT ( column_name ) DO (NOTHING | UPDATE SET field_assignments)
+fn parse_on_conflict<'a, I>(insert: Command, mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(CONFLICT) => {},
+        Some(token) => return Err(ParserError::OnConflictExpected(token)),
+        None => return Err(ParserError::ConflictMissing),
+    }
+    parse_left_parenthesis(&mut token, "conflict column")?;
+    let conflict_column_name = parse_column_name(&mut token)?;
+    parse_right_parenthesis(&mut token, "conflict column")?;
+
+    match token.next() {
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(DO) => {},
+        Some(token) => return Err(ParserError::ConflictActionInvalid(token)),
+        None => return Err(ParserError::ConflictActionMissing),
+    }
+    let action = match token.next() {
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(NOTHING) => ConflictAction::Nothing,
+        Some(Token::Update) => {
+            let (field_assignments, where_provided) = parse_field_assignments(&mut token)?;
+            if where_provided { return Err(ParserError::ConflictUpdateWhereUnsupported) }
+            ConflictAction::Update(field_assignments)
+        },
+        Some(token) => return Err(ParserError::ConflictActionInvalid(token)),
+        None => return Err(ParserError::ConflictActionMissing),
+    };
+
+    Ok(Command::Upsert { insert: Box::new(insert), conflict_column_name, action })
+}
+
+fn parse_values_expression<'a, I>(mut token: I) -> Result<Vec<InsertValue>, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
@@ -41,7 +84,7 @@ where
     parse_left_parenthesis(&mut token, "column values")?;
 
     loop {
-        let value = parse_column_value(&mut token)?;
+        let value = parse_insert_value(&mut token)?;
         values.push(value);
 
         match parse_csl_right_parenthesis(&mut token, "column values")? {
@@ -52,3 +95,25 @@ where
 
     Ok(values)
 }
+
+fn parse_insert_value<'a, I>(mut token: I) -> Result<InsertValue, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(value)) => Ok(InsertValue::Value(value.clone())),
+        Some(Token::NextVal) => {
+            parse_left_parenthesis(&mut token, "NEXTVAL argument")?;
+            let sequence_name = parse_sequence_name(&mut token)?;
+            parse_right_parenthesis(&mut token, "NEXTVAL argument")?;
+            Ok(InsertValue::NextVal(sequence_name))
+        },
+        Some(Token::Random) => {
+            parse_left_parenthesis(&mut token, "RANDOM arguments")?;
+            parse_right_parenthesis(&mut token, "RANDOM arguments")?;
+            Ok(InsertValue::Random)
+        },
+        Some(token) => Err(ParserError::ColumnValueInvalid(token)),
+        None => Err(ParserError::ColumnValueMissing),
+    }
+}
@@ -1,41 +1,228 @@
-use crate::command::{Command, SelectColumnName};
-use crate::lexer::Token;
+use std::iter::Peekable;
+
+use crate::command::{Command, IndexHint, SelectColumnName, NullsOrder, OrderingKey, OrderingTerm};
+use crate::lexer::{Token, SqlValue};
 use crate::parser::where_clause::parse_where_clause;
 use crate::parser::error::ParserError;
-use crate::parser::shared::parse_table_name;
+use crate::parser::shared::{parse_function_call, parse_index_name, parse_table_name, position_outside_parentheses, parse_left_parenthesis, parse_right_parenthesis, parse_csl_right_parenthesis};
+use crate::parser::parse_int;
+use crate::series::SERIES_FUNCTION_NAME;
+
+const CHANGES_FUNCTION_NAME: &str = "changes";
+const COUNT_FUNCTION_NAME: &str = "count";
+const ASC: &str = "asc";
+const DESC: &str = "desc";
+const NULLS: &str = "nulls";
+const FIRST: &str = "first";
+const LAST: &str = "last";
+const USE: &str = "use";
+const IGNORE: &str = "ignore";
+
+/// select_statement = ordered_select [LIMIT integer] [OFFSET integer]
+pub fn parse_select_statement<'a, I>(token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let tokens: Vec<&Token> = token.collect();
+    let limit_position = [Token::Limit, Token::Offset].iter()
+        .filter_map(|keyword| position_outside_parentheses(&tokens, keyword))
+        .min();
+    let limit_position = match limit_position {
+        Some(limit_position) => limit_position,
+        None => return parse_ordered_select(tokens.into_iter()),
+    };
+
+    let select = parse_ordered_select(tokens[..limit_position].iter().copied())?;
+    let mut token = tokens[limit_position..].iter().copied().peekable();
+    let limit = match token.next_if_eq(&&Token::Limit) {
+        Some(_) => Some(parse_int(&mut token)?),
+        None => None,
+    };
+    let offset = match token.next() {
+        Some(Token::Offset) => parse_int(&mut token)?,
+        Some(token) => return Err(ParserError::OffsetExpected(token)),
+        None => 0,
+    };
+    let remainder: Vec<&Token> = token.collect();
+    if !remainder.is_empty() {
+        return Err(ParserError::ExcessTokens(remainder))
+    }
+
+    Ok(Command::SelectLimited { select: Box::new(select), limit, offset })
+}
+
+/// ordered_select = unordered_select [ORDER BY ordering_term {, ordering_term}]
+fn parse_ordered_select<'a, I>(token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let tokens: Vec<&Token> = token.collect();
+    // ORDER inside of subquery parentheses belongs to subquery
+    let order_position = match position_outside_parentheses(&tokens, &Token::Order) {
+        Some(order_position) => order_position,
+        None => return parse_unordered_select(tokens.into_iter()),
+    };
+    let select = parse_unordered_select(tokens[..order_position].iter().copied())?;
+    let mut token = tokens[order_position + 1..].iter().copied();
+    match token.next() {
+        Some(Token::By) => {},
+        Some(token) => return Err(ParserError::OrderByExpected(token)),
+        None => return Err(ParserError::OrderByMissing),
+    }
+
+    let order_by = parse_ordering_terms(token)?;
+    Ok(Command::SelectOrdered { select: Box::new(select), order_by })
+}
+
+/// ordering_term = (column_name | RANDOM()) [ASC | DESC] [NULLS (FIRST | LAST)]
+fn parse_ordering_terms<'a, I>(token: I) -> Result<Vec<OrderingTerm>, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut token = token.peekable();
+    let mut order_by = vec![];
+    loop {
+        let key = match token.next() {
+            Some(Token::Value(name @ SqlValue::Identificator(_))) => OrderingKey::Column(name.clone()),
+            Some(Token::Random) => {
+                parse_left_parenthesis(&mut token, "RANDOM arguments")?;
+                parse_right_parenthesis(&mut token, "RANDOM arguments")?;
+                OrderingKey::Random
+            },
+            Some(token) => return Err(ParserError::OrderingTermInvalid(token)),
+            None => return Err(ParserError::OrderingTermMissing),
+        };
+
+        let descending = match token.peek() {
+            Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(ASC) => { token.next(); false },
+            Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(DESC) => { token.next(); true },
+            _ => false,
+        };
+
+        let nulls = match token.peek() {
+            Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(NULLS) => {
+                token.next();
+                match token.next() {
+                    Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(FIRST) => NullsOrder::First,
+                    Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(LAST) => NullsOrder::Last,
+                    Some(token) => return Err(ParserError::NullsOrderInvalid(token)),
+                    None => return Err(ParserError::NullsOrderMissing),
+                }
+            },
+            // NULL is greater than any value, as in PostgreSQL
+            _ if descending => NullsOrder::First,
+            _ => NullsOrder::Last,
+        };
+        order_by.push(OrderingTerm { key, descending, nulls });
+
+        match token.next() {
+            Some(Token::Comma) => {},
+            Some(token) => return Err(ParserError::OrderingTermInvalid(token)),
+            None => return Ok(order_by),
+        }
+    }
+}
 
-pub fn parse_select_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+/// unordered_select = SELECT column_names FROM table_name [index_hint] [WHERE condition] | ...
+fn parse_unordered_select<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
     let column_names = parse_column_names(&mut token)?;
     let table_name = parse_table_name(&mut token)?;
+    let mut token = token.peekable();
 
-    match token.next() {
+    if table_name == SqlValue::Identificator(CHANGES_FUNCTION_NAME.to_string()) &&
+        token.peek() == Some(&&Token::LeftParenthesis) {
+        return parse_select_changes(column_names, token)
+    }
+
+    if table_name == SqlValue::Identificator(SERIES_FUNCTION_NAME.to_string()) &&
+        token.peek() == Some(&&Token::LeftParenthesis) {
+        return parse_select_series(column_names, token)
+    }
+
+    if token.peek() == Some(&&Token::Comma) {
+        return parse_select_cross_join(column_names, table_name, token)
+    }
+
+    let index_hint = parse_index_hint(&mut token)?;
+    let select = match token.next() {
         Some(Token::Where) => {
             let where_clause = parse_where_clause(token)?;
-            Ok(Command::Select { column_names, table_name, where_clause: Some(where_clause) })
+            Command::Select { column_names, table_name, where_clause: Some(where_clause) }
         },
-        Some(token) => Err(ParserError::WhereExpected(token)),
-        None => Ok(Command::Select { column_names, table_name, where_clause: None })
+        Some(token) => return Err(ParserError::WhereExpected(token)),
+        None => Command::Select { column_names, table_name, where_clause: None }
+    };
+
+    match index_hint {
+        Some(index_hint) => Ok(Command::SelectIndexHinted { select: Box::new(select), index_hint }),
+        None => Ok(select),
     }
 }
 
-fn parse_column_names<'a, I>(mut token: I) -> Result<Vec<SelectColumnName>, ParserError<'a>>
+/// index_hint = (USE | IGNORE) INDEX (index_name {, index_name}).
+/// USE and IGNORE are not keywords, so they still can be used as names.
+fn parse_index_hint<'a, I>(token: &mut Peekable<I>) -> Result<Option<IndexHint>, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
-    let mut columns = vec![];
+    let is_use = match token.peek() {
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(USE) => true,
+        Some(Token::Value(SqlValue::Identificator(name))) if name.eq_ignore_ascii_case(IGNORE) => false,
+        _ => return Ok(None),
+    };
+    token.next();
+    match token.next() {
+        Some(Token::Index) => {},
+        Some(token) => return Err(ParserError::IndexKeywordExpected(token)),
+        None => return Err(ParserError::IndexKeywordMissing),
+    }
 
+    parse_left_parenthesis(&mut *token, "index names")?;
+    let mut index_names = vec![];
     loop {
-        let name = match token.next() {
-            Some(Token::AllColumns) => SelectColumnName::AllColumns,
-            Some(Token::Value(name)) => SelectColumnName::Name(name.clone()),
-            Some(token) => return Err(ParserError::ColumnNameInvalid(token)),
-            None => return Err(ParserError::ColumnNameMissing),
-        };
+        index_names.push(parse_index_name(&mut *token)?.to_string());
+        if parse_csl_right_parenthesis(&mut *token, "index names")? {
+            break
+        }
+    }
 
-        columns.push(name);
+    match is_use {
+        true => Ok(Some(IndexHint::Use(index_names))),
+        false => Ok(Some(IndexHint::Ignore(index_names))),
+    }
+}
+
+fn parse_select_cross_join<'a, I>(column_names: Vec<SelectColumnName>, table_name: SqlValue, mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut table_names = vec![table_name];
+    loop {
+        match token.next() {
+            Some(Token::Comma) => table_names.push(parse_table_name(&mut token)?),
+            Some(Token::Where) => {
+                let where_clause = parse_where_clause(token)?;
+                return Ok(Command::SelectCrossJoin { table_names, column_names, where_clause: Some(where_clause) })
+            },
+            Some(token) => return Err(ParserError::WhereExpected(token)),
+            None => return Ok(Command::SelectCrossJoin { table_names, column_names, where_clause: None }),
+        }
+    }
+}
+
+pub fn parse_column_names<'a, I>(token: I) -> Result<Vec<SelectColumnName>, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut columns = vec![];
+    // peeked token is always consumed before FROM, so no tokens after it are lost
+    let mut token = token.peekable();
+
+    loop {
+        columns.push(parse_select_column_name(&mut token)?);
 
         match token.next() {
             Some(Token::From) => break,
@@ -47,3 +234,134 @@ where
 
     Ok(columns)
 }
+
+/// returning_clause = RETURNING column_name {, column_name}, aggregates are not allowed
+pub fn parse_returning_column_names<'a, I>(token: I) -> Result<Vec<SelectColumnName>, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut columns = vec![];
+    let mut token = token.peekable();
+
+    loop {
+        let column_name = parse_select_column_name(&mut token)?;
+        if column_name.is_aggregate() {
+            return Err(ParserError::ReturningAggregate)
+        }
+        columns.push(column_name);
+
+        match token.next() {
+            Some(Token::Comma) => { },
+            Some(token) => return Err(ParserError::ReturningColumnNamesInvalid(token)),
+            None => return Ok(columns),
+        };
+    }
+}
+
+fn parse_select_column_name<'a, I>(token: &mut Peekable<I>) -> Result<SelectColumnName, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let name = match token.next() {
+        Some(Token::AllColumns) => SelectColumnName::AllColumns,
+        // lexer splits `table.*` into `table.` identifier and `*`
+        Some(Token::Value(SqlValue::Identificator(name))) if name.ends_with('.') => {
+            match token.next() {
+                Some(Token::AllColumns) =>
+                    SelectColumnName::TableAllColumns(SqlValue::Identificator(name.trim_end_matches('.').to_string())),
+                Some(token) => return Err(ParserError::ColumnNameInvalid(token)),
+                None => return Err(ParserError::ColumnNameMissing),
+            }
+        },
+        Some(Token::Value(SqlValue::Identificator(name)))
+            if name.eq_ignore_ascii_case(COUNT_FUNCTION_NAME) && token.peek() == Some(&&Token::LeftParenthesis) => {
+            parse_count(&mut *token)?
+        },
+        Some(Token::Value(name)) => SelectColumnName::Name(parse_function_call(name, &mut *token)?),
+        Some(token) => return Err(ParserError::ColumnNameInvalid(token)),
+        None => return Err(ParserError::ColumnNameMissing),
+    };
+
+    Ok(name)
+}
+
+fn parse_count<'a, I>(mut token: I) -> Result<SelectColumnName, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    parse_left_parenthesis(&mut token, "COUNT argument")?;
+    let count = match token.next() {
+        Some(Token::AllColumns) => SelectColumnName::CountAll,
+        Some(Token::Value(name @ SqlValue::Identificator(_))) => SelectColumnName::Count(name.clone()),
+        Some(token) => return Err(ParserError::ColumnNameInvalid(token)),
+        None => return Err(ParserError::ColumnNameMissing),
+    };
+    parse_right_parenthesis(&mut token, "COUNT argument")?;
+
+    Ok(count)
+}
+
+fn parse_select_series<'a, I>(column_names: Vec<SelectColumnName>, mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    parse_left_parenthesis(&mut token, "generate_series arguments")?;
+    let start = parse_series_argument(&mut token)?;
+    match token.next() {
+        Some(Token::Comma) => { },
+        Some(token) => return Err(ParserError::SeriesArgumentInvalid(token)),
+        None => return Err(ParserError::SeriesArgumentMissing),
+    }
+    let stop = parse_series_argument(&mut token)?;
+    let step = match parse_csl_right_parenthesis(&mut token, "generate_series arguments")? {
+        true => 1,
+        false => {
+            let step = parse_series_argument(&mut token)?;
+            parse_right_parenthesis(&mut token, "generate_series arguments")?;
+            step
+        },
+    };
+
+    if step == 0 { return Err(ParserError::SeriesStepZero) }
+
+    let where_clause = match token.next() {
+        Some(Token::Where) => Some(parse_where_clause(token)?),
+        Some(token) => return Err(ParserError::WhereExpected(token)),
+        None => None,
+    };
+
+    Ok(Command::SelectSeries { column_names, start, stop, step, where_clause })
+}
+
+fn parse_series_argument<'a, I>(mut token: I) -> Result<i64, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(SqlValue::Integer(value))) => Ok(*value),
+        Some(token) => Err(ParserError::SeriesArgumentInvalid(token)),
+        None => Err(ParserError::SeriesArgumentMissing),
+    }
+}
+
+fn parse_select_changes<'a, I>(column_names: Vec<SelectColumnName>, mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    if !matches!(column_names[..], [SelectColumnName::AllColumns]) {
+        return Err(ParserError::ChangesColumnsInvalid)
+    }
+
+    parse_left_parenthesis(&mut token, "changes table name")?;
+    let table_name = parse_table_name(&mut token)?;
+    parse_right_parenthesis(&mut token, "changes table name")?;
+
+    match token.next() {
+        Some(Token::Since) => {
+            let since_lsn = parse_int(&mut token)? as u64;
+            Ok(Command::SelectChanges { table_name, since_lsn })
+        },
+        Some(token) => Err(ParserError::SinceExpected(token)),
+        None => Ok(Command::SelectChanges { table_name, since_lsn: 0 }),
+    }
+}
@@ -0,0 +1,31 @@
+use crate::command::Command;
+use crate::lexer::{Token, SqlValue};
+use crate::parser::ParserError;
+use crate::parser::parse_int;
+
+const STATEMENT_TIMEOUT_SETTING: &str = "statement_timeout";
+const RANDOM_SEED_SETTING: &str = "random_seed";
+
+pub fn parse_set_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let setting_name = match token.next() {
+        Some(Token::Value(SqlValue::Identificator(name)))
+            if name == STATEMENT_TIMEOUT_SETTING || name == RANDOM_SEED_SETTING => name,
+        Some(token) => return Err(ParserError::SettingUnknown(token)),
+        None => return Err(ParserError::SettingMissing),
+    };
+
+    match token.next() {
+        Some(Token::Equals) => { },
+        Some(token) => return Err(ParserError::EqualsExpected(token)),
+        None => return Err(ParserError::EqualsMissing)
+    }
+
+    let value = parse_int(&mut token)? as u64;
+    match setting_name.as_str() {
+        RANDOM_SEED_SETTING => Ok(Command::SetRandomSeed { seed: value }),
+        _ => Ok(Command::SetStatementTimeout { milliseconds: value }),
+    }
+}
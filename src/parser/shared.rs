@@ -1,10 +1,27 @@
+use std::iter::Peekable;
+
 use crate::parser::error::ParserError;
 use crate::lexer::{SqlValue, Token};
 use crate::command::ColumnDefinition;
 use crate::table::ColumnType;
-use crate::table::Constraint;
+use crate::table::{Constraint, ForeignKey};
 use crate::cmp_operator::CmpOperator;
 use crate::binary_condition::BinaryCondition;
+use crate::function::{FunctionCall, ScalarFunction};
+
+/// Position of `keyword` which is not inside of parentheses, e.g. not in a subquery
+pub fn position_outside_parentheses(tokens: &[&Token], keyword: &Token) -> Option<usize> {
+    let mut depth = 0;
+    tokens.iter().position(|token| {
+        match token {
+            Token::LeftParenthesis => depth += 1,
+            Token::RightParenthesis => depth -= 1,
+            token if *token == keyword => return depth == 0,
+            _ => {},
+        }
+        false
+    })
+}
 
 pub fn parse_table_name<'a, I>(mut token: I) -> Result<SqlValue, ParserError<'a>>
 where
@@ -28,6 +45,17 @@ where
     }
 }
 
+pub fn parse_user_name<'a, I>(mut token: I) -> Result<SqlValue, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(name)) => Ok(name.clone()),
+        Some(token) => Err(ParserError::UserNameInvalid(token)),
+        None => Err(ParserError::UserNameMissing),
+    }
+}
+
 // TODO: use parse_entity_name method or something
 pub fn parse_index_name<'a, I>(mut token: I) -> Result<SqlValue, ParserError<'a>>
 where
@@ -40,6 +68,29 @@ where
     }
 }
 
+pub fn parse_constraint_name<'a, I>(mut token: I) -> Result<SqlValue, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Value(name @ SqlValue::Identificator(_))) => Ok(name.clone()),
+        Some(token) => Err(ParserError::ConstraintNameInvalid(token)),
+        None => Err(ParserError::ConstraintNameMissing),
+    }
+}
+
+pub fn parse_sequence_name<'a, I>(mut token: I) -> Result<SqlValue, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    // sequence is stored in a file named after it, so name cannot be a path like `..`
+    match token.next() {
+        Some(Token::Value(name @ SqlValue::Identificator(string))) if !string.contains('.') => Ok(name.clone()),
+        Some(token) => Err(ParserError::SequenceNameInvalid(token)),
+        None => Err(ParserError::SequenceNameMissing),
+    }
+}
+
 pub fn parse_column_type<'a, I>(mut token: I) -> Result<ColumnType, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
@@ -131,7 +182,7 @@ where
         Ok((ColumnDefinition { name, kind, column_constraints }, last_token))
 }
 
-fn collect_constraint_tokens<'a, I>(mut token: I) -> Result<(Vec<&'a Token>, Option<Token>), ParserError<'a>>
+pub fn collect_constraint_tokens<'a, I>(mut token: I) -> Result<(Vec<&'a Token>, Option<Token>), ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
@@ -151,7 +202,8 @@ where
                     tokens.push(&Token::RightParenthesis);
                 }
             },
-            Some(Token::Comma) => return Ok((tokens, Some(Token::Comma))),
+            // commas inside parenthesis separate function arguments of check constraint
+            Some(Token::Comma) if parenthesis_depth == 0 => return Ok((tokens, Some(Token::Comma))),
             Some(Token::Semicolon) => return Ok((tokens, Some(Token::Semicolon))),
             Some(token) => tokens.push(token),
             None => return Ok((tokens, None)),
@@ -160,7 +212,7 @@ where
 }
 
 pub fn parse_constraint_tokens(tokens: Vec<&Token>) -> Result<Vec<Constraint>, ParserError> {
-    let mut iter = tokens.clone().into_iter();
+    let mut iter = tokens.clone().into_iter().peekable();
     let mut result = vec![];
 
     loop {
@@ -177,12 +229,21 @@ pub fn parse_constraint_tokens(tokens: Vec<&Token>) -> Result<Vec<Constraint>, P
                     _ => return Err(ParserError::InvalidConstraint(tokens)),
                 }
             },
-            Some(Token::Check) => {
-                parse_left_parenthesis(&mut iter, "check constraint definition")?;
-                let condition = parse_binary_condition(&mut iter)?;
-                result.push(Constraint::Check(condition));
-                parse_right_parenthesis(&mut iter, "check constraint definition")?;
-            }
+            Some(Token::Check) => result.push(Constraint::Check(parse_check_condition(&mut iter)?)),
+            Some(Token::Constraint) => {
+                let constraint_name = parse_constraint_name(&mut iter)?.to_string();
+                match iter.next() {
+                    Some(Token::Check) => result.push(Constraint::NamedCheck(constraint_name, parse_check_condition(&mut iter)?)),
+                    _ => return Err(ParserError::InvalidConstraint(tokens)),
+                }
+            },
+            Some(Token::References) => {
+                let table_name = parse_table_name(&mut iter)?.to_string();
+                parse_left_parenthesis(&mut iter, "referenced column")?;
+                let column_name = parse_column_name(&mut iter)?.to_string();
+                parse_right_parenthesis(&mut iter, "referenced column")?;
+                result.push(Constraint::References(ForeignKey { table_name, column_name }));
+            },
             None => break,
             _ => return Err(ParserError::InvalidConstraint(tokens)),
         }
@@ -191,16 +252,60 @@ pub fn parse_constraint_tokens(tokens: Vec<&Token>) -> Result<Vec<Constraint>, P
     Ok(result)
 }
 
-pub fn parse_binary_condition<'a, I>(mut token: I) -> Result<BinaryCondition, ParserError<'a>>
+/// table_constraint = CONSTRAINT constraint_name CHECK ( binary_condition )
+///
+/// Table constraint is kept with the first column referenced by its condition, the column
+/// is returned along with constraint.
+pub fn parse_table_constraint_tokens(tokens: Vec<&Token>) -> Result<(SqlValue, Constraint), ParserError<'_>> {
+    let mut constraints = parse_constraint_tokens(tokens.clone())?;
+    if constraints.len() > 1 {
+        return Err(ParserError::MultipleConstraintsGiven)
+    }
+
+    match constraints.pop() {
+        Some(Constraint::NamedCheck(constraint_name, condition)) => {
+            let column_name = condition.left_value.operands().into_iter()
+                .chain(condition.right_value.operands())
+                .find(|value| matches!(value, SqlValue::Identificator(_)))
+                .cloned();
+            match column_name {
+                Some(column_name) => Ok((column_name, Constraint::NamedCheck(constraint_name, condition))),
+                None => Err(ParserError::CheckColumnMissing(constraint_name)),
+            }
+        },
+        _ => Err(ParserError::InvalidConstraint(tokens)),
+    }
+}
+
+fn parse_check_condition<'a, I>(token: &mut Peekable<I>) -> Result<BinaryCondition, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    parse_left_parenthesis(&mut *token, "check constraint definition")?;
+    let condition = parse_binary_condition(token)?;
+    parse_right_parenthesis(&mut *token, "check constraint definition")?;
+    Ok(condition)
+}
+
+pub fn parse_binary_condition<'a, I>(token: &mut Peekable<I>) -> Result<BinaryCondition, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
     let left_value = match token.next() {
-        Some(Token::Value(sql_value)) => sql_value.clone(),
+        Some(Token::Value(sql_value)) => parse_function_call(sql_value, token)?,
         Some(token) => return Err(ParserError::LvalueInvalid(token)),
         None => return Err(ParserError::LvalueMissing),
     };
 
+    parse_comparison(left_value, token)
+}
+
+/// Parses operator and right value of binary condition which left value is already parsed
+pub fn parse_comparison<'a, I>(left_value: SqlValue, token: &mut Peekable<I>) -> Result<BinaryCondition, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+
     let operator = match token.next() {
         Some(Token::Equals) => CmpOperator::Equals,
         Some(Token::NotEquals) => CmpOperator::NotEquals,
@@ -219,7 +324,7 @@ where
         Some(Token::Value(sql_value)) => {
             match operator {
                 CmpOperator::IsNull => return Err(ParserError::RvalueNotNull(next_token.unwrap())),
-                _ => sql_value.clone(),
+                _ => parse_function_call(sql_value, token)?,
             }
         },
         Some(token) => return Err(ParserError::RvalueInvalid(token)),
@@ -228,3 +333,31 @@ where
 
     Ok(BinaryCondition { left_value, right_value, operator })
 }
+
+/// Identifier followed by `(` is a function call, its arguments are values or other function calls:
+/// function_call = function_name ( value [, value ...] ). Other values are returned as they are.
+pub fn parse_function_call<'a, I>(value: &'a SqlValue, token: &mut Peekable<I>) -> Result<SqlValue, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let function_name = match value {
+        SqlValue::Identificator(name) if token.peek() == Some(&&Token::LeftParenthesis) => name,
+        _ => return Ok(value.clone()),
+    };
+    let function = ScalarFunction::from_name(function_name).ok_or(ParserError::FunctionUnknown(function_name))?;
+    token.next();
+
+    let mut arguments = vec![];
+    loop {
+        match token.next() {
+            Some(Token::Value(argument)) => arguments.push(parse_function_call(argument, token)?),
+            Some(token) => return Err(ParserError::FunctionArgumentInvalid(token)),
+            None => return Err(ParserError::FunctionArgumentMissing),
+        }
+
+        if parse_csl_right_parenthesis(&mut *token, "function arguments")? { break }
+    }
+
+    function.check_argument_count(arguments.len()).map_err(ParserError::FunctionError)?;
+    Ok(SqlValue::Function(Box::new(FunctionCall { function, arguments })))
+}
@@ -0,0 +1,15 @@
+use crate::command::Command;
+use crate::lexer::Token;
+use crate::parser::ParserError;
+use crate::parser::shared::parse_table_name;
+
+pub fn parse_show_statement<'a, I>(mut token: I) -> Result<Command, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    match token.next() {
+        Some(Token::Storage) => Ok(Command::ShowStorage { table_name: parse_table_name(&mut token)? }),
+        None => Err(ParserError::ShowTypeMissing),
+        Some(token) => Err(ParserError::ShowTypeUnknown(token)),
+    }
+}
@@ -20,7 +20,7 @@ where
     Ok(Command::Update { table_name, field_assignments, where_clause })
 }
 
-fn parse_field_assignments<'a, I>(mut token: I) -> Result<(Vec<FieldAssignment>, bool), ParserError<'a>>
+pub fn parse_field_assignments<'a, I>(mut token: I) -> Result<(Vec<FieldAssignment>, bool), ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
@@ -1,11 +1,136 @@
-use crate::binary_condition::BinaryCondition;
-use crate::lexer::Token;
+use std::iter::Peekable;
+
+use crate::condition::Condition;
+use crate::lexer::{SqlValue, Token};
 use crate::parser::error::ParserError;
-use crate::parser::shared::parse_binary_condition;
+use crate::parser::select::parse_column_names;
+use crate::parser::shared::{parse_comparison, parse_function_call, parse_table_name, parse_left_parenthesis, parse_right_parenthesis, parse_csl_right_parenthesis};
+
+// conditions are parsed with usual precedence, NOT binds tighter than AND, and AND binds tighter than OR:
+// condition = and_condition [OR and_condition ...]
+// and_condition = operand [AND operand ...]
+// operand = NOT operand | ( condition ) | EXISTS ( subquery ) | value [NOT] IN ( value [, value ...] ) | binary_condition
+// value = literal | column_name | function_name ( value [, value ...] )
+// subquery = SELECT column_names FROM table_name [WHERE condition]
+pub fn parse_where_clause<'a, I>(token: I) -> Result<Condition, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut token = token.peekable();
+    let condition = parse_condition(&mut token)?;
+
+    match token.next() {
+        Some(token) => Err(ParserError::ConditionEndExpected(token)),
+        None => Ok(condition),
+    }
+}
+
+fn parse_condition<'a, I>(token: &mut Peekable<I>) -> Result<Condition, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut condition = parse_and_condition(token)?;
+    while token.next_if_eq(&&Token::Or).is_some() {
+        condition = Condition::or(condition, parse_and_condition(token)?);
+    }
+
+    Ok(condition)
+}
+
+fn parse_and_condition<'a, I>(token: &mut Peekable<I>) -> Result<Condition, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut condition = parse_operand(token)?;
+    while token.next_if_eq(&&Token::And).is_some() {
+        condition = Condition::and(condition, parse_operand(token)?);
+    }
 
-pub fn parse_where_clause<'a, I>(mut token: I) -> Result<BinaryCondition, ParserError<'a>>
+    Ok(condition)
+}
+
+fn parse_operand<'a, I>(token: &mut Peekable<I>) -> Result<Condition, ParserError<'a>>
 where
     I: Iterator<Item = &'a Token>
 {
-    parse_binary_condition(&mut token)
+    match token.peek() {
+        Some(Token::Not) => {
+            token.next();
+            Ok(Condition::negate(parse_operand(token)?))
+        },
+        Some(Token::LeftParenthesis) => {
+            token.next();
+            let condition = parse_condition(token)?;
+            parse_right_parenthesis(&mut *token, "where condition group")?;
+            Ok(condition)
+        },
+        Some(Token::Exists) => {
+            token.next();
+            parse_exists_subquery(token)
+        },
+        _ => {
+            let left_value = match token.next() {
+                Some(Token::Value(sql_value)) => parse_function_call(sql_value, token)?,
+                Some(token) => return Err(ParserError::LvalueInvalid(token)),
+                None => return Err(ParserError::LvalueMissing),
+            };
+            match token.peek() {
+                Some(Token::In) => {
+                    token.next();
+                    Ok(Condition::in_list(left_value, parse_in_list(token)?))
+                },
+                Some(Token::Not) => {
+                    token.next();
+                    match token.next() {
+                        Some(Token::In) => Ok(Condition::negate(Condition::in_list(left_value, parse_in_list(token)?))),
+                        Some(token) => Err(ParserError::OperatorInvalid(token)),
+                        None => Err(ParserError::OperatorMissing),
+                    }
+                },
+                _ => Ok(parse_comparison(left_value, token)?.into()),
+            }
+        },
+    }
+}
+
+fn parse_exists_subquery<'a, I>(token: &mut Peekable<I>) -> Result<Condition, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    parse_left_parenthesis(&mut *token, "EXISTS subquery")?;
+    match token.next() {
+        Some(Token::Select) => {},
+        Some(token) => return Err(ParserError::SubquerySelectExpected(token)),
+        None => return Err(ParserError::SubquerySelectMissing),
+    }
+
+    parse_column_names(&mut *token)?;
+    let table_name = parse_table_name(&mut *token)?;
+    let where_clause = match token.next_if_eq(&&Token::Where) {
+        Some(_) => Some(parse_condition(token)?),
+        None => None,
+    };
+    parse_right_parenthesis(&mut *token, "EXISTS subquery")?;
+
+    Ok(Condition::exists(table_name, where_clause))
+}
+
+fn parse_in_list<'a, I>(token: &mut Peekable<I>) -> Result<Vec<SqlValue>, ParserError<'a>>
+where
+    I: Iterator<Item = &'a Token>
+{
+    let mut list = vec![];
+    parse_left_parenthesis(&mut *token, "IN list")?;
+
+    loop {
+        match token.next() {
+            Some(Token::Value(sql_value)) => list.push(sql_value.clone()),
+            Some(token) => return Err(ParserError::ColumnValueInvalid(token)),
+            None => return Err(ParserError::ColumnValueMissing),
+        }
+
+        if parse_csl_right_parenthesis(&mut *token, "IN list")? { break }
+    }
+
+    Ok(list)
 }
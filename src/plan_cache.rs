@@ -0,0 +1,218 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::command::{Command, ConflictAction, InsertValue, SelectColumnName};
+use crate::lexer::{SqlValue, Token};
+use crate::pager::lru::Lru;
+
+const PLAN_CACHE_SIZE: usize = 64;
+const LITERAL_PLACEHOLDER: &str = "?";
+
+#[derive(Debug)]
+struct CachedCommand {
+    normalized_tokens: Vec<String>,
+    command: Command,
+}
+
+/// Cache of parsed statements. Key is statement tokens with literals (strings, integers and floats)
+/// replaced by placeholders, so statement sent again with other values is not parsed again, its
+/// cached command gets new literals instead. Only `SELECT`, `INSERT`, `UPDATE` and `DELETE` are cached.
+/// Where clauses are compiled against table columns on each execution, so cached commands stay
+/// valid after table is altered.
+#[derive(Debug)]
+pub struct PlanCache {
+    entries: Lru<u64, CachedCommand>,
+}
+
+impl Default for PlanCache {
+    fn default() -> Self {
+        Self { entries: Lru::new(PLAN_CACHE_SIZE).unwrap() }
+    }
+}
+
+impl PlanCache {
+    /// Returns cached command for statement of the same shape, with literals taken from given tokens
+    pub fn get(&mut self, tokens: &[Token]) -> Option<Command> {
+        let normalized_tokens = Self::normalize(tokens);
+        let cached_command = self.entries.get(&Self::hash(&normalized_tokens))?;
+        if cached_command.normalized_tokens != normalized_tokens { return None }
+
+        let mut command = cached_command.command.clone();
+        let command_literals = Self::command_literals_mut(&mut command)?;
+        for (command_literal, literal) in command_literals.into_iter().zip(Self::literals(tokens)) {
+            *command_literal = literal.clone();
+        }
+
+        Some(command)
+    }
+
+    /// Caches command parsed from given tokens. Command is skipped if it is not a data statement,
+    /// or if its literals do not match statement literals one to one in the same order, since
+    /// such command cannot be reused with other values.
+    pub fn insert(&mut self, tokens: &[Token], command: &Command) {
+        let mut command = command.clone();
+        let literals_match = match Self::command_literals_mut(&mut command) {
+            Some(command_literals) =>
+                command_literals.iter().map(|literal| &**literal).eq(Self::literals(tokens)),
+            None => false,
+        };
+        if !literals_match { return }
+
+        let normalized_tokens = Self::normalize(tokens);
+        let key = Self::hash(&normalized_tokens);
+        self.entries.set(key, CachedCommand { normalized_tokens, command });
+    }
+
+    fn is_literal(value: &SqlValue) -> bool {
+        matches!(value, SqlValue::String(_) | SqlValue::Integer(_) | SqlValue::Float(_))
+    }
+
+    fn literals(tokens: &[Token]) -> impl Iterator<Item = &SqlValue> {
+        tokens.iter().filter_map(|token| match token {
+            Token::Value(value) if Self::is_literal(value) => Some(value),
+            _ => None,
+        })
+    }
+
+    fn normalize(tokens: &[Token]) -> Vec<String> {
+        tokens.iter()
+            .map(|token| match token {
+                Token::Value(value) if Self::is_literal(value) => LITERAL_PLACEHOLDER.to_string(),
+                // debug format is used, so string value and identificator with the same text differ
+                _ => format!("{:?}", token),
+            })
+            .collect()
+    }
+
+    fn hash(normalized_tokens: &[String]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        normalized_tokens.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // literals of command in the same order as they appear in statement, None if command is not cacheable
+    fn command_literals_mut(command: &mut Command) -> Option<Vec<&mut SqlValue>> {
+        let mut values = vec![];
+        match command {
+            Command::InsertInto { table_name, column_names, values: insert_values } => {
+                values.push(table_name);
+                values.extend(column_names.iter_mut().flatten());
+                for insert_value in insert_values {
+                    match insert_value {
+                        InsertValue::Value(value) | InsertValue::NextVal(value) => values.push(value),
+                        InsertValue::Random => {},
+                    }
+                }
+            },
+            Command::Select { table_name, column_names, where_clause } => {
+                Self::extend_with_column_names(&mut values, column_names);
+                values.push(table_name);
+                if let Some(where_clause) = where_clause {
+                    values.extend(where_clause.values_mut());
+                }
+            },
+            Command::Update { table_name, field_assignments, where_clause } => {
+                values.push(table_name);
+                values.extend(field_assignments.iter_mut().map(|assignment| &mut assignment.value));
+                if let Some(where_clause) = where_clause {
+                    values.extend(where_clause.values_mut());
+                }
+            },
+            Command::Delete { table_name, where_clause } => {
+                values.push(table_name);
+                if let Some(where_clause) = where_clause {
+                    values.extend(where_clause.values_mut());
+                }
+            },
+            // ordering terms are column names, so literals are only in the select itself
+            Command::SelectOrdered { select, .. } => return Self::command_literals_mut(select),
+            // index names are not literals
+            Command::SelectIndexHinted { select, .. } => return Self::command_literals_mut(select),
+            Command::Upsert { insert, conflict_column_name, action } => {
+                values = Self::command_literals_mut(insert)?;
+                values.push(conflict_column_name);
+                if let ConflictAction::Update(field_assignments) = action {
+                    values.extend(field_assignments.iter_mut().map(|assignment| &mut assignment.value));
+                }
+            },
+            Command::Returning { command, column_names } => {
+                values = Self::command_literals_mut(command)?;
+                Self::extend_with_column_names(&mut values, column_names);
+            },
+            // limit and offset are not sql values, so they cannot be replaced in cached command
+            Command::SelectLimited { .. } => return None,
+            _ => return None,
+        }
+
+        Some(values.into_iter().filter(|value| Self::is_literal(value)).collect())
+    }
+
+    fn extend_with_column_names<'a>(values: &mut Vec<&'a mut SqlValue>, column_names: &'a mut [SelectColumnName]) {
+        for column_name in column_names {
+            match column_name {
+                SelectColumnName::Name(value) => values.extend(value.operands_mut()),
+                SelectColumnName::TableAllColumns(value) | SelectColumnName::Count(value) => values.push(value),
+                SelectColumnName::AllColumns | SelectColumnName::CountAll => {},
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser;
+
+    fn parse_and_cache(plan_cache: &mut PlanCache, statement: &str) -> Command {
+        let tokens = lexer::to_tokens(statement).unwrap();
+        let command = parser::parse_statement(tokens.iter()).unwrap();
+        plan_cache.insert(&tokens, &command);
+        command
+    }
+
+    fn cached(plan_cache: &mut PlanCache, statement: &str) -> Option<Command> {
+        plan_cache.get(&lexer::to_tokens(statement).unwrap())
+    }
+
+    #[test]
+    fn cached_command_gets_new_literals() {
+        let mut plan_cache = PlanCache::default();
+        parse_and_cache(&mut plan_cache, "update users set name = \"john\" where id = 1");
+
+        let command = cached(&mut plan_cache, "update users set name = \"pete\" where id = 2").unwrap();
+        let expected_command = parse_and_cache(&mut PlanCache::default(), "update users set name = \"pete\" where id = 2");
+        assert_eq!(format!("{:?}", command), format!("{:?}", expected_command));
+
+        let command = cached(&mut plan_cache, "UPDATE users SET name = \"pete\" WHERE id = 2.5").unwrap();
+        let expected_command = parse_and_cache(&mut PlanCache::default(), "update users set name = \"pete\" where id = 2.5");
+        assert_eq!(format!("{:?}", command), format!("{:?}", expected_command));
+    }
+
+    #[test]
+    fn statements_of_other_shape_are_not_matched() {
+        let mut plan_cache = PlanCache::default();
+        parse_and_cache(&mut plan_cache, "select id from users where id = 1");
+
+        assert!(cached(&mut plan_cache, "select id from users where age = 1").is_none());
+        assert!(cached(&mut plan_cache, "select id from users where id = name").is_none());
+        assert!(cached(&mut plan_cache, "select id from users where id is null").is_none());
+        assert!(cached(&mut plan_cache, "select id from users where id = 1").is_some());
+
+        parse_and_cache(&mut plan_cache, "select id from users where id = 1 and not name = \"john\"");
+        assert!(cached(&mut plan_cache, "select id from users where id = 2 and not name = \"pete\"").is_some());
+        assert!(cached(&mut plan_cache, "select id from users where id = 2 or not name = \"pete\"").is_none());
+    }
+
+    #[test]
+    fn only_data_statements_are_cached() {
+        let mut plan_cache = PlanCache::default();
+        parse_and_cache(&mut plan_cache, "create table users (id int default 1)");
+        parse_and_cache(&mut plan_cache, "select * from changes(users) since 10");
+        parse_and_cache(&mut plan_cache, "insert into users (name, id) values (\"john\", nextval(user_ids))");
+
+        assert!(cached(&mut plan_cache, "create table users (id int default 2)").is_none());
+        assert!(cached(&mut plan_cache, "select * from changes(users) since 20").is_none());
+        assert!(cached(&mut plan_cache, "insert into users (name, id) values (\"pete\", nextval(user_ids))").is_some());
+    }
+}
@@ -1,29 +1,87 @@
+use std::fmt;
+
 use crate::table::ColumnType;
 use crate::row::Row;
+use crate::lexer::SqlValue;
+use crate::serialize::SerDeError;
 
 
 /// This struct represents simple collection of rows,
 /// plus information on its columns types and names. It does not check if row matches
 /// column types - that is a job of the code that generates the result.
+/// Column nullable flags are taken from the declared constraints of the source columns,
+/// column is nullable unless it has `NOT NULL` constraint.
 #[derive(Debug)]
 pub struct QueryResult {
     pub column_types: Vec<ColumnType>,
     pub column_names: Vec<String>,
+    pub column_nullable_flags: Vec<bool>,
     pub rows: Vec<Row>,
 }
 
+/// Position of rows returned by select with `LIMIT` or `OFFSET` among all matching rows,
+/// see `Database::last_statement_pagination`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pagination {
+    pub offset: usize,
+    /// Number of rows which would be returned without `LIMIT` and `OFFSET`
+    pub total_count: usize,
+    /// True if there are matching rows after the returned ones
+    pub has_more: bool,
+}
+
+impl fmt::Display for Pagination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} matching rows", self.total_count)?;
+        if self.has_more {
+            write!(f, ", more rows after this page")?;
+        }
+        Ok(())
+    }
+}
+
 impl QueryResult {
+    /// Keeps at most `limit` rows after the first `offset` ones
+    pub fn paginate(&mut self, limit: Option<usize>, offset: usize) -> Pagination {
+        let total_count = self.rows.len();
+        self.rows.drain(..offset.min(total_count));
+        if let Some(limit) = limit {
+            self.rows.truncate(limit);
+        }
+        Pagination { offset, total_count, has_more: offset + self.rows.len() < total_count }
+    }
+
     pub fn spawn_row(&mut self) -> &mut Row {
         let row = Row::new(&self.column_types);
         self.rows.push(row);
         self.rows.last_mut().unwrap()
     }
 
-    #[allow(dead_code)]
     pub fn len(&self) -> usize {
         self.rows.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.column_types.len()
+    }
+
+    pub fn column_type(&self, column_index: usize) -> Option<ColumnType> {
+        self.column_types.get(column_index).copied()
+    }
+
+    pub fn column_is_nullable(&self, column_index: usize) -> Option<bool> {
+        self.column_nullable_flags.get(column_index).copied()
+    }
+
+    pub fn row_values(&self, row_index: usize) -> Option<Result<Vec<SqlValue>, SerDeError>> {
+        self.rows.get(row_index)
+            .map(|row| row.get_sql_values(&self.column_types))
+    }
+
     //pub fn get<'a, T: From<&'a SqlValue>>(&self, index: usize) -> Result<T, String> {
     //    let value = self.column_values.get(index)
     //        .ok_or(format!("row does not contain data with offset {}", index))?;
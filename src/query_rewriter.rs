@@ -0,0 +1,198 @@
+use crate::cmp_operator::CmpOperator;
+use crate::lexer::SqlValue;
+use crate::row_check::{RowCheck, RowCheckValue};
+
+/// Planning pass which is applied to compiled where clause before choosing a scan.
+/// It folds checks that do not depend on any column into constant checks, and moves
+/// static value to the right side (`3 = id` becomes `id = 3`), so planner only needs
+/// to look for `column op literal` shape when searching for applicable index.
+/// `AND`, `OR` and `NOT` with constant operands are folded as well.
+pub fn rewrite(row_check: RowCheck) -> RowCheck {
+    match row_check {
+        RowCheck::Compare { .. } => fold_constant(normalize_operands_order(row_check)),
+        RowCheck::And(left, right) => {
+            let (left, right) = (rewrite(*left), rewrite(*right));
+            if left == always_false() || right == always_false() {
+                always_false()
+            } else if left == always_true() {
+                right
+            } else if right == always_true() {
+                left
+            } else {
+                RowCheck::And(Box::new(left), Box::new(right))
+            }
+        },
+        RowCheck::Or(left, right) => {
+            let (left, right) = (rewrite(*left), rewrite(*right));
+            if left == always_true() || right == always_true() {
+                always_true()
+            } else if left == always_false() {
+                right
+            } else if right == always_false() {
+                left
+            } else {
+                RowCheck::Or(Box::new(left), Box::new(right))
+            }
+        },
+        RowCheck::Not(row_check) => {
+            let row_check = rewrite(*row_check);
+            if row_check == always_true() {
+                always_false()
+            } else if row_check == always_false() {
+                always_true()
+            } else {
+                RowCheck::Not(Box::new(row_check))
+            }
+        },
+    }
+}
+
+pub fn always_true() -> RowCheck {
+    RowCheck::dummy()
+}
+
+pub fn always_false() -> RowCheck {
+    RowCheck::Compare {
+        operator: CmpOperator::NotEquals,
+        left: RowCheckValue::Static(SqlValue::Integer(1)),
+        right: RowCheckValue::Static(SqlValue::Integer(1)),
+    }
+}
+
+fn normalize_operands_order(row_check: RowCheck) -> RowCheck {
+    match row_check {
+        // IS NULL is unary, its right value is always NULL and cannot be swapped
+        RowCheck::Compare { operator, left: left @ RowCheckValue::Static(_), right: right @ RowCheckValue::TableColumn(_) }
+            if operator != CmpOperator::IsNull => {
+            RowCheck::Compare {
+                operator: operator.flip(),
+                left: right,
+                right: left,
+            }
+        },
+        _ => row_check,
+    }
+}
+
+fn fold_constant(row_check: RowCheck) -> RowCheck {
+    let folded = match &row_check {
+        // comparison with NULL is unknown, which is neither always true nor always false under NOT
+        RowCheck::Compare { operator, left: RowCheckValue::Static(left), right: RowCheckValue::Static(right) }
+            if *operator == CmpOperator::IsNull || (*left != SqlValue::Null && *right != SqlValue::Null) =>
+            operator.apply(left, right),
+        _ => return row_check,
+    };
+
+    match folded {
+        Ok(true) => always_true(),
+        Ok(false) => always_false(),
+        // incomparable values will be reported by the filter itself
+        Err(_) => row_check,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_static_left_value() {
+        let row_check = RowCheck::Compare {
+            operator: CmpOperator::Less,
+            left: RowCheckValue::Static(SqlValue::Integer(3)),
+            right: RowCheckValue::TableColumn(1),
+        };
+
+        assert_eq!(
+            rewrite(row_check),
+            RowCheck::Compare {
+                operator: CmpOperator::Greater,
+                left: RowCheckValue::TableColumn(1),
+                right: RowCheckValue::Static(SqlValue::Integer(3)),
+            });
+    }
+
+    #[test]
+    fn keep_column_checks() {
+        let row_check = RowCheck::Compare {
+            operator: CmpOperator::Equals,
+            left: RowCheckValue::TableColumn(0),
+            right: RowCheckValue::TableColumn(1),
+        };
+        assert_eq!(rewrite(row_check.clone()), row_check);
+
+        let row_check = RowCheck::Compare {
+            operator: CmpOperator::IsNull,
+            left: RowCheckValue::TableColumn(0),
+            right: RowCheckValue::Static(SqlValue::Null),
+        };
+        assert_eq!(rewrite(row_check.clone()), row_check);
+    }
+
+    #[test]
+    fn fold_static_checks() {
+        let row_check = RowCheck::Compare {
+            operator: CmpOperator::GreaterEquals,
+            left: RowCheckValue::Static(SqlValue::Integer(3)),
+            right: RowCheckValue::Static(SqlValue::Integer(2)),
+        };
+        assert_eq!(rewrite(row_check), always_true());
+
+        let row_check = RowCheck::Compare {
+            operator: CmpOperator::Equals,
+            left: RowCheckValue::Static(SqlValue::String("john".to_string())),
+            right: RowCheckValue::Static(SqlValue::String("bob".to_string())),
+        };
+        assert_eq!(rewrite(row_check), always_false());
+
+        let row_check = RowCheck::Compare {
+            operator: CmpOperator::IsNull,
+            left: RowCheckValue::Static(SqlValue::Null),
+            right: RowCheckValue::Static(SqlValue::Null),
+        };
+        assert_eq!(rewrite(row_check), always_true());
+
+        // comparison with NULL is unknown, so it is not folded into false, which NOT would turn into true
+        let row_check = RowCheck::Not(Box::new(RowCheck::Compare {
+            operator: CmpOperator::Equals,
+            left: RowCheckValue::Static(SqlValue::Null),
+            right: RowCheckValue::Static(SqlValue::Integer(1)),
+        }));
+        assert_eq!(rewrite(row_check.clone()), row_check);
+    }
+
+    #[test]
+    fn fold_boolean_operators() {
+        let column_check = RowCheck::Compare {
+            operator: CmpOperator::Equals,
+            left: RowCheckValue::TableColumn(0),
+            right: RowCheckValue::Static(SqlValue::Integer(1)),
+        };
+        let and = |left: RowCheck, right: RowCheck| RowCheck::And(Box::new(left), Box::new(right));
+        let or = |left: RowCheck, right: RowCheck| RowCheck::Or(Box::new(left), Box::new(right));
+
+        assert_eq!(rewrite(and(column_check.clone(), always_true())), column_check);
+        assert_eq!(rewrite(and(column_check.clone(), always_false())), always_false());
+        assert_eq!(rewrite(or(always_false(), column_check.clone())), column_check);
+        assert_eq!(rewrite(or(column_check.clone(), always_true())), always_true());
+        assert_eq!(rewrite(RowCheck::Not(Box::new(always_false()))), always_true());
+
+        let swapped_check = RowCheck::Compare {
+            operator: CmpOperator::Equals,
+            left: RowCheckValue::Static(SqlValue::Integer(1)),
+            right: RowCheckValue::TableColumn(0),
+        };
+        let row_check = RowCheck::Not(Box::new(and(swapped_check, column_check.clone())));
+        assert_eq!(rewrite(row_check), RowCheck::Not(Box::new(and(column_check.clone(), column_check))));
+    }
+
+    #[test]
+    fn keep_incomparable_static_checks() {
+        let row_check = RowCheck::Compare {
+            operator: CmpOperator::Equals,
+            left: RowCheckValue::Static(SqlValue::Integer(1)),
+            right: RowCheckValue::Static(SqlValue::String("1".to_string())),
+        };
+        assert_eq!(rewrite(row_check.clone()), row_check);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::helpers::get_timestamp;
+
+/// Pseudo random numbers generator (splitmix64). It is not suitable for anything security
+/// related, but is fast, has no dependencies and gives the same numbers for the same seed,
+/// so scripts using `RANDOM()` can be made reproducible with `SET random_seed`.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    pub fn from_time() -> Random {
+        Self::new(get_timestamp() as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_i64(&mut self) -> i64 {
+        self.next_u64() as i64
+    }
+
+    // modulo bias is negligible for small bounds, which is fine for test data
+    pub fn next_below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_numbers() {
+        let mut random = Random::new(42);
+        let mut same_seed_random = Random::new(42);
+        let mut other_seed_random = Random::new(43);
+
+        let numbers: Vec<u64> = (0..5).map(|_| random.next_u64()).collect();
+        let same_seed_numbers: Vec<u64> = (0..5).map(|_| same_seed_random.next_u64()).collect();
+        let other_seed_numbers: Vec<u64> = (0..5).map(|_| other_seed_random.next_u64()).collect();
+
+        assert_eq!(numbers, same_seed_numbers);
+        assert_ne!(numbers, other_seed_numbers);
+        assert_ne!(numbers[0], numbers[1]);
+    }
+}
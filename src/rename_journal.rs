@@ -0,0 +1,158 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+pub const JOURNAL_EXTENSION: &str = "journal";
+const MOVE_SEPARATOR: char = '\t';
+
+/// Set of file moves which should be applied all together, e.g. table file and its index files
+/// on table rename. Journal is synced to disk before the first move and is removed once the
+/// rename is recorded in schema, so a rename interrupted by a crash can be rolled back on next start.
+#[derive(Debug)]
+pub struct RenameJournal {
+    pub old_name: String,
+    pub new_name: String,
+    filepath: PathBuf,
+    moves: Vec<(PathBuf, PathBuf)>,
+}
+
+impl RenameJournal {
+    pub fn new(tables_dir: &Path, old_name: &str, new_name: &str, moves: Vec<(PathBuf, PathBuf)>) -> RenameJournal {
+        let mut filepath = tables_dir.join(old_name);
+        filepath.set_extension(JOURNAL_EXTENSION);
+
+        RenameJournal { old_name: old_name.to_string(), new_name: new_name.to_string(), filepath, moves }
+    }
+
+    /// Writes journal and moves files. If any move fails, already moved files are moved back,
+    /// and journal is removed.
+    pub fn apply(&self) -> io::Result<()> {
+        self.write()?;
+
+        for (applied_count, (from, to)) in self.moves.iter().enumerate() {
+            if let Err(error) = fs::rename(from, to) {
+                for (from, to) in self.moves[..applied_count].iter().rev() {
+                    fs::rename(to, from)?;
+                }
+                fs::remove_file(&self.filepath)?;
+                return Err(error)
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn commit(self) -> io::Result<()> {
+        fs::remove_file(&self.filepath)
+    }
+
+    /// Moves files back, skipping the ones which were not moved before interruption
+    pub fn rollback(self) -> io::Result<()> {
+        for (from, to) in self.moves.iter().rev() {
+            if to.exists() && !from.exists() {
+                fs::rename(to, from)?;
+            }
+        }
+        fs::remove_file(&self.filepath)
+    }
+
+    /// Loads all journals left in tables dir
+    pub fn load_all(tables_dir: &Path) -> io::Result<Vec<RenameJournal>> {
+        let mut journals = vec![];
+        for entry in fs::read_dir(tables_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == JOURNAL_EXTENSION) {
+                journals.push(Self::load(path)?);
+            }
+        }
+        Ok(journals)
+    }
+
+    fn load(filepath: PathBuf) -> io::Result<RenameJournal> {
+        let mut lines = BufReader::new(File::open(&filepath)?).lines();
+        let header = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+        let (old_name, new_name) = header.split_once(' ')
+            .ok_or_else(|| Self::invalid_data_error(&filepath))?;
+
+        let mut moves = vec![];
+        for line in lines {
+            let line = line?;
+            let (from, to) = line.split_once(MOVE_SEPARATOR)
+                .ok_or_else(|| Self::invalid_data_error(&filepath))?;
+            moves.push((PathBuf::from(from), PathBuf::from(to)));
+        }
+
+        Ok(RenameJournal { old_name: old_name.to_string(), new_name: new_name.to_string(), filepath, moves })
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let mut file = File::create(&self.filepath)?;
+        writeln!(file, "{} {}", self.old_name, self.new_name)?;
+        for (from, to) in &self.moves {
+            writeln!(file, "{}{}{}", from.display(), MOVE_SEPARATOR, to.display())?;
+        }
+        file.sync_data()
+    }
+
+    fn invalid_data_error(filepath: &Path) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("rename journal '{}' is corrupted", filepath.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_file::TempFile;
+
+    #[test]
+    fn apply_commit_and_rollback() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let tables_dir = table_file.temp_dir_path.clone();
+        let index_filepath = tables_dir.join("users-id.hash");
+        fs::write(&index_filepath, b"").unwrap();
+
+        let moves = vec![
+            (table_file.file_path.clone(), tables_dir.join("people.table")),
+            (index_filepath.clone(), tables_dir.join("people-id.hash")),
+        ];
+        let journal = RenameJournal::new(&tables_dir, "users", "people", moves.clone());
+        journal.apply().unwrap();
+
+        assert!(tables_dir.join("people.table").exists());
+        assert!(tables_dir.join("people-id.hash").exists());
+
+        let journals = RenameJournal::load_all(&tables_dir).unwrap();
+        assert_eq!(journals.len(), 1);
+        assert_eq!(journals[0].old_name, "users");
+        assert_eq!(journals[0].new_name, "people");
+        assert_eq!(journals[0].moves, moves);
+
+        journals.into_iter().next().unwrap().rollback().unwrap();
+        assert!(table_file.file_path.exists());
+        assert!(index_filepath.exists());
+        assert!(RenameJournal::load_all(&tables_dir).unwrap().is_empty());
+
+        let journal = RenameJournal::new(&tables_dir, "users", "people", moves);
+        journal.apply().unwrap();
+        journal.commit().unwrap();
+        assert!(RenameJournal::load_all(&tables_dir).unwrap().is_empty());
+        assert!(tables_dir.join("people.table").exists());
+    }
+
+    #[test]
+    fn failed_move_is_rolled_back() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let tables_dir = table_file.temp_dir_path.clone();
+
+        let moves = vec![
+            (table_file.file_path.clone(), tables_dir.join("people.table")),
+            (tables_dir.join("users-id.hash"), tables_dir.join("people-id.hash")),
+        ];
+        let journal = RenameJournal::new(&tables_dir, "users", "people", moves);
+
+        assert!(journal.apply().is_err());
+        assert!(table_file.file_path.exists());
+        assert!(!tables_dir.join("people.table").exists());
+        assert!(RenameJournal::load_all(&tables_dir).unwrap().is_empty());
+    }
+}
@@ -5,11 +5,13 @@ use crate::table::error::TableError;
 use crate::row::Row;
 use crate::table::ColumnType;
 use crate::cmp_operator::CmpOperator;
+use crate::function::ScalarFunction;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RowCheckValue {
     TableColumn(usize),
     Static(SqlValue),
+    Function { function: ScalarFunction, arguments: Vec<RowCheckValue> },
 }
 
 impl fmt::Display for RowCheckValue {
@@ -17,65 +19,298 @@ impl fmt::Display for RowCheckValue {
         match self {
             Self::TableColumn(index) => write!(f, "column {}", index),
             Self::Static(sql_value) => write!(f, "{}", sql_value),
+            Self::Function { function, arguments } => {
+                let arguments: Vec<String> = arguments.iter().map(RowCheckValue::to_string).collect();
+                write!(f, "{}({})", function, arguments.join(", "))
+            },
         }
     }
 }
 
+/// Compiled where clause or check constraint. Comparisons refer to columns by their numbers,
+/// so they can be applied to rows without looking up column names.
 #[derive(Debug, Clone, PartialEq)]
-pub struct RowCheck {
-    pub operator: CmpOperator,
-    pub left: RowCheckValue,
-    pub right: RowCheckValue,
+pub enum RowCheck {
+    Compare {
+        operator: CmpOperator,
+        left: RowCheckValue,
+        right: RowCheckValue,
+    },
+    And(Box<RowCheck>, Box<RowCheck>),
+    Or(Box<RowCheck>, Box<RowCheck>),
+    Not(Box<RowCheck>),
 }
 
 impl fmt::Display for RowCheck {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {} {}", self.left, self.operator, self.right)
+        match self {
+            Self::Compare { operator, left, right } => write!(f, "{} {} {}", left, operator, right),
+            Self::And(left, right) => write!(f, "({}) AND ({})", left, right),
+            Self::Or(left, right) => write!(f, "({}) OR ({})", left, right),
+            Self::Not(row_check) => write!(f, "NOT ({})", row_check),
+        }
     }
 }
 
 impl RowCheck {
     pub fn dummy() -> Self {
-        Self {
+        Self::Compare {
             operator: CmpOperator::Equals,
             left: RowCheckValue::Static(SqlValue::Integer(1)),
             right: RowCheckValue::Static(SqlValue::Integer(1)),
         }
     }
 
+    /// Row matches only if check is true for it. Comparison with NULL (other than `IS NULL`) is
+    /// unknown, and so is `NOT` of it, thus `NOT age > 18` does not match rows with NULL age.
     pub fn matches(&self, row: &Row, column_types: &[ColumnType]) -> Result<bool, TableError> {
-        self
-            .operator
-            .apply(&self.get_value(&self.left, row, column_types)?, &self.get_value(&self.right, row, column_types)?)
-            .map_err(TableError::CmpError)
+        Ok(self.evaluate(row, column_types)? == Some(true))
+    }
 
+    // three-valued result, where unknown is None
+    fn evaluate(&self, row: &Row, column_types: &[ColumnType]) -> Result<Option<bool>, TableError> {
+        match self {
+            Self::Compare { operator, left, right } =>
+                Self::compare(operator, &Self::get_value(left, row, column_types)?, &Self::get_value(right, row, column_types)?),
+            Self::And(left, right) => match left.evaluate(row, column_types)? {
+                Some(false) => Ok(Some(false)),
+                left_result => Ok(match (left_result, right.evaluate(row, column_types)?) {
+                    (_, Some(false)) => Some(false),
+                    (Some(true), right_result) => right_result,
+                    _ => None,
+                }),
+            },
+            Self::Or(left, right) => match left.evaluate(row, column_types)? {
+                Some(true) => Ok(Some(true)),
+                left_result => Ok(match (left_result, right.evaluate(row, column_types)?) {
+                    (_, Some(true)) => Some(true),
+                    (Some(false), right_result) => right_result,
+                    _ => None,
+                }),
+            },
+            Self::Not(row_check) => Ok(row_check.evaluate(row, column_types)?.map(|result| !result)),
+        }
     }
 
-    pub fn is_column_value_eq_static_check(&self) -> Option<(usize, SqlValue)> {
-        if self.operator == CmpOperator::Equals {
-            match &self.left {
-                RowCheckValue::TableColumn(column_number) => {
-                    if let RowCheckValue::Static(sql_value) = &self.right {
-                        return Some((*column_number, sql_value.clone()))
-                    }
-                },
-                RowCheckValue::Static(sql_value) => {
-                    if let RowCheckValue::TableColumn(column_number) = self.right {
-                        return Some((column_number, sql_value.clone()))
+    fn compare(operator: &CmpOperator, left: &SqlValue, right: &SqlValue) -> Result<Option<bool>, TableError> {
+        if *operator != CmpOperator::IsNull && (*left == SqlValue::Null || *right == SqlValue::Null) {
+            return Ok(None)
+        }
+        operator.apply(left, right).map(Some).map_err(TableError::CmpError)
+    }
+
+    /// Same as `matches` applied to each row, but compared columns are read from the whole batch at once.
+    /// Operands of `AND` and `OR` are checked only against rows for which the result is not known yet.
+    pub fn matches_batch(&self, rows: &[&Row], column_types: &[ColumnType]) -> Result<Vec<bool>, TableError> {
+        let mut matched = vec![false; rows.len()];
+        for position in self.filter_batch(rows, column_types, (0..rows.len()).collect(), true)? {
+            matched[position] = true;
+        }
+        Ok(matched)
+    }
+
+    // returns ascending positions from `selection` of rows for which the check gives `expected`
+    // result, rows for which it is unknown are never returned, so `NOT` just flips `expected`
+    fn filter_batch(&self, rows: &[&Row], column_types: &[ColumnType], selection: Vec<usize>, expected: bool) -> Result<Vec<usize>, TableError> {
+        if selection.is_empty() { return Ok(selection) }
+
+        match (self, expected) {
+            (Self::Compare { operator, left, right }, _) => {
+                let selected_rows: Vec<&Row> = selection.iter().map(|position| rows[*position]).collect();
+                let left_values = Self::get_batch_values(left, &selected_rows, column_types)?;
+                let right_values = Self::get_batch_values(right, &selected_rows, column_types)?;
+
+                let mut matched = vec![];
+                for (i, position) in selection.into_iter().enumerate() {
+                    if Self::compare(operator, left_values.get(i), right_values.get(i))? == Some(expected) {
+                        matched.push(position);
                     }
+                }
+                Ok(matched)
+            },
+            // both operands are true, or both are false
+            (Self::And(left, right), true) | (Self::Or(left, right), false) => {
+                let left_matched = left.filter_batch(rows, column_types, selection, expected)?;
+                right.filter_batch(rows, column_types, left_matched, expected)
+            },
+            // any of operands is false, or any of them is true
+            (Self::And(left, right), false) | (Self::Or(left, right), true) => {
+                let left_matched = left.filter_batch(rows, column_types, selection.clone(), expected)?;
+                let unmatched = Self::exclude(selection, &left_matched);
+                let mut matched = left_matched;
+                matched.extend(right.filter_batch(rows, column_types, unmatched, expected)?);
+                matched.sort_unstable();
+                Ok(matched)
+            },
+            (Self::Not(row_check), _) => row_check.filter_batch(rows, column_types, selection, !expected),
+        }
+    }
+
+    // both lists are ascending, and `excluded` is a subset of `selection`
+    fn exclude(selection: Vec<usize>, excluded: &[usize]) -> Vec<usize> {
+        let mut excluded = excluded.iter().peekable();
+        selection.into_iter()
+            .filter(|position| match excluded.peek() {
+                Some(&&excluded_position) if excluded_position == *position => {
+                    excluded.next();
+                    false
                 },
-            }
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Finds `column = literal` comparison which should hold for every matching row, so rows
+    /// can be looked up in column index. Such comparison is either the whole check, or one of
+    /// `AND` operands, rows found by it are still filtered by the whole check.
+    pub fn is_column_value_eq_static_check(&self) -> Option<(usize, SqlValue)> {
+        match self {
+            // where clause is normalized by query rewriter, so static value is always at the right side
+            Self::Compare { operator: CmpOperator::Equals, left: RowCheckValue::TableColumn(column_number), right: RowCheckValue::Static(sql_value) } =>
+                Some((*column_number, sql_value.clone())),
+            Self::And(left, right) => left.is_column_value_eq_static_check().or_else(|| right.is_column_value_eq_static_check()),
+            _ => None,
+        }
+    }
+
+    /// Same as `is_column_value_eq_static_check`, but also accepts `OR` of such comparisons
+    /// on the same column (that's what `column IN (...)` is compiled to), so matching rows are
+    /// the union of rows found by each value.
+    pub fn is_column_value_in_static_check(&self) -> Option<(usize, Vec<SqlValue>)> {
+        match self {
+            Self::Compare { .. } =>
+                self.is_column_value_eq_static_check().map(|(column_number, sql_value)| (column_number, vec![sql_value])),
+            Self::Or(left, right) => {
+                let (left_column_number, mut values) = left.is_column_value_in_static_check()?;
+                let (right_column_number, right_values) = right.is_column_value_in_static_check()?;
+                if left_column_number != right_column_number { return None }
+
+                for sql_value in right_values {
+                    if !values.contains(&sql_value) { values.push(sql_value) }
+                }
+                Some((left_column_number, values))
+            },
+            Self::And(left, right) => left.is_column_value_in_static_check().or_else(|| right.is_column_value_in_static_check()),
+            Self::Not(_) => None,
+        }
+    }
+
+    /// All `column IN (...)` like checks which should hold for every matching row, i.e. the
+    /// whole check or operands of top level `AND`s, so rows can be looked up by several indexes
+    pub fn column_value_in_static_checks(&self) -> Vec<(usize, Vec<SqlValue>)> {
+        match self {
+            Self::And(left, right) => {
+                let mut checks = left.column_value_in_static_checks();
+                checks.extend(right.column_value_in_static_checks());
+                checks
+            },
+            _ => self.is_column_value_in_static_check().into_iter().collect(),
+        }
+    }
+
+    /// Finds `column IS NULL` comparison which should hold for every matching row
+    pub fn is_column_is_null_check(&self) -> Option<usize> {
+        match self {
+            Self::Compare { operator: CmpOperator::IsNull, left: RowCheckValue::TableColumn(column_number), .. } => Some(*column_number),
+            Self::And(left, right) => left.is_column_is_null_check().or_else(|| right.is_column_is_null_check()),
+            _ => None,
         }
+    }
 
-        None
+    fn get_batch_values<'a>(value: &'a RowCheckValue, rows: &[&Row], column_types: &[ColumnType]) -> Result<BatchValues<'a>, TableError> {
+        match value {
+            RowCheckValue::Static(sql_value) => Ok(BatchValues::Static(sql_value)),
+            RowCheckValue::TableColumn(index) =>
+                Row::get_column_sql_values(rows, column_types, *index).map(BatchValues::Column).map_err(TableError::CannotGetCell),
+            RowCheckValue::Function { .. } => rows.iter()
+                .map(|row| Self::get_value(value, row, column_types))
+                .collect::<Result<Vec<SqlValue>, TableError>>()
+                .map(BatchValues::Column),
+        }
     }
 
-    fn get_value(&self, value: &RowCheckValue, row: &Row, column_types: &[ColumnType]) -> Result<SqlValue, TableError> {
+    pub fn get_value(value: &RowCheckValue, row: &Row, column_types: &[ColumnType]) -> Result<SqlValue, TableError> {
         match value {
             RowCheckValue::Static(sql_value) => Ok(sql_value.clone()),
             RowCheckValue::TableColumn(index) =>
                 row.get_cell_sql_value(column_types, *index).map_err(TableError::CannotGetCell),
+            RowCheckValue::Function { function, arguments } => {
+                let arguments = arguments.iter()
+                    .map(|argument| Self::get_value(argument, row, column_types))
+                    .collect::<Result<Vec<SqlValue>, TableError>>()?;
+                function.apply(&arguments).map_err(TableError::FunctionError)
+            },
         }
     }
 }
 
+
+// static value is shared by all rows of a batch instead of being cloned for each of them
+enum BatchValues<'a> {
+    Static(&'a SqlValue),
+    Column(Vec<SqlValue>),
+}
+
+impl BatchValues<'_> {
+    fn get(&self, i: usize) -> &SqlValue {
+        match self {
+            Self::Static(sql_value) => sql_value,
+            Self::Column(sql_values) => &sql_values[i],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_batch() {
+        let column_types = [ColumnType::Integer, ColumnType::String];
+        let rows: Vec<Row> = [(1, Some("a")), (2, None), (3, Some("b")), (4, Some("a"))].iter()
+            .map(|(id, name)| {
+                let name = name.map_or(SqlValue::Null, |name| SqlValue::String(name.to_string()));
+                Row::from_sql_values(&[SqlValue::Integer(*id), name], &column_types).unwrap()
+            })
+            .collect();
+        let rows: Vec<&Row> = rows.iter().collect();
+
+        let compare = |operator, column_number, value| RowCheck::Compare {
+            operator,
+            left: RowCheckValue::TableColumn(column_number),
+            right: RowCheckValue::Static(value),
+        };
+        let name_is_a = compare(CmpOperator::Equals, 1, SqlValue::String("a".to_string()));
+        let id_greater_2 = compare(CmpOperator::Greater, 0, SqlValue::Integer(2));
+        let row_checks = [
+            name_is_a.clone(),
+            RowCheck::And(Box::new(name_is_a.clone()), Box::new(id_greater_2.clone())),
+            RowCheck::Or(Box::new(id_greater_2.clone()), Box::new(name_is_a.clone())),
+            RowCheck::Not(Box::new(RowCheck::Or(Box::new(name_is_a.clone()), Box::new(compare(CmpOperator::IsNull, 1, SqlValue::Null))))),
+            // comparisons with NULL name are unknown, and stay unknown under NOT
+            RowCheck::Not(Box::new(name_is_a.clone())),
+            RowCheck::Not(Box::new(RowCheck::And(Box::new(name_is_a.clone()), Box::new(id_greater_2.clone())))),
+            RowCheck::Not(Box::new(RowCheck::Or(Box::new(name_is_a.clone()), Box::new(id_greater_2.clone())))),
+            RowCheck::Not(Box::new(RowCheck::Not(Box::new(name_is_a)))),
+            RowCheck::Not(Box::new(compare(CmpOperator::Greater, 1, SqlValue::Null))),
+        ];
+
+        let expected = [
+            vec![true, false, false, true],
+            vec![false, false, false, true],
+            vec![true, false, true, true],
+            vec![false, false, true, false],
+            vec![false, false, true, false],
+            vec![true, true, true, false],
+            vec![false, false, false, false],
+            vec![true, false, false, true],
+            vec![false, false, false, false],
+        ];
+        for (row_check, expected) in row_checks.iter().zip(expected) {
+            let matched: Vec<bool> = rows.iter().map(|row| row_check.matches(row, &column_types).unwrap()).collect();
+            assert_eq!(matched, expected);
+            assert_eq!(row_check.matches_batch(&rows, &column_types).unwrap(), expected);
+        }
+        assert!(id_greater_2.matches_batch(&[], &column_types).unwrap().is_empty());
+    }
+}
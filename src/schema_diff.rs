@@ -0,0 +1,288 @@
+use std::fs;
+use std::path::Path;
+
+use crate::binary_condition::BinaryCondition;
+use crate::cmp_operator::CmpOperator;
+use crate::database::{SEQUENCE_SCHEMA_PREFIX, USER_SCHEMA_PREFIX};
+use crate::lexer::SqlValue;
+use crate::meta_command_error::MetaCommandError;
+use crate::parser::{self, TableSchemaDefinitionLine};
+use crate::table::{ColumnType, Constraint, TableOptions};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub kind: ColumnType,
+    pub constraints: Vec<Constraint>,
+}
+
+/// Definition of a table as it is kept in database file, without its data
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    /// index name and name of indexed column
+    pub indexes: Vec<(String, String)>,
+    pub options: TableOptions,
+}
+
+/// Tables and sequences of a database, both ordered by name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    pub tables: Vec<TableSchema>,
+    pub sequences: Vec<String>,
+}
+
+impl Catalog {
+    /// Reads catalog from database file, table files are not opened, so database can be
+    /// inspected while it is connected elsewhere
+    pub fn load(database_filepath: &Path) -> Result<Catalog, MetaCommandError> {
+        let contents = fs::read_to_string(database_filepath)?;
+        let mut catalog = Catalog::default();
+
+        for line in contents.lines().skip(1).map(str::trim).filter(|line| !line.is_empty()) {
+            if line.starts_with(USER_SCHEMA_PREFIX) { continue }
+
+            match line.strip_prefix(SEQUENCE_SCHEMA_PREFIX) {
+                Some(sequence_name) => catalog.sequences.push(sequence_name.to_string()),
+                None => {
+                    let schema_line = parser::parse_schema_line(line)
+                        .map_err(|parser_error| MetaCommandError::ParseError(parser_error.to_string()))?;
+                    catalog.tables.push(TableSchema::from(schema_line));
+                },
+            }
+        }
+
+        catalog.sort();
+        Ok(catalog)
+    }
+
+    pub fn sort(&mut self) {
+        self.tables.sort_by(|left, right| left.name.cmp(&right.name));
+        self.sequences.sort();
+    }
+
+    /// Statements which create all tables, indexes and sequences of the catalog
+    pub fn create_statements(&self) -> Vec<String> {
+        let mut statements: Vec<String> = self.tables.iter().flat_map(TableSchema::create_statements).collect();
+        statements.extend(self.sequences.iter().map(|sequence_name| format!("CREATE SEQUENCE {}", sequence_name)));
+        statements
+    }
+
+    /// Statements which turn this catalog into the `target` one. Column order is not compared,
+    /// and column with different type is dropped and added again, so its values are lost.
+    pub fn diff(&self, target: &Catalog) -> Vec<String> {
+        let mut statements = vec![];
+
+        for table in &self.tables {
+            if !target.tables.iter().any(|target_table| target_table.name == table.name) {
+                statements.push(format!("DROP TABLE {}", table.name));
+            }
+        }
+        for target_table in &target.tables {
+            match self.tables.iter().find(|table| table.name == target_table.name) {
+                Some(table) => statements.extend(table.diff(target_table)),
+                None => statements.extend(target_table.create_statements()),
+            }
+        }
+
+        for sequence_name in &self.sequences {
+            if !target.sequences.contains(sequence_name) {
+                statements.push(format!("DROP SEQUENCE {}", sequence_name));
+            }
+        }
+        for sequence_name in &target.sequences {
+            if !self.sequences.contains(sequence_name) {
+                statements.push(format!("CREATE SEQUENCE {}", sequence_name));
+            }
+        }
+
+        statements
+    }
+}
+
+impl From<TableSchemaDefinitionLine> for TableSchema {
+    fn from(schema_line: TableSchemaDefinitionLine) -> Self {
+        let columns: Vec<ColumnSchema> = schema_line.column_definitions.into_iter()
+            .map(|column_definition| ColumnSchema {
+                name: column_definition.name.to_string(),
+                kind: column_definition.kind,
+                constraints: column_definition.column_constraints,
+            })
+            .collect();
+        let indexes = schema_line.indexes_definitions.into_iter()
+            .map(|(column_number, index_name)| (index_name, columns[column_number].name.clone()))
+            .collect();
+
+        Self { name: schema_line.name, columns, indexes, options: schema_line.options }
+    }
+}
+
+impl TableSchema {
+    pub fn create_statements(&self) -> Vec<String> {
+        let column_definitions: Vec<String> = self.columns.iter().map(format_column_definition).collect();
+        let mut create_table = format!("CREATE TABLE {} ({})", self.name, column_definitions.join(", "));
+        let option_names = self.options.names();
+        if !option_names.is_empty() {
+            create_table.push_str(&format!(" WITH ({})", option_names.join(", ")));
+        }
+
+        let mut statements = vec![create_table];
+        statements.extend(self.indexes.iter().map(|(index_name, column_name)| self.create_index_statement(index_name, column_name)));
+        statements
+    }
+
+    fn diff(&self, target: &TableSchema) -> Vec<String> {
+        let mut statements = vec![];
+
+        if self.options != target.options {
+            let enabled_option_names = target.options.names().into_iter()
+                .filter(|option_name| !self.options.names().contains(option_name))
+                .map(str::to_string);
+            let disabled_option_names = self.options.names().into_iter()
+                .filter(|option_name| !target.options.names().contains(option_name))
+                .map(|option_name| format!("{} = false", option_name));
+            let option_values: Vec<String> = enabled_option_names.chain(disabled_option_names).collect();
+            statements.push(format!("ALTER TABLE {} SET ({})", self.name, option_values.join(", ")));
+        }
+
+        for (index_name, column_name) in &self.indexes {
+            if !target.indexes.contains(&(index_name.clone(), column_name.clone())) {
+                statements.push(format!("DROP INDEX {} ON {}", index_name, self.name));
+            }
+        }
+
+        for column in &self.columns {
+            let kept = target.columns.iter()
+                .any(|target_column| target_column.name == column.name && target_column.kind == column.kind);
+            if !kept {
+                statements.push(format!("ALTER TABLE {} DROP COLUMN {}", self.name, column.name));
+            }
+        }
+
+        for target_column in &target.columns {
+            let column = self.columns.iter()
+                .find(|column| column.name == target_column.name && column.kind == target_column.kind);
+            match column {
+                None => statements.push(format!("ALTER TABLE {} ADD COLUMN {}", self.name, format_column_definition(target_column))),
+                Some(column) => {
+                    for constraint in &column.constraints {
+                        if !target_column.constraints.contains(constraint) {
+                            statements.push(format!("ALTER TABLE {} DROP CONSTRAINT {} ({})", self.name, format_constraint(constraint), column.name));
+                        }
+                    }
+                    for constraint in &target_column.constraints {
+                        if !column.constraints.contains(constraint) {
+                            statements.push(format!("ALTER TABLE {} ADD CONSTRAINT {} ({})", self.name, format_constraint(constraint), column.name));
+                        }
+                    }
+                },
+            }
+        }
+
+        for (index_name, column_name) in &target.indexes {
+            if !self.indexes.contains(&(index_name.clone(), column_name.clone())) {
+                statements.push(self.create_index_statement(index_name, column_name));
+            }
+        }
+
+        statements
+    }
+
+    fn create_index_statement(&self, index_name: &str, column_name: &str) -> String {
+        format!("CREATE INDEX {} ON {} {}", index_name, self.name, column_name)
+    }
+}
+
+fn format_column_definition(column: &ColumnSchema) -> String {
+    let mut definition = format!("{} {}", column.name, column.kind);
+    for constraint in &column.constraints {
+        definition.push(' ');
+        definition.push_str(&format_constraint(constraint));
+    }
+    definition
+}
+
+// unlike display of constraint, strings are quoted, so statement can be parsed back
+fn format_constraint(constraint: &Constraint) -> String {
+    match constraint {
+        Constraint::NotNull | Constraint::References(_) => constraint.to_string(),
+        Constraint::Default(value) => format!("DEFAULT {}", format_value(value)),
+        Constraint::Check(BinaryCondition { left_value, operator: CmpOperator::IsNull, .. }) =>
+            format!("CHECK ({} IS NULL)", format_value(left_value)),
+        Constraint::Check(BinaryCondition { left_value, right_value, operator }) =>
+            format!("CHECK ({} {} {})", format_value(left_value), operator, format_value(right_value)),
+        Constraint::NamedCheck(name, check) => format!("CONSTRAINT {} {}", name, format_constraint(&Constraint::Check(check.clone()))),
+    }
+}
+
+fn format_value(value: &SqlValue) -> String {
+    match value {
+        SqlValue::String(string) => format!("\"{}\"", string),
+        // debug format keeps fraction for whole floats, so value is not parsed back as integer
+        SqlValue::Float(float) => format!("{:?}", float),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users_catalog() -> Catalog {
+        let users = TableSchema {
+            name: "users".to_string(),
+            columns: vec![
+                ColumnSchema { name: "id".to_string(), kind: ColumnType::Integer, constraints: vec![Constraint::NotNull] },
+                ColumnSchema { name: "name".to_string(), kind: ColumnType::String, constraints: vec![] },
+            ],
+            indexes: vec![("users_id".to_string(), "id".to_string())],
+            options: TableOptions::default(),
+        };
+        Catalog { tables: vec![users], sequences: vec!["user_ids".to_string()] }
+    }
+
+    #[test]
+    fn create_statements() {
+        assert_eq!(users_catalog().create_statements(), vec![
+            "CREATE TABLE users (id INT NOT NULL, name STRING)",
+            "CREATE INDEX users_id ON users id",
+            "CREATE SEQUENCE user_ids",
+        ]);
+    }
+
+    #[test]
+    fn diff_catalogs() {
+        let current = users_catalog();
+        assert!(current.diff(&current).is_empty());
+
+        let mut target = users_catalog();
+        let users = &mut target.tables[0];
+        users.options.soft_delete = true;
+        users.columns[0].constraints = vec![Constraint::Default(SqlValue::Integer(0))];
+        users.columns[1].kind = ColumnType::Float;
+        users.columns.push(ColumnSchema {
+            name: "role".to_string(),
+            kind: ColumnType::String,
+            constraints: vec![Constraint::Default(SqlValue::String("guest".to_string()))],
+        });
+        users.indexes = vec![("users_name".to_string(), "name".to_string())];
+        target.tables.push(TableSchema { name: "orders".to_string(), columns: vec![], indexes: vec![], options: TableOptions::default() });
+        target.tables[1].columns.push(ColumnSchema { name: "user_id".to_string(), kind: ColumnType::Integer, constraints: vec![] });
+        target.sequences.clear();
+
+        assert_eq!(current.diff(&target), vec![
+            "ALTER TABLE users SET (soft_delete)",
+            "DROP INDEX users_id ON users",
+            "ALTER TABLE users DROP COLUMN name",
+            "ALTER TABLE users DROP CONSTRAINT NOT NULL (id)",
+            "ALTER TABLE users ADD CONSTRAINT DEFAULT 0 (id)",
+            "ALTER TABLE users ADD COLUMN name FLOAT",
+            "ALTER TABLE users ADD COLUMN role STRING DEFAULT \"guest\"",
+            "CREATE INDEX users_name ON users name",
+            "CREATE TABLE orders (user_id INT)",
+            "DROP SEQUENCE user_ids",
+        ]);
+        assert_eq!(target.diff(&Catalog::default()), vec!["DROP TABLE users", "DROP TABLE orders"]);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::command::Command;
+use crate::schema_diff::TableSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaChangeKind {
+    CreateTable,
+    DropTable,
+    /// Table or column rename, added or dropped column and column constraint, changed table options
+    AlterTable,
+    CreateIndex,
+    DropIndex,
+}
+
+/// Change of a table definition made by a statement, it is passed to schema hooks once the
+/// statement succeeded, see `Database::add_schema_hook`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaChange {
+    pub kind: SchemaChangeKind,
+    /// Table definition before the statement, `None` for created table
+    pub before: Option<TableSchema>,
+    /// Table definition after the statement, `None` for dropped table. Renamed table has its new name here.
+    pub after: Option<TableSchema>,
+}
+
+/// Callback of embedding application, it is sent to other threads along with database, see `SharedDatabase`
+pub type SchemaHook = Box<dyn FnMut(&SchemaChange) + Send>;
+
+impl SchemaChangeKind {
+    /// Kind of change made by command, with names of changed table before and after the change
+    pub fn of(command: &Command) -> Option<(SchemaChangeKind, String, String)> {
+        let (kind, table_name) = match command {
+            Command::CreateTable { table_name, .. } => (Self::CreateTable, table_name),
+            Command::DropTable { table_name } => (Self::DropTable, table_name),
+            Command::RenameTable { table_name, new_table_name } =>
+                return Some((Self::AlterTable, table_name.to_string(), new_table_name.to_string())),
+            Command::RenameTableColumn { table_name, .. } | Command::AddTableColumn { table_name, .. } |
+                Command::DropTableColumn { table_name, .. } | Command::AddColumnConstraint { table_name, .. } |
+                Command::DropColumnConstraint { table_name, .. } | Command::DropNamedConstraint { table_name, .. } |
+                Command::SetTableOptions { table_name, .. } =>
+                    (Self::AlterTable, table_name),
+            Command::CreateIndex { table_name, .. } => (Self::CreateIndex, table_name),
+            Command::DropIndex { table_name, .. } => (Self::DropIndex, table_name),
+            _ => return None,
+        };
+        Some((kind, table_name.to_string(), table_name.to_string()))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::database::Database;
+use crate::query_result::QueryResult;
+use crate::{lexer, parser};
+
+const STATEMENT_SEPARATOR: char = ';';
+
+/// Statement of a script file with the number of line it starts on
+#[derive(Debug, PartialEq)]
+pub struct ScriptStatement {
+    pub line_number: usize,
+    pub text: String,
+}
+
+/// Splits script into statements. Statement ends with `;` or with line break, unless the break
+/// is inside of quotes or parentheses, so multiline `CREATE TABLE` and `INSERT` do not need
+/// `;`, and dumps made in `.mode insert` can be read as is. Empty statements are skipped.
+pub fn split_statements(script: &str) -> Vec<ScriptStatement> {
+    let mut statements = vec![];
+    let mut text = String::new();
+    let mut line_number = 1;
+    let mut statement_line_number = 1;
+    let mut in_quotes = false;
+    let mut depth = 0;
+
+    for char in script.chars() {
+        let statement_ends = match char {
+            '"' => { in_quotes = !in_quotes; false },
+            '(' if !in_quotes => { depth += 1; false },
+            ')' if !in_quotes => { depth -= 1; false },
+            STATEMENT_SEPARATOR if !in_quotes => true,
+            '\n' => !in_quotes && depth <= 0,
+            _ => false,
+        };
+
+        if statement_ends {
+            push_statement(&mut statements, &mut text, statement_line_number);
+            depth = 0;
+        } else {
+            if text.trim().is_empty() {
+                statement_line_number = line_number;
+            }
+            text.push(char);
+        }
+        if char == '\n' {
+            line_number += 1;
+        }
+    }
+    push_statement(&mut statements, &mut text, statement_line_number);
+    statements
+}
+
+fn push_statement(statements: &mut Vec<ScriptStatement>, text: &mut String, line_number: usize) {
+    let statement = std::mem::take(text);
+    if !statement.trim().is_empty() {
+        statements.push(ScriptStatement { line_number, text: statement.trim().to_string() })
+    }
+}
+
+/// Parses and executes a statement of script, error message tells at which stage it failed
+pub fn execute_statement(database: &mut Database, statement: &str) -> Result<Option<QueryResult>, String> {
+    let tokens = lexer::to_tokens(statement).map_err(|error| format!("cannot parse statement: {}", error))?;
+    let tokens = database.expand_macros(tokens).map_err(|error| format!("cannot expand macro: {}", error))?;
+    let command = parser::parse_statement(tokens.iter()).map_err(|error| format!("error parsing statement: {}", error))?;
+    database.execute(command).map_err(|error| format!("cannot execute statement: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_script() {
+        let script = "CREATE TABLE users (\n  id INT,\n  name STRING\n);\n\n\
+                      INSERT INTO users (id, name) VALUES (1, \"semi; colon\")\n\
+                      insert into users (id, name) values (2, \"multi\nline\"); SELECT * FROM users;;\n";
+        let statement = |line_number, text: &str| ScriptStatement { line_number, text: text.to_string() };
+        assert_eq!(split_statements(script), vec![
+            statement(1, "CREATE TABLE users (\n  id INT,\n  name STRING\n)"),
+            statement(6, "INSERT INTO users (id, name) VALUES (1, \"semi; colon\")"),
+            statement(7, "insert into users (id, name) values (2, \"multi\nline\")"),
+            statement(8, "SELECT * FROM users"),
+        ]);
+        assert!(split_statements(" ;\n\n").is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const SEQUENCE_EXTENSION: &str = "sequence";
+
+/// Persistent counter which is not bound to any table. Last issued value is stored
+/// in a separate 8 byte file, and is synced to disk on every increment, so value
+/// is never issued twice even if database was not closed properly.
+#[derive(Debug)]
+pub struct Sequence {
+    pub name: String,
+    filepath: PathBuf,
+}
+
+impl Sequence {
+    pub fn new(tables_dir: &Path, name: &str) -> Sequence {
+        Sequence { name: name.to_string(), filepath: Self::build_filepath(tables_dir, name) }
+    }
+
+    pub fn filepath(&self) -> &Path {
+        &self.filepath
+    }
+
+    pub fn create(tables_dir: &Path, name: &str) -> io::Result<Sequence> {
+        let sequence = Self::new(tables_dir, name);
+        let mut file = File::create(&sequence.filepath)?;
+        file.write_all(&0i64.to_le_bytes())?;
+        file.sync_data()?;
+
+        Ok(sequence)
+    }
+
+    pub fn next_value(&self) -> io::Result<i64> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.filepath)?;
+
+        let mut bytes = [0u8; 8];
+        file.read_exact(&mut bytes)?;
+        let value = i64::from_le_bytes(bytes) + 1;
+
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&value.to_le_bytes())?;
+        file.sync_data()?;
+
+        Ok(value)
+    }
+
+    pub fn destroy(self) -> io::Result<()> {
+        fs::remove_file(self.filepath)
+    }
+
+    fn build_filepath(tables_dir: &Path, name: &str) -> PathBuf {
+        let mut filepath = tables_dir.join(name);
+        filepath.set_extension(SEQUENCE_EXTENSION);
+        filepath
+    }
+}
@@ -6,6 +6,11 @@ use crate::table::ColumnType;
 use crate::lexer::SqlValue;
 use crate::row;
 
+// first byte of string cell holds string length
+const MAX_STRING_LENGTH: usize = row::STRING_SIZE - 1;
+// string cells are padded from this buffer, so no cell sized array is built on each call
+const ZERO_BYTES: [u8; row::STRING_SIZE] = [0; row::STRING_SIZE];
+
 #[derive(Debug)]
 pub enum SerDeError {
     WriteError(io::Error),
@@ -15,6 +20,8 @@ pub enum SerDeError {
     CannotReadFloatBytesError(io::Error),
     CannotSerializeStringAsInt(String),
     CannotConvertBytesToString(std::str::Utf8Error),
+    StringTooLong(usize),
+    CannotSerializeFunction(String),
 }
 
 impl fmt::Display for SerDeError {
@@ -27,6 +34,10 @@ impl fmt::Display for SerDeError {
             Self::CannotReadFloatBytesError(_io_error) => "error reading float bytes from source".to_string(),
             Self::CannotSerializeStringAsInt(string) => format!("string '{}' cannot be used as integer value", string),
             Self::CannotConvertBytesToString(_utf8_error) => "cannot convert provided bytes to a utf8 string".to_string(),
+            Self::StringTooLong(len) =>
+                format!("string of {} bytes is too long, strings are limited to {} bytes", len, MAX_STRING_LENGTH),
+            Self::CannotSerializeFunction(function_call) =>
+                format!("function call '{}' cannot be stored, only values are allowed", function_call),
         };
         write!(f, "{}", message)
     }
@@ -42,6 +53,7 @@ impl Error for SerDeError {
             Self::CannotReadFloatBytesError(io_error) => Some(io_error),
             Self::CannotSerializeStringAsInt(_) => None,
             Self::CannotConvertBytesToString(utf8_error) => Some(utf8_error),
+            Self::StringTooLong(_) | Self::CannotSerializeFunction(_) => None,
         }
     }
 }
@@ -49,10 +61,7 @@ impl Error for SerDeError {
 
 pub fn serialize_into<W: Write>(mut destination: W, column_type: ColumnType, value: &SqlValue) -> Result<(), SerDeError> {
     match column_type {
-        ColumnType::String => {
-            let blob = serialize_string(value);
-            destination.write(&blob).map_err(SerDeError::WriteError)?;
-        },
+        ColumnType::String => serialize_string_into(destination, value)?,
         ColumnType::Integer | ColumnType::Float => {
             let blob = serialize_number(value)?;
             destination.write(&blob).map_err(SerDeError::WriteError)?;
@@ -68,9 +77,10 @@ pub fn deserialize<R: Read>(mut source: R, column_type: ColumnType) -> Result<Sq
             let mut len_blob = [0u8];
             source.read(&mut len_blob).map_err(SerDeError::CannotReadStringLenError)?;
             let len = len_blob[0] as usize;
-            let mut str_blob = vec![0u8; len];
-            source.read(&mut str_blob).map_err(SerDeError::CannotReadStringBytesError)?;
-            let string = std::str::from_utf8(&str_blob).map_err(SerDeError::CannotConvertBytesToString)?;
+            // string bytes are read into stack buffer, so the only allocation is the resulting string
+            let mut str_blob = [0u8; MAX_STRING_LENGTH];
+            source.read(&mut str_blob[..len]).map_err(SerDeError::CannotReadStringBytesError)?;
+            let string = std::str::from_utf8(&str_blob[..len]).map_err(SerDeError::CannotConvertBytesToString)?;
             Ok(SqlValue::String(string.to_owned()))
         },
         ColumnType::Integer => {
@@ -97,32 +107,36 @@ fn serialize_number(value: &SqlValue) -> Result<[u8; row::NUMBER_SIZE], SerDeErr
         SqlValue::String(string) | SqlValue::Identificator(string) =>
             Err(SerDeError::CannotSerializeStringAsInt(string.clone())),
         SqlValue::Null => Ok([0; 8]),
+        SqlValue::Function(function_call) => Err(SerDeError::CannotSerializeFunction(function_call.to_string())),
     }
 }
 
-fn serialize_string(value: &SqlValue) -> [u8; row::STRING_SIZE] {
+fn serialize_string_into<W: Write>(destination: W, value: &SqlValue) -> Result<(), SerDeError> {
     match value {
-        SqlValue::Integer(int) => {
-            let string = int.to_string();
-            serialize_native_string(&string)
-        },
-        SqlValue::Float(float) => {
-            let string = float.to_string();
-            serialize_native_string(&string)
-        },
-        SqlValue::String(string) | SqlValue::Identificator(string) => serialize_native_string(string),
-        SqlValue::Null => [0; 256],
+        SqlValue::Integer(int) => serialize_native_string_into(destination, &int.to_string()),
+        SqlValue::Float(float) => serialize_native_string_into(destination, &float.to_string()),
+        SqlValue::String(string) | SqlValue::Identificator(string) => serialize_native_string_into(destination, string),
+        SqlValue::Null => write_all_fitting(destination, &[&ZERO_BYTES]),
+        SqlValue::Function(function_call) => Err(SerDeError::CannotSerializeFunction(function_call.to_string())),
     }
 }
 
-fn serialize_native_string(string: &str) -> [u8; row::STRING_SIZE] {
+fn serialize_native_string_into<W: Write>(destination: W, string: &str) -> Result<(), SerDeError> {
     let len = string.len();
-    let mut result = [0u8; 256];
-    result[0] = len as u8;
-    for (i, byte) in string.bytes().enumerate() {
-        result[i+1] = byte;
+    if len > MAX_STRING_LENGTH {
+        return Err(SerDeError::StringTooLong(len))
     }
-    result
+
+    write_all_fitting(destination, &[&[len as u8], string.as_bytes(), &ZERO_BYTES[len + 1..]])
+}
+
+// writes parts one after another until destination is full, like a single write of them joined
+fn write_all_fitting<W: Write>(mut destination: W, parts: &[&[u8]]) -> Result<(), SerDeError> {
+    for part in parts {
+        let written = destination.write(part).map_err(SerDeError::WriteError)?;
+        if written < part.len() { break }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -175,6 +189,21 @@ mod tests {
         let result = serialize_into(&mut dest[..], ColumnType::String, &SqlValue::Identificator(text));
         assert!(result.is_ok());
         assert_eq!(dest[0..6], [3u8, 97, 98, 99, 0, 0]);
+
+        let mut dest = vec![0u8; 256];
+        let result = serialize_into(&mut dest[..], ColumnType::String, &SqlValue::String("a".repeat(255)));
+        assert!(result.is_ok());
+        assert_eq!(dest[0], 255);
+
+        let result = serialize_into(&mut dest[..], ColumnType::String, &SqlValue::String("a".repeat(256)));
+        assert!(matches!(result, Err(SerDeError::StringTooLong(256))));
+
+        // cell is padded with zeros, so previous string bytes do not remain
+        let result = serialize_into(&mut dest[..], ColumnType::String, &SqlValue::String("ab".to_string()));
+        assert!(result.is_ok());
+        assert!(dest[3..].iter().all(|byte| *byte == 0));
+        serialize_into(&mut dest[..], ColumnType::String, &SqlValue::Null).unwrap();
+        assert_eq!(dest, vec![0u8; 256]);
     }
 
     #[test]
@@ -0,0 +1,160 @@
+use std::time::Instant;
+
+use crate::condition::Condition;
+use crate::command::SelectColumnName;
+use crate::lexer::SqlValue;
+use crate::query_rewriter;
+use crate::query_result::QueryResult;
+use crate::row::Row;
+use crate::row_check::RowCheck;
+use crate::table::ColumnType;
+use crate::table::error::TableError;
+
+pub const SERIES_FUNCTION_NAME: &str = "generate_series";
+const SERIES_COLUMN_NAME: &str = "value";
+/// Query result is kept in memory, so series which matches more rows fails instead
+const MAX_SERIES_ROWS: usize = 1_000_000;
+
+/// Scans virtual table with a single not null integer `value` column, which holds numbers from
+/// `start` to `stop` inclusive, taken with `step`. Rows are generated during scan and are not stored,
+/// only matching ones are collected into result, and there can be at most `MAX_SERIES_ROWS` of them.
+pub fn select_series(select_column_names: &[SelectColumnName], start: i64, stop: i64, step: i64,
+                     where_clause: Option<Condition>, deadline: Option<Instant>) -> Result<QueryResult, TableError> {
+    let column_names = vec![SERIES_COLUMN_NAME.to_string()];
+    let column_types = [ColumnType::Integer];
+
+    let mut result_column_names = vec![];
+    for select_column_name in select_column_names {
+        match select_column_name {
+            SelectColumnName::AllColumns => {},
+            SelectColumnName::CountAll | SelectColumnName::Count(_) =>
+                return Err(TableError::AggregateMixedWithColumns(SERIES_FUNCTION_NAME.to_string())),
+            SelectColumnName::TableAllColumns(qualifier) if qualifier.to_string() == SERIES_FUNCTION_NAME => {},
+            SelectColumnName::TableAllColumns(qualifier) =>
+                return Err(TableError::UnknownTableQualifier {
+                    table_name: SERIES_FUNCTION_NAME.to_string(),
+                    qualifier: qualifier.to_string(),
+                }),
+            SelectColumnName::Name(function_call @ SqlValue::Function(_)) =>
+                return Err(TableError::SelectedFunctionNotSupported(function_call.to_string())),
+            SelectColumnName::Name(column_name) => {
+                let column_name = column_name.to_string();
+                let unqualified_name = column_name.strip_prefix(SERIES_FUNCTION_NAME)
+                    .and_then(|name| name.strip_prefix('.'))
+                    .unwrap_or(&column_name);
+                if unqualified_name != SERIES_COLUMN_NAME {
+                    return Err(TableError::ColumnNotExist { table_name: SERIES_FUNCTION_NAME.to_string(), column_name })
+                }
+            },
+        }
+        result_column_names.push(SERIES_COLUMN_NAME.to_string());
+    }
+
+    let where_filter = match where_clause {
+        None => RowCheck::dummy(),
+        Some(where_clause) => query_rewriter::rewrite(where_clause.compile(SERIES_FUNCTION_NAME, &column_names)?),
+    };
+
+    let result_column_count = result_column_names.len();
+    let mut result = QueryResult {
+        column_types: vec![ColumnType::Integer; result_column_count],
+        column_nullable_flags: vec![false; result_column_count],
+        column_names: result_column_names,
+        rows: vec![],
+    };
+
+    // i128 is used so stepping past i64 bounds does not overflow
+    let mut value = start as i128;
+    while (step > 0 && value <= stop as i128) || (step < 0 && value >= stop as i128) {
+        if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            return Err(TableError::StatementTimeout(SERIES_FUNCTION_NAME.to_string()))
+        }
+
+        let sql_value = SqlValue::Integer(value as i64);
+        let row = Row::from_sql_values(std::slice::from_ref(&sql_value), &column_types)
+            .map_err(TableError::CannotSetCell)?;
+
+        if where_filter.matches(&row, &column_types)? {
+            if result.rows.len() == MAX_SERIES_ROWS {
+                return Err(TableError::TooManyRows { table_name: SERIES_FUNCTION_NAME.to_string(), max_row_count: MAX_SERIES_ROWS })
+            }
+            let result_row = Row::from_sql_values(&vec![sql_value; result_column_count], &result.column_types)
+                .map_err(TableError::CannotSetCell)?;
+            result.rows.push(result_row);
+        }
+        value += step as i128;
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmp_operator::CmpOperator;
+    use crate::binary_condition::BinaryCondition;
+
+    fn values(result: &QueryResult) -> Vec<i64> {
+        result.rows.iter()
+            .map(|row| match row.get_cell_sql_value(&result.column_types, 0).unwrap() {
+                SqlValue::Integer(value) => value,
+                value => panic!("expected integer, got {}", value),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn series_with_steps() {
+        let result = select_series(&[SelectColumnName::AllColumns], 1, 5, 1, None, None).unwrap();
+        assert_eq!(result.column_names, vec!["value".to_string()]);
+        assert_eq!(values(&result), vec![1, 2, 3, 4, 5]);
+
+        let result = select_series(&[SelectColumnName::AllColumns], 1, 6, 2, None, None).unwrap();
+        assert_eq!(values(&result), vec![1, 3, 5]);
+
+        let result = select_series(&[SelectColumnName::AllColumns], 3, 1, -1, None, None).unwrap();
+        assert_eq!(values(&result), vec![3, 2, 1]);
+
+        let result = select_series(&[SelectColumnName::AllColumns], 5, 1, 1, None, None).unwrap();
+        assert!(result.is_empty());
+
+        let result = select_series(&[SelectColumnName::AllColumns], i64::MAX - 1, i64::MAX, 5, None, None).unwrap();
+        assert_eq!(values(&result), vec![i64::MAX - 1]);
+    }
+
+    #[test]
+    fn series_columns_and_where_clause() {
+        let column_names = [
+            SelectColumnName::Name(SqlValue::Identificator("value".to_string())),
+            SelectColumnName::Name(SqlValue::Identificator("generate_series.value".to_string())),
+        ];
+        let where_clause = BinaryCondition {
+            left_value: SqlValue::Identificator("value".to_string()),
+            right_value: SqlValue::Integer(8),
+            operator: CmpOperator::Greater,
+        };
+        let result = select_series(&column_names, 1, 10, 1, Some(where_clause.into()), None).unwrap();
+        assert_eq!(result.column_count(), 2);
+        assert_eq!(values(&result), vec![9, 10]);
+
+        let column_names = [SelectColumnName::Name(SqlValue::Identificator("id".to_string()))];
+        assert!(matches!(select_series(&column_names, 1, 10, 1, None, None), Err(TableError::ColumnNotExist { .. })));
+    }
+
+    #[test]
+    fn series_is_cancelled_after_deadline() {
+        let deadline = Some(Instant::now() - std::time::Duration::from_millis(1));
+        let result = select_series(&[SelectColumnName::AllColumns], 1, i64::MAX, 1, None, deadline);
+        assert!(matches!(result, Err(TableError::StatementTimeout(_))));
+    }
+
+    #[test]
+    fn series_result_is_capped() {
+        let stop = MAX_SERIES_ROWS as i64;
+        let result = select_series(&[SelectColumnName::AllColumns], 1, stop, 1, None, None).unwrap();
+        assert_eq!(result.len(), MAX_SERIES_ROWS);
+
+        let result = select_series(&[SelectColumnName::AllColumns], 0, stop, 1, None, None);
+        assert!(matches!(result, Err(TableError::TooManyRows { max_row_count: MAX_SERIES_ROWS, .. })));
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::access::StatementPolicy;
+use crate::cursor::Cursor;
+use crate::query_result::Pagination;
+use crate::random::Random;
+use crate::sql_macro::Macro;
+use crate::statement_stats::StatementStats;
+use crate::warning::Warning;
+
+/// Settings and objects of a single client, they are not stored in database file. Handles of the
+/// same database file have a session each, which is put into database while the handle holds
+/// its lock, see `SharedDatabase::lock`, so clients do not see each other users,
+/// cursors, macros, statement policies or statement stats.
+#[derive(Debug)]
+pub struct Session {
+    // statements are checked against privileges of this user, database owner has no restrictions
+    pub current_user: Option<String>,
+    pub statement_timeout: Option<Duration>,
+    // source of RANDOM() values, seeded from current time unless seed is set
+    pub random: Random,
+    // declared cursors by name
+    pub cursors: HashMap<String, Cursor>,
+    // macros by lowercase name
+    pub macros: HashMap<String, Macro>,
+    pub statement_policy: Option<StatementPolicy>,
+    // resources, warnings and pagination of the latest statement executed in this session
+    pub last_statement_stats: StatementStats,
+    pub last_statement_warnings: Vec<Warning>,
+    pub last_statement_pagination: Option<Pagination>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            current_user: None,
+            statement_timeout: None,
+            random: Random::from_time(),
+            cursors: HashMap::new(),
+            macros: HashMap::new(),
+            statement_policy: None,
+            last_statement_stats: StatementStats::default(),
+            last_statement_warnings: vec![],
+            last_statement_pagination: None,
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError, Weak};
+
+use crate::database::Database;
+use crate::execution_error::ExecutionError;
+use crate::meta_command_error::MetaCommandError;
+use crate::session::Session;
+
+// databases opened in this process by canonical path of database file
+static OPEN_DATABASES: LazyLock<Mutex<HashMap<PathBuf, Weak<Mutex<Database>>>>> = LazyLock::new(Default::default);
+
+/// Handle of a database opened in this process. Handles which open the same database file share
+/// one `Database`, so they see the same tables, sequences, users and cached pages, instead of
+/// overwriting each other files. Database lock is taken for a whole statement, so statements of
+/// different handles, including ones from other threads, are executed one after another.
+/// Each handle has its own session: current user, statement timeout, random seed, cursors, macros,
+/// statement policy and last statement stats are not seen by other handles. Cloned handle starts
+/// with a new session.
+pub struct SharedDatabase {
+    database_filepath: PathBuf,
+    database: Arc<Mutex<Database>>,
+    session: Mutex<Session>,
+}
+
+/// Database locked by a handle, with the session of that handle
+pub struct DatabaseGuard<'a> {
+    database: MutexGuard<'a, Database>,
+    session: MutexGuard<'a, Session>,
+}
+
+impl Deref for DatabaseGuard<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.database
+    }
+}
+
+impl DerefMut for DatabaseGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Database {
+        &mut self.database
+    }
+}
+
+impl Drop for DatabaseGuard<'_> {
+    fn drop(&mut self) {
+        self.database.swap_session(&mut self.session);
+    }
+}
+
+impl Clone for SharedDatabase {
+    fn clone(&self) -> Self {
+        Self::new(self.database_filepath.clone(), Arc::clone(&self.database))
+    }
+}
+
+impl SharedDatabase {
+    /// Returns handle of already opened database, or opens it if there is none
+    pub fn open(database_filepath: &Path) -> Result<SharedDatabase, MetaCommandError> {
+        let canonical_filepath = fs::canonicalize(database_filepath)?;
+        let mut open_databases = Self::open_databases();
+        if let Some(database) = open_databases.get(&canonical_filepath).and_then(Weak::upgrade) {
+            return Ok(Self::new(canonical_filepath, database))
+        }
+
+        let database = Arc::new(Mutex::new(Database::from(database_filepath)?));
+        open_databases.insert(canonical_filepath.clone(), Arc::downgrade(&database));
+        Ok(Self::new(canonical_filepath, database))
+    }
+
+    /// Opens damaged database read-only, see `Database::salvage`. Database which is already opened
+    /// by other handles is not salvaged, since its tables have been opened successfully.
+    pub fn salvage(database_filepath: &Path) -> Result<SharedDatabase, MetaCommandError> {
+        let canonical_filepath = fs::canonicalize(database_filepath)?;
+        let mut open_databases = Self::open_databases();
+        if open_databases.get(&canonical_filepath).is_some_and(|database| database.strong_count() > 0) {
+            return Err(MetaCommandError::DatabaseInUse(canonical_filepath))
+        }
+
+        let database = Arc::new(Mutex::new(Database::salvage(database_filepath)?));
+        open_databases.insert(canonical_filepath.clone(), Arc::downgrade(&database));
+        Ok(Self::new(canonical_filepath, database))
+    }
+
+    fn new(database_filepath: PathBuf, database: Arc<Mutex<Database>>) -> SharedDatabase {
+        Self { database_filepath, database, session: Mutex::new(Session::default()) }
+    }
+
+    /// True if database file is opened by any handle in this process
+    pub fn is_open(database_filepath: &Path) -> bool {
+        match fs::canonicalize(database_filepath) {
+            Ok(canonical_filepath) => Self::open_databases().get(&canonical_filepath)
+                .is_some_and(|database| database.strong_count() > 0),
+            Err(_) => false,
+        }
+    }
+
+    /// Waits until statements of other handles are finished. Lock is not poisoned by a panic
+    /// during statement, since database has no state which is valid only between statements.
+    /// Session of this handle is put into database until the lock is released.
+    pub fn lock(&self) -> DatabaseGuard<'_> {
+        let mut database = self.database.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+        database.swap_session(&mut session);
+        DatabaseGuard { database, session }
+    }
+
+    /// Number of handles of this database, including this one
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.database)
+    }
+
+    /// Closes database if this is its last handle, otherwise only syncs it, so that
+    /// changes made through this handle are on disk
+    pub fn close(self) -> Result<(), ExecutionError> {
+        // registry is locked, so database cannot be opened again while it is being closed
+        let mut open_databases = Self::open_databases();
+        match Arc::try_unwrap(self.database) {
+            Ok(database) => {
+                open_databases.remove(&self.database_filepath);
+                database.into_inner().unwrap_or_else(PoisonError::into_inner).close()
+            },
+            Err(database) => {
+                drop(open_databases);
+                let result = database.lock().unwrap_or_else(PoisonError::into_inner).sync();
+                result
+            },
+        }
+    }
+
+    fn open_databases() -> MutexGuard<'static, HashMap<PathBuf, Weak<Mutex<Database>>>> {
+        OPEN_DATABASES.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use crate::access::{StatementKind, StatementPolicy};
+    use crate::command::{Command, ColumnDefinition, SelectColumnName};
+    use crate::lexer::{SqlValue, Token};
+    use crate::table::{ColumnType, TableOptions};
+    use crate::temp_file::TempFile;
+
+    fn create_database() -> TempFile {
+        let db_file = TempFile::new("database.db").unwrap();
+        db_file.writeln_str(db_file.temp_dir_path.to_str().unwrap()).unwrap();
+        db_file
+    }
+
+    fn select_count(database: &SharedDatabase) -> usize {
+        let select = Command::Select {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: vec![SelectColumnName::AllColumns],
+            where_clause: None,
+        };
+        database.lock().execute(select).unwrap().unwrap().len()
+    }
+
+    #[test]
+    fn handles_share_database() {
+        let db_file = create_database();
+        let first = SharedDatabase::open(db_file.path()).unwrap();
+        let second = SharedDatabase::open(&db_file.temp_dir_path.join(".").join("database.db")).unwrap();
+        assert_eq!(first.handle_count(), 2);
+
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        first.lock().execute(create_table).unwrap();
+        assert_eq!(select_count(&second), 0);
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let database = second.clone();
+                thread::spawn(move || {
+                    for j in 0..25 {
+                        let insert = Command::InsertInto {
+                            table_name: SqlValue::Identificator("users".to_string()),
+                            column_names: None,
+                            values: vec![SqlValue::Integer(i * 100 + j).into()],
+                        };
+                        database.lock().execute(insert).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(select_count(&first), 100);
+
+        first.close().unwrap();
+        assert!(SharedDatabase::is_open(db_file.path()));
+        assert_eq!(select_count(&second), 100);
+        second.close().unwrap();
+        assert!(!SharedDatabase::is_open(db_file.path()));
+
+        let reopened = SharedDatabase::open(db_file.path()).unwrap();
+        assert_eq!(reopened.handle_count(), 1);
+        assert_eq!(select_count(&reopened), 100);
+    }
+
+    #[test]
+    fn handles_have_own_sessions() {
+        let db_file = create_database();
+        let first = SharedDatabase::open(db_file.path()).unwrap();
+        let second = SharedDatabase::open(db_file.path()).unwrap();
+        first.lock().execute(Command::CreateUser { user_name: SqlValue::Identificator("alice".to_string()) }).unwrap();
+
+        first.lock().set_current_user(Some("alice")).unwrap();
+        assert_eq!(first.lock().current_user(), Some("alice"));
+        assert_eq!(second.lock().current_user(), None);
+        assert_eq!(second.clone().lock().current_user(), None);
+
+        // user dropped by other handle has no privileges at all
+        second.lock().execute(Command::DropUser { user_name: SqlValue::Identificator("alice".to_string()) }).unwrap();
+        let drop_table = Command::DropTable { table_name: SqlValue::Identificator("users".to_string()) };
+        assert!(matches!(first.lock().execute(drop_table), Err(ExecutionError::UserNotExist(_))));
+
+        let create_macro = Command::CreateMacro {
+            macro_name: SqlValue::Identificator("adult".to_string()),
+            parameter_names: vec![],
+            body: vec![Token::Value(SqlValue::Integer(18))],
+        };
+        second.lock().execute(create_macro).unwrap();
+        let call = vec![Token::Value(SqlValue::Identificator("adult".to_string())), Token::LeftParenthesis, Token::RightParenthesis];
+        assert_eq!(second.lock().expand_macros(call.clone()).unwrap(), vec![Token::Value(SqlValue::Integer(18))]);
+        assert_eq!(first.lock().expand_macros(call.clone()).unwrap(), call);
+    }
+
+    #[test]
+    fn handles_have_own_statement_policies() {
+        let db_file = create_database();
+        let first = SharedDatabase::open(db_file.path()).unwrap();
+        let second = SharedDatabase::open(db_file.path()).unwrap();
+        let create_table = Command::CreateTable {
+            table_name: SqlValue::Identificator("users".to_string()),
+            columns: vec![
+                ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ],
+            options: TableOptions::default(),
+            if_not_exists: false,
+        };
+        second.lock().execute(create_table).unwrap();
+        let insert = Command::InsertInto {
+            table_name: SqlValue::Identificator("users".to_string()),
+            column_names: None,
+            values: vec![SqlValue::Integer(1).into()],
+        };
+        second.lock().execute(insert).unwrap();
+        first.lock().set_statement_policy(Some(StatementPolicy::allow_kinds(&[StatementKind::Read])));
+
+        let delete = || Command::Delete { table_name: SqlValue::Identificator("users".to_string()), where_clause: None };
+        assert!(matches!(first.lock().execute(delete()), Err(ExecutionError::StatementNotAllowed(_))));
+        second.lock().execute(delete()).unwrap();
+        assert_eq!(first.lock().last_statement_stats().rows_scanned, 0);
+        assert_eq!(second.lock().last_statement_stats().rows_scanned, 1);
+        assert_eq!(select_count(&first), 0);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::lexer::{Token, SqlValue};
+
+// macro which calls itself would be expanded endlessly
+const MAX_EXPANSION_DEPTH: usize = 16;
+
+/// Statement fragment defined by `CREATE MACRO name(parameter, ...) AS body`. Macro call is replaced
+/// by its body tokens before statement is parsed, and each parameter in body is replaced by tokens
+/// of the corresponding argument. Macros belong to database connection and are not stored in file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    parameter_names: Vec<String>,
+    body: Vec<Token>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MacroError {
+    ArgumentCountInvalid { macro_name: String, expected: usize, count: usize },
+    ArgumentsNotFinished(String),
+    ExpansionTooDeep(String),
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ArgumentCountInvalid { macro_name, expected, count } =>
+                write!(f, "macro {}() expects {} argument(s), got {}", macro_name, expected, count),
+            Self::ArgumentsNotFinished(macro_name) => write!(f, "arguments of macro {}() are not finished, ')' is missing", macro_name),
+            Self::ExpansionTooDeep(macro_name) =>
+                write!(f, "macro {}() is nested deeper than {} levels, it probably calls itself", macro_name, MAX_EXPANSION_DEPTH),
+        }
+    }
+}
+
+impl Error for MacroError { }
+
+impl Macro {
+    pub fn new(parameter_names: Vec<String>, body: Vec<Token>) -> Macro {
+        Macro { parameter_names, body }
+    }
+
+    fn substitute(&self, arguments: &[&[Token]]) -> Vec<Token> {
+        self.body.iter()
+            .flat_map(|token| {
+                let parameter_position = match token {
+                    Token::Value(SqlValue::Identificator(name)) =>
+                        self.parameter_names.iter().position(|parameter_name| parameter_name.eq_ignore_ascii_case(name)),
+                    _ => None,
+                };
+                match parameter_position {
+                    Some(position) => arguments[position].to_vec(),
+                    None => vec![token.clone()],
+                }
+            })
+            .collect()
+    }
+}
+
+/// Replaces calls of macros in statement tokens, names of `macros` should be in lowercase.
+/// Definition of a macro is left as is, so its body is expanded only when it is called.
+pub fn expand(tokens: Vec<Token>, macros: &HashMap<String, Macro>) -> Result<Vec<Token>, MacroError> {
+    if macros.is_empty() || matches!(tokens.as_slice(), [Token::Create, Token::Macro, ..]) {
+        return Ok(tokens)
+    }
+    expand_tokens(&tokens, macros, 0)
+}
+
+fn expand_tokens(tokens: &[Token], macros: &HashMap<String, Macro>, depth: usize) -> Result<Vec<Token>, MacroError> {
+    let mut expanded = vec![];
+    let mut position = 0;
+    while position < tokens.len() {
+        let called_macro = match (&tokens[position], tokens.get(position + 1)) {
+            (Token::Value(SqlValue::Identificator(name)), Some(Token::LeftParenthesis)) =>
+                macros.get(&name.to_lowercase()).map(|called_macro| (name, called_macro)),
+            _ => None,
+        };
+        let (macro_name, called_macro) = match called_macro {
+            Some(called_macro) => called_macro,
+            None => {
+                expanded.push(tokens[position].clone());
+                position += 1;
+                continue
+            },
+        };
+        if depth == MAX_EXPANSION_DEPTH {
+            return Err(MacroError::ExpansionTooDeep(macro_name.to_string()))
+        }
+
+        let (arguments, arguments_len) = split_arguments(&tokens[position + 2..])
+            .ok_or_else(|| MacroError::ArgumentsNotFinished(macro_name.to_string()))?;
+        if arguments.len() != called_macro.parameter_names.len() {
+            return Err(MacroError::ArgumentCountInvalid {
+                macro_name: macro_name.to_string(),
+                expected: called_macro.parameter_names.len(),
+                count: arguments.len(),
+            })
+        }
+        // arguments are expanded along with the body they are placed into
+        expanded.extend(expand_tokens(&called_macro.substitute(&arguments), macros, depth + 1)?);
+        position += 2 + arguments_len + 1;
+    }
+    Ok(expanded)
+}
+
+// tokens after the opening parenthesis are split by commas outside of nested parentheses,
+// returns arguments and number of tokens before the closing parenthesis
+fn split_arguments(tokens: &[Token]) -> Option<(Vec<&[Token]>, usize)> {
+    let mut arguments = vec![];
+    let mut nesting = 0;
+    let mut argument_start = 0;
+    for (position, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftParenthesis => nesting += 1,
+            Token::RightParenthesis if nesting > 0 => nesting -= 1,
+            Token::RightParenthesis => {
+                if position > 0 {
+                    arguments.push(&tokens[argument_start..position]);
+                }
+                return Some((arguments, position))
+            },
+            Token::Comma if nesting == 0 => {
+                arguments.push(&tokens[argument_start..position]);
+                argument_start = position + 1;
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+
+    #[test]
+    fn macro_calls_are_expanded() {
+        let mut macros = HashMap::new();
+        let body = lexer::to_tokens("(a >= 18)").unwrap();
+        macros.insert("adult".to_string(), Macro::new(vec!["a".to_string()], body));
+        let body = lexer::to_tokens("(ADULT(a) AND b IN (1, 2))").unwrap();
+        macros.insert("active_adult".to_string(), Macro::new(vec!["a".to_string(), "b".to_string()], body));
+
+        let tokens = lexer::to_tokens("SELECT * FROM users WHERE Active_Adult(age, status) OR adult(lower(x))").unwrap();
+        assert_eq!(
+            expand(tokens, &macros).unwrap(),
+            lexer::to_tokens("SELECT * FROM users WHERE ((age >= 18) AND status IN (1, 2)) OR (lower(x) >= 18)").unwrap(),
+        );
+        // column which is named as macro is not a call
+        let tokens = lexer::to_tokens("SELECT adult FROM users").unwrap();
+        assert_eq!(expand(tokens, &macros).unwrap(), lexer::to_tokens("SELECT adult FROM users").unwrap());
+        let tokens = lexer::to_tokens("CREATE MACRO adult(a) AS (adult(a))").unwrap();
+        assert_eq!(expand(tokens, &macros).unwrap(), lexer::to_tokens("CREATE MACRO adult(a) AS (adult(a))").unwrap());
+
+        let tokens = lexer::to_tokens("SELECT * FROM users WHERE adult(age, 1)").unwrap();
+        assert_eq!(expand(tokens, &macros), Err(MacroError::ArgumentCountInvalid { macro_name: "adult".to_string(), expected: 1, count: 2 }));
+        let tokens = lexer::to_tokens("SELECT * FROM users WHERE adult(age").unwrap();
+        assert_eq!(expand(tokens, &macros), Err(MacroError::ArgumentsNotFinished("adult".to_string())));
+
+        macros.insert("endless".to_string(), Macro::new(vec![], lexer::to_tokens("endless()").unwrap()));
+        let tokens = lexer::to_tokens("SELECT * FROM users WHERE endless()").unwrap();
+        assert_eq!(expand(tokens, &macros), Err(MacroError::ExpansionTooDeep("endless".to_string())));
+    }
+}
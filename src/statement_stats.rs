@@ -0,0 +1,30 @@
+use std::fmt;
+use std::ops::AddAssign;
+
+/// Resources used by a single statement, collected from every table it touched
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StatementStats {
+    pub pages_read: u64,
+    pub pages_written: u64,
+    pub rows_scanned: u64,
+    pub index_probes: u64,
+    // rows which scans of salvaged table could not read, they are reported as a warning instead
+    pub damaged_rows_skipped: u64,
+}
+
+impl AddAssign for StatementStats {
+    fn add_assign(&mut self, other: Self) {
+        self.pages_read += other.pages_read;
+        self.pages_written += other.pages_written;
+        self.rows_scanned += other.rows_scanned;
+        self.index_probes += other.index_probes;
+        self.damaged_rows_skipped += other.damaged_rows_skipped;
+    }
+}
+
+impl fmt::Display for StatementStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pages read: {}, pages written: {}, rows scanned: {}, index probes: {}",
+               self.pages_read, self.pages_written, self.rows_scanned, self.index_probes)
+    }
+}
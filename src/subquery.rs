@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use crate::binary_condition::BinaryCondition;
+use crate::cmp_operator::CmpOperator;
+use crate::condition::{Condition, Subquery};
+use crate::lexer::SqlValue;
+use crate::query_result::QueryResult;
+use crate::table::error::TableError;
+
+/// Condition which is true for every row if `value` is true, and false otherwise
+pub fn constant(value: bool) -> Condition {
+    BinaryCondition {
+        left_value: SqlValue::Integer(1),
+        right_value: SqlValue::Integer(value as i64),
+        operator: CmpOperator::Equals,
+    }.into()
+}
+
+/// Columns of outer table referenced in subquery where clause. Column name is resolved to subquery
+/// table column first, so outer column should be qualified with table name if subquery table has a
+/// column with the same name. Subquery on the same table cannot reference outer row.
+pub fn outer_column_names(subquery: &Subquery, inner: (&str, &[String]), outer: (&str, &[String])) -> Vec<String> {
+    let mut column_names = vec![];
+    let binary_conditions = subquery.where_clause.iter().flat_map(Condition::binary_conditions);
+    for binary_condition in binary_conditions {
+        for value in binary_condition.left_value.operands().into_iter().chain(binary_condition.right_value.operands()) {
+            match outer_column_name(value, inner, outer) {
+                Some(column_name) if !column_names.contains(&column_name) => column_names.push(column_name),
+                _ => {},
+            }
+        }
+    }
+    column_names
+}
+
+/// Replaces correlated subquery with a condition on outer table columns. Subquery rows are checked
+/// once for each distinct combination of referenced outer columns values, until first matching row
+/// is found. Resulting condition is an `OR` of combinations for which matching row exists, e.g.
+/// `user_id = 1 OR user_id = 3`, so outer table can still be looked up in index.
+/// `outer_values` is a result of selecting referenced columns from outer table.
+pub fn resolve_correlated(subquery: Subquery, inner: (&str, &QueryResult), outer: (&str, &QueryResult),
+                          deadline: Option<Instant>) -> Result<Condition, TableError> {
+    let (inner_table_name, inner_rows) = inner;
+    let (outer_table_name, outer_values) = outer;
+    let mut seen_combinations = HashSet::new();
+    let mut matched_conditions = vec![];
+
+    for outer_row in &outer_values.rows {
+        // cells bytes with null flags identify combination without comparing floats
+        let combination_key: Vec<u8> = (0..outer_values.column_count())
+            .flat_map(|i| outer_row.get_cell_bytes(&outer_values.column_types, i).iter().copied().chain([outer_row.cell_is_null(i) as u8]))
+            .collect();
+        if !seen_combinations.insert(combination_key) { continue }
+
+        if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            return Err(TableError::StatementTimeout(inner_table_name.to_string()))
+        }
+
+        let combination = outer_row.get_sql_values(&outer_values.column_types).map_err(TableError::CannotGetCell)?;
+        let mut where_clause = match subquery.where_clause.clone() {
+            Some(where_clause) => where_clause,
+            None => constant(true),
+        };
+        for value in where_clause.values_mut() {
+            let outer_column_name = outer_column_name(value, (inner_table_name, &inner_rows.column_names), (outer_table_name, &outer_values.column_names));
+            if let Some(column_name) = outer_column_name {
+                let column_index = outer_values.column_names.iter().position(|name| *name == column_name).unwrap();
+                *value = combination[column_index].clone();
+            }
+        }
+
+        let row_check = where_clause.compile(inner_table_name, &inner_rows.column_names)?;
+        let mut matched = false;
+        for inner_row in &inner_rows.rows {
+            if row_check.matches(inner_row, &inner_rows.column_types)? {
+                matched = true;
+                break
+            }
+        }
+
+        if matched {
+            let column_conditions = outer_values.column_names.iter()
+                .zip(combination)
+                .map(|(column_name, value)| {
+                    let operator = match value {
+                        SqlValue::Null => CmpOperator::IsNull,
+                        _ => CmpOperator::Equals,
+                    };
+                    BinaryCondition { left_value: SqlValue::Identificator(column_name.clone()), right_value: value, operator }.into()
+                })
+                .reduce(Condition::and)
+                .expect("correlated subquery should reference at least one outer column");
+            matched_conditions.push(column_conditions);
+        }
+    }
+
+    Ok(any_of(matched_conditions))
+}
+
+fn outer_column_name(value: &SqlValue, inner: (&str, &[String]), outer: (&str, &[String])) -> Option<String> {
+    let name = match value {
+        SqlValue::Identificator(name) => name,
+        _ => return None,
+    };
+    if inner.1.contains(name) { return None }
+
+    let column_name = match name.split_once('.') {
+        Some((qualifier, column_name)) if qualifier == outer.0 && qualifier != inner.0 => column_name,
+        Some(_) => return None,
+        None => name.as_str(),
+    };
+    outer.1.iter().any(|outer_column_name| outer_column_name == column_name).then(|| column_name.to_string())
+}
+
+// conditions are combined into a balanced tree, so long lists do not cause deep recursion on compile and match
+fn any_of(mut conditions: Vec<Condition>) -> Condition {
+    match conditions.len() {
+        0 => constant(false),
+        1 => conditions.pop().unwrap(),
+        len => {
+            let right = conditions.split_off(len / 2);
+            Condition::or(any_of(conditions), any_of(right))
+        },
+    }
+}
@@ -0,0 +1,157 @@
+use std::time::Instant;
+
+use crate::command::SelectColumnName;
+use crate::condition::Condition;
+use crate::cross_join;
+use crate::lexer::SqlValue;
+use crate::query_result::QueryResult;
+use crate::row::Row;
+use crate::schema_diff::Catalog;
+use crate::table::ColumnType;
+use crate::table::error::TableError;
+
+pub const TABLES_TABLE_NAME: &str = "yarrd_tables";
+pub const COLUMNS_TABLE_NAME: &str = "yarrd_columns";
+pub const INDEXES_TABLE_NAME: &str = "yarrd_indexes";
+
+// column name, type and whether column can be null
+type SystemColumn = (&'static str, ColumnType, bool);
+
+pub fn is_system_table_name(table_name: &str) -> bool {
+    [TABLES_TABLE_NAME, COLUMNS_TABLE_NAME, INDEXES_TABLE_NAME].contains(&table_name)
+}
+
+/// Rows of read-only virtual table which describes database schema, they are built from catalog
+/// on each select, so there are no files behind system tables. `row_count` gives number of rows
+/// of a catalog table. Returns `None` if table name is not a name of system table.
+pub fn rows(table_name: &str, catalog: &Catalog, row_count: impl Fn(&str) -> usize) -> Option<Result<QueryResult, TableError>> {
+    let (columns, rows): (&[SystemColumn], Vec<Vec<SqlValue>>) = match table_name {
+        TABLES_TABLE_NAME => {
+            let rows = catalog.tables.iter()
+                .map(|table| vec![
+                    SqlValue::String(table.name.clone()),
+                    SqlValue::Integer(row_count(&table.name) as i64),
+                    SqlValue::Integer(table.columns.len() as i64),
+                    SqlValue::Integer(table.indexes.len() as i64),
+                    optional_string(table.options.names().join(", ")),
+                ])
+                .collect();
+            (&[
+                ("table_name", ColumnType::String, false),
+                ("row_count", ColumnType::Integer, false),
+                ("column_count", ColumnType::Integer, false),
+                ("index_count", ColumnType::Integer, false),
+                ("options", ColumnType::String, true),
+            ], rows)
+        },
+        COLUMNS_TABLE_NAME => {
+            let rows = catalog.tables.iter()
+                .flat_map(|table| table.columns.iter().enumerate().map(|(i, column)| {
+                    let constraints: Vec<String> = column.constraints.iter().map(ToString::to_string).collect();
+                    vec![
+                        SqlValue::String(table.name.clone()),
+                        SqlValue::String(column.name.clone()),
+                        SqlValue::Integer(i as i64 + 1),
+                        SqlValue::String(column.kind.to_string()),
+                        optional_string(constraints.join(" ")),
+                    ]
+                }))
+                .collect();
+            (&[
+                ("table_name", ColumnType::String, false),
+                ("column_name", ColumnType::String, false),
+                ("position", ColumnType::Integer, false),
+                ("column_type", ColumnType::String, false),
+                ("constraints", ColumnType::String, true),
+            ], rows)
+        },
+        INDEXES_TABLE_NAME => {
+            let rows = catalog.tables.iter()
+                .flat_map(|table| table.indexes.iter().map(|(index_name, column_name)| vec![
+                    SqlValue::String(index_name.clone()),
+                    SqlValue::String(table.name.clone()),
+                    SqlValue::String(column_name.clone()),
+                ]))
+                .collect();
+            (&[
+                ("index_name", ColumnType::String, false),
+                ("table_name", ColumnType::String, false),
+                ("column_name", ColumnType::String, false),
+            ], rows)
+        },
+        _ => return None,
+    };
+
+    let column_types: Vec<ColumnType> = columns.iter().map(|(_, column_type, _)| *column_type).collect();
+    let rows = rows.iter()
+        .map(|values| Row::from_sql_values(values, &column_types).map_err(TableError::CannotSetCell))
+        .collect::<Result<Vec<Row>, TableError>>();
+    Some(rows.map(|rows| QueryResult {
+        column_names: columns.iter().map(|(column_name, _, _)| column_name.to_string()).collect(),
+        column_nullable_flags: columns.iter().map(|(_, _, nullable)| *nullable).collect(),
+        column_types,
+        rows,
+    }))
+}
+
+/// Selects from system table rows as from a regular table
+pub fn select(select_column_names: &[SelectColumnName], table_name: &str, rows: QueryResult,
+              where_clause: Option<Condition>, deadline: Option<Instant>) -> Result<QueryResult, TableError> {
+    // joined columns are qualified with table name, unlike columns of a table selected with `table.*`
+    let select_column_names: Vec<SelectColumnName> = select_column_names.iter()
+        .map(|select_column_name| match select_column_name {
+            SelectColumnName::AllColumns => SelectColumnName::TableAllColumns(SqlValue::Identificator(table_name.to_string())),
+            select_column_name => select_column_name.clone(),
+        })
+        .collect();
+    cross_join::select_cross_join(&select_column_names, vec![(table_name.to_string(), rows)], where_clause, deadline)
+}
+
+fn optional_string(string: String) -> SqlValue {
+    match string.is_empty() {
+        true => SqlValue::Null,
+        false => SqlValue::String(string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema_diff::{ColumnSchema, TableSchema};
+    use crate::table::{Constraint, TableOptions};
+
+    #[test]
+    fn catalog_rows() {
+        let users = TableSchema {
+            name: "users".to_string(),
+            columns: vec![
+                ColumnSchema { name: "id".to_string(), kind: ColumnType::Integer, constraints: vec![Constraint::NotNull] },
+                ColumnSchema { name: "name".to_string(), kind: ColumnType::String, constraints: vec![] },
+            ],
+            indexes: vec![("users_id".to_string(), "id".to_string())],
+            options: TableOptions { soft_delete: true, ..Default::default() },
+        };
+        let catalog = Catalog { tables: vec![users], sequences: vec![] };
+
+        let tables = rows(TABLES_TABLE_NAME, &catalog, |_| 7).unwrap().unwrap();
+        assert_eq!(tables.column_names, vec!["table_name", "row_count", "column_count", "index_count", "options"]);
+        assert_eq!(tables.row_values(0).unwrap().unwrap(), vec![
+            SqlValue::String("users".to_string()), SqlValue::Integer(7), SqlValue::Integer(2), SqlValue::Integer(1),
+            SqlValue::String("soft_delete".to_string()),
+        ]);
+
+        let columns = rows(COLUMNS_TABLE_NAME, &catalog, |_| 7).unwrap().unwrap();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns.row_values(0).unwrap().unwrap()[2..], [
+            SqlValue::Integer(1), SqlValue::String("INT".to_string()), SqlValue::String("NOT NULL".to_string()),
+        ]);
+        assert_eq!(columns.row_values(1).unwrap().unwrap()[4], SqlValue::Null);
+
+        let indexes = rows(INDEXES_TABLE_NAME, &catalog, |_| 7).unwrap().unwrap();
+        assert_eq!(indexes.row_values(0).unwrap().unwrap(), vec![
+            SqlValue::String("users_id".to_string()), SqlValue::String("users".to_string()), SqlValue::String("id".to_string()),
+        ]);
+
+        assert!(rows("users", &catalog, |_| 7).is_none());
+    }
+}
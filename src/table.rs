@@ -1,22 +1,46 @@
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::iter::zip;
-use std::fs;
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, Read, Write};
+use std::fs::{self, File, OpenOptions};
+use std::time::Instant;
 
-use crate::command::{ColumnDefinition, FieldAssignment, SelectColumnName};
+use crate::command::{ColumnDefinition, FieldAssignment, IndexHint, SelectColumnName};
 use crate::binary_condition::BinaryCondition;
+use crate::cmp_operator::CmpOperator;
+use crate::condition::Condition;
 use crate::lexer::SqlValue;
-use crate::row::Row;
+use crate::function::FunctionCall;
+use crate::row::{Row, STRING_SIZE};
 use crate::query_result::QueryResult;
-use crate::pager::Pager;
-use crate::row_check::RowCheck;
+use crate::pager::{Pager, PagerError};
+use crate::row_check::{RowCheck, RowCheckValue};
+use crate::helpers::hex_dump;
+use crate::query_rewriter;
 use crate::hash_index::HashIndex;
 use crate::hash_index::error::HashIndexError;
+use crate::statement_stats::StatementStats;
 use error::TableError;
+use change_log::{ChangeLog, ChangeOperation};
+use checksums::RowChecksums;
+use row_id_bitmap::RowIdBitmap;
 
 pub mod error;
-
-#[derive(Debug, Clone, Copy)]
+pub mod change_log;
+pub mod checksums;
+mod row_id_bitmap;
+
+pub(crate) const TOMBSTONES_EXTENSION: &str = "tombstones";
+pub(crate) const CHANGE_LOG_EXTENSION: &str = "changes";
+pub(crate) const CHECKSUMS_EXTENSION: &str = "checksums";
+// rows found by index are fetched one by one, so if index is expected to return more than this
+// part of table rows, row ids are collected into bitmap first to read pages in order
+const INDEX_SCAN_MAX_ROWS_FRACTION: u64 = 16;
+// if index is expected to return more than this part of table rows, reading the whole table is cheaper
+const BITMAP_SCAN_MAX_ROWS_FRACTION: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ColumnType {
     Integer,
     Float,
@@ -51,6 +75,32 @@ pub enum Constraint {
     NotNull,
     Default(SqlValue),
     Check(BinaryCondition),
+    /// Check which can be dropped by its name, names are unique within a table
+    NamedCheck(String, BinaryCondition),
+    References(ForeignKey),
+}
+
+impl Constraint {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::NamedCheck(name, _) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Column values should be present in the referenced column of another table (or of the same one),
+/// it is enforced by database, since table does not see other tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    pub table_name: String,
+    pub column_name: String,
+}
+
+impl fmt::Display for ForeignKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.table_name, self.column_name)
+    }
 }
 
 impl fmt::Display for Constraint {
@@ -59,15 +109,102 @@ impl fmt::Display for Constraint {
             Self::NotNull => write!(f, "NOT NULL"),
             Self::Default(value) => write!(f, "DEFAULT {}", value),
             Self::Check(row_check) => write!(f, "CHECK ({})", row_check),
+            Self::NamedCheck(name, row_check) => write!(f, "CONSTRAINT {} CHECK ({})", name, row_check),
+            Self::References(foreign_key) => write!(f, "REFERENCES {}", foreign_key),
         }
     }
 }
 
+/// Per-table settings given in `CREATE TABLE ... WITH (...)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TableOptions {
+    /// DELETE marks rows with tombstone instead of freeing their slots, such rows are
+    /// hidden from other statements and can be restored by UNDELETE until next vacuum.
+    pub soft_delete: bool,
+    /// Every row change is appended to the table's change log, which can be read
+    /// with `SELECT * FROM changes(table_name) SINCE lsn`.
+    pub change_log: bool,
+    /// Checksum of each row is stored next to table and verified when row is read, so
+    /// silent corruption of table file is reported instead of returning damaged values.
+    pub checksums: bool,
+    /// String cells are replaced with codes of page dictionary, where each distinct string value
+    /// of a page is stored once, so pages of columns with few distinct values hold more rows.
+    pub compression: bool,
+}
+
+impl TableOptions {
+    pub const SOFT_DELETE: &'static str = "soft_delete";
+    pub const CHANGE_LOG: &'static str = "change_log";
+    pub const CHECKSUMS: &'static str = "checksums";
+    pub const COMPRESSION: &'static str = "compression";
+
+    pub fn set(&mut self, option_name: &str, enabled: bool) -> Result<(), TableError> {
+        match option_name {
+            Self::SOFT_DELETE => self.soft_delete = enabled,
+            Self::CHANGE_LOG => self.change_log = enabled,
+            Self::CHECKSUMS => self.checksums = enabled,
+            Self::COMPRESSION => self.compression = enabled,
+            _ => return Err(TableError::TableOptionUnknown(option_name.to_string())),
+        }
+
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+        if self.soft_delete { names.push(Self::SOFT_DELETE) }
+        if self.change_log { names.push(Self::CHANGE_LOG) }
+        if self.checksums { names.push(Self::CHECKSUMS) }
+        if self.compression { names.push(Self::COMPRESSION) }
+        names
+    }
+}
+
 struct ScanProduct {
     row_id: u64,
     row: Row,
 }
 
+/// Applies where clause to batches of scanned rows (one page of rows by default), so each compared
+/// column is read from all rows of a batch at once instead of checking rows one by one
+struct BatchFilter<'a, I> {
+    scan: I,
+    row_check: RowCheck,
+    column_types: &'a [ColumnType],
+    batch_size: usize,
+    matched: VecDeque<Result<ScanProduct, TableError>>,
+}
+
+impl<I: Iterator<Item = Result<ScanProduct, TableError>>> Iterator for BatchFilter<'_, I> {
+    type Item = Result<ScanProduct, TableError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.matched.is_empty() {
+            let mut batch = vec![];
+            let mut scan_error = None;
+            while batch.len() < self.batch_size {
+                match self.scan.next() {
+                    None => break,
+                    Some(Ok(scan_product)) => batch.push(scan_product),
+                    Some(Err(error)) => {
+                        scan_error = Some(error);
+                        break
+                    },
+                }
+            }
+            if batch.is_empty() && scan_error.is_none() { return None }
+
+            let rows: Vec<&Row> = batch.iter().map(|scan_product| &scan_product.row).collect();
+            match self.row_check.matches_batch(&rows, self.column_types) {
+                Ok(matched) => self.matched.extend(zip(batch, matched).filter(|(_, matched)| *matched).map(|(scan_product, _)| Ok(scan_product))),
+                Err(error) => self.matched.push_back(Err(error)),
+            }
+            self.matched.extend(scan_error.map(Err));
+        }
+        self.matched.pop_front()
+    }
+}
+
 #[derive(Debug)]
 struct TableHeaders {
     pub name: String,
@@ -81,6 +218,9 @@ struct TableHeaders {
 #[derive(Debug)]
 pub struct Table {
     pub row_count: usize, // this should go to metadata if we'll introduce more stats
+    // number of NULL cells of each column among not deleted rows, so `IS NULL` filter can be
+    // skipped without scanning when column has no nulls
+    null_counts: Vec<usize>,
     headers: TableHeaders,
     pager: Pager,
     table_filepath: PathBuf,
@@ -88,11 +228,25 @@ pub struct Table {
     // B-Tree, inverted, or any other type of index soon, I'm leaving straight index class inside
     // Option
     column_indexes: Vec<Option<HashIndex>>,
+    options: TableOptions,
+    // ids of soft deleted rows, they are stored in a separate file next to table file,
+    // so table file layout stays the same regardless of table options
+    tombstones: HashSet<u64>,
+    change_log: Option<ChangeLog>,
+    checksums: Option<RowChecksums>,
+    // set by database before each statement, scans are aborted once it is passed
+    statement_deadline: Option<Instant>,
+    // set by database for a select with index hint, and reset once it is executed
+    index_hint: Option<IndexHint>,
+    // damaged rows are skipped by scans instead of failing them, see `Database::salvage`
+    salvage: bool,
+    stats: StatementStats,
 }
 
 impl Table {
-    pub fn new(table_filepath: PathBuf, name: &str, row_count: usize,
-               column_definitions: &Vec<ColumnDefinition>, indexes_definitions: Vec<(usize, String)>)
+    pub fn new(table_filepath: PathBuf, name: &str, row_count: usize, null_counts: Option<Vec<usize>>,
+               column_definitions: &Vec<ColumnDefinition>, indexes_definitions: Vec<(usize, String)>,
+               options: TableOptions)
         -> Result<Table, TableError> {
 
         let tables_dir = table_filepath.parent().unwrap();
@@ -132,8 +286,12 @@ impl Table {
                 }
             }
         }
+        Self::check_constraint_names_unique(name, &column_constraints)?;
         let row_size = Row::calculate_row_size(&column_types);
-        let pager = Pager::new(table_filepath.as_path(), row_size)
+        let pager = match options.compression {
+            true => Pager::with_string_compression(table_filepath.as_path(), &column_types),
+            false => Pager::new(table_filepath.as_path(), row_size),
+        }
             .map_err(TableError::CreateError)?;
         let headers = TableHeaders {
             name: name.to_string(),
@@ -144,9 +302,38 @@ impl Table {
             defaults,
         };
 
-        let mut table = Self { pager, table_filepath, headers, column_indexes, row_count };
+        let tombstones = match options.soft_delete {
+            true => Self::load_tombstones(&Self::tombstones_filepath(&table_filepath))?,
+            false => HashSet::new(),
+        };
+
+        let change_log = match options.change_log {
+            true => Some(ChangeLog::new(Self::change_log_filepath(&table_filepath), row_size)),
+            false => None,
+        };
+
+        let checksums = match options.checksums {
+            true => Some(RowChecksums::load(Self::checksums_filepath(&table_filepath)).map_err(TableError::IoError)?),
+            false => None,
+        };
+
+        let column_count = headers.column_types.len();
+        let mut table = Self {
+            pager, table_filepath, headers, column_indexes, row_count, options, tombstones, change_log, checksums,
+            null_counts: vec![0; column_count],
+            statement_deadline: None,
+            index_hint: None,
+            salvage: false,
+            stats: StatementStats::default(),
+        };
         table.compile_checks()?;
 
+        // schemas written before null counts were introduced do not have them, so they are recounted
+        match null_counts {
+            Some(null_counts) if null_counts.len() == column_count => table.null_counts = null_counts,
+            _ => table.recount_nulls()?,
+        }
+
         Ok(table)
     }
 
@@ -162,10 +349,101 @@ impl Table {
         &self.headers.name
     }
 
+    pub fn null_counts(&self) -> &[usize] {
+        &self.null_counts
+    }
+
     pub fn column_indexes(&self) -> &[Option<HashIndex>] {
         &self.column_indexes
     }
 
+    pub fn options(&self) -> TableOptions {
+        self.options
+    }
+
+    /// Soft deleted rows are not selected, so they are lost when rows are moved into a rebuilt table
+    pub fn check_no_soft_deleted_rows(&self) -> Result<(), TableError> {
+        match self.tombstones.is_empty() {
+            true => Ok(()),
+            false => Err(TableError::SoftDeletedRowsNotMovable(self.name().to_string())),
+        }
+    }
+
+    /// Change log entries refer to row ids, which are not kept when rows are moved into a rebuilt table
+    pub fn check_change_log_is_empty(&self) -> Result<(), TableError> {
+        match &self.change_log {
+            Some(change_log) if !change_log.is_empty().map_err(TableError::IoError)? =>
+                Err(TableError::ChangeLogNotMovable(self.name().to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Files which belong to the table: table file, index files and files of enabled options
+    pub fn filepaths(&self) -> Vec<PathBuf> {
+        let mut filepaths = vec![self.table_filepath.clone()];
+        if self.options.soft_delete {
+            filepaths.push(Self::tombstones_filepath(&self.table_filepath));
+        }
+        if self.options.change_log {
+            filepaths.push(Self::change_log_filepath(&self.table_filepath));
+        }
+        if self.options.checksums {
+            filepaths.push(Self::checksums_filepath(&self.table_filepath));
+        }
+        for column_index in self.column_indexes.iter().flatten() {
+            filepaths.push(column_index.filepath().to_path_buf());
+        }
+        filepaths
+    }
+
+    pub fn sync(&mut self) -> Result<(), TableError> {
+        self.pager.sync().map_err(TableError::SyncFailed)?;
+        if let Some(checksums) = self.checksums.as_mut() {
+            checksums.sync().map_err(TableError::IoError)?;
+        }
+        for column_index in self.column_indexes.iter().flatten() {
+            column_index.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Returns resources used by the table since the previous call
+    pub fn take_stats(&mut self) -> StatementStats {
+        let mut stats = self.pager.take_stats();
+        stats += std::mem::take(&mut self.stats);
+        stats
+    }
+
+    pub fn set_salvage(&mut self, salvage: bool) {
+        self.salvage = salvage;
+    }
+
+    pub fn set_statement_deadline(&mut self, deadline: Option<Instant>) {
+        self.statement_deadline = deadline;
+    }
+
+    /// Restricts indexes which are used by scans, each hinted index should exist
+    pub fn set_index_hint(&mut self, index_hint: Option<IndexHint>) -> Result<(), TableError> {
+        let index_names = index_hint.iter().flat_map(IndexHint::index_names);
+        for index_name in index_names {
+            if !self.column_indexes.iter().flatten().any(|index| index.name == *index_name) {
+                return Err(TableError::HashIndexMissing { table_name: self.name().to_string(), index_name: index_name.clone() })
+            }
+        }
+        self.index_hint = index_hint;
+        Ok(())
+    }
+
+    /// Size of table file with rows, index and other table files are not included
+    pub fn file_size(&self) -> io::Result<u64> {
+        self.pager.file_size()
+    }
+
+    /// Insert which needs a new page fails if table file would become bigger than `max_file_size`
+    pub fn set_max_file_size(&mut self, max_file_size: Option<u64>) {
+        self.pager.set_max_file_size(max_file_size);
+    }
+
     fn set_name(&mut self, name: &str) {
         self.headers.name = name.to_string();
     }
@@ -178,48 +456,261 @@ impl Table {
         &self.headers.defaults
     }
 
-    pub fn select(&mut self, select_column_names: Vec<SelectColumnName>, where_clause: Option<BinaryCondition>) -> Result<QueryResult, TableError> {
+    pub fn column_is_nullable(&self, column_number: usize) -> bool {
+        !self.column_constraints()[column_number].contains(&Constraint::NotNull)
+    }
+
+    pub fn select(&mut self, select_column_names: Vec<SelectColumnName>, where_clause: Option<Condition>) -> Result<QueryResult, TableError> {
+        if select_column_names.iter().any(SelectColumnName::is_aggregate) {
+            return self.select_aggregates(select_column_names, where_clause)
+        }
+
+        let (mut result, result_column_values) = self.projection(&select_column_names)?;
+        for scan_result in Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, false, self.statement_deadline, &mut self.stats, where_clause)? {
+            let row = scan_result?.row;
+            Self::project_row(&mut result, &result_column_values, &row, &self.headers.column_types)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Same as `select`, but each row is passed to `handle_row` as soon as it is found, instead of being
+    /// collected into result, so selected rows are not kept in memory
+    pub fn select_each(&mut self, select_column_names: Vec<SelectColumnName>, where_clause: Option<Condition>,
+                       mut handle_row: impl FnMut(&[SqlValue]) -> io::Result<()>) -> Result<(), TableError> {
+        if select_column_names.iter().any(SelectColumnName::is_aggregate) {
+            let result = self.select_aggregates(select_column_names, where_clause)?;
+            for row in &result.rows {
+                handle_row(&row.get_sql_values(&result.column_types).map_err(TableError::CannotGetCell)?).map_err(TableError::IoError)?;
+            }
+            return Ok(())
+        }
+
+        let (mut result, result_column_values) = self.projection(&select_column_names)?;
+        for scan_result in Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, false, self.statement_deadline, &mut self.stats, where_clause)? {
+            let row = scan_result?.row;
+            Self::project_row(&mut result, &result_column_values, &row, &self.headers.column_types)?;
+            if let Some(result_row) = result.rows.pop() {
+                handle_row(&result_row.get_sql_values(&result.column_types).map_err(TableError::CannotGetCell)?).map_err(TableError::IoError)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Result with selected columns and no rows, e.g. when no rows are affected by statement with `RETURNING`
+    pub fn empty_result(&self, select_column_names: &[SelectColumnName]) -> Result<QueryResult, TableError> {
+        Ok(self.projection(select_column_names)?.0)
+    }
+
+    /// True if any row has `value` in column, row is looked up in column index, so column should be indexed
+    pub fn indexed_value_present(&mut self, column_name: &str, value: &SqlValue) -> Result<bool, TableError> {
+        let column_number = self.column_number_result(column_name)?;
+        if self.column_indexes[column_number].is_none() {
+            return Err(TableError::ColumnNotIndexed { column_name: column_name.to_string(), table_name: self.name().to_string() })
+        }
+
+        let where_clause = BinaryCondition {
+            left_value: SqlValue::Identificator(column_name.to_string()),
+            right_value: value.clone(),
+            operator: CmpOperator::Equals,
+        };
+        let mut matching_rows = Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, false, self.statement_deadline, &mut self.stats, Some(where_clause.into()))?;
+        matching_rows.next().transpose().map(|scan_product| scan_product.is_some())
+    }
+
+    /// Empty result with selected columns, and values which fill result row from table row
+    fn projection(&self, select_column_names: &[SelectColumnName]) -> Result<(QueryResult, Vec<RowCheckValue>), TableError> {
         let mut result_column_names = vec![];
         let mut result_column_types = vec![];
-        let mut result_column_numbers = vec![];
+        let mut result_column_nullable_flags = vec![];
+        // function calls are computed for each row, other columns are copied from table row
+        let mut result_column_values = vec![];
 
-        for select_column_name in &select_column_names {
+        for select_column_name in select_column_names {
             match select_column_name {
+                SelectColumnName::Name(function_call @ SqlValue::Function(call)) => {
+                    result_column_names.push(function_call.to_string());
+                    result_column_types.push(self.function_result_type(call)?);
+                    result_column_nullable_flags.push(true);
+                    result_column_values.push(BinaryCondition::build_row_check_value(function_call.clone(), self.name(), self.column_names())?);
+                },
                 SelectColumnName::Name(column_name) => {
-                    let column_name = column_name.to_string();
+                    let column_name = self.unqualified_column_name(&column_name.to_string())?.to_string();
                     let column_number = self.column_number_result(&column_name)?;
                     let column_type = *self.column_types().get(column_number)
                         .ok_or(TableError::ColumnNthNotExist { column_number, table_name: self.name().to_string() })?;
                     result_column_names.push(column_name);
                     result_column_types.push(column_type);
-                    result_column_numbers.push(column_number);
+                    result_column_nullable_flags.push(self.column_is_nullable(column_number));
+                    result_column_values.push(RowCheckValue::TableColumn(column_number));
+                },
+                SelectColumnName::TableAllColumns(qualifier) if qualifier.to_string() != self.name() => {
+                    return Err(TableError::UnknownTableQualifier { table_name: self.name().to_string(), qualifier: qualifier.to_string() })
                 },
-                SelectColumnName::AllColumns => {
+                SelectColumnName::AllColumns | SelectColumnName::TableAllColumns(_) => {
                     result_column_names.extend_from_slice(self.column_names());
                     result_column_types.extend_from_slice(self.column_types());
-                    for i in 0..self.column_types().len() { result_column_numbers.push(i) };
-                }
+                    for i in 0..self.column_types().len() {
+                        result_column_nullable_flags.push(self.column_is_nullable(i));
+                        result_column_values.push(RowCheckValue::TableColumn(i))
+                    };
+                },
+                SelectColumnName::CountAll | SelectColumnName::Count(_) =>
+                    return Err(TableError::AggregateNotSupported(self.name().to_string())),
             }
         }
 
-        let mut result = QueryResult { column_names: result_column_names, column_types: result_column_types.clone(), rows: vec![] };
+        let result = QueryResult {
+            column_names: result_column_names,
+            column_types: result_column_types,
+            column_nullable_flags: result_column_nullable_flags,
+            rows: vec![],
+        };
+        Ok((result, result_column_values))
+    }
 
-        for scan_result in Self::matching_rows(&mut self.pager, &self.column_indexes, &self.headers, where_clause)? {
-            let row = scan_result?.row;
-            let result_row = result.spawn_row();
+    fn project_row(result: &mut QueryResult, result_column_values: &[RowCheckValue], row: &Row, column_types: &[ColumnType]) -> Result<(), TableError> {
+        let result_column_types = &result.column_types;
+        let mut result_row = Row::new(result_column_types);
+
+        for (i, column_value) in result_column_values.iter().enumerate() {
+            match column_value {
+                RowCheckValue::TableColumn(column_number) => {
+                    let column_values_data = row.get_cell_bytes(column_types, *column_number);
+                    let column_is_null = row.cell_is_null(*column_number);
+                    result_row.set_cell_bytes(result_column_types, i, column_values_data, column_is_null)
+                        .map_err(TableError::CannotSetCell)?
+                },
+                _ => {
+                    let value = RowCheck::get_value(column_value, row, column_types)?;
+                    result_row.set_cell(result_column_types, i, &value).map_err(TableError::CannotSetCell)?
+                },
+            }
+        }
+        result.rows.push(result_row);
+        Ok(())
+    }
+
+    // returns single row with aggregate values, column numbers of COUNT(column) are resolved
+    // before scan, and null cells are checked via row null bitmask without reading cell values
+    fn select_aggregates(&mut self, select_column_names: Vec<SelectColumnName>, where_clause: Option<Condition>) -> Result<QueryResult, TableError> {
+        let mut result_column_names = vec![];
+        let mut counted_column_numbers = vec![];
 
-            for (i, column_number) in result_column_numbers.iter().enumerate() {
-                let column_values_data = row.get_cell_bytes(&self.headers.column_types, *column_number);
-                let column_is_null = row.cell_is_null(*column_number);
-                result_row.set_cell_bytes(&result_column_types, i, column_values_data, column_is_null)
-                    .map_err(TableError::CannotSetCell)?
+        for select_column_name in &select_column_names {
+            match select_column_name {
+                SelectColumnName::CountAll => {
+                    result_column_names.push("COUNT(*)".to_string());
+                    counted_column_numbers.push(None);
+                },
+                SelectColumnName::Count(column_name) => {
+                    let column_name = self.unqualified_column_name(&column_name.to_string())?.to_string();
+                    counted_column_numbers.push(Some(self.column_number_result(&column_name)?));
+                    result_column_names.push(format!("COUNT({})", column_name));
+                },
+                _ => return Err(TableError::AggregateMixedWithColumns(self.name().to_string())),
             }
         }
 
-        Ok(result)
+        let mut counts = vec![0; counted_column_numbers.len()];
+        let where_filter = match where_clause.clone() {
+            None => RowCheck::dummy(),
+            Some(where_clause) => query_rewriter::rewrite(where_clause.compile(self.name(), self.column_names())?),
+        };
+        if self.is_scanned_sequentially(&where_filter) {
+            self.count_in_scan(&where_filter, &counted_column_numbers, &mut counts)?;
+        } else {
+            for scan_result in Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, false, self.statement_deadline, &mut self.stats, where_clause)? {
+                Self::count_row(&scan_result?.row, &counted_column_numbers, &mut counts);
+            }
+        }
+
+        let column_types = vec![ColumnType::Integer; counts.len()];
+        let values: Vec<SqlValue> = counts.into_iter().map(SqlValue::Integer).collect();
+        let row = Row::from_sql_values(&values, &column_types).map_err(TableError::CannotSetCell)?;
+
+        Ok(QueryResult {
+            column_nullable_flags: vec![false; column_types.len()],
+            column_names: result_column_names,
+            column_types,
+            rows: vec![row],
+        })
+    }
+
+    // single comparison which can't be answered by index or null counts, so all rows are read anyway
+    fn is_scanned_sequentially(&self, where_filter: &RowCheck) -> bool {
+        matches!(where_filter, RowCheck::Compare { .. }) &&
+            *where_filter != query_rewriter::always_false() &&
+            where_filter.is_column_is_null_check().is_none() &&
+            where_filter.column_value_in_static_checks().iter().all(|(column_number, _)| self.column_indexes[*column_number].is_none())
     }
 
-    pub fn insert(&mut self, column_names: Option<Vec<String>>, values: Vec<SqlValue>) -> Result<(), TableError> {
+    // counts rows right in the scan loop, each row is read into the same buffer and is not passed further
+    fn count_in_scan(&mut self, where_filter: &RowCheck, counted_column_numbers: &[Option<usize>], counts: &mut [i64]) -> Result<(), TableError> {
+        let column_types = &self.headers.column_types;
+        let mut row = Row::new(column_types);
+
+        for row_id in 0..self.pager.max_rows() {
+            match self.pager.read_scanned_row(row_id, &mut row) {
+                Ok(true) => {},
+                Ok(false) => continue,
+                Err(_) if self.salvage => {
+                    self.stats.damaged_rows_skipped += 1;
+                    continue
+                },
+                Err(error) => return Err(TableError::CannotGetRow(error)),
+            }
+
+            self.stats.rows_scanned += 1;
+            if self.statement_deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                return Err(TableError::StatementTimeout(self.headers.name.clone()))
+            }
+            if self.tombstones.contains(&row_id) { continue }
+            if self.checksums.as_ref().is_some_and(|checksums| !checksums.matches(row_id, &row)) {
+                if self.salvage {
+                    self.stats.damaged_rows_skipped += 1;
+                    continue
+                }
+                return Err(TableError::RowChecksumMismatch { table_name: self.headers.name.clone(), row_id })
+            }
+
+            if where_filter.matches(&row, column_types)? {
+                Self::count_row(&row, counted_column_numbers, counts);
+            }
+        }
+        Ok(())
+    }
+
+    // None stands for COUNT(*)
+    fn count_row(row: &Row, counted_column_numbers: &[Option<usize>], counts: &mut [i64]) {
+        for (count, column_number) in counts.iter_mut().zip(counted_column_numbers) {
+            match column_number {
+                Some(column_number) if row.cell_is_null(*column_number) => {},
+                _ => *count += 1,
+            }
+        }
+    }
+
+    pub fn get_row(&mut self, row_id: u64) -> Result<Option<Vec<SqlValue>>, TableError> {
+        if row_id >= self.pager.max_rows() || self.tombstones.contains(&row_id) {
+            return Ok(None)
+        }
+
+        let row = match self.pager.get_row(row_id).map_err(TableError::CannotGetRow)? {
+            None => return Ok(None),
+            Some(row) => row,
+        };
+        self.stats.rows_scanned += 1;
+        if self.checksums.as_ref().is_some_and(|checksums| !checksums.matches(row_id, &row)) {
+            return Err(TableError::RowChecksumMismatch { table_name: self.name().to_string(), row_id })
+        }
+
+        Ok(Some(row.get_sql_values(self.column_types()).map_err(TableError::CannotGetCell)?))
+    }
+
+    /// Inserts row, and returns its `returning` columns if they are given, as do `update` and `delete`
+    pub fn insert(&mut self, column_names: Option<Vec<String>>, values: Vec<SqlValue>,
+                  returning: Option<&[SelectColumnName]>) -> Result<Option<QueryResult>, TableError> {
         let column_names = match &column_names {
             Some(column_names) => column_names,
             None => self.column_names(),
@@ -227,33 +718,83 @@ impl Table {
 
         let input_column_numbers = self.get_columns_numbers(column_names)?;
         self.validate_values_type(&values, &input_column_numbers)?;
+        let mut returned = returning.map(|column_names| self.projection(column_names)).transpose()?;
 
-        let (result_values, _numbers) = self.apply_defaults(&values, &input_column_numbers);
+        let (result_values, result_column_numbers) = self.apply_defaults(&values, &input_column_numbers);
 
         let row = Row::from_sql_values(&result_values, self.column_types())
             .map_err(TableError::CannotGetCell)?;
 
         Self::validate_constraints(&self.headers, &row)?;
 
-        // TODO: this should be rollbackable if index update fails
-        let row_id = self.pager.insert_row(row).map_err(TableError::CannotInsertRow)?;
+        let row_id = self.pager.insert_row(&row).map_err(|pager_error| match pager_error {
+            PagerError::FileSizeLimitReached(max_file_size) =>
+                TableError::SizeQuotaExceeded { table_name: self.name().to_string(), max_file_size },
+            _ => TableError::CannotInsertRow(pager_error),
+        })?;
         self.row_count += 1;
-        self.update_indexes_on_insert(&input_column_numbers, &result_values, row_id)
+        Self::add_row_nulls(&mut self.null_counts, &row);
+
+        if let Some(checksums) = self.checksums.as_mut() {
+            checksums.set(row_id, &row);
+        }
+        // change log entry is written last, since it cannot be taken back
+        let recorded = self.update_indexes_on_insert(&result_column_numbers, &result_values, row_id)
+            .and_then(|()| Self::log_change(&self.change_log, ChangeOperation::Insert, row_id, None, Some(&row)));
+        if let Err(error) = recorded {
+            return Err(match self.rollback_insert(row_id, &row, &result_column_numbers, &result_values) {
+                Ok(()) => error,
+                Err(rollback_error) => TableError::InsertRollbackFailed { error: Box::new(error), rollback_error: Box::new(rollback_error) },
+            })
+        }
+
+        if let Some((result, result_column_values)) = returned.as_mut() {
+            Self::project_row(result, result_column_values, &row, &self.headers.column_types)?;
+        }
+        Ok(returned.map(|(result, _)| result))
+    }
+
+    // removes row which is inserted into pager, but which index entries or change log entry could not
+    // be written, e.g. when there is no space left on device. Row might not reach some of the indexes,
+    // so missing index entries are skipped, while other errors are returned.
+    fn rollback_insert(&mut self, row_id: u64, row: &Row, column_numbers: &[usize], values: &[SqlValue]) -> Result<(), TableError> {
+        self.row_count -= 1;
+        Self::remove_row_nulls(&mut self.null_counts, row);
+        if let Some(checksums) = self.checksums.as_mut() {
+            checksums.clear(row_id);
+        }
+        for (column_number, value) in zip(column_numbers, values) {
+            if let Some(hash_index) = &self.column_indexes[*column_number] {
+                match hash_index.delete_row(row_id, value) {
+                    Ok(()) | Err(HashIndexError::RowDoesNotExists(_)) => {},
+                    Err(error) => return Err(error.into()),
+                }
+            }
+        }
+        self.pager.delete_row(row_id).map_err(TableError::CannotDeleteRow)
     }
 
-    pub fn update(&mut self, field_assignments: Vec<FieldAssignment>, where_clause: Option<BinaryCondition>) -> Result<(), TableError> {
+    /// Updates matching rows, returned rows have new values, including rows in which assigned values
+    /// are already set
+    pub fn update(&mut self, field_assignments: Vec<FieldAssignment>, where_clause: Option<Condition>,
+                  returning: Option<&[SelectColumnName]>) -> Result<Option<QueryResult>, TableError> {
         let (column_names, column_values): (Vec<String>, Vec<SqlValue>) = field_assignments.into_iter()
             .map(|assignment| (assignment.column_name, assignment.value))
             .unzip();
 
         let column_numbers = self.get_columns_numbers(&column_names)?;
         self.validate_values_type(&column_values, &column_numbers)?;
+        let mut returned = returning.map(|column_names| self.projection(column_names)).transpose()?;
         let pager_raw: *mut Pager = &mut self.pager;
+        let mut null_counts = self.null_counts.clone();
+        // checksums are read during scan, so checksums of updated rows are written after it
+        let mut updated_rows = vec![];
 
-        let matching_rows = Self::matching_rows(&mut self.pager, &self.column_indexes, &self.headers, where_clause)?;
+        let matching_rows = Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, false, self.statement_deadline, &mut self.stats, where_clause)?;
         let updation_error = matching_rows
             .map(|scan_result| {
                 let mut scan_product = scan_result?;
+                let old_row = scan_product.row.clone();
 
                 let mut old_column_values = vec![];
 
@@ -265,11 +806,19 @@ impl Table {
                         .map_err(TableError::CannotSetCell)?;
 
                 }
+                if let Some((result, result_column_values)) = returned.as_mut() {
+                    Self::project_row(result, result_column_values, &scan_product.row, &self.headers.column_types)?;
+                }
+
+                // row already has assigned values, so it is not written, reindexed or logged
+                if scan_product.row.as_bytes() == old_row.as_bytes() {
+                    return Ok(scan_product.row_id)
+                }
 
                 Self::validate_constraints(&self.headers, &scan_product.row)?;
 
-                Self::update_indexes_on_update(&self.column_indexes, scan_product.row_id, &column_numbers, &old_column_values, &column_values)?;
-
+                // row is written first, so row which does not fit into page leaves no index changes,
+                // change log entry, null counts or checksum behind
                 // pager will not reallocate to a new space during matching_rows iteration
                 // so we can safely dereference raw mut pointer
                 // TODO: check if we can move pager_raw and give it back, i.e.
@@ -277,24 +826,54 @@ impl Table {
                 unsafe {
                     (*pager_raw)
                         .update_row(scan_product.row_id, &scan_product.row)
-                        .map_err(TableError::CannotUpdateRow)
+                        .map_err(TableError::CannotUpdateRow)?;
                 }
+
+                Self::update_indexes_on_update(&self.column_indexes, scan_product.row_id, &column_numbers, &old_column_values, &column_values);
+                Self::log_change(&self.change_log, ChangeOperation::Update, scan_product.row_id, Some(&old_row), Some(&scan_product.row))?;
+                if self.checksums.is_some() {
+                    updated_rows.push((scan_product.row_id, scan_product.row.clone()));
+                }
+                for (i, column_number) in column_numbers.iter().enumerate() {
+                    match (old_column_values[i] == SqlValue::Null, column_values[i] == SqlValue::Null) {
+                        (true, false) => null_counts[*column_number] -= 1,
+                        (false, true) => null_counts[*column_number] += 1,
+                        _ => {},
+                    }
+                }
+                Ok(scan_product.row_id)
             })
             .find(|updation_result: &Result<u64, TableError>| updation_result.is_err());
+        self.null_counts = null_counts;
+        // rows updated before an error are already written, so their index changes are applied anyway
+        self.apply_pending_index_changes()?;
+
+        if let Some(checksums) = self.checksums.as_mut() {
+            for (row_id, row) in &updated_rows {
+                checksums.set(*row_id, row);
+            }
+        }
 
         match updation_error {
-            None => Ok(()),
+            None => Ok(returned.map(|(result, _)| result)),
             Some(error) => Err(error.unwrap_err()),
         }
     }
 
-    pub fn delete(&mut self, where_clause: Option<BinaryCondition>) -> Result<(), TableError> {
+    /// Deletes matching rows, returned rows have values they had before deletion
+    pub fn delete(&mut self, where_clause: Option<Condition>, returning: Option<&[SelectColumnName]>) -> Result<Option<QueryResult>, TableError> {
+        let mut returned = returning.map(|column_names| self.projection(column_names)).transpose()?;
+        if self.options.soft_delete {
+            return self.soft_delete(where_clause, returned)
+        }
+
         let pager_raw: *mut Pager = &mut self.pager;
-        let mut column_values = vec![];
+        let mut null_counts = self.null_counts.clone();
 
-        Self::matching_rows(&mut self.pager, &self.column_indexes, &self.headers, where_clause)?
-            .map(|scan_result| {
+        let deletion_result = Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, false, self.statement_deadline, &mut self.stats, where_clause)?
+            .try_for_each(|scan_result| {
                 let scan_product = scan_result?;
+                let mut column_values = vec![];
                 for column_number in 0..self.headers.column_types.len() {
                     column_values
                         .push(
@@ -305,40 +884,157 @@ impl Table {
                         );
                 }
 
+                if let Some((result, result_column_values)) = returned.as_mut() {
+                    Self::project_row(result, result_column_values, &scan_product.row, &self.headers.column_types)?;
+                }
                 let row_number = scan_product.row_id;
-                Self::update_indexes_on_delete(&self.column_indexes, row_number, &column_values)?;
+                Self::update_indexes_on_delete(&self.column_indexes, row_number, &column_values);
+                Self::log_change(&self.change_log, ChangeOperation::Delete, row_number, Some(&scan_product.row), None)?;
                 // pager will not reallocate to a new space during matching_rows iteration
                 // so we can safely dereference raw mut pointer
                 unsafe {
                     (*pager_raw).delete_row(row_number).map_err(TableError::CannotDeleteRow)?;
                 }
                 self.row_count -= 1;
+                Self::remove_row_nulls(&mut null_counts, &scan_product.row);
                 Ok::<(), TableError>(())
-        })
-        .for_each(drop);
+        });
+        self.null_counts = null_counts;
+        self.apply_pending_index_changes()?;
 
-        Ok(())
+        deletion_result.map(|()| returned.map(|(result, _)| result))
     }
 
-    pub fn rename(&mut self, new_name: &str, new_table_filepath: &Path) -> Result<(), TableError> {
+    fn soft_delete(&mut self, where_clause: Option<Condition>, mut returned: Option<(QueryResult, Vec<RowCheckValue>)>)
+        -> Result<Option<QueryResult>, TableError> {
+        let scan_products = Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, false, self.statement_deadline, &mut self.stats, where_clause)?
+            .collect::<Result<Vec<ScanProduct>, TableError>>()?;
+
+        for scan_product in scan_products {
+            Self::log_change(&self.change_log, ChangeOperation::Delete, scan_product.row_id, Some(&scan_product.row), None)?;
+            self.tombstones.insert(scan_product.row_id);
+            self.row_count -= 1;
+            Self::remove_row_nulls(&mut self.null_counts, &scan_product.row);
+            if let Some((result, result_column_values)) = returned.as_mut() {
+                Self::project_row(result, result_column_values, &scan_product.row, &self.headers.column_types)?;
+            }
+        }
+        self.flush_tombstones()?;
+        Ok(returned.map(|(result, _)| result))
+    }
+
+    pub fn undelete(&mut self, where_clause: Option<Condition>) -> Result<(), TableError> {
+        if !self.options.soft_delete {
+            return Err(TableError::SoftDeleteDisabled(self.name().to_string()))
+        }
+
+        let scan_products = Self::matching_rows(&mut self.pager, &self.column_indexes, self.index_hint.as_ref(), &self.headers, &self.null_counts, &self.tombstones, &self.checksums, self.salvage, true, self.statement_deadline, &mut self.stats, where_clause)?
+            .collect::<Result<Vec<ScanProduct>, TableError>>()?;
+
+        for scan_product in scan_products {
+            Self::log_change(&self.change_log, ChangeOperation::Undelete, scan_product.row_id, None, Some(&scan_product.row))?;
+            self.tombstones.remove(&scan_product.row_id);
+            self.row_count += 1;
+            Self::add_row_nulls(&mut self.null_counts, &scan_product.row);
+        }
+        self.flush_tombstones()
+    }
+
+    /// Returns change log entries after given lsn. Each result row has entry lsn, operation
+    /// and row id, followed by `old_` and `new_` values for each table column. Old values are
+    /// NULL for inserted rows, and new values are NULL for deleted rows.
+    pub fn changes(&self, since_lsn: u64) -> Result<QueryResult, TableError> {
+        let change_log = self.change_log.as_ref()
+            .ok_or(TableError::ChangeLogDisabled(self.name().to_string()))?;
+
+        let mut column_names = vec!["lsn".to_string(), "operation".to_string(), "row_id".to_string()];
+        let mut column_types = vec![ColumnType::Integer, ColumnType::String, ColumnType::Integer];
+        for prefix in ["old", "new"] {
+            for (column_name, column_type) in zip(self.column_names(), self.column_types()) {
+                column_names.push(format!("{}_{}", prefix, column_name));
+                column_types.push(*column_type);
+            }
+        }
+
+        let mut result = QueryResult {
+            column_nullable_flags: (0..column_types.len()).map(|i| i >= 3).collect(),
+            column_names,
+            column_types,
+            rows: vec![],
+        };
+
+        let blank_values = vec![SqlValue::Null; self.column_types().len()];
+        for entry in change_log.entries_since(since_lsn).map_err(TableError::IoError)? {
+            let mut values = vec![
+                SqlValue::Integer(entry.lsn as i64),
+                SqlValue::String(entry.operation.to_string()),
+                SqlValue::Integer(entry.row_id as i64),
+            ];
+            for row in [&entry.old_row, &entry.new_row] {
+                match row {
+                    Some(row) => values.extend(row.get_sql_values(self.column_types()).map_err(TableError::CannotGetCell)?),
+                    None => values.extend_from_slice(&blank_values),
+                }
+            }
+
+            let row = Row::from_sql_values(&values, &result.column_types).map_err(TableError::CannotSetCell)?;
+            result.rows.push(row);
+        }
+
+        Ok(result)
+    }
+
+    fn log_change(change_log: &Option<ChangeLog>, operation: ChangeOperation, row_id: u64,
+                  old_row: Option<&Row>, new_row: Option<&Row>) -> Result<(), TableError> {
+        match change_log {
+            Some(change_log) => change_log.append(operation, row_id, old_row, new_row).map_err(TableError::IoError),
+            None => Ok(()),
+        }
+    }
+
+    /// File moves needed to rename the table: table file, index files and existing option files
+    pub fn rename_moves(&self, new_name: &str, new_table_filepath: &Path) -> Vec<(PathBuf, PathBuf)> {
         let tables_dir = self.table_filepath.parent().unwrap();
+        let mut moves = vec![(self.table_filepath.clone(), new_table_filepath.to_path_buf())];
 
-        match fs::rename(self.table_filepath.clone(), new_table_filepath) {
-            Err(io_error) => Err(TableError::IoError(io_error)),
-            Ok(_) => {
-                self.column_indexes.iter_mut()
-                    .try_for_each(|index_option: &mut Option<HashIndex>| {
-                        if let Some(index) = index_option.as_mut() {
-                            index.adjust_filepaths(new_name, tables_dir)?;
-                        }
+        let tombstones_filepath = Self::tombstones_filepath(&self.table_filepath);
+        if tombstones_filepath.exists() {
+            moves.push((tombstones_filepath, Self::tombstones_filepath(new_table_filepath)));
+        }
+        if let Some(change_log) = self.change_log.as_ref() {
+            if change_log.filepath().exists() {
+                moves.push((change_log.filepath().to_path_buf(), Self::change_log_filepath(new_table_filepath)));
+            }
+        }
+        if let Some(checksums) = self.checksums.as_ref() {
+            if checksums.filepath().exists() {
+                moves.push((checksums.filepath().to_path_buf(), Self::checksums_filepath(new_table_filepath)));
+            }
+        }
+        for index in self.column_indexes.iter().flatten() {
+            moves.push((index.filepath().to_path_buf(), index.renamed_table_filepath(tables_dir, new_name)));
+        }
 
-                        Ok::<(), TableError>(())
-                    })?;
-                self.set_name(new_name);
-                self.table_filepath = new_table_filepath.to_path_buf();
-                Ok(())
-            },
+        moves
+    }
+
+    /// Renames table in memory, files should be moved by `rename_moves` beforehand
+    pub fn rename(&mut self, new_name: &str, new_table_filepath: &Path) -> Result<(), TableError> {
+        let tables_dir = self.table_filepath.parent().unwrap().to_path_buf();
+
+        if let Some(change_log) = self.change_log.as_mut() {
+            change_log.set_filepath(Self::change_log_filepath(new_table_filepath));
+        }
+        if let Some(checksums) = self.checksums.as_mut() {
+            checksums.set_filepath(Self::checksums_filepath(new_table_filepath));
         }
+        for index in self.column_indexes.iter_mut().flatten() {
+            index.set_table_name(new_name, &tables_dir)?;
+        }
+
+        self.set_name(new_name);
+        self.table_filepath = new_table_filepath.to_path_buf();
+        Ok(())
     }
 
     pub fn rename_column(&mut self, column_name: String, new_column_name: String) -> Result<(), TableError> {
@@ -357,11 +1053,48 @@ impl Table {
         }
 
         column_constraints.push(constraint);
+        if let Err(error) = Self::check_constraint_names_unique(&self.headers.name, &self.headers.column_constraints) {
+            self.headers.column_constraints[column_number].pop();
+            return Err(error)
+        }
+        self.compile_checks()?;
+
+        Ok(())
+    }
+
+    pub fn drop_named_constraint(&mut self, constraint_name: &str) -> Result<(), TableError> {
+        let position = self.headers.column_constraints.iter().enumerate()
+            .find_map(|(column_number, column_constraints)| {
+                column_constraints.iter()
+                    .position(|constraint| constraint.name() == Some(constraint_name))
+                    .map(|index| (column_number, index))
+            });
+
+        match position {
+            Some((column_number, index)) => self.headers.column_constraints[column_number].remove(index),
+            None => return Err(TableError::ConstraintNameNotExists {
+                table_name: self.name().to_string(),
+                constraint_name: constraint_name.to_string(),
+            }),
+        };
         self.compile_checks()?;
 
         Ok(())
     }
 
+    fn check_constraint_names_unique(table_name: &str, column_constraints: &[Vec<Constraint>]) -> Result<(), TableError> {
+        let mut constraint_names = HashSet::new();
+        for constraint_name in column_constraints.iter().flatten().filter_map(Constraint::name) {
+            if !constraint_names.insert(constraint_name) {
+                return Err(TableError::ConstraintNameAlreadyExists {
+                    table_name: table_name.to_string(),
+                    constraint_name: constraint_name.to_string(),
+                })
+            }
+        }
+        Ok(())
+    }
+
     pub fn drop_column_constraint(&mut self, column_name: String, constraint: Constraint) -> Result<(), TableError> {
         let column_number = self.column_number_result(column_name.as_str())?;
         let column_constraints = &mut self.headers.column_constraints[column_number];
@@ -402,11 +1135,61 @@ impl Table {
         self.reindex_column(column_number)
     }
 
+    /// Enables and disables options which do not change layout of table file. Disabling `soft_delete`
+    /// frees soft deleted rows, and files of disabled options are removed.
+    pub fn set_options(&mut self, options: TableOptions) -> Result<(), TableError> {
+        if options.soft_delete != self.options.soft_delete {
+            let tombstones_filepath = Self::tombstones_filepath(&self.table_filepath);
+            match options.soft_delete {
+                true => self.flush_tombstones()?,
+                false => {
+                    let purged = !self.tombstones.is_empty();
+                    self.purge_tombstones()?;
+                    if purged { self.reindex()?; }
+                    if tombstones_filepath.exists() {
+                        fs::remove_file(tombstones_filepath).map_err(TableError::IoError)?;
+                    }
+                },
+            }
+        }
+
+        match (options.change_log, self.change_log.take()) {
+            (true, None) => self.change_log = Some(ChangeLog::new(Self::change_log_filepath(&self.table_filepath), self.pager.row_size())),
+            (false, Some(change_log)) => change_log.destroy().map_err(TableError::IoError)?,
+            (_, change_log) => self.change_log = change_log,
+        }
+
+        match (options.checksums, self.checksums.take()) {
+            (true, None) => {
+                self.checksums = Some(RowChecksums::load(Self::checksums_filepath(&self.table_filepath)).map_err(TableError::IoError)?);
+                self.rebuild_checksums()?;
+            },
+            (false, Some(checksums)) => checksums.destroy().map_err(TableError::IoError)?,
+            (_, checksums) => self.checksums = checksums,
+        }
+
+        self.options = TableOptions { compression: self.options.compression, ..options };
+        Ok(())
+    }
+
     pub fn destroy(mut self) -> Result<(), TableError> {
         for i in 0..self.column_types().len() {
             self.drop_index(i)?;
         }
 
+        let tombstones_filepath = Self::tombstones_filepath(&self.table_filepath);
+        if tombstones_filepath.exists() {
+            fs::remove_file(tombstones_filepath).map_err(TableError::IoError)?;
+        }
+
+        if let Some(change_log) = self.change_log.take() {
+            change_log.destroy().map_err(TableError::IoError)?;
+        }
+
+        if let Some(checksums) = self.checksums.take() {
+            checksums.destroy().map_err(TableError::IoError)?;
+        }
+
         fs::remove_file(self.table_filepath).map_err(TableError::IoError)?;
         Ok(())
     }
@@ -451,8 +1234,8 @@ impl Table {
         Ok(())
     }
 
-    fn update_indexes_on_insert(&mut self, input_column_numbers: &[usize], result_values: &Vec<SqlValue>, row_id: u64) -> Result<(), TableError> {
-        for (column_number, value) in zip(input_column_numbers, result_values) {
+    fn update_indexes_on_insert(&mut self, column_numbers: &[usize], values: &[SqlValue], row_id: u64) -> Result<(), TableError> {
+        for (column_number, value) in zip(column_numbers, values) {
             match &mut self.column_indexes[*column_number] {
                 Some(hash_index) => hash_index.insert_row(value, row_id, self.row_count)?,
                 None => (),
@@ -462,31 +1245,51 @@ impl Table {
         Ok(())
     }
 
+    // index changes of update and delete are deferred, and are applied once scan is finished
     fn update_indexes_on_update(column_indexes: &[Option<HashIndex>], row_id: u64, input_column_numbers: &[usize],
-                                old_column_values: &Vec<SqlValue>, new_column_values: &Vec<SqlValue>)
-        -> Result<(), TableError> {
-
+                                old_column_values: &Vec<SqlValue>, new_column_values: &Vec<SqlValue>) {
         for (index, (old_value, new_value)) in zip(input_column_numbers, zip(old_column_values, new_column_values)) {
             match &column_indexes[*index] {
-                Some(hash_index) => hash_index.update_row(row_id, old_value, new_value)?,
-                None => {},
+                Some(hash_index) if old_value != new_value => hash_index.defer_update_row(row_id, old_value, new_value),
+                _ => {},
             }
         }
-
-        Ok(())
     }
 
-    fn update_indexes_on_delete(column_indexes: &[Option<HashIndex>], row_id: u64, column_values: &[SqlValue]) -> Result<(), TableError> {
+    fn update_indexes_on_delete(column_indexes: &[Option<HashIndex>], row_id: u64, column_values: &[SqlValue]) {
         for (column_index, value) in zip(column_indexes, column_values) {
             match column_index {
-                Some(hash_index) => hash_index.delete_row(row_id, value)?,
+                Some(hash_index) => hash_index.defer_delete_row(row_id, value),
                 None => {},
             }
         }
+    }
+
+    // index which is left mostly of deleted slots after the statement is rebuilt right away,
+    // so bulk deletes do not keep lookups slow until the table is vacuumed
+    fn apply_pending_index_changes(&mut self) -> Result<(), TableError> {
+        for column_index in self.column_indexes.iter_mut().flatten() {
+            column_index.apply_pending_changes()?;
+            if column_index.is_mostly_deleted() {
+                column_index.reindex()?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Foreign keys of table columns with names of their columns
+    pub fn foreign_keys(&self) -> impl Iterator<Item = (&str, &ForeignKey)> {
+        self.column_names().iter()
+            .zip(self.column_constraints())
+            .flat_map(|(column_name, column_constraints)| {
+                column_constraints.iter().filter_map(move |constraint| match constraint {
+                    Constraint::References(foreign_key) => Some((column_name.as_str(), foreign_key)),
+                    _ => None,
+                })
+            })
+    }
+
     pub fn column_definitions(&self) -> Vec<ColumnDefinition> {
         self.column_names().iter().enumerate().zip(self.column_types().iter())
             .map(|((i, name), kind)| {
@@ -500,10 +1303,191 @@ impl Table {
     }
 
     pub fn vacuum(&mut self) -> Result<(), TableError> {
+        self.purge_tombstones()?;
         self.pager.vacuum().map_err(TableError::VacuumFailed)?;
+        self.rebuild_checksums()?;
         self.reindex()
     }
 
+    /// Verifies checksums of all stored rows, including soft deleted ones, and returns
+    /// ids of rows which do not match their checksums.
+    pub fn check(&mut self) -> Result<QueryResult, TableError> {
+        let checksums = self.checksums.as_ref()
+            .ok_or(TableError::ChecksumsDisabled(self.name().to_string()))?;
+
+        let column_types = vec![ColumnType::Integer];
+        let mut rows = vec![];
+        for scan_result in Self::seq_scan(&mut self.pager) {
+            let scan_product = scan_result?;
+            if !checksums.matches(scan_product.row_id, &scan_product.row) {
+                let row_id = SqlValue::Integer(scan_product.row_id as i64);
+                rows.push(Row::from_sql_values(&[row_id], &column_types).map_err(TableError::CannotSetCell)?);
+            }
+        }
+
+        Ok(QueryResult {
+            column_names: vec!["corrupted_row_id".to_string()],
+            column_nullable_flags: vec![false],
+            column_types,
+            rows,
+        })
+    }
+
+    /// Human readable content of table file page: free row bitmask, dictionary of compressed strings,
+    /// and bytes of each occupied slot with values of its row, as shown by `.pagedump`
+    pub fn page_dump(&mut self, page_id: u64) -> Result<String, TableError> {
+        let rows_per_page = self.pager.rows_per_page() as u64;
+        let page_count = self.pager.page_count().map_err(TableError::IoError)?;
+        let page = self.pager.scanned_page(page_id)
+            .map_err(TableError::CannotGetRow)?
+            .ok_or_else(|| TableError::PageNotExist { table_name: self.headers.name.clone(), page_id, page_count })?;
+        let layout = page.layout();
+
+        let mut lines = vec![
+            format!("page {} of {} in table '{}': {} slots of {} bytes, {} occupied",
+                    page_id, page_count, self.headers.name, layout.slot_count, layout.slot_size, layout.occupied_slots.len()),
+            "free row bitmask:".to_string(),
+        ];
+        lines.extend(hex_dump(layout.free_row_bitmask, 0));
+        if let Some(dictionary) = layout.dictionary {
+            lines.push(format!("dictionary of {} entries:", dictionary.len()));
+            for (code, entry) in dictionary.iter().enumerate() {
+                lines.push(format!("entry {}: {}", code, String::from_utf8_lossy(entry.get(1..).unwrap_or_default())));
+            }
+        }
+        for slot in &layout.occupied_slots {
+            let row_id = page_id * rows_per_page + slot.row_number as u64;
+            let values = slot.row.get_sql_values(&self.headers.column_types).map_err(TableError::CannotGetCell)?;
+            let cells: Vec<String> = zip(&self.headers.column_names, values)
+                .map(|(column_name, value)| match value {
+                    SqlValue::String(string) => format!("{} = \"{}\"", column_name, string),
+                    value => format!("{} = {}", column_name, value),
+                })
+                .collect();
+            let deleted = match self.tombstones.contains(&row_id) {
+                true => " (soft deleted)",
+                false => "",
+            };
+            lines.push(format!("slot {}, row {}{}: {}", slot.row_number, row_id, deleted, cells.join(", ")));
+            lines.extend(hex_dump(slot.bytes, slot.offset));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    pub fn bucket_dump(&self, index_name: &str, bucket_number: u64) -> Result<String, TableError> {
+        let index = self.column_indexes.iter().flatten()
+            .find(|index| index.name == index_name)
+            .ok_or_else(|| TableError::HashIndexMissing { table_name: self.name().to_string(), index_name: index_name.to_string() })?;
+        Ok(index.bucket_dump(bucket_number)?)
+    }
+
+    /// Row layout and page usage: row size, rows per page, pages, stored rows (soft deleted included),
+    /// free row slots, bytes of string cells not taken by strings themselves, and the longest stored string
+    pub fn storage_stats(&mut self) -> Result<QueryResult, TableError> {
+        let column_types = self.column_types().to_vec();
+        let mut stored_rows = 0;
+        let mut string_bytes_wasted = 0;
+        let mut longest_string = 0;
+        for scan_result in Self::seq_scan(&mut self.pager) {
+            let scan_product = scan_result?;
+            stored_rows += 1;
+            for (column_index, _) in column_types.iter().enumerate().filter(|(_, kind)| **kind == ColumnType::String) {
+                // string cell holds a length byte followed by string bytes
+                let string_size = match scan_product.row.get_cell_sql_value(&column_types, column_index).map_err(TableError::CannotGetCell)? {
+                    SqlValue::String(string) => string.len() + 1,
+                    _ => 0,
+                };
+                string_bytes_wasted += STRING_SIZE - string_size;
+                longest_string = longest_string.max(string_size.saturating_sub(1));
+            }
+        }
+
+        let page_count = self.pager.page_count().map_err(TableError::IoError)?;
+        let stats = [
+            self.pager.row_size() as u64,
+            self.pager.rows_per_page() as u64,
+            page_count,
+            stored_rows,
+            self.pager.max_rows() - stored_rows,
+            string_bytes_wasted as u64,
+            longest_string as u64,
+        ];
+        let column_names = ["row_size", "rows_per_page", "page_count", "row_count", "free_slots", "string_bytes_wasted", "longest_string"];
+        let stats_types = vec![ColumnType::Integer; stats.len()];
+        let values: Vec<SqlValue> = stats.iter().map(|stat| SqlValue::Integer(*stat as i64)).collect();
+        let row = Row::from_sql_values(&values, &stats_types).map_err(TableError::CannotSetCell)?;
+
+        Ok(QueryResult {
+            column_names: column_names.iter().map(|name| name.to_string()).collect(),
+            column_nullable_flags: vec![false; stats.len()],
+            column_types: stats_types,
+            rows: vec![row],
+        })
+    }
+
+    // vacuum moves rows to other slots, so checksums are recalculated for row ids they got
+    fn rebuild_checksums(&mut self) -> Result<(), TableError> {
+        if self.checksums.is_none() { return Ok(()) }
+
+        let rows = Self::seq_scan(&mut self.pager)
+            .map(|scan_result| scan_result.map(|scan_product| (scan_product.row_id, scan_product.row)))
+            .collect::<Result<Vec<(u64, Row)>, TableError>>()?;
+        self.checksums.as_mut().unwrap()
+            .rebuild(rows.iter().map(|(row_id, row)| (*row_id, row)))
+            .map_err(TableError::IoError)
+    }
+
+    // frees soft deleted rows, index entries will be rebuilt by reindex after vacuum
+    fn purge_tombstones(&mut self) -> Result<(), TableError> {
+        if self.tombstones.is_empty() { return Ok(()) }
+
+        for row_id in self.tombstones.drain() {
+            self.pager.delete_row(row_id).map_err(TableError::CannotDeleteRow)?;
+        }
+        self.flush_tombstones()
+    }
+
+    fn change_log_filepath(table_filepath: &Path) -> PathBuf {
+        table_filepath.with_extension(CHANGE_LOG_EXTENSION)
+    }
+
+    fn checksums_filepath(table_filepath: &Path) -> PathBuf {
+        table_filepath.with_extension(CHECKSUMS_EXTENSION)
+    }
+
+    fn tombstones_filepath(table_filepath: &Path) -> PathBuf {
+        table_filepath.with_extension(TOMBSTONES_EXTENSION)
+    }
+
+    fn load_tombstones(tombstones_filepath: &Path) -> Result<HashSet<u64>, TableError> {
+        let mut bytes = vec![];
+        match File::open(tombstones_filepath) {
+            Ok(mut file) => file.read_to_end(&mut bytes).map_err(TableError::IoError)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(error) => return Err(TableError::IoError(error)),
+        };
+
+        Ok(
+            bytes.chunks_exact(8)
+                .map(|row_id_bytes| u64::from_le_bytes(row_id_bytes.try_into().unwrap()))
+                .collect()
+        )
+    }
+
+    fn flush_tombstones(&self) -> Result<(), TableError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(Self::tombstones_filepath(&self.table_filepath))
+            .map_err(TableError::IoError)?;
+
+        let bytes: Vec<u8> = self.tombstones.iter()
+            .flat_map(|row_id| row_id.to_le_bytes())
+            .collect();
+        file.write_all(&bytes).map_err(TableError::IoError)
+    }
+
     fn reindex(&mut self) -> Result<(), TableError> {
         self.reindex_columns((0..self.column_indexes.len()).collect())
     }
@@ -540,43 +1524,94 @@ impl Table {
             })
     }
 
-    fn matching_rows<'a>(pager: &'a mut Pager, column_indexes: &'a [Option<HashIndex>],
-                         table_headers: &'a TableHeaders, where_clause: Option<BinaryCondition>)
+    // when `deleted` is set, only soft deleted rows are matched, otherwise they are skipped;
+    // `skip_damaged` skips unreadable rows and ones not matching checksums instead of returning an error;
+    // deadline is checked on each scanned row, so the scan is cancelled as soon as it is passed
+    #[allow(clippy::too_many_arguments)]
+    fn matching_rows<'a>(pager: &'a mut Pager, column_indexes: &'a [Option<HashIndex>], index_hint: Option<&IndexHint>,
+                         table_headers: &'a TableHeaders, null_counts: &[usize], tombstones: &'a HashSet<u64>,
+                         checksums: &'a Option<RowChecksums>, skip_damaged: bool, deleted: bool,
+                         deadline: Option<Instant>, stats: &'a mut StatementStats, where_clause: Option<Condition>)
         -> Result<impl Iterator<Item = Result<ScanProduct, TableError>> + 'a, TableError> {
 
         let where_filter = match where_clause {
             None => RowCheck::dummy(),
-            Some(where_clause) => where_clause.compile(&table_headers.name, &table_headers.column_names)?,
+            Some(where_clause) => query_rewriter::rewrite(where_clause.compile(&table_headers.name, &table_headers.column_names)?),
         };
 
-        let base_query_iter = Self::plan_query(pager, column_indexes, &where_filter);
+        let batch_size = pager.rows_per_page();
+        // null counts do not include soft deleted rows, so they can't be used to skip scan of deleted ones
+        let base_query_iter = match where_filter.is_column_is_null_check() {
+            Some(column_number) if !deleted && null_counts[column_number] == 0 => Box::new(std::iter::empty()),
+            _ => Self::plan_query(pager, column_indexes, index_hint, &where_filter, stats),
+        };
 
         let filter_closure = {
-            let column_types = &table_headers.column_types;
+            let table_name = &table_headers.name;
 
             move |scan_result: Result<ScanProduct, TableError>| {
+                stats.rows_scanned += 1;
+                if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                    return Some(Err(TableError::StatementTimeout(table_name.to_string())))
+                }
+
                 match scan_result {
-                    Ok(scan_product) =>
-                        match where_filter.matches(&scan_product.row, column_types) {
-                            Ok(true) => Some(Ok(scan_product)),
-                            Ok(false) => None,
-                            Err(error) => Some(Err(error)),
-                        }
-                    Err(error) => Some(Err(error)),
+                    Ok(scan_product) if tombstones.contains(&scan_product.row_id) != deleted => None,
+                    Ok(scan_product) if checksums.as_ref().is_some_and(|checksums| !checksums.matches(scan_product.row_id, &scan_product.row)) =>
+                        match skip_damaged {
+                            true => { stats.damaged_rows_skipped += 1; None },
+                            false => Some(Err(TableError::RowChecksumMismatch { table_name: table_name.to_string(), row_id: scan_product.row_id })),
+                        },
+                    Err(TableError::CannotGetRow(_)) if skip_damaged => { stats.damaged_rows_skipped += 1; None },
+                    scan_result => Some(scan_result),
                 }
             }
         };
 
-        Ok(base_query_iter.filter_map(filter_closure))
+        // whole where clause is checked against rows found by index as well, since index
+        // lookup can return rows of other values with the same hash
+        Ok(BatchFilter {
+            scan: base_query_iter.filter_map(filter_closure),
+            row_check: where_filter,
+            column_types: &table_headers.column_types,
+            batch_size,
+            matched: VecDeque::new(),
+        })
     }
 
-    fn plan_query<'a, 'b>(pager: &'a mut Pager, column_indexes: &'a [Option<HashIndex>], where_filter: &'b RowCheck)
+    // indexes of USE INDEX hint are scanned even if estimated row count is too large for them
+    fn plan_query<'a, 'b>(pager: &'a mut Pager, column_indexes: &'a [Option<HashIndex>], index_hint: Option<&IndexHint>,
+                          where_filter: &'b RowCheck, stats: &mut StatementStats)
         -> Box<dyn Iterator<Item = Result<ScanProduct, TableError>> + 'a> {
 
-        if let Some((column_number, value)) = where_filter.is_column_value_eq_static_check() {
-            if let Some(ref column_index) = column_indexes[column_number] {
-                return Self::index_scan(pager, column_index, value)
-            }
+        if *where_filter == query_rewriter::always_false() {
+            return Box::new(std::iter::empty())
+        }
+
+        let max_rows = pager.max_rows();
+        let is_forced = matches!(index_hint, Some(IndexHint::Use(_)));
+        let mut index_lookups: Vec<(&HashIndex, Vec<SqlValue>, usize)> = where_filter.column_value_in_static_checks()
+            .into_iter()
+            .filter_map(|(column_number, values)| {
+                let column_index = column_indexes[column_number].as_ref()
+                    .filter(|column_index| index_hint.is_none_or(|index_hint| index_hint.allows(&column_index.name)))?;
+                let estimated_row_count: usize = values.iter().map(|value| column_index.estimated_row_count(value)).sum();
+                (is_forced || estimated_row_count as u64 <= max_rows / BITMAP_SCAN_MAX_ROWS_FRACTION)
+                    .then_some((column_index, values, estimated_row_count))
+            })
+            .collect();
+        stats.index_probes += index_lookups.iter().map(|(_, values, _)| values.len() as u64).sum::<u64>();
+
+        match index_lookups.len() {
+            0 => {},
+            1 if is_forced || index_lookups[0].2 as u64 <= max_rows / INDEX_SCAN_MAX_ROWS_FRACTION => {
+                let (column_index, values, _) = index_lookups.pop().unwrap();
+                return Self::index_scan(pager, column_index, values)
+            },
+            _ => {
+                let index_lookups = index_lookups.into_iter().map(|(column_index, values, _)| (column_index, values)).collect();
+                return Self::bitmap_scan(pager, index_lookups)
+            },
         }
 
         Self::seq_scan(pager)
@@ -587,7 +1622,7 @@ impl Table {
 
         Box::new(
             (0..max_rows)
-            .map(|row_number| (row_number, pager.get_row(row_number)))
+            .map(|row_number| (row_number, pager.get_scanned_row(row_number)))
             .filter(|(_, get_row_result)| get_row_result.is_err() || get_row_result.as_ref().unwrap().is_some())
             .map(|(row_number, get_row_result)| {
                 match get_row_result {
@@ -599,12 +1634,47 @@ impl Table {
         )
     }
 
-    fn index_scan<'a>(pager: &'a mut Pager, column_index: &'a HashIndex, value: SqlValue)
+    // row ids are collected from each index into bitmaps before fetching rows, so only rows
+    // found by all of them are read, and pages are read in the order they are stored in table file
+    fn bitmap_scan<'a>(pager: &'a mut Pager, index_lookups: Vec<(&'a HashIndex, Vec<SqlValue>)>)
         -> Box<dyn Iterator<Item = Result<ScanProduct, TableError>> + 'a> {
 
+            let mut row_ids: Option<RowIdBitmap> = None;
+            for (column_index, values) in index_lookups {
+                let index_row_ids: Result<RowIdBitmap, HashIndexError> = values.iter()
+                    .flat_map(|value| column_index.find_row_ids(value))
+                    .collect();
+                match (index_row_ids, &mut row_ids) {
+                    (Err(error), _) => return Box::new(std::iter::once(Err(error.into()))),
+                    (Ok(index_row_ids), Some(row_ids)) => row_ids.intersect(&index_row_ids),
+                    (Ok(index_row_ids), None) => row_ids = Some(index_row_ids),
+                }
+            }
+            let row_ids: Vec<u64> = row_ids.unwrap_or_default().iter().collect();
+
             Box::new(
-                column_index
-                .find_row_ids(&value)
+                row_ids
+                .into_iter()
+                .map(|row_number| {
+                    let row = pager.get_row(row_number).map_err(TableError::CannotGetRow)?.unwrap();
+                    Ok(ScanProduct { row_id: row_number, row })
+                })
+            )
+    }
+
+    // rows of each value are looked up separately, row found by several values (due to
+    // hash collision) is returned once
+    fn index_scan<'a>(pager: &'a mut Pager, column_index: &'a HashIndex, values: Vec<SqlValue>)
+        -> Box<dyn Iterator<Item = Result<ScanProduct, TableError>> + 'a> {
+
+            let mut found_row_numbers = HashSet::new();
+            Box::new(
+                values
+                .into_iter()
+                .flat_map(|value| column_index.find_row_ids(&value))
+                .filter(move |row_number_result| {
+                    row_number_result.as_ref().map_or(true, |row_number| found_row_numbers.insert(*row_number))
+                })
                 .map(|row_number_result| {
                     let row_number = row_number_result?;
                     let row = pager.get_row(row_number).map_err(TableError::CannotGetRow)?.unwrap();
@@ -618,12 +1688,39 @@ impl Table {
             )
     }
 
+    fn recount_nulls(&mut self) -> Result<(), TableError> {
+        let mut null_counts = vec![0; self.headers.column_types.len()];
+        Self::seq_scan(&mut self.pager)
+            .try_for_each(|scan_result| {
+                let scan_product = scan_result?;
+                if !self.tombstones.contains(&scan_product.row_id) {
+                    Self::add_row_nulls(&mut null_counts, &scan_product.row);
+                }
+                Ok::<(), TableError>(())
+            })?;
+        self.null_counts = null_counts;
+
+        Ok(())
+    }
+
+    fn add_row_nulls(null_counts: &mut [usize], row: &Row) {
+        for (column_number, null_count) in null_counts.iter_mut().enumerate() {
+            if row.cell_is_null(column_number) { *null_count += 1 }
+        }
+    }
+
+    fn remove_row_nulls(null_counts: &mut [usize], row: &Row) {
+        for (column_number, null_count) in null_counts.iter_mut().enumerate() {
+            if row.cell_is_null(column_number) { *null_count -= 1 }
+        }
+    }
+
     fn compile_checks(&mut self) -> Result<(), TableError> {
         self.headers.checks.clear();
         for column_constraints in self.headers.column_constraints.iter() {
             for constraint in column_constraints {
                 match constraint {
-                    Constraint::Check(binary_condition) => {
+                    Constraint::Check(binary_condition) | Constraint::NamedCheck(_, binary_condition) => {
                         let check_condition = binary_condition.clone();
                         self.headers.checks.push(check_condition.compile(&self.headers.name, &self.headers.column_names)?);
                     },
@@ -693,11 +1790,15 @@ impl Table {
         match constraint {
             Constraint::NotNull => !row.cell_is_null(column_number),
             Constraint::Default(_) => { true },
-            Constraint::Check(_) => { true },
+            Constraint::Check(_) | Constraint::NamedCheck(..) => { true },
+            Constraint::References(_) => { true },
         }
     }
 
     fn validate_values_type(&self, columns_values: &[SqlValue], column_numbers: &[usize]) -> Result<(), TableError> {
+        if columns_values.len() != column_numbers.len() {
+            return Err(TableError::ValuesCountMismatch { values_count: columns_values.len(), columns_count: column_numbers.len() })
+        }
         for (value_index, value) in columns_values.iter().enumerate() {
             let column_number = column_numbers[value_index];
 
@@ -719,8 +1820,203 @@ impl Table {
             .position(|table_column_name| table_column_name.eq(column_name))
     }
 
+    // strips `table.` prefix from column name, unless column is named with a dot itself
+    fn function_result_type(&self, call: &FunctionCall) -> Result<ColumnType, TableError> {
+        let argument_types = call.arguments.iter()
+            .map(|argument| match argument {
+                SqlValue::Null => Ok(None),
+                SqlValue::Integer(_) => Ok(Some(ColumnType::Integer)),
+                SqlValue::Float(_) => Ok(Some(ColumnType::Float)),
+                SqlValue::String(_) => Ok(Some(ColumnType::String)),
+                SqlValue::Function(call) => self.function_result_type(call).map(Some),
+                column_name => {
+                    let column_name = self.unqualified_column_name(&column_name.to_string())?.to_string();
+                    Ok(Some(self.column_types()[self.column_number_result(&column_name)?]))
+                },
+            })
+            .collect::<Result<Vec<Option<ColumnType>>, TableError>>()?;
+        call.function.result_type(&argument_types).map_err(TableError::FunctionError)
+    }
+
+    fn unqualified_column_name<'a>(&self, column_name: &'a str) -> Result<&'a str, TableError> {
+        match column_name.split_once('.') {
+            Some(_) if self.column_number(column_name).is_some() => Ok(column_name),
+            Some((qualifier, unqualified_name)) if qualifier == self.name() => Ok(unqualified_name),
+            Some((qualifier, _)) =>
+                Err(TableError::UnknownTableQualifier { table_name: self.name().to_string(), qualifier: qualifier.to_string() }),
+            None => Ok(column_name),
+        }
+    }
+
     pub fn column_number_result(&self, column_name: &str) -> Result<usize, TableError> {
         self.column_number(column_name)
             .ok_or(TableError::ColumnNotExist { column_name: column_name.to_string(), table_name: self.name().to_string() })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::temp_file::TempFile;
+    use crate::cmp_operator::CmpOperator;
+
+    #[test]
+    fn scan_is_cancelled_after_statement_deadline() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let column_definitions = vec![
+            ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+        ];
+        let mut table = Table::new(table_file.file_path.clone(), "users", 0, None, &column_definitions, vec![], TableOptions::default()).unwrap();
+        table.insert(None, vec![SqlValue::Integer(1)], None).unwrap();
+
+        table.set_statement_deadline(Some(Instant::now() + Duration::from_secs(60)));
+        assert_eq!(table.select(vec![SelectColumnName::AllColumns], None).unwrap().len(), 1);
+
+        table.set_statement_deadline(Some(Instant::now() - Duration::from_millis(1)));
+        assert!(matches!(table.select(vec![SelectColumnName::AllColumns], None), Err(TableError::StatementTimeout(_))));
+        assert!(matches!(table.delete(None, None), Err(TableError::StatementTimeout(_))));
+
+        table.set_statement_deadline(None);
+        assert_eq!(table.select(vec![SelectColumnName::AllColumns], None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn aggregate_is_not_returned_by_delete() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let column_definitions = vec![
+            ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+        ];
+        let mut table = Table::new(table_file.file_path.clone(), "users", 0, None, &column_definitions, vec![], TableOptions::default()).unwrap();
+        table.insert(None, vec![SqlValue::Integer(1)], None).unwrap();
+
+        assert!(matches!(table.delete(None, Some(&[SelectColumnName::CountAll])), Err(TableError::AggregateNotSupported(_))));
+        assert_eq!(table.select(vec![SelectColumnName::AllColumns], None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn update_skips_unchanged_rows() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let column_definitions = vec![
+            ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+        ];
+        let options = TableOptions { change_log: true, ..TableOptions::default() };
+        let mut table = Table::new(table_file.file_path.clone(), "users", 0, None, &column_definitions, vec![], options).unwrap();
+        table.insert(None, vec![SqlValue::Integer(1), SqlValue::String("john".to_string())], None).unwrap();
+        table.insert(None, vec![SqlValue::Integer(2), SqlValue::String("john".to_string())], None).unwrap();
+        table.sync().unwrap();
+        table.take_stats();
+
+        let set_name = |name: &str| vec![FieldAssignment { column_name: "name".to_string(), value: SqlValue::String(name.to_string()) }];
+        table.update(set_name("john"), None, None).unwrap();
+        table.sync().unwrap();
+        assert_eq!(table.take_stats().pages_written, 0);
+        assert_eq!(table.changes(0).unwrap().len(), 2);
+
+        table.update(set_name("anna"), None, None).unwrap();
+        table.sync().unwrap();
+        assert_eq!(table.take_stats().pages_written, 1);
+        assert_eq!(table.changes(0).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn update_of_row_which_does_not_fit_into_page_is_not_recorded() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let column_definitions = vec![
+            ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+        ];
+        let options = TableOptions { compression: true, checksums: true, change_log: true, ..TableOptions::default() };
+        let mut table = Table::new(table_file.file_path.clone(), "users", 0, None, &column_definitions, vec![], options).unwrap();
+        for id in 0..60 {
+            table.insert(None, vec![SqlValue::Integer(id), SqlValue::String(format!("{:0>100}", id))], None).unwrap();
+        }
+        table.create_index("name", "users_name".to_string(), &table_file.temp_dir_path).unwrap();
+
+        let name_equals = |name: &str| Condition::Binary(BinaryCondition {
+            left_value: SqlValue::Identificator("name".to_string()),
+            right_value: SqlValue::String(name.to_string()),
+            operator: CmpOperator::Equals,
+        });
+        let long_name = |id: i64| format!("{:x>200}", id);
+        let set_long_name = |table: &mut Table, id: i64| table.update(
+            vec![FieldAssignment { column_name: "name".to_string(), value: SqlValue::String(long_name(id)) }],
+            Some(Condition::Binary(BinaryCondition {
+                left_value: SqlValue::Identificator("id".to_string()),
+                right_value: SqlValue::Integer(id),
+                operator: CmpOperator::Equals,
+            })),
+            None,
+        );
+
+        let failed_id = (0..60).find(|id| set_long_name(&mut table, *id).is_err()).unwrap();
+        assert!(matches!(set_long_name(&mut table, failed_id), Err(TableError::CannotUpdateRow(PagerError::PageDictionaryIsFull))));
+        table.sync().unwrap();
+
+        assert!(table.select(vec![SelectColumnName::AllColumns], Some(name_equals(&long_name(failed_id)))).unwrap().is_empty());
+        assert_eq!(table.select(vec![SelectColumnName::AllColumns], Some(name_equals(&format!("{:0>100}", failed_id)))).unwrap().len(), 1);
+        assert!(table.check().unwrap().is_empty());
+        assert_eq!(table.changes(0).unwrap().len(), 60 + failed_id as usize);
+    }
+
+    #[test]
+    fn checksums_are_written_on_sync() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let column_definitions = vec![
+            ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+        ];
+        let options = TableOptions { checksums: true, ..TableOptions::default() };
+        let mut table = Table::new(table_file.file_path.clone(), "users", 0, None, &column_definitions, vec![], options).unwrap();
+        let checksums_filepath = Table::checksums_filepath(&table_file.file_path);
+        for id in 0..3 {
+            table.insert(None, vec![SqlValue::Integer(id)], None).unwrap();
+        }
+        assert!(!checksums_filepath.exists());
+
+        table.sync().unwrap();
+        assert_eq!(fs::metadata(&checksums_filepath).unwrap().len(), 24);
+        drop(table);
+        let mut table = Table::new(table_file.file_path.clone(), "users", 3, None, &column_definitions, vec![], options).unwrap();
+        assert!(table.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rows_found_by_colliding_index_entries_are_filtered_out() {
+        let table_file = TempFile::new("users.table").unwrap();
+        let column_definitions = vec![
+            ColumnDefinition { name: SqlValue::Identificator("id".to_string()), kind: ColumnType::Integer, column_constraints: vec![] },
+            ColumnDefinition { name: SqlValue::Identificator("name".to_string()), kind: ColumnType::String, column_constraints: vec![] },
+        ];
+        let mut table = Table::new(table_file.file_path.clone(), "users", 0, None, &column_definitions, vec![], TableOptions::default()).unwrap();
+        for (id, name) in [(1, "john"), (2, "pete"), (3, "anna")] {
+            table.insert(None, vec![SqlValue::Integer(id), SqlValue::String(name.to_string())], None).unwrap();
+        }
+        table.create_index("id", "users_id".to_string(), &table_file.temp_dir_path).unwrap();
+        // entry of row with id 1 is stored under hash of 2, as if values had the same hash
+        table.column_indexes[0].as_mut().unwrap().insert_row(&SqlValue::Integer(2), 0, 3).unwrap();
+
+        let id_equals = |id| Condition::Binary(BinaryCondition {
+            left_value: SqlValue::Identificator("id".to_string()),
+            right_value: SqlValue::Integer(id),
+            operator: CmpOperator::Equals,
+        });
+        let select_names = |table: &mut Table, where_clause| {
+            let result = table.select(vec![SelectColumnName::Name(SqlValue::Identificator("name".to_string()))], Some(where_clause)).unwrap();
+            (0..result.len()).map(|row_index| result.row_values(row_index).unwrap().unwrap()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(select_names(&mut table, id_equals(2)), vec![vec![SqlValue::String("pete".to_string())]]);
+        assert_eq!(table.take_stats().index_probes, 1);
+        assert_eq!(
+            select_names(&mut table, Condition::in_list(SqlValue::Identificator("id".to_string()), vec![SqlValue::Integer(3), SqlValue::Integer(2)])),
+            vec![vec![SqlValue::String("pete".to_string())], vec![SqlValue::String("anna".to_string())]]
+        );
+
+        let set_name = vec![FieldAssignment { column_name: "name".to_string(), value: SqlValue::String("bob".to_string()) }];
+        table.update(set_name, Some(id_equals(2)), None).unwrap();
+        table.delete(Some(id_equals(2)), None).unwrap();
+        assert_eq!(select_names(&mut table, id_equals(1)), vec![vec![SqlValue::String("john".to_string())]]);
+        assert!(select_names(&mut table, id_equals(2)).is_empty());
+    }
+}
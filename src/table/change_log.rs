@@ -0,0 +1,162 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::row::Row;
+
+const OPERATION_SIZE: usize = 1;
+const ROW_ID_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeOperation {
+    Insert,
+    Update,
+    Delete,
+    Undelete,
+}
+
+impl fmt::Display for ChangeOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Insert => write!(f, "INSERT"),
+            Self::Update => write!(f, "UPDATE"),
+            Self::Delete => write!(f, "DELETE"),
+            Self::Undelete => write!(f, "UNDELETE"),
+        }
+    }
+}
+
+impl ChangeOperation {
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Insert => 1,
+            Self::Update => 2,
+            Self::Delete => 3,
+            Self::Undelete => 4,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<ChangeOperation> {
+        match byte {
+            1 => Some(Self::Insert),
+            2 => Some(Self::Update),
+            3 => Some(Self::Delete),
+            4 => Some(Self::Undelete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ChangeEntry {
+    pub lsn: u64,
+    pub operation: ChangeOperation,
+    pub row_id: u64,
+    pub old_row: Option<Row>,
+    pub new_row: Option<Row>,
+}
+
+/// Append-only log of row changes. Entries have fixed size, so entry's log sequence
+/// number (lsn) is its position in the file starting from 1, and reading entries
+/// after some lsn does not require scanning the whole file.
+/// Each entry is: operation byte, row id, old row bytes and new row bytes. Row which
+/// does not exist for the operation (like old row for insert) is stored as zeroes.
+#[derive(Debug)]
+pub struct ChangeLog {
+    filepath: PathBuf,
+    row_size: usize,
+}
+
+impl ChangeLog {
+    pub fn new(filepath: PathBuf, row_size: usize) -> ChangeLog {
+        ChangeLog { filepath, row_size }
+    }
+
+    pub fn append(&self, operation: ChangeOperation, row_id: u64, old_row: Option<&Row>, new_row: Option<&Row>) -> io::Result<()> {
+        let mut entry_bytes = Vec::with_capacity(self.entry_size());
+        entry_bytes.push(operation.to_byte());
+        entry_bytes.extend_from_slice(&row_id.to_le_bytes());
+        for row in [old_row, new_row] {
+            match row {
+                Some(row) => entry_bytes.extend_from_slice(row.as_bytes()),
+                None => entry_bytes.resize(entry_bytes.len() + self.row_size, 0),
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.filepath)?;
+        file.write_all(&entry_bytes)
+    }
+
+    pub fn entries_since(&self, since_lsn: u64) -> io::Result<Vec<ChangeEntry>> {
+        let mut file = match File::open(&self.filepath) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error),
+        };
+
+        file.seek(SeekFrom::Start(since_lsn * self.entry_size() as u64))?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+
+        Ok(
+            bytes.chunks_exact(self.entry_size())
+                .enumerate()
+                .filter_map(|(i, entry_bytes)| self.parse_entry(since_lsn + i as u64 + 1, entry_bytes))
+                .collect()
+        )
+    }
+
+    pub fn filepath(&self) -> &Path {
+        &self.filepath
+    }
+
+    pub fn is_empty(&self) -> io::Result<bool> {
+        match fs::metadata(&self.filepath) {
+            Ok(metadata) => Ok(metadata.len() == 0),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn set_filepath(&mut self, new_filepath: PathBuf) {
+        self.filepath = new_filepath;
+    }
+
+    pub fn destroy(self) -> io::Result<()> {
+        match fs::remove_file(&self.filepath) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn parse_entry(&self, lsn: u64, entry_bytes: &[u8]) -> Option<ChangeEntry> {
+        let operation = ChangeOperation::from_byte(entry_bytes[0])?;
+        let row_id = u64::from_le_bytes(entry_bytes[OPERATION_SIZE..OPERATION_SIZE + ROW_ID_SIZE].try_into().unwrap());
+        let old_row_offset = OPERATION_SIZE + ROW_ID_SIZE;
+        let new_row_offset = old_row_offset + self.row_size;
+        let old_row_bytes = &entry_bytes[old_row_offset..new_row_offset];
+        let new_row_bytes = &entry_bytes[new_row_offset..new_row_offset + self.row_size];
+
+        let (old_row, new_row) = match operation {
+            ChangeOperation::Insert | ChangeOperation::Undelete => (None, Some(new_row_bytes)),
+            ChangeOperation::Update => (Some(old_row_bytes), Some(new_row_bytes)),
+            ChangeOperation::Delete => (Some(old_row_bytes), None),
+        };
+
+        Some(ChangeEntry {
+            lsn,
+            operation,
+            row_id,
+            old_row: old_row.map(|bytes| Row::from_bytes(bytes.to_vec())),
+            new_row: new_row.map(|bytes| Row::from_bytes(bytes.to_vec())),
+        })
+    }
+
+    fn entry_size(&self) -> usize {
+        OPERATION_SIZE + ROW_ID_SIZE + 2 * self.row_size
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::row::Row;
+
+const CHECKSUM_SIZE: usize = 8;
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Checksums of table rows, stored in a separate file next to table file. File holds
+/// a checksum per row slot, checksum of row with id N is at offset N * 8. Checksums are
+/// kept in memory to verify rows on read, changed ones are written to file on `sync`,
+/// together with table pages, and only changed slots are rewritten.
+#[derive(Debug)]
+pub struct RowChecksums {
+    filepath: PathBuf,
+    checksums: Vec<u64>,
+    changed_slots: BTreeSet<usize>,
+}
+
+impl RowChecksums {
+    pub fn load(filepath: PathBuf) -> io::Result<RowChecksums> {
+        let mut bytes = vec![];
+        match File::open(&filepath) {
+            Ok(mut file) => { file.read_to_end(&mut bytes)?; },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+            Err(error) => return Err(error),
+        };
+
+        let checksums = bytes.chunks_exact(CHECKSUM_SIZE)
+            .map(|checksum_bytes| u64::from_le_bytes(checksum_bytes.try_into().unwrap()))
+            .collect();
+
+        Ok(RowChecksums { filepath, checksums, changed_slots: BTreeSet::new() })
+    }
+
+    pub fn set(&mut self, row_id: u64, row: &Row) {
+        let slot = row_id as usize;
+        if slot >= self.checksums.len() {
+            self.checksums.resize(slot + 1, 0);
+        }
+        self.checksums[slot] = Self::checksum(row);
+        self.changed_slots.insert(slot);
+    }
+
+    /// Resets checksum of a row slot, e.g. when inserted row is taken back
+    pub fn clear(&mut self, row_id: u64) {
+        let slot = row_id as usize;
+        if slot < self.checksums.len() {
+            self.checksums[slot] = 0;
+            self.changed_slots.insert(slot);
+        }
+    }
+
+    /// Writes checksums changed since the previous sync and waits until they are synced
+    pub fn sync(&mut self) -> io::Result<()> {
+        if self.changed_slots.is_empty() { return Ok(()) }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.filepath)?;
+        for slot in &self.changed_slots {
+            file.seek(SeekFrom::Start((*slot * CHECKSUM_SIZE) as u64))?;
+            file.write_all(&self.checksums[*slot].to_le_bytes())?;
+        }
+        file.sync_data()?;
+        self.changed_slots.clear();
+        Ok(())
+    }
+
+    /// Recalculates checksums of all rows, e.g. after vacuum moved rows to other slots
+    pub fn rebuild<'a>(&mut self, rows: impl Iterator<Item = (u64, &'a Row)>) -> io::Result<()> {
+        self.checksums.clear();
+        for (row_id, row) in rows {
+            let slot = row_id as usize;
+            if slot >= self.checksums.len() {
+                self.checksums.resize(slot + 1, 0);
+            }
+            self.checksums[slot] = Self::checksum(row);
+        }
+
+        self.changed_slots.clear();
+        let bytes: Vec<u8> = self.checksums.iter()
+            .flat_map(|checksum| checksum.to_le_bytes())
+            .collect();
+        fs::write(&self.filepath, bytes)
+    }
+
+    pub fn matches(&self, row_id: u64, row: &Row) -> bool {
+        self.checksums.get(row_id as usize) == Some(&Self::checksum(row))
+    }
+
+    pub fn filepath(&self) -> &Path {
+        &self.filepath
+    }
+
+    pub fn set_filepath(&mut self, new_filepath: PathBuf) {
+        self.filepath = new_filepath;
+    }
+
+    pub fn destroy(mut self) -> io::Result<()> {
+        self.changed_slots.clear();
+        match fs::remove_file(&self.filepath) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    // FNV-1a, it is not cryptographic, but is enough to catch accidental corruption
+    fn checksum(row: &Row) -> u64 {
+        row.as_bytes().iter()
+            .fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+    }
+}
+
+impl Drop for RowChecksums {
+    fn drop(&mut self) {
+        // drop cannot return an error, call `sync` beforehand to handle it
+        if let Err(error) = self.sync() {
+            eprintln!("failed to write row checksums: {}", error);
+        }
+    }
+}
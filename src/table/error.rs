@@ -11,6 +11,7 @@ use crate::cmp_operator::CmpError;
 use crate::row::Row;
 use crate::row_check::RowCheck;
 use crate::hash_index::error::HashIndexError;
+use crate::function::FunctionError;
 
 #[derive(Debug)]
 pub enum TableError {
@@ -18,10 +19,12 @@ pub enum TableError {
     CreateError(PagerError),
     ColumnNotExist { table_name: String, column_name: String },
     ColumnNthNotExist { table_name: String, column_number: usize },
+    ColumnNotIndexed { table_name: String, column_name: String },
     CannotGetRow(PagerError),
     CannotSetCell(SerDeError),
     CannotGetCell(SerDeError),
     ValueColumnMismatch { value: SqlValue, column_name: String, column_type: ColumnType },
+    ValuesCountMismatch { values_count: usize, columns_count: usize },
     CannotInsertRow(PagerError),
     CannotUpdateRow(PagerError),
     CannotDeleteRow(PagerError),
@@ -30,12 +33,35 @@ pub enum TableError {
     IndexAlreadyExists { table_name: String, column_name: String, index_name: String },
     ConstraintAlreadyExists { table_name: String, column_name: String, constraint: Constraint },
     ConstraintNotExists { table_name: String, column_name: String, constraint: Constraint },
+    ConstraintNameAlreadyExists { table_name: String, constraint_name: String },
+    ConstraintNameNotExists { table_name: String, constraint_name: String },
     ColumnConstraintViolation { table_name: String, constraint: Constraint, column_name: String, value: SqlValue },
     CheckViolation { table_name: String, row_check: RowCheck, row: Row },
     UnexpectedBinaryConditionError { table_name: String, column_string: String },
     HashIndexMissing { table_name: String, index_name: String },
     IoError(io::Error),
     HashIndexError(HashIndexError),
+    TableOptionUnknown(String),
+    SoftDeleteDisabled(String),
+    ChangeLogDisabled(String),
+    StatementTimeout(String),
+    SyncFailed(PagerError),
+    UnknownTableQualifier { table_name: String, qualifier: String },
+    AggregateMixedWithColumns(String),
+    AggregateNotSupported(String),
+    ChecksumsDisabled(String),
+    RowChecksumMismatch { table_name: String, row_id: u64 },
+    AmbiguousColumnName(String),
+    TableJoinedTwice(String),
+    SubqueryNotSupported(String),
+    FunctionError(FunctionError),
+    SelectedFunctionNotSupported(String),
+    SizeQuotaExceeded { table_name: String, max_file_size: u64 },
+    PageNotExist { table_name: String, page_id: u64, page_count: u64 },
+    SoftDeletedRowsNotMovable(String),
+    InsertRollbackFailed { error: Box<TableError>, rollback_error: Box<TableError> },
+    TooManyRows { table_name: String, max_row_count: usize },
+    ChangeLogNotMovable(String),
 }
 
 impl fmt::Display for TableError {
@@ -47,6 +73,8 @@ impl fmt::Display for TableError {
                 write!(f, "table '{}' does not have column '{}'", table_name, column_name),
             Self::ColumnNthNotExist { table_name, column_number } =>
                 write!(f, "table '{}' does not have column with index [{}]", table_name, column_number),
+            Self::ColumnNotIndexed { table_name, column_name } =>
+                write!(f, "column '{}' of table '{}' should be indexed to check conflicts on it", column_name, table_name),
             Self::CannotGetRow(_pager_error) => write!(f, "cannot to get a row from pager"),
             Self::CannotSetCell(_ser_de_error) => write!(f, "cannot set row bytes for a cell"),
             Self::CannotGetCell(_ser_de_error) => write!(f, "cannot get sql value from a row cell"),
@@ -54,6 +82,8 @@ impl fmt::Display for TableError {
                 write!(f,
                     "value {} is not acceptable for column '{}' which has type '{}'",
                     value, column_name, column_type),
+            Self::ValuesCountMismatch { values_count, columns_count } =>
+                write!(f, "{} values are given for {} columns", values_count, columns_count),
             Self::CannotInsertRow(_pager_error) => write!(f, "cannot insert row into table"),
             Self::CannotUpdateRow(_pager_error) => write!(f, "cannot update row in the table"),
             Self::CannotDeleteRow(_pager_error) => write!(f, "cannot delete row in the table"),
@@ -65,6 +95,10 @@ impl fmt::Display for TableError {
                 write!(f, "table's '{}' column '{}' already has constraint '{}'", table_name, column_name, constraint),
             Self::ConstraintNotExists { table_name, column_name, constraint } =>
                 write!(f, "table's '{}' column '{}' does not have constraint '{}'", table_name, column_name, constraint),
+            Self::ConstraintNameAlreadyExists { table_name, constraint_name } =>
+                write!(f, "table '{}' already has constraint named '{}'", table_name, constraint_name),
+            Self::ConstraintNameNotExists { table_name, constraint_name } =>
+                write!(f, "table '{}' does not have constraint named '{}'", table_name, constraint_name),
             Self::ColumnConstraintViolation { table_name, constraint, column_name, value } =>
                 write!(f,
                     "value {} violates '{}' constraint on column '{}' from table '{}'",
@@ -80,6 +114,49 @@ impl fmt::Display for TableError {
             Self::IoError(io_error) => write!(f, "io error: {}", io_error),
             Self::HashIndexMissing { table_name, index_name } => write!(f, "table '{}' does not have index with name '{}'", table_name, index_name),
             Self::HashIndexError(index_error) => write!(f, "{}", index_error),
+            Self::TableOptionUnknown(option_name) => write!(f, "unknown table option '{}'", option_name),
+            Self::SoftDeleteDisabled(table_name) =>
+                write!(f, "table '{}' does not have soft_delete option, deleted rows cannot be restored", table_name),
+            Self::ChangeLogDisabled(table_name) =>
+                write!(f, "table '{}' does not have change_log option, its changes are not recorded", table_name),
+            Self::UnknownTableQualifier { table_name, qualifier } =>
+                write!(f, "table '{}' is not queried, only '{}' columns can be selected", qualifier, table_name),
+            Self::SyncFailed(pager_error) => write!(f, "failed to sync table pages to disk: {}", pager_error),
+            Self::StatementTimeout(table_name) =>
+                write!(f, "canceling statement due to statement timeout while scanning table '{}'", table_name),
+            Self::ChecksumsDisabled(table_name) =>
+                write!(f, "table '{}' does not have checksums option, its rows cannot be checked", table_name),
+            Self::RowChecksumMismatch { table_name, row_id } =>
+                write!(f, "row {} of table '{}' does not match its checksum, table file may be corrupted, \
+                       run `CHECK TABLE {}` to find all such rows", row_id, table_name, table_name),
+            Self::AmbiguousColumnName(column_name) =>
+                write!(f, "column '{}' is present in several joined tables, qualify it with table name", column_name),
+            Self::TableJoinedTwice(table_name) =>
+                write!(f, "table '{}' is listed in FROM more than once", table_name),
+            Self::SubqueryNotSupported(table_name) =>
+                write!(f, "EXISTS subquery on table '{}' is supported only in WHERE of single table SELECT, UPDATE \
+                       and DELETE, and cannot contain another subquery", table_name),
+            Self::AggregateMixedWithColumns(table_name) =>
+                write!(f, "aggregates and plain columns of table '{}' cannot be selected together, since there is no GROUP BY", table_name),
+            Self::AggregateNotSupported(table_name) =>
+                write!(f, "aggregates of table '{}' can be selected only by SELECT, they cannot be returned by RETURNING", table_name),
+            Self::FunctionError(function_error) => write!(f, "{}", function_error),
+            Self::SizeQuotaExceeded { table_name, max_file_size } =>
+                write!(f, "table '{}' cannot grow beyond {} bytes, since table or database size quota is reached", table_name, max_file_size),
+            Self::SelectedFunctionNotSupported(function_call) =>
+                write!(f, "function call '{}' can be selected only from a single table, it can still be used in WHERE", function_call),
+            Self::PageNotExist { table_name, page_id, page_count } =>
+                write!(f, "table '{}' does not have page {}, its file has {} pages", table_name, page_id, page_count),
+            Self::InsertRollbackFailed { error, rollback_error } =>
+                write!(f, "{}, and inserted row could not be removed: {}, \
+                       consider running VACUUM on the table, which rebuilds its indexes", error, rollback_error),
+            Self::TooManyRows { table_name, max_row_count } =>
+                write!(f, "'{}' matches more than {} rows, narrow it down", table_name, max_row_count),
+            Self::SoftDeletedRowsNotMovable(table_name) =>
+                write!(f, "table '{}' has soft deleted rows, which would be lost when table is rebuilt, \
+                       VACUUM or UNDELETE them first", table_name),
+            Self::ChangeLogNotMovable(table_name) =>
+                write!(f, "change log of table '{}' would be lost when table is rebuilt, UNSET change_log first", table_name),
         }
     }
 }
@@ -94,7 +171,15 @@ impl Error for TableError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             Self::CmpError(cmp_error) => Some(cmp_error),
+            Self::CreateError(pager_error) | Self::CannotGetRow(pager_error) | Self::CannotInsertRow(pager_error) |
+                Self::CannotUpdateRow(pager_error) | Self::CannotDeleteRow(pager_error) => Some(pager_error),
+            Self::CannotSetCell(ser_de_error) | Self::CannotGetCell(ser_de_error) => Some(ser_de_error),
             Self::VacuumFailed(vacuum_error) => Some(vacuum_error),
+            Self::SyncFailed(pager_error) => Some(pager_error),
+            Self::IoError(io_error) => Some(io_error),
+            Self::HashIndexError(index_error) => Some(index_error),
+            Self::FunctionError(function_error) => Some(function_error),
+            Self::InsertRollbackFailed { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
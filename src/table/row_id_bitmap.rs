@@ -0,0 +1,63 @@
+const WORD_BITS: u64 = u64::BITS as u64;
+
+/// Set of row ids, row id N is stored as bit N % 64 of word N / 64. Row ids are iterated in
+/// ascending order, so rows found by index are read in the order they are stored in table file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RowIdBitmap {
+    words: Vec<u64>,
+}
+
+impl RowIdBitmap {
+    pub fn insert(&mut self, row_id: u64) {
+        let word_number = (row_id / WORD_BITS) as usize;
+        if word_number >= self.words.len() {
+            self.words.resize(word_number + 1, 0);
+        }
+        self.words[word_number] |= 1 << (row_id % WORD_BITS);
+    }
+
+    /// Keeps only row ids present in both bitmaps
+    pub fn intersect(&mut self, other: &RowIdBitmap) {
+        self.words.truncate(other.words.len());
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word &= other_word;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.words.iter()
+            .enumerate()
+            .flat_map(|(word_number, word)| {
+                (0..WORD_BITS)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| word_number as u64 * WORD_BITS + bit)
+            })
+    }
+}
+
+impl FromIterator<u64> for RowIdBitmap {
+    fn from_iter<I: IntoIterator<Item = u64>>(row_ids: I) -> Self {
+        let mut bitmap = RowIdBitmap::default();
+        for row_id in row_ids {
+            bitmap.insert(row_id);
+        }
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_intersect_and_iterate() {
+        let mut bitmap: RowIdBitmap = [130, 3, 64, 3, 0].into_iter().collect();
+        assert_eq!(bitmap.iter().collect::<Vec<u64>>(), vec![0, 3, 64, 130]);
+
+        bitmap.intersect(&[3, 64, 65].into_iter().collect());
+        assert_eq!(bitmap.iter().collect::<Vec<u64>>(), vec![3, 64]);
+
+        bitmap.intersect(&RowIdBitmap::default());
+        assert_eq!(bitmap.iter().count(), 0);
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+use crate::binary_condition::BinaryCondition;
+use crate::cmp_operator::CmpOperator;
+use crate::command::Command;
+use crate::lexer::SqlValue;
+
+/// Non-fatal issue found in a statement. Statement is executed as usual, warnings are
+/// reported alongside its result, see `Database::last_statement_warnings`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// `column = NULL` and alike are false for every row, `IS NULL` should be used instead
+    ComparisonWithNull(String),
+    /// Rows which salvaged database could not read, see `Database::salvage`
+    DamagedRowsSkipped { table_name: String, row_count: u64 },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ComparisonWithNull(condition) =>
+                write!(f, "'{}' is never true, use `IS NULL` or `NOT ... IS NULL` to check for nulls", condition),
+            Self::DamagedRowsSkipped { table_name, row_count } =>
+                write!(f, "{} rows of table '{}' are skipped, their pages cannot be read or they do not match checksums",
+                       row_count, table_name),
+        }
+    }
+}
+
+/// Warnings which can be found without executing the statement
+pub fn check_command(command: &Command) -> Vec<Warning> {
+    let where_clause = match command {
+        Command::Select { where_clause, .. } | Command::SelectCrossJoin { where_clause, .. } |
+            Command::Update { where_clause, .. } | Command::Delete { where_clause, .. } |
+            Command::Undelete { where_clause, .. } | Command::SelectSeries { where_clause, .. } => where_clause.as_ref(),
+        Command::DeclareCursor { select, .. } | Command::SelectOrdered { select, .. } | Command::SelectLimited { select, .. } |
+            Command::SelectIndexHinted { select, .. } | Command::CopyTo { select, .. } | Command::Returning { command: select, .. } => return check_command(select),
+        _ => None,
+    };
+
+    where_clause
+        .map(|where_clause| where_clause.binary_conditions())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|binary_condition| is_comparison_with_null(binary_condition))
+        .map(|binary_condition| Warning::ComparisonWithNull(binary_condition.to_string()))
+        .collect()
+}
+
+fn is_comparison_with_null(binary_condition: &BinaryCondition) -> bool {
+    binary_condition.operator != CmpOperator::IsNull &&
+        (binary_condition.left_value == SqlValue::Null || binary_condition.right_value == SqlValue::Null)
+}
@@ -0,0 +1,104 @@
+//! Runs `tests/golden/*.sql` scripts against a fresh database and compares their output with
+//! `.out` files next to them. Each line of a script is a statement or a metacommand, lines
+//! starting with `--` are comments. Output lists every statement after `> ` followed by what
+//! REPL would print for it. Run with `UPDATE_GOLDEN=1` to rewrite `.out` files after intended changes.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use yarrd::connection::Connection;
+use yarrd::database::Database;
+use yarrd::meta_command::MetaCommandResult;
+use yarrd::{lexer, output, parser};
+
+const GOLDEN_DIR: &str = "tests/golden";
+const SCRIPT_EXTENSION: &str = "sql";
+const EXPECTED_OUTPUT_EXTENSION: &str = "out";
+const COMMENT_PREFIX: &str = "--";
+const UPDATE_ENV_VAR: &str = "UPDATE_GOLDEN";
+
+#[test]
+fn golden_scripts() {
+    let mut script_paths: Vec<PathBuf> = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_DIR))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == SCRIPT_EXTENSION))
+        .collect();
+    script_paths.sort();
+    assert!(!script_paths.is_empty(), "no scripts found in {}", GOLDEN_DIR);
+
+    let update = env::var_os(UPDATE_ENV_VAR).is_some();
+    let mut failed_scripts = vec![];
+
+    for script_path in script_paths {
+        let output = run_script(&script_path);
+        let expected_output_path = script_path.with_extension(EXPECTED_OUTPUT_EXTENSION);
+
+        if update {
+            fs::write(&expected_output_path, &output).unwrap();
+        } else if fs::read_to_string(&expected_output_path).ok().as_deref() != Some(output.as_str()) {
+            eprintln!("output of {} differs from {}:\n{}", script_path.display(), expected_output_path.display(), output);
+            failed_scripts.push(script_path);
+        }
+    }
+
+    assert!(failed_scripts.is_empty(), "golden scripts failed: {:?}, rerun with {}=1 if changes are intended",
+            failed_scripts, UPDATE_ENV_VAR);
+}
+
+fn run_script(script_path: &Path) -> String {
+    let script = fs::read_to_string(script_path).unwrap();
+    let script_name = script_path.file_stem().unwrap().to_str().unwrap();
+    let database_dir = env::temp_dir().join(format!("yarrd-golden-{}-{}", process::id(), script_name));
+    fs::create_dir_all(&database_dir).unwrap();
+    let database_path = database_dir.join(format!("{}.db", script_name));
+    Database::create(&database_path, &database_dir).unwrap();
+
+    let mut connection = Connection::blank();
+    connection.from(&database_path).unwrap();
+
+    let mut output = String::new();
+    for line in script.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(COMMENT_PREFIX) { continue }
+
+        output.push_str(&format!("> {}\n", line));
+        output.push_str(&execute_line(&mut connection, line));
+    }
+
+    connection.close().unwrap();
+    fs::remove_dir_all(&database_dir).unwrap();
+    output
+}
+
+// mirrors what REPL prints for the line, timer output is skipped since it is not stable
+fn execute_line(connection: &mut Connection, line: &str) -> String {
+    match parser::parse_meta_command(line).execute(connection) {
+        MetaCommandResult::Ok => return "OK\n".to_string(),
+        MetaCommandResult::Output(output) => return format!("{}\n", output),
+        MetaCommandResult::Err(error) => return format!("error executing meta command: {}\n", error),
+        MetaCommandResult::Exit => return "exit\n".to_string(),
+        MetaCommandResult::None => {},
+    }
+
+    let tokens = match lexer::to_tokens(line) {
+        Ok(tokens) => tokens,
+        Err(error) => return format!("cannot parse statement: {}\n", error),
+    };
+    let command = match parser::parse_statement(tokens.iter()) {
+        Ok(command) => command,
+        Err(error) => return format!("error parsing statement: {}\n", error),
+    };
+
+    let output_settings = connection.output_settings.clone();
+    let mut database = connection.get_mut_database().unwrap();
+    match database.execute(command) {
+        Ok(Some(result)) => match output::format_query_result(&result, &output_settings) {
+            Ok(formatted_result) => formatted_result,
+            Err(error) => format!("cannot display query result: {}\n", error),
+        },
+        Ok(None) => "OK\n".to_string(),
+        Err(error) => format!("cannot execute statement: {}\n", error),
+    }
+}
@@ -0,0 +1,21 @@
+-- create, fill and query a table
+create table users (id int, name string, age int) with (soft_delete)
+insert into users (id, name, age) values (1, "john", 30)
+insert into users (id, name, age) values (2, "pete", 17)
+insert into users (id, name) values (3, "anna")
+select * from users
+select name, users.age from users where age > 18
+select * from users where age is null
+update users set age = 18 where id = 3
+delete from users where name = "pete"
+select * from users
+undelete from users
+select count(*), count(age) from users
+create table orders (id int, user_id int)
+insert into orders (id, user_id) values (10, 1)
+insert into orders (id, user_id) values (11, 3)
+select users.name, orders.id from users, orders where users.id = orders.user_id
+select * from users, orders where id = 10
+drop table orders
+drop table users
+select * from users
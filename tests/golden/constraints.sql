@@ -0,0 +1,12 @@
+-- NOT NULL, DEFAULT and CHECK constraints
+create table items (id int not null, price float default 1.5, title string check (id > 0))
+insert into items (id) values (1)
+insert into items (price) values (2.5)
+insert into items (id, price, title) values (0, 3.0, "broken")
+insert into items (id, price, title) values (2, 4.25, "table")
+.types on
+select * from items
+.types off
+alter table items drop constraint not null (id)
+insert into items (price) values (2.5)
+select count(*), count(id) from items
@@ -0,0 +1,13 @@
+-- series, random values and output modes
+select * from generate_series(1, 10, 3)
+select value from generate_series(5, 1, -2) where value <> 3
+select * from generate_series(1, 2, 0)
+create table numbers (n int)
+set random_seed = 42
+insert into numbers (n) values (random())
+insert into numbers (n) values (random())
+.mode insert numbers_copy
+select * from numbers
+.mode table
+select count(*) from numbers
+select foo from numbers